@@ -0,0 +1,45 @@
+// Generates the protobuf message types from proto/game.proto when the `protobuf` feature is
+// enabled.  protoc comes from protoc-bin-vendored so no system install is needed.
+//
+// Always solves the game too, writing the perfect play table src/perfect_play.rs embeds
+#[cfg(feature = "protobuf")]
+extern crate prost_build;
+#[cfg(feature = "protobuf")]
+extern crate protoc_bin_vendored;
+
+// The board encoding, the win lines and the minimax are the crate's own
+#[allow(dead_code, unused_macros)]
+#[path = "src/consts.rs"]
+mod consts;
+#[path = "src/minimax.rs"]
+mod minimax;
+
+use consts::{BOARD_ITEM_FREE, BOARD_ITEM_X, POSITION_COUNT};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/consts.rs");
+    println!("cargo:rerun-if-changed=src/minimax.rs");
+
+    // Sorted by packed board, the order game::position_index counts in
+    let mut values = BTreeMap::new();
+    minimax::minimax(&mut [BOARD_ITEM_FREE; 9], BOARD_ITEM_X, &mut values);
+    assert_eq!(values.len(), POSITION_COUNT);
+    let table: Vec<u8> = values.values().map(|&value| value as u8).collect();
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("perfect_play.bin"), table).unwrap();
+
+    #[cfg(feature = "protobuf")]
+    {
+        println!("cargo:rerun-if-changed=proto/game.proto");
+        let protoc = protoc_bin_vendored::protoc_bin_path().unwrap();
+        prost_build::Config::new()
+            .protoc_executable(protoc)
+            .compile_protos(&["proto/game.proto"], &["proto"])
+            .unwrap();
+    }
+}
@@ -0,0 +1,291 @@
+// Plays one game end to end against a cluster, checking the game account after every
+// transaction against a local copy of the game the same moves are played on:
+//
+//   cargo run --example demo --features client -- http://127.0.0.1:8899 <program id>
+//
+// The program id is the base58 key the native program was deployed under.  Exits nonzero when
+// a transaction fails or the game on the cluster ever differs from the local one
+extern crate bincode;
+extern crate bs58;
+#[macro_use]
+extern crate serde_json;
+extern crate solana_sdk;
+extern crate tictactoe;
+
+use serde_json::Value;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, KeypairUtil};
+use solana_sdk::system_transaction::SystemTransaction;
+use solana_sdk::transaction::Transaction;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::{env, process, thread, time};
+use tictactoe::batch::decode_game;
+use tictactoe::demo;
+use tictactoe::game::Game;
+
+type DemoResult<T> = Result<T, Box<dyn Error>>;
+
+// Tokens airdropped to each player, X's paying for the dashboard that funds the moves
+const AIRDROP: u64 = 1000;
+const DASHBOARD_TOKENS: u64 = 500;
+// Bytes of userdata the program's accounts get, as the web client allocates them
+const ACCOUNT_SPACE: u64 = 255;
+
+// JSON-RPC over plain HTTP, enough for a local validator
+struct Rpc {
+    host: String,
+    next_id: u64,
+}
+
+impl Rpc {
+    fn new(url: &str) -> DemoResult<Rpc> {
+        let host = url.trim_start_matches("http://").trim_end_matches('/');
+        if host.is_empty() || host.contains('/') {
+            Err(format!(
+                "unsupported RPC URL {}, expected http://host:port",
+                url
+            ))?;
+        }
+        Ok(Rpc {
+            host: host.to_string(),
+            next_id: 1,
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> DemoResult<Value> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id,
+            "method": method,
+            "params": params,
+        })
+        .to_string();
+        self.next_id += 1;
+
+        let mut stream = TcpStream::connect(&self.host)?;
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.host,
+            request.len(),
+            request
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let split = response.find("\r\n\r\n").ok_or("malformed HTTP response")?;
+        let (head, body) = (&response[..split], &response[split + 4..]);
+        let body = if head
+            .to_ascii_lowercase()
+            .contains("transfer-encoding: chunked")
+        {
+            dechunk(body)?
+        } else {
+            body.to_string()
+        };
+
+        let mut reply: Value = serde_json::from_str(&body)?;
+        if let Some(err) = reply.get("error") {
+            Err(format!("{} failed: {}", method, err))?;
+        }
+        Ok(reply["result"].take())
+    }
+
+    fn last_id(&mut self) -> DemoResult<Hash> {
+        let encoded = self.call("getRecentBlockhash", json!([]))?;
+        let encoded = encoded.as_str().ok_or("blockhash is not a string")?;
+        let bytes = bs58::decode(encoded).into_vec()?;
+        if bytes.len() != 32 {
+            Err(format!("blockhash {} is not 32 bytes", encoded))?;
+        }
+        Ok(Hash::new(&bytes))
+    }
+
+    fn airdrop(&mut self, to: &Pubkey, tokens: u64) -> DemoResult<()> {
+        let signature = self.call("requestAirdrop", json!([to.to_string(), tokens]))?;
+        self.confirm("airdrop", &signature)
+    }
+
+    // Build `what` against a fresh blockhash, send it and wait for it to be confirmed
+    fn send<F>(&mut self, what: &str, build: F) -> DemoResult<()>
+    where
+        F: FnOnce(Hash) -> DemoResult<Transaction>,
+    {
+        let tx = build(self.last_id()?)?;
+        let signature = self.call("sendTransaction", json!([bincode::serialize(&tx)?]))?;
+        self.confirm(what, &signature)
+    }
+
+    fn confirm(&mut self, what: &str, signature: &Value) -> DemoResult<()> {
+        for _ in 0..60 {
+            let status = self.call("getSignatureStatus", json!([signature]))?;
+            match status.as_str() {
+                Some("Confirmed") => return Ok(()),
+                Some("SignatureNotFound") => thread::sleep(time::Duration::from_millis(500)),
+                _ => Err(format!("{} failed: {}", what, status))?,
+            }
+        }
+        Err(format!("{} was not confirmed", what))?
+    }
+
+    fn game(&mut self, game: &Pubkey) -> DemoResult<Game> {
+        let info = self.call("getAccountInfo", json!([game.to_string()]))?;
+        let userdata = info
+            .get("userdata")
+            .or_else(|| info.get("data"))
+            .ok_or("account info without userdata")?;
+        let userdata: Vec<u8> = serde_json::from_value(userdata.clone())?;
+        Ok(decode_game(&userdata)?)
+    }
+}
+
+// The body of a chunked HTTP response
+fn dechunk(mut chunked: &str) -> DemoResult<String> {
+    let mut body = String::new();
+    loop {
+        let line_end = chunked.find("\r\n").ok_or("malformed chunk")?;
+        let size = usize::from_str_radix(chunked[..line_end].trim(), 16)?;
+        if size == 0 {
+            return Ok(body);
+        }
+        let start = line_end + 2;
+        body.push_str(chunked.get(start..start + size).ok_or("truncated chunk")?);
+        chunked = chunked.get(start + size + 2..).ok_or("truncated chunk")?;
+    }
+}
+
+// Read the game back after `what`, show it and hold it against the local game
+fn check(rpc: &mut Rpc, what: &str, local: &Game, game: &Pubkey) -> DemoResult<()> {
+    let remote = rpc.game(game)?;
+    println!(
+        "after {}: {:?}\n{}\n",
+        what,
+        remote.game_state,
+        remote.display_board_with_coords()
+    );
+    demo::verify_step(local, &remote)?;
+    Ok(())
+}
+
+fn run(url: &str, program_id: Pubkey) -> DemoResult<()> {
+    let mut rpc = Rpc::new(url)?;
+    let players = [Keypair::new(), Keypair::new()];
+    let (player_x, player_o) = (players[0].pubkey(), players[1].pubkey());
+    let (dashboard, game, trophy) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let (dashboard_key, game_key) = (dashboard.pubkey(), game.pubkey());
+    println!("player X {}, player O {}", player_x, player_o);
+    for player in players.iter() {
+        rpc.airdrop(&player.pubkey(), AIRDROP)?;
+    }
+
+    // The players create the program's accounts while the system program still owns theirs,
+    // the program funds the game account itself
+    let accounts = [
+        (
+            "create dashboard",
+            &players[0],
+            dashboard_key,
+            DASHBOARD_TOKENS,
+        ),
+        ("create game", &players[1], game_key, 0),
+        ("create trophy", &players[1], trophy.pubkey(), 0),
+    ];
+    for &(what, from, to, tokens) in accounts.iter() {
+        rpc.send(what, |last_id| {
+            Ok(Transaction::system_create(
+                from,
+                to,
+                last_id,
+                tokens,
+                ACCOUNT_SPACE,
+                program_id,
+                0,
+            ))
+        })?;
+    }
+    rpc.send("init dashboard", |last_id| {
+        Ok(demo::init_dashboard_tx(&program_id, &dashboard, last_id)?)
+    })?;
+    for player in players.iter() {
+        rpc.send("assign player", |last_id| {
+            Ok(Transaction::system_assign(player, last_id, program_id, 0))
+        })?;
+        rpc.send("init player", |last_id| {
+            let tx = demo::init_player_tx(&program_id, &dashboard, &player.pubkey(), last_id)?;
+            Ok(tx)
+        })?;
+    }
+
+    rpc.send("init game", |last_id| {
+        let tx = demo::init_game_tx(&program_id, &game, &dashboard_key, &player_x, last_id)?;
+        Ok(tx)
+    })?;
+    let mut local = Game::try_create(&player_x, 1)?;
+    check(&mut rpc, "init game", &local, &game_key)?;
+    rpc.send("join", |last_id| {
+        Ok(demo::join_tx(
+            &program_id,
+            &players[1],
+            &dashboard_key,
+            &game_key,
+            last_id,
+        )?)
+    })?;
+    local.join(player_o, 2)?;
+    check(&mut rpc, "join", &local, &game_key)?;
+
+    for (i, &(x, y)) in demo::SCRIPT.iter().enumerate() {
+        let player = &players[i % 2];
+        let what = format!("move ({}, {})", x, y);
+        rpc.send(&what, |last_id| {
+            let tx = demo::move_tx(
+                &program_id,
+                player,
+                &dashboard_key,
+                &game_key,
+                (x, y),
+                last_id,
+            )?;
+            Ok(tx)
+        })?;
+        local.next_move(player.pubkey(), x as usize, y as usize)?;
+        check(&mut rpc, &what, &local, &game_key)?;
+    }
+
+    rpc.send("finalize", |last_id| {
+        Ok(demo::finalize_tx(
+            &program_id,
+            &players[0],
+            &dashboard_key,
+            &game_key,
+            &trophy.pubkey(),
+            last_id,
+        )?)
+    })?;
+    check(&mut rpc, "finalize", &local, &game_key)?;
+    println!("trophy {} awarded to player X", trophy.pubkey());
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} <rpc url> <program id>", args[0]);
+        process::exit(2);
+    }
+    let program_id = match bs58::decode(&args[2]).into_vec() {
+        Ok(ref bytes) if bytes.len() == 32 => Pubkey::new(bytes),
+        _ => {
+            eprintln!("invalid program id {}", args[2]);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = run(&args[1], program_id) {
+        eprintln!("demo failed: {}", err);
+        process::exit(1);
+    }
+    println!("demo passed");
+}
@@ -0,0 +1,121 @@
+// Plays a game between two players taking turns at one terminal, handy for reproducing a
+// position before taking it to the cluster:
+//
+//   cargo run --example play -- [--load <file>] [--save <file>]
+//
+// --load resumes a game saved earlier instead of starting a new one.  --save writes the game
+// to the file when the session is suspended with `q`, and again once the game is over
+extern crate solana_sdk;
+extern crate tictactoe;
+
+use solana_sdk::pubkey::Pubkey;
+use std::io::{self, BufRead, Write};
+use std::{env, process};
+use tictactoe::game::Game;
+
+struct Options {
+    load: Option<String>,
+    save: Option<String>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        load: None,
+        save: None,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let slot = match arg.as_str() {
+            "--load" => &mut options.load,
+            "--save" => &mut options.save,
+            _ => return Err(format!("unknown argument {}", arg)),
+        };
+        *slot = Some(args.next().ok_or(format!("{} needs a file", arg))?.clone());
+    }
+    Ok(options)
+}
+
+// "0x2" names column 0, row 2, numbered the way the board is printed
+fn parse_move(line: &str) -> Option<(usize, usize)> {
+    let line = line.as_bytes();
+    match line {
+        [x @ b'0'..=b'2', b'x', y @ b'0'..=b'2'] => {
+            Some((usize::from(x - b'0'), usize::from(y - b'0')))
+        }
+        _ => None,
+    }
+}
+
+fn save(game: &Game, path: &Option<String>) -> Result<(), String> {
+    if let Some(path) = path {
+        game.save_to(path)
+            .map_err(|err| format!("cannot save to {}: {}", path, err))?;
+        println!("game saved to {}", path);
+    }
+    Ok(())
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut game = match options.load {
+        Some(ref path) => {
+            Game::load_from(path).map_err(|err| format!("cannot load {}: {}", path, err))?
+        }
+        None => {
+            let mut game =
+                Game::try_create(&Pubkey::new(&[1; 32]), 1).map_err(|e| e.to_string())?;
+            game.join(Pubkey::new(&[2; 32]), 2)
+                .map_err(|err| err.to_string())?;
+            game
+        }
+    };
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    while let Some(player) = game.current_player() {
+        let mark = if player == *game.player_x() { 'X' } else { 'O' };
+        print!(
+            "\n{}\n{} to move, column and row (eg. 0x2) or q to suspend: ",
+            game.display_board_with_coords(),
+            mark
+        );
+        io::stdout().flush().map_err(|err| err.to_string())?;
+        let line = match lines.next() {
+            Some(line) => line.map_err(|err| err.to_string())?,
+            None => "q".to_string(),
+        };
+        if line.trim() == "q" {
+            return save(&game, &options.save);
+        }
+        match parse_move(line.trim()) {
+            Some((x, y)) => {
+                if let Err(err) = game.next_move(player, x, y) {
+                    println!("{}", err);
+                }
+            }
+            None => println!("Invalid response: {}", line.trim()),
+        }
+    }
+
+    println!(
+        "\nGame Over: {:?}\n{}",
+        game.game_state,
+        game.display_board_with_coords()
+    );
+    save(&game, &options.save)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let options = match parse_options(&args[1..]) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("usage: {} [--load <file>] [--save <file>]", args[0]);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = run(&options) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,401 @@
+use game::{
+    cell_coords, cell_index, cell_name, mark_to_move, outcome, Game, GameState, MoveQuality,
+    OpeningClass, Solver, TimeoutUnit, Variant, BOARD_ITEM_FREE, SYMMETRIES,
+};
+use sim::CompletedGame;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+
+// Results of the games that share an opening
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+pub struct OpeningBucket {
+    pub opening: String, // Canonical opening in Game::move_notation form
+    pub games: u32,      // Number of finished games with this opening
+    pub x_wins: u32,     // Games won by player X
+    pub draws: u32,      // Drawn games
+    pub o_wins: u32,     // Games won by player O
+    pub x_win_rate: f64, // x_wins / games
+    pub draw_rate: f64,  // draws / games
+    pub o_win_rate: f64, // o_wins / games
+}
+
+// Games bucketed by their canonical first move, and by their canonical first two moves
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+pub struct OpeningReport {
+    pub first_move: Vec<OpeningBucket>,
+    pub first_two_moves: Vec<OpeningBucket>,
+}
+
+// Board index of a cell in Game::move_notation form
+fn parse_cell(cell: &str) -> Option<usize> {
+    let bytes = cell.as_bytes();
+    match (bytes.first(), bytes.get(1), bytes.len()) {
+        (Some(&column @ b'a'..=b'c'), Some(&row @ b'1'..=b'3'), 2) => {
+            cell_index(usize::from(column - b'a'), usize::from(row - b'1')).ok()
+        }
+        _ => None,
+    }
+}
+
+// The smallest of the symmetric images of a move sequence, so that openings that only differ by
+// a rotation or reflection end up in the same bucket
+fn canonical_opening(moves: &[usize]) -> String {
+    let canonical = SYMMETRIES
+        .iter()
+        .map(|symmetry| moves.iter().map(|&i| symmetry[i]).collect::<Vec<_>>())
+        .min()
+        .unwrap_or_default();
+    canonical
+        .iter()
+        .map(|&i| cell_name(i))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn buckets(games: &[CompletedGame], depth: usize) -> Vec<OpeningBucket> {
+    let mut buckets: BTreeMap<String, OpeningBucket> = BTreeMap::new();
+    for game in games {
+        let moves: Option<Vec<usize>> = game.moves.split_whitespace().map(parse_cell).collect();
+        let moves = match moves {
+            Some(ref moves) if moves.len() >= depth => &moves[..depth],
+            _ => continue,
+        };
+        let opening = canonical_opening(moves);
+        let bucket = buckets
+            .entry(opening.clone())
+            .or_insert_with(|| OpeningBucket {
+                opening,
+                ..OpeningBucket::default()
+            });
+        match game.outcome {
+            GameState::XWon => bucket.x_wins += 1,
+            GameState::OWon => bucket.o_wins += 1,
+            GameState::Draw => bucket.draws += 1,
+            _ => continue, // Unfinished and aborted games have no result to report
+        }
+        bucket.games += 1;
+    }
+
+    let mut buckets: Vec<OpeningBucket> = buckets
+        .into_values()
+        .filter(|bucket| bucket.games > 0)
+        .collect();
+    for bucket in &mut buckets {
+        let games = f64::from(bucket.games);
+        bucket.x_win_rate = f64::from(bucket.x_wins) / games;
+        bucket.draw_rate = f64::from(bucket.draws) / games;
+        bucket.o_win_rate = f64::from(bucket.o_wins) / games;
+    }
+    buckets
+}
+
+pub fn opening_stats(games: &[CompletedGame]) -> OpeningReport {
+    OpeningReport {
+        first_move: buckets(games, 1),
+        first_two_moves: buckets(games, 2),
+    }
+}
+
+// Number of moves that made the game-theoretic value worse for the player making them, a
+// win thrown into a draw or loss, or a draw into a loss
+pub fn blunder_count(game: &Game, solver: &Solver) -> u8 {
+    let positions: Vec<Game> = game.iter_positions().collect();
+    let mut blunders = 0;
+    for pair in positions.windows(2) {
+        let before = match solver.value(&pair[0]) {
+            Some(value) => value,
+            None => continue,
+        };
+        let after = match pair[1].game_state {
+            GameState::XWon | GameState::OWon => 1,
+            GameState::Draw => 0,
+            _ => solver.value(&pair[1]).map_or(0, |value| -value),
+        };
+        if after < before {
+            blunders += 1;
+        }
+    }
+    blunders
+}
+
+// A finished game summed up for analytics dashboards.  Moves are counted from 0 in the order
+// they were made
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameStats {
+    pub total_moves: usize,                  // Moves made
+    pub x_mistakes: u8,                      // Moves by X that worsened X's optimal outcome
+    pub o_mistakes: u8,                      // The same for O
+    pub critical_moments: Vec<usize>,        // Moves where another move would have been worse
+    pub winner: Option<Pubkey>,              // None for draws and aborted games
+    pub duration_seconds: Option<u64>,       // Creation to end, for games timed in seconds
+    pub opening_class: Option<OpeningClass>, // Of the first move
+    pub first_threat_move: Option<usize>,    // First move leaving its player a line to win on
+    pub decisive_move: Option<usize>,        // The move that completed the winning line
+}
+
+// Sum up `game`.  Move quality takes the solver, which knows nothing of wild games, so those
+// have no mistakes or critical moments
+pub fn compute_stats(game: &Game) -> GameStats {
+    let mut stats = GameStats {
+        total_moves: game.move_count(),
+        winner: match game.game_state {
+            GameState::XWon => Some(*game.player_x()),
+            GameState::OWon => Some(*game.player_o()),
+            _ => None,
+        },
+        ..GameStats::default()
+    };
+    let timed_in_seconds = game.config().timeout_unit == TimeoutUnit::Seconds;
+    if timed_in_seconds && game.created_at != 0 && game.finished_at != 0 {
+        stats.duration_seconds = game.finished_at.checked_sub(game.created_at);
+    }
+
+    let moves: Vec<(usize, usize)> = game.moves[..game.move_count()]
+        .iter()
+        .filter_map(|&i| cell_coords(usize::from(i)).ok())
+        .collect();
+    stats.opening_class = moves.first().and_then(|&(x, y)| Game::opening_class(x, y));
+    let positions: Vec<Game> = game.iter_positions().collect();
+    for (n, (pair, &(x, y))) in positions.windows(2).zip(moves.iter()).enumerate() {
+        let (position, after) = (&pair[0], &pair[1]);
+        // The mark the move placed, which in a wild game need not be the mover's own
+        let mark = cell_index(x, y).map_or(BOARD_ITEM_FREE, |i| after.board[i]);
+        if game.config().variant == Variant::Classic {
+            if position.move_quality(x, y).ok() != Some(MoveQuality::Optimal) {
+                if position.game_state == GameState::XMove {
+                    stats.x_mistakes = stats.x_mistakes.saturating_add(1);
+                } else {
+                    stats.o_mistakes = stats.o_mistakes.saturating_add(1);
+                }
+            }
+            let critical = position
+                .available_moves()
+                .iter()
+                .any(|&(x, y)| position.move_quality(x, y).ok() != Some(MoveQuality::Optimal));
+            if critical {
+                stats.critical_moments.push(n);
+            }
+        }
+        if stats.first_threat_move.is_none() && !after.threats(mark).is_empty() {
+            stats.first_threat_move = Some(n);
+        }
+    }
+    if game.winning_line().is_some() {
+        stats.decisive_move = game.move_count().checked_sub(1);
+    }
+    stats
+}
+
+// Every board alternating classic play reaches from the empty one, with the moves between
+// them.  Nodes are in breadth first order, so the empty board is node 0 and a move always
+// leads to a later node
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameStateGraph {
+    pub nodes: Vec<[u8; 9]>,
+    pub edges: Vec<(usize, usize, (usize, usize))>, // (from node, to node, (x, y) of the move)
+}
+
+impl GameStateGraph {
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    // Boards the game is over on, won or drawn
+    pub fn leaf_count(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|board| outcome(Variant::Classic, &board[..]).is_some())
+            .count()
+    }
+
+    // Number of distinct games, the paths from the empty board to a finished one
+    pub fn game_count(&self) -> u64 {
+        let mut paths = vec![0u64; self.nodes.len()];
+        if !paths.is_empty() {
+            paths[0] = 1;
+        }
+        // Edges come out of the nodes in breadth first order, so every node has all of its
+        // paths counted before its own edges are followed
+        for &(from, to, _) in self.edges.iter() {
+            paths[to] += paths[from];
+        }
+        (0..self.nodes.len())
+            .filter(|&i| outcome(Variant::Classic, &self.nodes[i][..]).is_some())
+            .map(|i| paths[i])
+            .sum()
+    }
+}
+
+// Build the GameStateGraph by breadth first search from the empty board
+pub fn build_game_tree() -> GameStateGraph {
+    let mut graph = GameStateGraph {
+        nodes: vec![[BOARD_ITEM_FREE; 9]],
+        edges: vec![],
+    };
+    let mut index = HashMap::new();
+    index.insert(graph.nodes[0], 0);
+    let mut next = 0;
+    while next < graph.nodes.len() {
+        let board = graph.nodes[next];
+        if outcome(Variant::Classic, &board).is_none() {
+            let x_or_o = mark_to_move(&board);
+            for cell in 0..board.len() {
+                if board[cell] != BOARD_ITEM_FREE {
+                    continue;
+                }
+                let mut child = board;
+                child[cell] = x_or_o;
+                let to = *index.entry(child).or_insert_with(|| {
+                    graph.nodes.push(child);
+                    graph.nodes.len() - 1
+                });
+                graph.edges.push((next, to, cell_coords(cell).unwrap()));
+            }
+        }
+        next += 1;
+    }
+    graph
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::{cell_coords, play, Cell, GameConfig, Hint, POSITION_COUNT};
+    use serde_json;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn completed(moves: &str, outcome: GameState) -> CompletedGame {
+        CompletedGame {
+            player_x: "x".to_string(),
+            player_o: "o".to_string(),
+            outcome,
+            moves: moves.to_string(),
+            move_count: moves.split_whitespace().count() as u8,
+            seed: 0,
+        }
+    }
+
+    #[test]
+    pub fn opening_stats() {
+        let games = vec![
+            completed("a1 b2 c3 c1 a3 b3 b1 a2 c2", GameState::Draw),
+            completed("c3 b2 a1 a3 c1 b1 b3 c2 a2", GameState::Draw),
+            completed("a3 c1 c3 b2 a1", GameState::XWon),
+            completed("b2 b1 a1 c3 c1", GameState::XWon),
+            completed("b1 b2 a1 c3", GameState::Aborted),
+            completed("c2", GameState::XMove),
+        ];
+        let report = super::opening_stats(&games);
+
+        // All three corner openings land in the same bucket
+        let first: Vec<_> = report
+            .first_move
+            .iter()
+            .map(|bucket| (bucket.opening.as_str(), bucket.games))
+            .collect();
+        assert_eq!(first, vec![("a1", 3), ("b2", 1)]);
+        let corner = &report.first_move[0];
+        assert_eq!((corner.x_wins, corner.draws, corner.o_wins), (1, 2, 0));
+        assert!((corner.draw_rate - 2.0 / 3.0).abs() < 1e-9);
+
+        let two: Vec<_> = report
+            .first_two_moves
+            .iter()
+            .map(|bucket| (bucket.opening.as_str(), bucket.games))
+            .collect();
+        assert_eq!(two, vec![("a1 b2", 2), ("a1 c3", 1), ("b2 b1", 1)]);
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.starts_with(r#"{"first_move":[{"opening":"a1","games":3,"x_wins":1,"#));
+    }
+
+    #[test]
+    pub fn blunder_count() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let solver = Solver::new();
+        let mut g = Game::new(player_x, player_o);
+
+        g.next_move(player_x, 1, 1).unwrap();
+        assert_eq!(super::blunder_count(&g, &solver), 0);
+
+        // An edge reply to the center loses for O
+        g.next_move(player_o, 1, 0).unwrap();
+        while let Some(player) = g.current_player() {
+            let (x, y) = match g.hint() {
+                Hint::WinNow(x, y) | Hint::BlockNow(x, y) | Hint::Optimal(x, y) => (x, y),
+                Hint::NoHint => unreachable!(),
+            };
+            g.next_move(player, x, y).unwrap();
+        }
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(super::blunder_count(&g, &solver), 1);
+    }
+
+    #[test]
+    pub fn build_game_tree() {
+        let graph = super::build_game_tree();
+        assert_eq!(graph.node_count(), POSITION_COUNT);
+        assert_eq!(graph.nodes[0], [BOARD_ITEM_FREE; 9]);
+        assert_eq!(graph.leaf_count(), 958);
+        assert_eq!(graph.game_count(), 255_168);
+        assert_eq!(graph.edge_count(), 16_167);
+
+        // The nine openings, each to its own board
+        let openings: Vec<_> = graph.edges.iter().take_while(|edge| edge.0 == 0).collect();
+        assert_eq!(openings.len(), 9);
+        assert_eq!(*openings[4], (0, 5, (1, 1)));
+        assert!(graph.edges.iter().all(|&(from, to, _)| from < to));
+    }
+
+    #[test]
+    pub fn compute_stats() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::create_with_config(&player_x, GameConfig::default(), 100).unwrap();
+        g.join(player_o, 101).unwrap();
+        // X plays on in the corner instead of blocking O's column, and O completes it
+        for &i in [0, 4, 8, 1, 2, 7].iter() {
+            let (x, y) = cell_coords(i).unwrap();
+            let player = g.current_player().unwrap();
+            g.next_move(player, x, y).unwrap();
+        }
+        g.set_finished_at(160);
+
+        let stats = super::compute_stats(&g);
+        assert_eq!(stats.total_moves, 6);
+        assert_eq!((stats.x_mistakes, stats.o_mistakes), (1, 0));
+        assert!(stats.critical_moments.contains(&4));
+        assert_eq!(stats.winner, Some(player_o));
+        assert_eq!(stats.duration_seconds, Some(60));
+        assert_eq!(stats.opening_class, Some(OpeningClass::Corner));
+        assert_eq!(stats.first_threat_move, Some(3));
+        assert_eq!(stats.decisive_move, Some(5));
+
+        // A draw has no winner and no decisive move
+        let g = play(&[4, 0, 2, 6, 3, 5, 1, 7, 8]);
+        let stats = super::compute_stats(&g);
+        assert_eq!(g.game_state, GameState::Draw);
+        assert_eq!((stats.winner, stats.decisive_move), (None, None));
+        assert_eq!(stats.duration_seconds, None);
+        assert_eq!(stats.opening_class, Some(OpeningClass::Center));
+
+        // In a wild game the threat is of the mark placed, not that of the mover's seat
+        let config = GameConfig {
+            variant: Variant::Wild,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 100).unwrap();
+        g.join(player_o, 101).unwrap();
+        for &(i, mark) in [(0, Cell::O), (4, Cell::X), (1, Cell::O)].iter() {
+            let (x, y) = cell_coords(i).unwrap();
+            let player = g.current_player().unwrap();
+            g.next_move_wild(player, x, y, mark).unwrap();
+        }
+        assert_eq!(super::compute_stats(&g).first_threat_move, Some(2));
+    }
+}
@@ -0,0 +1,152 @@
+use game::{cell_index, Game, BOARD_ITEM_FREE, BOARD_ITEM_O, BOARD_ITEM_X};
+use gif::{Encoder, Frame, Repeat};
+use result::{ProgramError, Result};
+use std::borrow::Cow;
+
+// Palette indices of the rendered board
+const BACKGROUND: u8 = 0;
+const GRID: u8 = 1;
+const MARK_X: u8 = 2;
+const MARK_O: u8 = 3;
+const PALETTE: [u8; 12] = [
+    0xff, 0xff, 0xff, // Background, white
+    0x00, 0x00, 0x00, // Grid, black
+    0xd0, 0x20, 0x20, // X, red
+    0x20, 0x40, 0xd0, // O, blue
+];
+
+// Palette index of pixel (u, v) of a cell holding `item`
+fn cell_pixel(item: u8, u: u32, v: u32, cell_size: u32) -> u8 {
+    let thickness = (cell_size / 10).max(1);
+    let margin = cell_size / 5;
+    let inside = |p: u32| p >= margin && p + margin < cell_size;
+    match item {
+        BOARD_ITEM_X if inside(u) && inside(v) => {
+            let (u, v) = (i64::from(u), i64::from(v));
+            let last = i64::from(cell_size) - 1;
+            if (u - v).abs() <= i64::from(thickness) / 2
+                || (u + v - last).abs() <= i64::from(thickness) / 2
+            {
+                MARK_X
+            } else {
+                BACKGROUND
+            }
+        }
+        BOARD_ITEM_O => {
+            let center = (cell_size as f64 - 1.0) / 2.0;
+            let radius = cell_size as f64 / 2.0 - margin as f64;
+            let distance = ((u as f64 - center).powi(2) + (v as f64 - center).powi(2)).sqrt();
+            if distance <= radius && distance > radius - thickness as f64 {
+                MARK_O
+            } else {
+                BACKGROUND
+            }
+        }
+        _ => BACKGROUND,
+    }
+}
+
+// Indexed pixels of the board of `game`, (3 * cell_size) pixels square
+fn rasterize(game: &Game, cell_size: u32) -> Vec<u8> {
+    let size = 3 * cell_size;
+    let thickness = (cell_size / 20).max(1);
+    let is_grid = |p: u32| p >= cell_size && p % cell_size < thickness;
+    let mut pixels = Vec::with_capacity((size * size) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            pixels.push(if is_grid(x) || is_grid(y) {
+                GRID
+            } else {
+                let (column, row) = ((x / cell_size) as usize, (y / cell_size) as usize);
+                let item = cell_index(column, row).map_or(BOARD_ITEM_FREE, |i| game.board()[i]);
+                cell_pixel(item, x % cell_size, y % cell_size, cell_size)
+            });
+        }
+    }
+    pixels
+}
+
+// Animated GIF of the game so far, one frame per position starting from the empty board
+pub fn to_animated_gif(game: &Game, cell_size: u32, frame_delay_ms: u32) -> Result<Vec<u8>> {
+    let size = 3 * u64::from(cell_size);
+    if cell_size == 0 || size > u64::from(u16::MAX) {
+        Err(ProgramError::InvalidInput)?;
+    }
+    let size = size as u16;
+    // GIF frame delays are in hundredths of a second
+    let delay = (frame_delay_ms / 10).min(u32::from(u16::MAX)) as u16;
+
+    let mut encoder =
+        Encoder::new(Vec::new(), size, size, &PALETTE).map_err(|_| ProgramError::InvalidInput)?;
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .map_err(|_| ProgramError::InvalidInput)?;
+    for position in game.iter_positions() {
+        let frame = Frame {
+            width: size,
+            height: size,
+            delay,
+            buffer: Cow::Owned(rasterize(&position, cell_size)),
+            ..Frame::default()
+        };
+        encoder
+            .write_frame(&frame)
+            .map_err(|_| ProgramError::InvalidInput)?;
+    }
+    encoder
+        .into_inner()
+        .map_err(|_| ProgramError::InvalidInput.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use gif::DecodeOptions;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    pub fn to_animated_gif() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::create(&player_x);
+        g.join(player_o, 1).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        g.next_move(player_o, 0, 0).unwrap();
+        g.next_move(player_x, 2, 0).unwrap();
+
+        let bytes = super::to_animated_gif(&g, 30, 500).unwrap();
+        assert!(!bytes.is_empty());
+        assert_eq!(&bytes[..6], b"GIF89a");
+
+        let mut decoder = DecodeOptions::new().read_info(&bytes[..]).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (90, 90));
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 50);
+            frames += 1;
+        }
+        assert_eq!(frames, g.move_count() + 1);
+
+        assert!(super::to_animated_gif(&g, 0, 500).is_err());
+        assert!(super::to_animated_gif(&g, 30_000, 500).is_err());
+    }
+
+    #[test]
+    pub fn rasterize() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::create(&player_x);
+        g.join(player_o, 1).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        g.next_move(player_o, 0, 0).unwrap();
+
+        let pixels = super::rasterize(&g, 20);
+        let at = |x: usize, y: usize| pixels[y * 60 + x];
+        assert_eq!(at(20, 5), GRID);
+        assert_eq!(at(30, 30), MARK_X); // Center of the X in the center cell
+        assert_eq!(at(10, 10), BACKGROUND); // Center of the O in the top left cell
+        assert!(pixels[..20].contains(&BACKGROUND));
+        assert!((0..20).any(|y| (0..20).any(|x| at(x, y) == MARK_O)));
+        assert!(!(41..60).any(|y| (41..60).any(|x| at(x, y) != BACKGROUND)));
+    }
+}
@@ -0,0 +1,299 @@
+use game::GameState;
+use record::FinalizedGame;
+use result::{ProgramError, Result};
+use solana_sdk::pubkey::Pubkey;
+
+// The digest of a finished game the archive keeps
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ArchiveEntry {
+    pub game: Pubkey,       // Game account
+    pub outcome: GameState, // XWon, OWon, Draw or Aborted
+    pub finished_at: u64,   // Time the game ended, 0 if unknown
+}
+
+impl ArchiveEntry {
+    // Serialized size of an ArchiveEntry
+    pub const LEN: usize = 32 + 4 + 8;
+
+    pub fn from_record(record: &FinalizedGame) -> ArchiveEntry {
+        ArchiveEntry {
+            game: record.game,
+            outcome: record.outcome,
+            finished_at: record.finished_at,
+        }
+    }
+}
+
+// Append-only list of the games a dashboard finalized, for deployments that want more history
+// than the dashboard's last few completed games.  The account is as large as its deployment
+// wants, and the capacity it was created with says how many entries fit.  The archive names
+// its dashboard rather than the other way round, as the dashboard has no room left in its
+// account for another key, only for the flag saying it has an archive
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Archive {
+    dashboard: Pubkey,          // Dashboard the archive belongs to
+    capacity: u32,              // Entries the account has room for
+    entries: Vec<ArchiveEntry>, // Oldest first
+}
+
+impl Archive {
+    // Bytes of an archive account ahead of the entries: the State tag, the dashboard, the
+    // capacity and the length of the entries
+    pub const HEADER_LEN: usize = 4 + 32 + 4 + 8;
+
+    // An empty archive filling an account of `space` bytes
+    pub fn new(dashboard: &Pubkey, space: usize) -> Archive {
+        let capacity = space.saturating_sub(Archive::HEADER_LEN) / ArchiveEntry::LEN;
+        Archive {
+            dashboard: *dashboard,
+            capacity: capacity.min(u32::MAX as usize) as u32,
+            entries: vec![],
+        }
+    }
+
+    pub fn dashboard(&self) -> &Pubkey {
+        &self.dashboard
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    // Add the entry of a finalized game.  Finalizing a game again does not add it twice, and
+    // a full archive fails with ArchiveFull
+    pub fn append(&mut self, entry: ArchiveEntry) -> Result<()> {
+        if self.entries.contains(&entry) {
+            return Ok(());
+        }
+        if self.entries.len() >= self.capacity() {
+            Err(ProgramError::ArchiveFull)?;
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    // The `page`th run of `page_size` entries, oldest first.  The last page may be short, and
+    // pages past it are empty
+    pub fn page(&self, page: usize, page_size: usize) -> &[ArchiveEntry] {
+        let start = page.saturating_mul(page_size).min(self.entries.len());
+        let end = start.saturating_add(page_size).min(self.entries.len());
+        &self.entries[start..end]
+    }
+
+    pub fn page_count(&self, page_size: usize) -> usize {
+        match page_size {
+            0 => 0,
+            _ => self.entries.len().div_ceil(page_size),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bincode;
+    use dashboard::Dashboard;
+    use game::{play, Game};
+    use program_command::Command;
+    use program_state::State;
+    use simple_serde::SimpleSerde;
+    use solana_sdk::account::{Account, KeyedAccount};
+    use std::mem::size_of;
+
+    fn entry(i: u8) -> ArchiveEntry {
+        ArchiveEntry {
+            game: Pubkey::new(&[i; 32]),
+            outcome: GameState::Draw,
+            finished_at: u64::from(i),
+        }
+    }
+
+    #[test]
+    pub fn layout() {
+        assert_eq!(
+            bincode::serialize(&entry(1)).unwrap().len(),
+            ArchiveEntry::LEN
+        );
+        let mut archive = Archive::new(&Pubkey::new(&[9; 32]), Archive::HEADER_LEN + 100);
+        assert_eq!(archive.capacity(), 2);
+        let empty = bincode::serialize(&State::Archive(archive.clone())).unwrap();
+        assert_eq!(empty.len(), Archive::HEADER_LEN);
+        archive.append(entry(1)).unwrap();
+        archive.append(entry(2)).unwrap();
+        let full = bincode::serialize(&State::Archive(archive)).unwrap();
+        assert_eq!(full.len(), Archive::HEADER_LEN + 2 * ArchiveEntry::LEN);
+    }
+
+    #[test]
+    pub fn pagination() {
+        let mut archive = Archive::new(
+            &Pubkey::default(),
+            Archive::HEADER_LEN + 7 * ArchiveEntry::LEN,
+        );
+        for i in 0..7 {
+            archive.append(entry(i)).unwrap();
+        }
+        assert_eq!(archive.page_count(3), 3);
+        assert_eq!(archive.page(0, 3), &[entry(0), entry(1), entry(2)]);
+        assert_eq!(archive.page(1, 3), &[entry(3), entry(4), entry(5)]);
+        // The last page runs up to the end of the archive and no further
+        assert_eq!(archive.page(2, 3), &[entry(6)]);
+        assert!(archive.page(3, 3).is_empty());
+        assert!(archive.page(usize::MAX, 3).is_empty());
+        assert_eq!(archive.page_count(0), 0);
+
+        // Full: appending again is a no-op, anything new is refused
+        archive.append(entry(6)).unwrap();
+        let err = archive.append(entry(7)).unwrap_err();
+        assert_eq!(err.kind, ProgramError::ArchiveFull);
+        assert_eq!(archive.len(), 7);
+    }
+
+    // A dashboard and a game one move away from a draw, optionally followed by an archive
+    // account of `archive_space` bytes
+    struct Deployment {
+        accounts: Vec<(Pubkey, Account)>,
+    }
+
+    impl Deployment {
+        fn new(archive_space: Option<usize>) -> Deployment {
+            let program_id = Pubkey::new(&[9; 32]);
+            let account = |tokens, space| Account::new(tokens, space, program_id);
+            let player_x = Pubkey::new(&[1; 32]);
+            let mut accounts = vec![
+                (player_x, account(0, 0)),
+                (Pubkey::new(&[3; 32]), account(100, size_of::<State>())),
+                (Pubkey::new(&[4; 32]), account(0, size_of::<State>())),
+            ];
+            if let Some(space) = archive_space {
+                accounts.push((Pubkey::new(&[7; 32]), account(0, space)));
+            }
+            let game = play(&[0, 4, 8, 2, 6, 3, 5, 7]);
+            State::Dashboard(Dashboard::default())
+                .serialize(&mut accounts[1].1.userdata)
+                .unwrap();
+            State::Game(game)
+                .serialize(&mut accounts[2].1.userdata)
+                .unwrap();
+            Deployment { accounts }
+        }
+
+        // Run `command` with the accounts at `indices`, the first of them signing
+        fn process(&mut self, command: Command, indices: &[usize]) -> Result<()> {
+            let mut data = vec![0; size_of::<Command>()];
+            command.serialize(&mut data).unwrap();
+            let mut info: Vec<KeyedAccount> = self
+                .accounts
+                .iter_mut()
+                .enumerate()
+                .filter(|&(i, _)| indices.contains(&i))
+                .map(|(i, &mut (ref key, ref mut account))| {
+                    KeyedAccount::new(key, i == indices[0], account)
+                })
+                .collect();
+            ::process_instruction(&mut info, &data, 1)
+        }
+
+        fn archive(&self) -> Option<Archive> {
+            match State::deserialize(&self.accounts.get(3)?.1.userdata) {
+                Ok(State::Archive(archive)) => Some(archive),
+                _ => None,
+            }
+        }
+
+        fn game(&self) -> Game {
+            match State::deserialize(&self.accounts[2].1.userdata) {
+                Ok(State::Game(game)) => game,
+                state => panic!("no game: {:?}", state),
+            }
+        }
+
+        // X draws the game with the accounts at `indices`
+        fn finish(&mut self, indices: &[usize]) -> Result<()> {
+            self.process(Command::Move(1, 0), indices)
+        }
+    }
+
+    #[test]
+    pub fn write_through_on_finish() {
+        let mut deployment = Deployment::new(Some(Archive::HEADER_LEN + 10 * ArchiveEntry::LEN));
+        deployment.process(Command::InitArchive, &[1, 3]).unwrap();
+        let archive = deployment.archive().unwrap();
+        assert_eq!(archive.capacity(), 10);
+        assert_eq!(archive.dashboard(), &Pubkey::new(&[3; 32]));
+
+        // Once the dashboard has an archive, finishing a game without it fails
+        let err = deployment.finish(&[0, 1, 2]).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidInput);
+        assert_eq!(deployment.game().game_state, GameState::XMove);
+
+        deployment.finish(&[0, 1, 2, 3]).unwrap();
+        let game = deployment.game();
+        assert_eq!(game.game_state, GameState::Draw);
+        assert_eq!(
+            deployment.archive().unwrap().entries(),
+            &[ArchiveEntry {
+                game: Pubkey::new(&[4; 32]),
+                outcome: GameState::Draw,
+                finished_at: game.finished_at,
+            }]
+        );
+
+        // A dashboard has one archive, even in a fresh account
+        assert!(deployment.process(Command::InitArchive, &[1, 3]).is_err());
+        deployment.accounts[3].1.userdata = vec![0; size_of::<State>()];
+        assert!(deployment.process(Command::InitArchive, &[1, 3]).is_err());
+
+        // and it is the only one the dashboard writes to
+        let mut deployment = Deployment::new(Some(size_of::<State>()));
+        deployment.process(Command::InitArchive, &[1, 3]).unwrap();
+        State::Archive(Archive::new(&Pubkey::new(&[8; 32]), size_of::<State>()))
+            .serialize(&mut deployment.accounts[3].1.userdata)
+            .unwrap();
+        assert_eq!(
+            deployment.finish(&[0, 1, 2, 3]).unwrap_err().kind,
+            ProgramError::InvalidInput
+        );
+    }
+
+    #[test]
+    pub fn full_archive_falls_back_to_the_dashboard() {
+        let mut deployment = Deployment::new(Some(size_of::<State>()));
+        deployment.process(Command::InitArchive, &[1, 3]).unwrap();
+        let mut archive = deployment.archive().unwrap();
+        let capacity = archive.capacity();
+        for i in 0..capacity {
+            archive.append(entry(10 + i as u8)).unwrap();
+        }
+        State::Archive(archive.clone())
+            .serialize(&mut deployment.accounts[3].1.userdata)
+            .unwrap();
+
+        deployment.finish(&[0, 1, 2, 3]).unwrap();
+        assert_eq!(deployment.game().game_state, GameState::Draw);
+        assert_eq!(deployment.archive().unwrap(), archive);
+    }
+
+    #[test]
+    pub fn dashboard_without_an_archive() {
+        let mut deployment = Deployment::new(None);
+        deployment.finish(&[0, 1, 2]).unwrap();
+
+        // Nor does an archive account passed along get written to
+        let mut deployment = Deployment::new(Some(size_of::<State>()));
+        deployment.finish(&[0, 1, 2, 3]).unwrap();
+        assert_eq!(deployment.archive(), None);
+    }
+}
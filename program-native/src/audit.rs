@@ -0,0 +1,274 @@
+use game::{cell_index, cell_name, Game, GameState};
+use program_command::Command;
+use result::{ProgramError, Result};
+use simple_serde::SimpleSerde;
+use solana_sdk::pubkey::Pubkey;
+
+// One instruction against a game account as the ledger shows it
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttestedAction {
+    pub signer: Pubkey,       // Key that signed the transaction
+    pub instruction: Vec<u8>, // Instruction data, a serialized Command
+    pub timestamp: u64,       // Time of the transaction
+}
+
+// What is wrong with an attested action
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Discrepancy {
+    Undecodable,               // The instruction data is no command
+    WrongSeat(Pubkey),         // A move signed by someone else than the player to move, given
+    DoubleMove,                // The player who made the previous move moved again
+    IllegalMove(ProgramError), // The command fails in the replayed position
+    TimestampRegressed(u64),   // Older than the action before it, whose timestamp is given
+    ExtraMove,                 // A move past the end of the recorded history
+    MissingMoves(usize),       // The game records this many moves more than were attested
+    HashMismatch,              // The replayed board stops matching the recorded one
+}
+
+// A discrepancy and the index of the attested action it was found at.  Discrepancies of the
+// history as a whole are at the index past the last action
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Finding {
+    pub index: usize,
+    pub discrepancy: Discrepancy,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AuditReport {
+    pub moves_checked: usize,   // Attested moves replayed
+    pub replayed: String,       // The replayed moves in Game::move_notation form
+    pub findings: Vec<Finding>, // In the order of the actions
+}
+
+impl AuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    fn flag(&mut self, index: usize, discrepancy: Discrepancy) {
+        self.findings.push(Finding { index, discrepancy });
+    }
+}
+
+// Replay the actions attested for `game`, from its join on, against the history the game
+// records.  Moves are credited to the player to move whoever signed them, so one bad record
+// does not hide the ones after it.  Fails only for a game no one joined yet
+pub fn verify_history(game: &Game, attested: &[AttestedAction]) -> Result<AuditReport> {
+    let mut position = game.initial_position()?;
+    position.config = game.config;
+    if attested
+        .iter()
+        .any(|action| Command::deserialize(&action.instruction) == Ok(Command::Swap))
+    {
+        // The swap puts the seats the game ends with in place, replay from the ones before it
+        std::mem::swap(&mut position.player_x, &mut position.player_o);
+    }
+
+    let mut report = AuditReport::default();
+    let mut last_timestamp = game.created_at;
+    let mut last_mover = None;
+    let mut diverged = false;
+    let mut moves = vec![];
+    for (index, action) in attested.iter().enumerate() {
+        if action.timestamp < last_timestamp {
+            report.flag(index, Discrepancy::TimestampRegressed(last_timestamp));
+        }
+        last_timestamp = last_timestamp.max(action.timestamp);
+
+        let command = match Command::deserialize(&action.instruction) {
+            Ok(command) => command,
+            Err(_) => {
+                report.flag(index, Discrepancy::Undecodable);
+                continue;
+            }
+        };
+        let (x, y, mark) = match command {
+            Command::Move(x, y) => (x as usize, y as usize, None),
+            Command::MoveWild(x, y, mark) => (x as usize, y as usize, Some(mark)),
+            Command::Swap => {
+                if let Err(err) = position.swap_seats(action.signer) {
+                    report.flag(index, Discrepancy::IllegalMove(err.kind));
+                }
+                continue;
+            }
+            _ => continue, // Only moves and the swap make the history
+        };
+
+        let player = match position.current_player() {
+            Some(player) => player,
+            None => {
+                report.flag(index, Discrepancy::IllegalMove(ProgramError::GameFinished));
+                continue;
+            }
+        };
+        if action.signer != player {
+            let solo = position.player_x == position.player_o;
+            if !solo && last_mover == Some(action.signer) {
+                report.flag(index, Discrepancy::DoubleMove);
+            } else {
+                report.flag(index, Discrepancy::WrongSeat(player));
+            }
+        }
+        let result = match mark {
+            Some(mark) => position.next_move_wild(player, x, y, mark),
+            None => position.next_move(player, x, y),
+        };
+        if let Err(err) = result {
+            report.flag(index, Discrepancy::IllegalMove(err.kind));
+            continue;
+        }
+        last_mover = Some(action.signer);
+        moves.extend(cell_index(x, y).map(cell_name));
+        report.moves_checked += 1;
+
+        // Every replayed position has to be the one the recorded history went through
+        if report.moves_checked > game.move_count() {
+            report.flag(index, Discrepancy::ExtraMove);
+        }
+        let recorded = game.at_move(report.moves_checked.min(game.move_count()))?;
+        if !diverged && position.board_hash() != recorded.board_hash() {
+            report.flag(index, Discrepancy::HashMismatch);
+            diverged = true;
+        }
+    }
+
+    let end = attested.len();
+    if report.moves_checked < game.move_count() {
+        let missing = game.move_count() - report.moves_checked;
+        report.flag(end, Discrepancy::MissingMoves(missing));
+    }
+    let finished =
+        game.game_state != GameState::Aborted && game.is_finished() && game.final_board_hash != 0;
+    let final_hash_differs = finished && game.final_board_hash != position.board_hash() as u32;
+    if !diverged && (position.board_hash() != game.board_hash() || final_hash_differs) {
+        report.flag(end, Discrepancy::HashMismatch);
+    }
+    report.replayed = moves.join(" ");
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::mem::size_of;
+
+    fn action(signer: Pubkey, command: Command, timestamp: u64) -> AttestedAction {
+        let mut instruction = vec![0; size_of::<Command>()];
+        command.serialize(&mut instruction).unwrap();
+        AttestedAction {
+            signer,
+            instruction,
+            timestamp,
+        }
+    }
+
+    // X wins the left column, every move a second after the one before
+    fn played() -> (Game, Vec<AttestedAction>) {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut game = Game::new(player_x, player_o);
+        let mut attested = vec![action(player_o, Command::Join, 1)];
+        for (i, &(x, y)) in [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)].iter().enumerate() {
+            let player = game.current_player().unwrap();
+            game.next_move(player, x, y).unwrap();
+            attested.push(action(
+                player,
+                Command::Move(x as u8, y as u8),
+                i as u64 + 2,
+            ));
+        }
+        assert_eq!(game.game_state, GameState::XWon);
+        (game, attested)
+    }
+
+    #[test]
+    pub fn clean_game_passes() {
+        let (game, attested) = played();
+        let report = verify_history(&game, &attested).unwrap();
+        assert!(report.is_clean(), "{:?}", report.findings);
+        assert_eq!(report.moves_checked, 5);
+        assert_eq!(report.replayed, game.move_notation());
+
+        // A game no one joined has no history to audit
+        let waiting = Game::create(game.player_x());
+        assert!(verify_history(&waiting, &[]).is_err());
+    }
+
+    #[test]
+    pub fn swapped_signer_is_flagged() {
+        let (game, mut attested) = played();
+        let player_x = *game.player_x();
+
+        // O's first move credited to X, who moved just before
+        attested[2].signer = player_x;
+        // X's second move signed by someone outside the game
+        attested[3].signer = Pubkey::new(&[3; 32]);
+        // The last move stamped before the one it follows
+        attested[5].timestamp = 0;
+
+        let report = verify_history(&game, &attested).unwrap();
+        assert_eq!(
+            report.findings,
+            vec![
+                Finding {
+                    index: 2,
+                    discrepancy: Discrepancy::DoubleMove,
+                },
+                Finding {
+                    index: 3,
+                    discrepancy: Discrepancy::WrongSeat(player_x),
+                },
+                Finding {
+                    index: 5,
+                    discrepancy: Discrepancy::TimestampRegressed(5),
+                },
+            ]
+        );
+        // The board itself is what the game recorded
+        assert_eq!(report.moves_checked, 5);
+    }
+
+    #[test]
+    pub fn forged_move_breaks_the_hash_chain() {
+        let (game, mut attested) = played();
+        let player_o = *game.player_o();
+
+        // A move by O slipped in before X's winning one
+        attested.insert(5, action(player_o, Command::Move(2, 2), 5));
+        let report = verify_history(&game, &attested).unwrap();
+        assert!(report.findings.contains(&Finding {
+            index: 5,
+            discrepancy: Discrepancy::DoubleMove,
+        }));
+        assert!(report.findings.contains(&Finding {
+            index: 5,
+            discrepancy: Discrepancy::HashMismatch,
+        }));
+        assert!(report.findings.contains(&Finding {
+            index: 6,
+            discrepancy: Discrepancy::ExtraMove,
+        }));
+
+        // Garbage where a command should be, and the history cut short
+        let (game, mut attested) = played();
+        attested[5].instruction = vec![0xff; 4];
+        let report = verify_history(&game, &attested).unwrap();
+        assert_eq!(
+            report.findings,
+            vec![
+                Finding {
+                    index: 5,
+                    discrepancy: Discrepancy::Undecodable,
+                },
+                Finding {
+                    index: 6,
+                    discrepancy: Discrepancy::MissingMoves(1),
+                },
+                Finding {
+                    index: 6,
+                    discrepancy: Discrepancy::HashMismatch,
+                },
+            ]
+        );
+    }
+}
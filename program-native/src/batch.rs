@@ -0,0 +1,170 @@
+use game::{Game, GameState, PlayerLiveness};
+use program_state::State;
+use result::{ProgramError, Result};
+use simple_serde::SimpleSerde;
+use solana_sdk::pubkey::Pubkey;
+use watch_list::WatchList;
+
+// What a dashboard lists about a game
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameSummary {
+    pub game: Pubkey,                     // Game account
+    pub game_state: GameState,            // Current state of the game
+    pub player_x: Pubkey,                 // Player who initialized the game
+    pub player_o: Option<Pubkey>,         // Player who joined the game, None while Waiting
+    pub move_count: usize,                // Marks on the board
+    pub empty_cells: u8,                  // Free cells left
+    pub progress: (u8, u8),               // Moves made and the most the variant allows
+    pub board_full: bool,                 // No free cells left
+    pub frozen: bool,                     // Play is stopped for review
+    pub watchers: u64,                    // Spectators, zero unless a watch list was added
+    pub liveness: Option<PlayerLiveness>, // Timeout countdown, None unless one was added
+}
+
+impl GameSummary {
+    pub fn new(game_pubkey: &Pubkey, game: &Game) -> GameSummary {
+        GameSummary {
+            game: *game_pubkey,
+            game_state: game.game_state,
+            player_x: *game.player_x(),
+            player_o: match game.game_state {
+                GameState::Waiting => None,
+                _ => Some(*game.player_o()),
+            },
+            move_count: game.move_count(),
+            empty_cells: game.empty_cells(),
+            progress: game.progress(),
+            board_full: game.is_board_full(),
+            frozen: game.is_frozen(),
+            watchers: 0,
+            liveness: None,
+        }
+    }
+
+    // Show the countdown from Game::liveness_status
+    pub fn with_liveness(mut self, liveness: PlayerLiveness) -> GameSummary {
+        self.liveness = Some(liveness);
+        self
+    }
+
+    pub fn with_watch_list(mut self, watch_list: &WatchList) -> GameSummary {
+        self.watchers = watch_list.count();
+        self
+    }
+}
+
+// The game held by the userdata of an account.  Anything else, including a game that could
+// not have been played, is an InvalidUserdata error
+pub fn decode_game(userdata: &[u8]) -> Result<Game> {
+    match State::deserialize(userdata)? {
+        State::Game(game) => {
+            game.validate().map_err(|_| ProgramError::InvalidUserdata)?;
+            Ok(game)
+        }
+        _ => Err(ProgramError::InvalidUserdata.into()),
+    }
+}
+
+// Decode every account on its own, in the order given, so one bad account only fails its
+// own entry
+pub fn decode_games<'a>(
+    accounts: impl Iterator<Item = (&'a Pubkey, &'a [u8])>,
+) -> Vec<(Pubkey, Result<Game>)> {
+    accounts
+        .map(|(pubkey, userdata)| (*pubkey, decode_game(userdata)))
+        .collect()
+}
+
+// Summaries of the accounts that hold games.  The others are logged and left out
+pub fn summarize<'a>(accounts: impl Iterator<Item = (&'a Pubkey, &'a [u8])>) -> Vec<GameSummary> {
+    decode_games(accounts)
+        .into_iter()
+        .filter_map(|(pubkey, result)| match result {
+            Ok(game) => Some(GameSummary::new(&pubkey, &game)),
+            Err(err) => {
+                warn!("skipping account {}: {}", pubkey, err);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dashboard::Dashboard;
+    use std::mem::size_of;
+
+    fn userdata(state: State) -> Vec<u8> {
+        let mut userdata = vec![0; size_of::<State>()];
+        state.serialize(&mut userdata).unwrap();
+        userdata
+    }
+
+    #[test]
+    pub fn mixed_batch() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let keys: Vec<Pubkey> = (10..15).map(|i| Pubkey::new(&[i; 32])).collect();
+
+        let waiting = Game::create(&player_x);
+        let mut playing = waiting.clone();
+        playing.join(player_o, 1).unwrap();
+        playing.next_move(player_x, 1, 1).unwrap();
+        let mut corrupt = playing.clone();
+        corrupt.board[0] = 7; // An unknown board item in the first cell
+        let corrupt = userdata(State::Game(corrupt));
+
+        let payloads = [
+            userdata(State::Game(waiting.clone())),
+            corrupt,
+            vec![0; 10],
+            userdata(State::Dashboard(Dashboard::default())),
+            userdata(State::Game(playing.clone())),
+        ];
+        let accounts = || keys.iter().zip(payloads.iter().map(|p| &p[..]));
+
+        let decoded = decode_games(accounts());
+        assert_eq!(decoded.len(), 5);
+        for (i, &(pubkey, _)) in decoded.iter().enumerate() {
+            assert_eq!(pubkey, keys[i]);
+        }
+        assert_eq!(decoded[0].1.as_ref().unwrap(), &waiting);
+        for entry in &decoded[1..4] {
+            assert_eq!(
+                entry.1.as_ref().unwrap_err().kind,
+                ProgramError::InvalidUserdata
+            );
+        }
+        assert_eq!(decoded[4].1.as_ref().unwrap(), &playing);
+
+        let summaries = summarize(accounts());
+        assert_eq!(
+            summaries,
+            vec![
+                GameSummary::new(&keys[0], &waiting),
+                GameSummary::new(&keys[4], &playing),
+            ]
+        );
+        assert_eq!(summaries[0].player_o, None);
+        assert_eq!(summaries[1].player_o, Some(player_o));
+        assert_eq!(summaries[1].move_count, 1);
+        assert_eq!(summaries[1].empty_cells, 8);
+        assert_eq!(summaries[1].progress, (1, 9));
+        assert!(!summaries[1].board_full);
+    }
+
+    #[test]
+    pub fn watchers() {
+        let game_pubkey = Pubkey::new(&[3; 32]);
+        let g = Game::create(&Pubkey::new(&[1; 32]));
+        assert_eq!(GameSummary::new(&game_pubkey, &g).watchers, 0);
+
+        let mut watch_list = WatchList::new(&game_pubkey);
+        for i in 0..12 {
+            watch_list.watch(Pubkey::new(&[10 + i; 32])).unwrap();
+        }
+        let summary = GameSummary::new(&game_pubkey, &g).with_watch_list(&watch_list);
+        assert_eq!(summary.watchers, 12);
+    }
+}
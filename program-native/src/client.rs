@@ -0,0 +1,793 @@
+use base64;
+use batch;
+use bincode;
+use dashboard::Dashboard;
+use game::{Game, GameState, Move};
+use program_command::Command;
+use program_state::State;
+use result::{GameError, ProgramError, Result as ProgramResult};
+use simple_serde::SimpleSerde;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{Instruction, Transaction};
+use std;
+use std::io;
+
+#[derive(Debug)]
+pub enum ClientError {
+    Transport(io::Error), // The pubsub connection failed and could not be re-established
+    Decode(GameError),    // A notification did not hold the expected account state
+    InvalidSignature,     // A signature is not by the expected signer or not over this message
+    Encoding,             // A transaction could not be encoded or decoded
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+// What a pubsub connection delivers
+pub enum Notification {
+    Account(u64, Vec<u8>), // New userdata of the account behind a subscription id
+    Closed,                // The connection went away
+}
+
+// The account subscription part of the pubsub websocket API, so the streams below can run over
+// a real connection or an injected one
+pub trait PubsubTransport {
+    fn connect(&mut self) -> io::Result<()>;
+    fn account_subscribe(&mut self, account: &Pubkey) -> io::Result<u64>;
+    fn account_unsubscribe(&mut self, subscription: u64) -> io::Result<()>;
+    fn next_notification(&mut self) -> io::Result<Notification>;
+}
+
+pub struct TicTacToeClient<T: PubsubTransport> {
+    transport: T,
+    max_reconnects: u32, // Reconnect attempts before a stream gives up
+}
+
+impl<T: PubsubTransport> TicTacToeClient<T> {
+    pub fn new(transport: T, max_reconnects: u32) -> TicTacToeClient<T> {
+        TicTacToeClient {
+            transport,
+            max_reconnects,
+        }
+    }
+
+    pub fn subscribe_game(&mut self, game: Pubkey) -> Result<AccountStream<'_, T, Game>> {
+        self.subscribe(game, batch::decode_game)
+    }
+
+    pub fn subscribe_dashboard(
+        &mut self,
+        dashboard: Pubkey,
+    ) -> Result<AccountStream<'_, T, Dashboard>> {
+        self.subscribe(dashboard, decode_dashboard)
+    }
+
+    fn subscribe<V>(
+        &mut self,
+        account: Pubkey,
+        decode: fn(&[u8]) -> ProgramResult<V>,
+    ) -> Result<AccountStream<'_, T, V>> {
+        let subscription = self
+            .transport
+            .account_subscribe(&account)
+            .map_err(ClientError::Transport)?;
+        Ok(AccountStream {
+            transport: &mut self.transport,
+            account,
+            subscription,
+            decode,
+            reconnects_left: self.max_reconnects,
+            stopped: false,
+        })
+    }
+}
+
+fn decode_dashboard(userdata: &[u8]) -> ProgramResult<Dashboard> {
+    match State::deserialize(userdata)? {
+        State::Dashboard(dashboard) => Ok(dashboard),
+        _ => Err(ProgramError::InvalidUserdata.into()),
+    }
+}
+
+// Every update of one account, decoded.  Undecodable updates are passed on as errors rather
+// than skipped.  A dropped connection is reconnected and the account resubscribed, and the
+// stream ends after an error once it runs out of reconnect attempts, or when stopped
+pub struct AccountStream<'a, T: 'a + PubsubTransport, V> {
+    transport: &'a mut T,
+    account: Pubkey,
+    subscription: u64,
+    decode: fn(&[u8]) -> ProgramResult<V>,
+    reconnects_left: u32,
+    stopped: bool,
+}
+
+impl<'a, T: PubsubTransport, V> AccountStream<'a, T, V> {
+    pub fn stop(&mut self) -> Result<()> {
+        if self.stopped {
+            return Ok(());
+        }
+        self.stopped = true;
+        self.transport
+            .account_unsubscribe(self.subscription)
+            .map_err(ClientError::Transport)
+    }
+
+    fn resubscribe(&mut self) -> io::Result<()> {
+        self.transport.connect()?;
+        self.subscription = self.transport.account_subscribe(&self.account)?;
+        Ok(())
+    }
+}
+
+impl<'a, T: PubsubTransport, V> Iterator for AccountStream<'a, T, V> {
+    type Item = Result<V>;
+
+    fn next(&mut self) -> Option<Result<V>> {
+        while !self.stopped {
+            let err = match self.transport.next_notification() {
+                Ok(Notification::Account(subscription, ref userdata))
+                    if subscription == self.subscription =>
+                {
+                    return Some((self.decode)(userdata).map_err(ClientError::Decode));
+                }
+                Ok(Notification::Account(..)) => continue, // Some other subscription
+                Ok(Notification::Closed) => {
+                    io::Error::new(io::ErrorKind::ConnectionAborted, "pubsub connection closed")
+                }
+                Err(err) => err,
+            };
+
+            let mut err = err;
+            loop {
+                if self.reconnects_left == 0 {
+                    self.stopped = true;
+                    return Some(Err(ClientError::Transport(err)));
+                }
+                self.reconnects_left -= 1;
+                match self.resubscribe() {
+                    Ok(()) => break,
+                    Err(retry_err) => err = retry_err,
+                }
+            }
+        }
+        None
+    }
+}
+
+// How settled a transaction has to be before it counts
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SubmitOptions {
+    pub max_retries: u32,         // Resubmissions after the first attempt
+    pub confirmation: Commitment, // Commitment to wait for and to verify against
+    pub skip_preflight: bool,     // Skip the RPC node's simulation of the transaction
+}
+
+impl Default for SubmitOptions {
+    fn default() -> SubmitOptions {
+        SubmitOptions {
+            max_retries: 3,
+            confirmation: Commitment::Confirmed,
+            skip_preflight: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RpcError {
+    BlockhashExpired,     // The transaction's blockhash is too old to land
+    Timeout,              // No confirmation arrived in time, the transaction may still land
+    ProgramError(u32),    // The program rejected the transaction with a custom error code
+    Transport(io::Error), // The RPC node could not be reached
+}
+
+// The RPC calls needed to submit a game instruction and check what happened to it
+pub trait RpcTransport {
+    fn latest_blockhash(&mut self) -> io::Result<Hash>;
+    fn send_instruction(
+        &mut self,
+        game: &Pubkey,
+        player: &Pubkey,
+        data: &[u8],
+        blockhash: &Hash,
+        options: &SubmitOptions,
+    ) -> std::result::Result<(), RpcError>;
+    fn game(&mut self, game: &Pubkey, commitment: Commitment) -> io::Result<Game>;
+    // getMultipleAccounts: the userdata of each account, None for accounts that do not exist
+    fn multiple_accounts(
+        &mut self,
+        accounts: &[Pubkey],
+        commitment: Commitment,
+    ) -> io::Result<Vec<Option<Vec<u8>>>>;
+}
+
+#[derive(Debug)]
+pub enum SubmitOutcome {
+    Landed { attempts: u32 },             // Confirmed by the RPC node
+    LandedDespiteError { attempts: u32 }, // Submission failed, but the game shows the change
+    Rejected(RpcError),                   // The program refused it, retrying will not help
+    RetriesExhausted { attempts: u32 },   // Never landed
+    Failed(RpcError),                     // The game could not be fetched to verify
+}
+
+// Sends moves and joins, retrying on expired blockhashes and timeouts.  After every attempt
+// the game is fetched again, since a timed out transaction may still have landed, and the
+// move history tells whether this particular move made it
+pub struct Submitter<R: RpcTransport> {
+    rpc: R,
+}
+
+impl<R: RpcTransport> Submitter<R> {
+    pub fn new(rpc: R) -> Submitter<R> {
+        Submitter { rpc }
+    }
+
+    pub fn make_move(
+        &mut self,
+        game: Pubkey,
+        player: Pubkey,
+        x: u8,
+        y: u8,
+        options: &SubmitOptions,
+    ) -> SubmitOutcome {
+        let before = match self.rpc.game(&game, options.confirmation) {
+            Ok(before) => before.move_count(),
+            Err(err) => return SubmitOutcome::Failed(RpcError::Transport(err)),
+        };
+        let expected = Move {
+            x: x as usize,
+            y: y as usize,
+        };
+        self.submit(game, player, &Command::Move(x, y), options, |game| {
+            game.move_history().get(before) == Some(&expected)
+        })
+    }
+
+    // Fetch many games in one RPC call.  Accounts that are missing or do not hold a game
+    // fail their own entry only
+    pub fn get_games(
+        &mut self,
+        games: &[Pubkey],
+        commitment: Commitment,
+    ) -> io::Result<Vec<(Pubkey, ProgramResult<Game>)>> {
+        let accounts = self.rpc.multiple_accounts(games, commitment)?;
+        let userdata = accounts
+            .iter()
+            .map(|account| account.as_ref().map_or(&[][..], |userdata| &userdata[..]));
+        Ok(batch::decode_games(games.iter().zip(userdata)))
+    }
+
+    pub fn join(&mut self, game: Pubkey, player: Pubkey, options: &SubmitOptions) -> SubmitOutcome {
+        self.submit(game, player, &Command::Join, options, |game| {
+            game.game_state != GameState::Waiting && *game.player_o() == player
+        })
+    }
+
+    fn submit<F>(
+        &mut self,
+        game: Pubkey,
+        player: Pubkey,
+        command: &Command,
+        options: &SubmitOptions,
+        landed: F,
+    ) -> SubmitOutcome
+    where
+        F: Fn(&Game) -> bool,
+    {
+        let data = match instruction_data(command) {
+            Ok(data) => data,
+            Err(err) => {
+                return SubmitOutcome::Failed(RpcError::Transport(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{:?}", err),
+                )));
+            }
+        };
+
+        let mut blockhash = None;
+        for attempt in 1..=options.max_retries + 1 {
+            if blockhash.is_none() {
+                match self.rpc.latest_blockhash() {
+                    Ok(latest) => blockhash = Some(latest),
+                    Err(err) => return SubmitOutcome::Failed(RpcError::Transport(err)),
+                }
+            }
+            let result = self.rpc.send_instruction(
+                &game,
+                &player,
+                &data,
+                blockhash.as_ref().unwrap(),
+                options,
+            );
+            if let Err(RpcError::BlockhashExpired) = result {
+                blockhash = None;
+            }
+
+            // Also check after a program error, an earlier attempt that timed out may have
+            // landed and turned this one into a duplicate
+            match self.rpc.game(&game, options.confirmation) {
+                Ok(ref state) if landed(state) => {
+                    return match result {
+                        Ok(()) => SubmitOutcome::Landed { attempts: attempt },
+                        Err(_) => SubmitOutcome::LandedDespiteError { attempts: attempt },
+                    };
+                }
+                Ok(_) => {}
+                Err(err) => return SubmitOutcome::Failed(RpcError::Transport(err)),
+            }
+            if let Err(RpcError::ProgramError(code)) = result {
+                return SubmitOutcome::Rejected(RpcError::ProgramError(code));
+            }
+        }
+        SubmitOutcome::RetriesExhausted {
+            attempts: options.max_retries + 1,
+        }
+    }
+}
+
+// Instruction userdata for `command`, just its encoding
+pub(crate) fn instruction_data(command: &Command) -> ProgramResult<Vec<u8>> {
+    let size = bincode::serialized_size(command).map_err(Command::map_to_invalid_args)?;
+    let mut data = vec![0; size as usize];
+    command.serialize(&mut data)?;
+    Ok(data)
+}
+
+// An unsigned transaction carrying one game instruction.  The player is the only signer and
+// pays the fee, their signature slot holds a placeholder until attach_signature fills it in
+fn build_unsigned_tx(
+    program_id: Pubkey,
+    dashboard: Pubkey,
+    game: Pubkey,
+    player: Pubkey,
+    command: &Command,
+    recent_blockhash: Hash,
+) -> ProgramResult<Transaction> {
+    Ok(Transaction {
+        signatures: vec![Signature::default()],
+        account_keys: vec![player, dashboard, game],
+        last_id: recent_blockhash,
+        fee: 0,
+        program_ids: vec![program_id],
+        instructions: vec![Instruction {
+            program_ids_index: 0,
+            accounts: vec![0, 1, 2],
+            userdata: instruction_data(command)?,
+        }],
+    })
+}
+
+pub fn build_unsigned_move_tx(
+    program_id: Pubkey,
+    dashboard: Pubkey,
+    game: Pubkey,
+    player: Pubkey,
+    x: u8,
+    y: u8,
+    recent_blockhash: Hash,
+) -> ProgramResult<Transaction> {
+    let command = Command::Move(x, y);
+    build_unsigned_tx(
+        program_id,
+        dashboard,
+        game,
+        player,
+        &command,
+        recent_blockhash,
+    )
+}
+
+pub fn build_unsigned_join_tx(
+    program_id: Pubkey,
+    dashboard: Pubkey,
+    game: Pubkey,
+    player: Pubkey,
+    recent_blockhash: Hash,
+) -> ProgramResult<Transaction> {
+    let command = Command::Join;
+    build_unsigned_tx(
+        program_id,
+        dashboard,
+        game,
+        player,
+        &command,
+        recent_blockhash,
+    )
+}
+
+pub fn build_unsigned_keep_alive_tx(
+    program_id: Pubkey,
+    dashboard: Pubkey,
+    game: Pubkey,
+    player: Pubkey,
+    recent_blockhash: Hash,
+) -> ProgramResult<Transaction> {
+    let command = Command::KeepAlive;
+    build_unsigned_tx(
+        program_id,
+        dashboard,
+        game,
+        player,
+        &command,
+        recent_blockhash,
+    )
+}
+
+// Put a signature made elsewhere into the slot of `pubkey`, after checking it is that
+// signer's signature over this exact transaction
+pub fn attach_signature(
+    mut tx: Transaction,
+    pubkey: &Pubkey,
+    signature: Signature,
+) -> Result<Transaction> {
+    let slot = tx.account_keys[..tx.signatures.len()]
+        .iter()
+        .position(|key| key == pubkey)
+        .ok_or(ClientError::InvalidSignature)?;
+    if !signature.verify(pubkey.as_ref(), &tx.get_sign_data()) {
+        Err(ClientError::InvalidSignature)?;
+    }
+    tx.signatures[slot] = signature;
+    Ok(tx)
+}
+
+pub fn transaction_to_base64(tx: &Transaction) -> Result<String> {
+    let bytes = bincode::serialize(tx).map_err(|_| ClientError::Encoding)?;
+    Ok(base64::encode(&bytes))
+}
+
+pub fn transaction_from_base64(encoded: &str) -> Result<Transaction> {
+    let bytes = base64::decode(encoded).map_err(|_| ClientError::Encoding)?;
+    bincode::deserialize(&bytes).map_err(|_| ClientError::Encoding)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::GameState;
+    use std::collections::VecDeque;
+
+    // Plays back scripted notifications.  Each connection gets the next batch, and the
+    // subscription id changes on every subscribe like it would on a new connection
+    #[derive(Default)]
+    struct MockTransport {
+        batches: VecDeque<Vec<Notification>>,
+        current: VecDeque<Notification>,
+        subscriptions: u64,
+        connects: u32,
+        unsubscribed: Vec<u64>,
+    }
+
+    impl MockTransport {
+        fn new(current: Vec<Notification>, batches: Vec<Vec<Notification>>) -> MockTransport {
+            MockTransport {
+                batches: batches.into_iter().collect(),
+                current: current.into_iter().collect(),
+                ..MockTransport::default()
+            }
+        }
+    }
+
+    impl PubsubTransport for MockTransport {
+        fn connect(&mut self) -> io::Result<()> {
+            self.connects += 1;
+            match self.batches.pop_front() {
+                Some(batch) => {
+                    self.current = batch.into_iter().collect();
+                    Ok(())
+                }
+                None => Err(io::Error::new(io::ErrorKind::ConnectionRefused, "down")),
+            }
+        }
+        fn account_subscribe(&mut self, _account: &Pubkey) -> io::Result<u64> {
+            self.subscriptions += 1;
+            Ok(self.subscriptions)
+        }
+        fn account_unsubscribe(&mut self, subscription: u64) -> io::Result<()> {
+            self.unsubscribed.push(subscription);
+            Ok(())
+        }
+        fn next_notification(&mut self) -> io::Result<Notification> {
+            Ok(self.current.pop_front().unwrap_or(Notification::Closed))
+        }
+    }
+
+    fn userdata(state: &State) -> Vec<u8> {
+        let mut userdata = vec![0; 255];
+        state.serialize(&mut userdata).unwrap();
+        userdata
+    }
+
+    #[test]
+    pub fn subscribe_game() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut game = Game::create(&player_x);
+        let waiting = userdata(&State::Game(game.clone()));
+        game.join(player_o, 1).unwrap();
+        let joined = userdata(&State::Game(game.clone()));
+
+        let transport = MockTransport::new(
+            vec![
+                Notification::Account(1, waiting),
+                Notification::Account(7, vec![]),
+                Notification::Account(1, vec![1, 2, 3]),
+            ],
+            vec![vec![Notification::Account(2, joined)]],
+        );
+
+        let mut client = TicTacToeClient::new(transport, 1);
+        {
+            let mut stream = client.subscribe_game(Pubkey::new(&[3; 32])).unwrap();
+            assert_eq!(
+                stream.next().unwrap().unwrap().game_state,
+                GameState::Waiting
+            );
+            match stream.next() {
+                Some(Err(ClientError::Decode(_))) => {}
+                other => panic!("expected a decode error, got {:?}", other),
+            }
+            // The connection drops, after which the stream resubscribes
+            assert_eq!(stream.next().unwrap().unwrap(), game);
+            // ... and then gives up once there are no reconnects left
+            match stream.next() {
+                Some(Err(ClientError::Transport(_))) => {}
+                other => panic!("expected a transport error, got {:?}", other),
+            }
+            assert!(stream.next().is_none());
+        }
+        assert_eq!(client.transport.connects, 1);
+    }
+
+    #[test]
+    pub fn stop() {
+        let dashboard = userdata(&State::Dashboard(Dashboard::default()));
+        let transport = MockTransport::new(
+            vec![
+                Notification::Account(1, dashboard.clone()),
+                Notification::Account(1, dashboard),
+            ],
+            vec![],
+        );
+
+        let mut client = TicTacToeClient::new(transport, 0);
+        {
+            let mut stream = client.subscribe_dashboard(Pubkey::new(&[3; 32])).unwrap();
+            assert_eq!(stream.next().unwrap().unwrap(), Dashboard::default());
+            stream.stop().unwrap();
+            assert!(stream.next().is_none());
+        }
+        assert_eq!(client.transport.unsubscribed, vec![1]);
+
+        // A Game stream rejects other account states
+        client.transport.current = vec![Notification::Account(
+            2,
+            userdata(&State::Dashboard(Dashboard::default())),
+        )]
+        .into_iter()
+        .collect();
+        let mut stream = client.subscribe_game(Pubkey::new(&[3; 32])).unwrap();
+        match stream.next() {
+            Some(Err(ClientError::Decode(ref err)))
+                if err.kind == ProgramError::InvalidUserdata => {}
+            other => panic!("expected a decode error, got {:?}", other),
+        }
+    }
+
+    // Replies to each send with the next scripted result.  A send "lands" when the script says
+    // so, whatever the reply
+    struct MockRpc {
+        game: Game,
+        player_x: Pubkey,
+        sends: VecDeque<(std::result::Result<(), RpcError>, bool)>,
+        blockhashes: u32,
+        stale_blockhash: Option<Hash>,
+    }
+
+    impl RpcTransport for MockRpc {
+        fn latest_blockhash(&mut self) -> io::Result<Hash> {
+            self.blockhashes += 1;
+            Ok(Hash::new(&[self.blockhashes as u8; 32]))
+        }
+        fn send_instruction(
+            &mut self,
+            _game: &Pubkey,
+            player: &Pubkey,
+            data: &[u8],
+            blockhash: &Hash,
+            _options: &SubmitOptions,
+        ) -> std::result::Result<(), RpcError> {
+            assert_ne!(Some(*blockhash), self.stale_blockhash);
+            let (result, lands) = self.sends.pop_front().unwrap();
+            if let Err(RpcError::BlockhashExpired) = result {
+                self.stale_blockhash = Some(*blockhash);
+            }
+            if lands {
+                match Command::deserialize(data).unwrap() {
+                    Command::Move(x, y) => self
+                        .game
+                        .next_move(*player, x as usize, y as usize)
+                        .unwrap(),
+                    Command::Join => {
+                        self.game.join(*player, 1).unwrap();
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            result
+        }
+        fn game(&mut self, _game: &Pubkey, _commitment: Commitment) -> io::Result<Game> {
+            Ok(self.game.clone())
+        }
+        // The game is at [3; 32], [4; 32] holds garbage and nothing else exists
+        fn multiple_accounts(
+            &mut self,
+            accounts: &[Pubkey],
+            _commitment: Commitment,
+        ) -> io::Result<Vec<Option<Vec<u8>>>> {
+            Ok(accounts
+                .iter()
+                .map(|account| match account.as_ref()[0] {
+                    3 => Some(userdata(&State::Game(self.game.clone()))),
+                    4 => Some(vec![0xff; 255]),
+                    _ => None,
+                })
+                .collect())
+        }
+    }
+
+    fn mock_rpc(sends: Vec<(std::result::Result<(), RpcError>, bool)>) -> MockRpc {
+        let player_x = Pubkey::new(&[1; 32]);
+        MockRpc {
+            game: Game::create(&player_x),
+            player_x,
+            sends: sends.into_iter().collect(),
+            blockhashes: 0,
+            stale_blockhash: None,
+        }
+    }
+
+    #[test]
+    pub fn get_games() {
+        let mut submitter = Submitter::new(mock_rpc(vec![]));
+        let keys = [
+            Pubkey::new(&[4; 32]),
+            Pubkey::new(&[3; 32]),
+            Pubkey::new(&[5; 32]),
+        ];
+        let games = submitter.get_games(&keys, Commitment::Confirmed).unwrap();
+        assert_eq!(games.len(), 3);
+        for (i, &(pubkey, _)) in games.iter().enumerate() {
+            assert_eq!(pubkey, keys[i]);
+        }
+        assert!(games[0].1.is_err());
+        assert_eq!(games[1].1.as_ref().unwrap(), &submitter.rpc.game);
+        assert!(games[2].1.is_err());
+    }
+
+    #[test]
+    pub fn submit_retries() {
+        let game = Pubkey::new(&[3; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let options = SubmitOptions::default();
+
+        // An expired blockhash is refreshed before the retry
+        let mut submitter = Submitter::new(mock_rpc(vec![
+            (Err(RpcError::BlockhashExpired), false),
+            (Ok(()), true),
+        ]));
+        match submitter.join(game, player_o, &options) {
+            SubmitOutcome::Landed { attempts: 2 } => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(submitter.rpc.blockhashes, 2);
+
+        // The move timed out but made it anyway, so it is not sent again
+        let player_x = submitter.rpc.player_x;
+        submitter.rpc.sends = vec![(Err(RpcError::Timeout), true)].into_iter().collect();
+        match submitter.make_move(game, player_x, 1, 1, &options) {
+            SubmitOutcome::LandedDespiteError { attempts: 1 } => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(submitter.rpc.sends.is_empty());
+
+        // The retry of a timed out move fails because the first attempt landed after all
+        submitter.rpc.sends = vec![
+            (Err(RpcError::Timeout), false),
+            (Err(RpcError::ProgramError(3)), true),
+        ]
+        .into_iter()
+        .collect();
+        match submitter.make_move(game, player_o, 0, 0, &options) {
+            SubmitOutcome::LandedDespiteError { attempts: 2 } => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Otherwise a program error is final
+        submitter.rpc.sends = vec![(Err(RpcError::ProgramError(6)), false)]
+            .into_iter()
+            .collect();
+        match submitter.make_move(game, player_x, 1, 1, &options) {
+            SubmitOutcome::Rejected(RpcError::ProgramError(6)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let options = SubmitOptions {
+            max_retries: 1,
+            ..options
+        };
+        submitter.rpc.sends = vec![
+            (Err(RpcError::Timeout), false),
+            (Err(RpcError::Timeout), false),
+        ]
+        .into_iter()
+        .collect();
+        match submitter.make_move(game, player_x, 2, 2, &options) {
+            SubmitOutcome::RetriesExhausted { attempts: 2 } => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(submitter.rpc.game.move_count(), 2);
+    }
+
+    #[test]
+    pub fn offline_signing() {
+        use solana_sdk::signature::{Keypair, KeypairUtil};
+
+        let program_id = Pubkey::new(&[9; 32]);
+        let dashboard = Pubkey::new(&[4; 32]);
+        let game = Pubkey::new(&[3; 32]);
+        let keypair = Keypair::new();
+        let player = keypair.pubkey();
+        let blockhash = Hash::new(&[5; 32]);
+
+        let tx =
+            build_unsigned_move_tx(program_id, dashboard, game, player, 1, 2, blockhash).unwrap();
+        assert!(!tx.verify_signature());
+        assert!(tx.verify_refs());
+
+        // The unsigned transaction travels to the signing device and back as base64
+        let unsigned = transaction_to_base64(&tx).unwrap();
+        let remote = transaction_from_base64(&unsigned).unwrap();
+        let signature = Signature::new(keypair.sign(&remote.get_sign_data()).as_ref());
+
+        let other = Keypair::new();
+        assert!(attach_signature(tx.clone(), &other.pubkey(), signature).is_err());
+        let wrong_message =
+            build_unsigned_join_tx(program_id, dashboard, game, player, blockhash).unwrap();
+        assert!(attach_signature(wrong_message, &player, signature).is_err());
+
+        let tx = attach_signature(tx, &player, signature).unwrap();
+        assert!(tx.verify_signature());
+        let tx = transaction_from_base64(&transaction_to_base64(&tx).unwrap()).unwrap();
+        assert!(tx.verify_signature());
+        assert_eq!(tx.signer_key(0, 0), Some(&player));
+        assert_eq!(tx.key(0, 2), Some(&game));
+        assert_eq!(
+            Command::deserialize(tx.userdata(0)).unwrap(),
+            Command::Move(1, 2)
+        );
+
+        let tx =
+            build_unsigned_keep_alive_tx(program_id, dashboard, game, player, blockhash).unwrap();
+        assert_eq!(
+            Command::deserialize(tx.userdata(0)).unwrap(),
+            Command::KeepAlive
+        );
+        assert!(transaction_from_base64("not base64!").is_err());
+    }
+}
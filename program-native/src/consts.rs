@@ -0,0 +1,88 @@
+// Board encoding, win lines and other tables shared by the game modules
+
+pub const BOARD_ITEM_FREE: u8 = 0; // Free slot
+pub const BOARD_ITEM_X: u8 = 1; // Player X
+pub const BOARD_ITEM_O: u8 = 2; // Player O
+
+pub const BOARD_WIDTH: usize = 3; // Cells per row and per column
+
+#[cfg(feature = "logging")]
+pub(crate) const LOG_TARGET: &str = "tictactoe::game";
+
+// Structured engine tracing, compiled out entirely unless the `logging` feature is enabled
+#[cfg(feature = "logging")]
+macro_rules! game_log {
+    ($($arg:tt)+) => (info!(target: $crate::consts::LOG_TARGET, $($arg)+));
+}
+#[cfg(not(feature = "logging"))]
+macro_rules! game_log {
+    ($($arg:tt)+) => {};
+}
+
+// Board indices of every row, column and diagonal, in the order win_lines::<3, 3, 8>()
+// generates them
+pub const WIN_LINES: [[usize; 3]; 8] = [
+    [0, 1, 2],
+    [3, 4, 5],
+    [6, 7, 8],
+    [0, 3, 6],
+    [1, 4, 7],
+    [2, 5, 8],
+    [0, 4, 8],
+    [2, 4, 6],
+];
+
+// Every run of K cells along a row, column or diagonal of an N×N board, in board index order
+// y * N + x.  Rows come first, then columns, diagonals down to the right and down to the left.
+// COUNT must be the number of such runs, which fails the build when it is not
+pub const fn win_lines<const N: usize, const K: usize, const COUNT: usize>() -> [[usize; K]; COUNT]
+{
+    let directions: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (-1, 1)];
+    let mut lines = [[0; K]; COUNT];
+    let mut count = 0;
+    let mut d = 0;
+    while d < directions.len() {
+        let (dx, dy) = directions[d];
+        let mut start = 0;
+        while start < N * N {
+            let (x, y) = ((start % N) as isize, (start / N) as isize);
+            let (end_x, end_y) = (x + dx * (K as isize - 1), y + dy * (K as isize - 1));
+            if end_x >= 0 && end_x < N as isize && end_y < N as isize {
+                assert!(count < COUNT, "COUNT is less than the number of lines");
+                let mut i = 0;
+                while i < K {
+                    lines[count][i] =
+                        ((y + dy * i as isize) * N as isize + x + dx * i as isize) as usize;
+                    i += 1;
+                }
+                count += 1;
+            }
+            start += 1;
+        }
+        d += 1;
+    }
+    assert!(count == COUNT, "COUNT is more than the number of lines");
+    lines
+}
+
+// The 8 symmetries of the board (rotations and reflections), each mapping a board index to
+// the index it moves to
+pub const SYMMETRIES: [[usize; 9]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8], // Identity
+    [2, 5, 8, 1, 4, 7, 0, 3, 6], // Rotate 90 degrees clockwise
+    [8, 7, 6, 5, 4, 3, 2, 1, 0], // Rotate 180 degrees
+    [6, 3, 0, 7, 4, 1, 8, 5, 2], // Rotate 270 degrees clockwise
+    [2, 1, 0, 5, 4, 3, 8, 7, 6], // Mirror left to right
+    [6, 7, 8, 3, 4, 5, 0, 1, 2], // Mirror top to bottom
+    [0, 3, 6, 1, 4, 7, 2, 5, 8], // Mirror along the main diagonal
+    [8, 5, 2, 7, 4, 1, 6, 3, 0], // Mirror along the anti-diagonal
+];
+
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+pub(crate) const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// A timeout that never expires
+pub const NO_TIMEOUT: u64 = u64::MAX;
+
+// Number of boards alternating play reaches, the empty board included
+pub const POSITION_COUNT: usize = 5478;
@@ -1,40 +1,315 @@
 use game::{Game, GameState};
-use result::Result;
+use result::{ErrorContext, ProgramError, Result};
 use solana_sdk::pubkey::Pubkey;
+use upgrade::DashboardV1;
 
-const MAX_COMPLETED_GAMES: usize = 5;
+pub(crate) const MAX_COMPLETED_GAMES: usize = 5;
 
-#[repr(C)]
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+// Written last in every dashboard, where a dashboard in the v1 layout has zeros
+const LAYOUT_VERSION: u8 = 2;
+
+// Packed, as the archive flag would otherwise pad the dashboard past the 255 bytes of its
+// account
+#[repr(C, packed)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct Dashboard {
-    total_games: u64,                               // Total number of completed games
-    pending_game: Pubkey,                           // Latest pending game
-    completed_games: [Pubkey; MAX_COMPLETED_GAMES], // Last N completed games
-    latest_completed_game_index: u8,                // Index of the latest completed game
+    total_games: u64,                                 // Total number of completed games
+    pending_game: Pubkey,                             // Latest pending game
+    completed_games: [Pubkey; MAX_COMPLETED_GAMES],   // Last N completed games
+    latest_completed_game_index: u8,                  // Index of the latest completed game
+    completed_generations: [u8; MAX_COMPLETED_GAMES], // Low byte of each game's generation
+    moderator: Pubkey,                                // May freeze games for review, if set
+    upgraded_games: u8,                               // Games moved to the current layout, mod 256
+    layout: u8,                                       // LAYOUT_VERSION
+    has_archive: bool,                                // InitArchive set up its one archive
+}
+impl Default for Dashboard {
+    fn default() -> Dashboard {
+        Dashboard {
+            total_games: 0,
+            pending_game: Pubkey::default(),
+            completed_games: [Pubkey::default(); MAX_COMPLETED_GAMES],
+            latest_completed_game_index: 0,
+            completed_generations: [0; MAX_COMPLETED_GAMES],
+            moderator: Pubkey::default(),
+            upgraded_games: 0,
+            layout: LAYOUT_VERSION,
+            has_archive: false,
+        }
+    }
+}
+
+// The v1 dashboard had no moderator and kept the whole generation of each completed game, of
+// which the low byte carries over
+impl From<DashboardV1> for Dashboard {
+    fn from(v1: DashboardV1) -> Dashboard {
+        let mut dashboard = Dashboard {
+            total_games: v1.total_games,
+            pending_game: v1.pending_game,
+            completed_games: v1.completed_games,
+            latest_completed_game_index: v1.latest_completed_game_index,
+            ..Dashboard::default()
+        };
+        for (low, &generation) in dashboard
+            .completed_generations
+            .iter_mut()
+            .zip(v1.completed_generations.iter())
+        {
+            *low = generation as u8;
+        }
+        dashboard
+    }
 }
 
 impl Dashboard {
+    pub fn with_moderator(moderator: &Pubkey) -> Dashboard {
+        Dashboard {
+            moderator: *moderator,
+            ..Dashboard::default()
+        }
+    }
+
+    pub fn moderator(&self) -> Option<&Pubkey> {
+        if self.moderator == Pubkey::default() {
+            None
+        } else {
+            Some(&self.moderator)
+        }
+    }
+
+    pub fn check_moderator(&self, key: &Pubkey) -> Result<()> {
+        if self.moderator() != Some(key) {
+            Err(ProgramError::NotModerator.at(ErrorContext::Player(*key)))?;
+        }
+        Ok(())
+    }
+
+    pub fn total_games(&self) -> u64 {
+        self.total_games
+    }
+
+    // The archive names its dashboard, which only records that there is one.  A dashboard has
+    // at most one archive, and then every game finished on it is archived
+    pub fn has_archive(&self) -> bool {
+        self.has_archive
+    }
+
+    pub(crate) fn set_archive(&mut self) -> Result<()> {
+        if self.has_archive {
+            Err(ProgramError::InvalidInput)?;
+        }
+        self.has_archive = true;
+        Ok(())
+    }
+
     pub fn update(self: &mut Dashboard, game_pubkey: &Pubkey, game: &Game) -> Result<()> {
         match game.game_state {
+            // Frozen games are not offered to anyone until they are unfrozen
+            GameState::Waiting if game.is_frozen() => {
+                if self.pending_game == *game_pubkey {
+                    self.pending_game = Pubkey::default();
+                }
+            }
             GameState::Waiting => {
                 self.pending_game = *game_pubkey;
             }
             GameState::XMove | GameState::OMove => {
                 // Nothing to do.  In progress games are not managed by the dashboard
             }
+            GameState::Aborted => {
+                // Aborted games are not completed games, but should no longer be advertised
+                if self.pending_game == *game_pubkey {
+                    self.pending_game = Pubkey::default();
+                }
+            }
             GameState::XWon | GameState::OWon | GameState::Draw => {
+                // A reset game account holds a new game once its generation changes.  The low
+                // byte is enough to tell it apart from the few games the dashboard remembers
+                let generation = game.generation() as u8;
                 if !self
                     .completed_games
                     .iter()
-                    .any(|pubkey| pubkey == game_pubkey)
+                    .zip(self.completed_generations.iter())
+                    .any(|(pubkey, &completed)| pubkey == game_pubkey && completed == generation)
                 {
                     self.total_games += 1;
                     self.latest_completed_game_index =
                         (self.latest_completed_game_index + 1) % MAX_COMPLETED_GAMES as u8;
-                    self.completed_games[self.latest_completed_game_index as usize] = *game_pubkey;
+                    let index = self.latest_completed_game_index as usize;
+                    self.completed_games[index] = *game_pubkey;
+                    self.completed_generations[index] = generation;
                 }
             }
         };
         Ok(())
     }
+
+    // A game account was rewritten in the current layout, at `new_pubkey` if it had to move
+    // to a larger account.  The dashboard follows the game to its new account
+    pub fn record_upgrade(self: &mut Dashboard, old_pubkey: &Pubkey, new_pubkey: &Pubkey) {
+        if self.pending_game == *old_pubkey {
+            self.pending_game = *new_pubkey;
+        }
+        for pubkey in self.completed_games.iter_mut() {
+            if pubkey == old_pubkey {
+                *pubkey = *new_pubkey;
+            }
+        }
+        self.upgraded_games = self.upgraded_games.wrapping_add(1);
+    }
+
+    pub fn upgraded_games(&self) -> u8 {
+        self.upgraded_games
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use program_state::State;
+    use simple_serde::SimpleSerde;
+    use std::mem::size_of;
+    use upgrade::{is_v1_dashboard, DASHBOARD_V1_LEN};
+
+    fn play_draw(game: &mut Game, player_x: Pubkey, player_o: Pubkey) {
+        game.next_move(player_x, 0, 0).unwrap();
+        game.next_move(player_o, 1, 1).unwrap();
+        game.next_move(player_x, 0, 2).unwrap();
+        game.next_move(player_o, 0, 1).unwrap();
+        game.next_move(player_x, 2, 1).unwrap();
+        game.next_move(player_o, 1, 0).unwrap();
+        game.next_move(player_x, 1, 2).unwrap();
+        game.next_move(player_o, 2, 2).unwrap();
+        game.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(game.game_state, GameState::Draw);
+    }
+
+    #[test]
+    pub fn reset_game_counts_again() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let game_pubkey = Pubkey::new(&[3; 32]);
+        let mut dashboard = Dashboard::default();
+
+        let mut game = Game::create(&player_x);
+        dashboard.update(&game_pubkey, &game).unwrap();
+        assert_eq!(dashboard.pending_game, game_pubkey);
+
+        game.join(player_o, 1).unwrap();
+        play_draw(&mut game, player_x, player_o);
+        dashboard.update(&game_pubkey, &game).unwrap();
+        dashboard.update(&game_pubkey, &game).unwrap();
+        assert_eq!(dashboard.total_games(), 1);
+
+        game.reset(&player_x, 2).unwrap();
+        dashboard.update(&game_pubkey, &game).unwrap();
+        game.join(player_o, 3).unwrap();
+        play_draw(&mut game, player_x, player_o);
+        dashboard.update(&game_pubkey, &game).unwrap();
+        dashboard.update(&game_pubkey, &game).unwrap();
+        assert_eq!(dashboard.total_games(), 2);
+    }
+
+    #[test]
+    pub fn aborted_game_is_not_counted() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let game_pubkey = Pubkey::new(&[3; 32]);
+        let mut dashboard = Dashboard::default();
+
+        let mut game = Game::create(&player_x);
+        dashboard.update(&game_pubkey, &game).unwrap();
+        game.request_abort(player_x).unwrap();
+        dashboard.update(&game_pubkey, &game).unwrap();
+        assert_eq!(dashboard.pending_game, Pubkey::default());
+        assert_eq!(dashboard.total_games(), 0);
+    }
+
+    #[test]
+    pub fn frozen_game_is_not_advertised() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let moderator = Pubkey::new(&[8; 32]);
+        let game_pubkey = Pubkey::new(&[3; 32]);
+        let mut dashboard = Dashboard::with_moderator(&moderator);
+        assert!(dashboard.check_moderator(&moderator).is_ok());
+        let err = dashboard.check_moderator(&player_x).unwrap_err();
+        assert_eq!(err.kind, ProgramError::NotModerator);
+        assert!(Dashboard::default().check_moderator(&moderator).is_err());
+
+        let mut game = Game::create(&player_x);
+        dashboard.update(&game_pubkey, &game).unwrap();
+        game.freeze(moderator).unwrap();
+        dashboard.update(&game_pubkey, &game).unwrap();
+        assert_eq!(dashboard.pending_game, Pubkey::default());
+        game.unfreeze(moderator).unwrap();
+        dashboard.update(&game_pubkey, &game).unwrap();
+        assert_eq!(dashboard.pending_game, game_pubkey);
+    }
+
+    #[test]
+    pub fn read_v1_layout() {
+        // Tag, game count, pending game, completed games, latest index, u32 generations
+        let mut userdata = vec![1, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0];
+        userdata.extend_from_slice(&[3; 32]);
+        for i in 0..MAX_COMPLETED_GAMES {
+            userdata.extend_from_slice(&[10 + i as u8; 32]);
+        }
+        userdata.push(2);
+        for i in 0..MAX_COMPLETED_GAMES as u32 {
+            userdata.extend_from_slice(&(0x100 + i).to_le_bytes());
+        }
+        assert_eq!(userdata.len(), DASHBOARD_V1_LEN);
+        userdata.resize(255, 0);
+        assert!(is_v1_dashboard(&userdata));
+
+        let dashboard = match State::deserialize(&userdata).unwrap() {
+            State::Dashboard(dashboard) => dashboard,
+            state => panic!("{:?}", state),
+        };
+        assert_eq!(dashboard.total_games(), 7);
+        assert_eq!(dashboard.pending_game, Pubkey::new(&[3; 32]));
+        assert_eq!(dashboard.completed_games[4], Pubkey::new(&[14; 32]));
+        assert_eq!(dashboard.latest_completed_game_index, 2);
+        assert_eq!(dashboard.completed_generations, [0, 1, 2, 3, 4]);
+        assert_eq!(dashboard.moderator(), None);
+
+        // Written back, it is read in the current layout
+        let mut rewritten = vec![0; size_of::<State>()];
+        State::Dashboard(dashboard)
+            .serialize(&mut rewritten)
+            .unwrap();
+        assert!(!is_v1_dashboard(&rewritten));
+        match State::deserialize(&rewritten).unwrap() {
+            State::Dashboard(decoded) => assert_eq!(decoded.layout, LAYOUT_VERSION),
+            state => panic!("{:?}", state),
+        }
+        assert!(!is_v1_dashboard(&vec![0; size_of::<State>()]));
+    }
+
+    #[test]
+    pub fn upgrade_follows_the_game() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let (old, new) = (Pubkey::new(&[3; 32]), Pubkey::new(&[4; 32]));
+        let mut dashboard = Dashboard::default();
+        let mut game = Game::create(&player_x);
+        dashboard.update(&old, &game).unwrap();
+
+        dashboard.record_upgrade(&old, &new);
+        assert_eq!(dashboard.pending_game, new);
+        assert_eq!(dashboard.upgraded_games(), 1);
+
+        game.join(player_o, 1).unwrap();
+        play_draw(&mut game, player_x, player_o);
+        dashboard.update(&new, &game).unwrap();
+        dashboard.record_upgrade(&new, &new);
+        assert_eq!(
+            dashboard
+                .completed_games
+                .iter()
+                .filter(|&&g| g == new)
+                .count(),
+            1
+        );
+        assert_eq!(dashboard.upgraded_games(), 2);
+    }
 }
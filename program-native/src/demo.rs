@@ -0,0 +1,360 @@
+use client::instruction_data;
+use game::{cell_name, Game, BOARD_ITEM_O, BOARD_ITEM_X};
+use program_command::Command;
+use result::Result;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::transaction::{Instruction, Transaction};
+use std;
+
+// The moves examples/demo.rs plays, X then O in turn.  X takes the top row on the fifth move
+pub const SCRIPT: [(u8, u8); 5] = [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)];
+
+// A transaction of one instruction carrying `command`, signed by `signer`, whose key goes
+// ahead of `keys` as the first account of the instruction
+fn signed_tx(
+    program_id: &Pubkey,
+    signer: &Keypair,
+    keys: &[Pubkey],
+    command: &Command,
+    last_id: Hash,
+) -> Result<Transaction> {
+    let instruction = Instruction {
+        program_ids_index: 0,
+        accounts: (0..=keys.len() as u8).collect(),
+        userdata: instruction_data(command)?,
+    };
+    Ok(Transaction::new_with_instructions(
+        &[signer],
+        keys,
+        last_id,
+        0,
+        vec![*program_id],
+        vec![instruction],
+    ))
+}
+
+pub fn init_dashboard_tx(
+    program_id: &Pubkey,
+    dashboard: &Keypair,
+    last_id: Hash,
+) -> Result<Transaction> {
+    signed_tx(program_id, dashboard, &[], &Command::InitDashboard, last_id)
+}
+
+// The dashboard funds a player account the program already owns
+pub fn init_player_tx(
+    program_id: &Pubkey,
+    dashboard: &Keypair,
+    player: &Pubkey,
+    last_id: Hash,
+) -> Result<Transaction> {
+    signed_tx(
+        program_id,
+        dashboard,
+        &[*player],
+        &Command::InitPlayer,
+        last_id,
+    )
+}
+
+pub fn init_game_tx(
+    program_id: &Pubkey,
+    game: &Keypair,
+    dashboard: &Pubkey,
+    player_x: &Pubkey,
+    last_id: Hash,
+) -> Result<Transaction> {
+    signed_tx(
+        program_id,
+        game,
+        &[*dashboard, *player_x],
+        &Command::InitGame,
+        last_id,
+    )
+}
+
+pub fn join_tx(
+    program_id: &Pubkey,
+    player_o: &Keypair,
+    dashboard: &Pubkey,
+    game: &Pubkey,
+    last_id: Hash,
+) -> Result<Transaction> {
+    signed_tx(
+        program_id,
+        player_o,
+        &[*dashboard, *game],
+        &Command::Join,
+        last_id,
+    )
+}
+
+pub fn move_tx(
+    program_id: &Pubkey,
+    player: &Keypair,
+    dashboard: &Pubkey,
+    game: &Pubkey,
+    (x, y): (u8, u8),
+    last_id: Hash,
+) -> Result<Transaction> {
+    signed_tx(
+        program_id,
+        player,
+        &[*dashboard, *game],
+        &Command::Move(x, y),
+        last_id,
+    )
+}
+
+// Settle a finished game, the trophy of a won one going to the empty account `trophy`
+pub fn finalize_tx(
+    program_id: &Pubkey,
+    player: &Keypair,
+    dashboard: &Pubkey,
+    game: &Pubkey,
+    trophy: &Pubkey,
+    last_id: Hash,
+) -> Result<Transaction> {
+    signed_tx(
+        program_id,
+        player,
+        &[*dashboard, *game, *trophy],
+        &Command::FinalizeWithTrophy,
+        last_id,
+    )
+}
+
+// A part of the game the local and the remote copy disagree on
+#[derive(Debug, PartialEq)]
+pub struct FieldMismatch {
+    pub field: &'static str,
+    pub local: String,
+    pub remote: String,
+}
+
+// Everything that differs between the local and the remote game after a step
+#[derive(Debug, PartialEq)]
+pub struct StepMismatch {
+    pub fields: Vec<FieldMismatch>,
+    pub cells: Vec<usize>, // Board indices of the cells that differ
+}
+
+impl std::fmt::Display for StepMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "remote game differs from the local one:")?;
+        for mismatch in &self.fields {
+            write!(
+                f,
+                "\n  {}: local {}, remote {}",
+                mismatch.field, mismatch.local, mismatch.remote
+            )?;
+        }
+        if !self.cells.is_empty() {
+            let cells: Vec<String> = self.cells.iter().map(|&i| cell_name(i)).collect();
+            write!(f, "\n  cells that differ: {}", cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StepMismatch {}
+
+// The board in Game::set_board_from_str notation
+fn board_string(board: &[u8; 9]) -> String {
+    board
+        .iter()
+        .map(|&item| match item {
+            BOARD_ITEM_X => 'X',
+            BOARD_ITEM_O => 'O',
+            _ => '_',
+        })
+        .collect()
+}
+
+// Check the game read back from the cluster against the local game the same moves were played
+// on.  Keep alives and other times are left out, the local game cannot see the cluster's clock
+pub fn verify_step(local: &Game, remote: &Game) -> std::result::Result<(), StepMismatch> {
+    let mut fields = vec![];
+    {
+        let mut compare = |field, local: String, remote: String| {
+            if local != remote {
+                fields.push(FieldMismatch {
+                    field,
+                    local,
+                    remote,
+                });
+            }
+        };
+        compare(
+            "player_x",
+            local.player_x().to_string(),
+            remote.player_x().to_string(),
+        );
+        compare(
+            "player_o",
+            local.player_o().to_string(),
+            remote.player_o().to_string(),
+        );
+        compare(
+            "game_state",
+            format!("{:?}", local.game_state),
+            format!("{:?}", remote.game_state),
+        );
+        compare("moves", local.move_notation(), remote.move_notation());
+        compare(
+            "board",
+            board_string(local.board()),
+            board_string(remote.board()),
+        );
+        compare(
+            "config",
+            format!("{:?}", local.config()),
+            format!("{:?}", remote.config()),
+        );
+    }
+    if fields.is_empty() {
+        return Ok(());
+    }
+    let cells = (0..9)
+        .filter(|&i| local.board()[i] != remote.board()[i])
+        .collect();
+    Err(StepMismatch { fields, cells })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use batch::decode_game;
+    use game::GameState;
+    use program_state::State;
+    use simple_serde::SimpleSerde;
+    use solana_sdk::account::{Account, KeyedAccount};
+    use solana_sdk::signature::KeypairUtil;
+    use std::collections::HashMap;
+
+    // Accounts by key, standing in for the cluster the demo runs against
+    struct Bank {
+        program_id: Pubkey,
+        accounts: HashMap<Pubkey, Account>,
+    }
+
+    impl Bank {
+        fn add(&mut self, key: Pubkey, tokens: u64, space: usize) {
+            let account = Account::new(tokens, space, self.program_id);
+            self.accounts.insert(key, account);
+        }
+
+        fn execute(&mut self, tx: &Transaction) -> Result<()> {
+            let instruction = &tx.instructions[0];
+            let keys: Vec<Pubkey> = instruction
+                .accounts
+                .iter()
+                .map(|&i| tx.account_keys[i as usize])
+                .collect();
+            let mut accounts: Vec<Account> =
+                keys.iter().map(|key| self.accounts[key].clone()).collect();
+            {
+                let mut info: Vec<KeyedAccount> = keys
+                    .iter()
+                    .zip(accounts.iter_mut())
+                    .enumerate()
+                    .map(|(i, (key, account))| {
+                        KeyedAccount::new(key, i < tx.signatures.len(), account)
+                    })
+                    .collect();
+                ::process_instruction(&mut info, &instruction.userdata, 1)?;
+            }
+            for (key, account) in keys.into_iter().zip(accounts) {
+                self.accounts.insert(key, account);
+            }
+            Ok(())
+        }
+
+        fn game(&self, key: &Pubkey) -> Game {
+            decode_game(&self.accounts[key].userdata).unwrap()
+        }
+    }
+
+    #[test]
+    pub fn script() {
+        let program_id = Pubkey::new(&[9; 32]);
+        let mut bank = Bank {
+            program_id,
+            accounts: HashMap::new(),
+        };
+        let last_id = Hash::default();
+        let (dashboard, game, trophy) = (Keypair::new(), Keypair::new(), Pubkey::new(&[7; 32]));
+        let players = [Keypair::new(), Keypair::new()];
+        bank.add(dashboard.pubkey(), 100, 255);
+        bank.add(game.pubkey(), 0, 255);
+        bank.add(trophy, 0, 255);
+        for player in players.iter() {
+            bank.add(player.pubkey(), 0, 0);
+        }
+
+        let dashboard_key = dashboard.pubkey();
+        let game_key = game.pubkey();
+        let (player_x, player_o) = (players[0].pubkey(), players[1].pubkey());
+        bank.execute(&init_dashboard_tx(&program_id, &dashboard, last_id).unwrap())
+            .unwrap();
+        for player in players.iter() {
+            let tx = init_player_tx(&program_id, &dashboard, &player.pubkey(), last_id).unwrap();
+            bank.execute(&tx).unwrap();
+        }
+        let tx = init_game_tx(&program_id, &game, &dashboard_key, &player_x, last_id).unwrap();
+        bank.execute(&tx).unwrap();
+        let tx = join_tx(&program_id, &players[1], &dashboard_key, &game_key, last_id).unwrap();
+        bank.execute(&tx).unwrap();
+
+        let mut local = Game::try_create(&player_x, 1).unwrap();
+        local.join(player_o, 2).unwrap();
+        verify_step(&local, &bank.game(&game_key)).unwrap();
+        for (i, &(x, y)) in SCRIPT.iter().enumerate() {
+            let player = &players[i % 2];
+            let tx = move_tx(
+                &program_id,
+                player,
+                &dashboard_key,
+                &game_key,
+                (x, y),
+                last_id,
+            );
+            bank.execute(&tx.unwrap()).unwrap();
+            local
+                .next_move(player.pubkey(), x as usize, y as usize)
+                .unwrap();
+            verify_step(&local, &bank.game(&game_key)).unwrap();
+        }
+        assert_eq!(local.game_state, GameState::XWon);
+
+        let tx = finalize_tx(
+            &program_id,
+            &players[0],
+            &dashboard_key,
+            &game_key,
+            &trophy,
+            last_id,
+        );
+        bank.execute(&tx.unwrap()).unwrap();
+        match State::deserialize(&bank.accounts[&trophy].userdata).unwrap() {
+            State::Trophy(trophy) => assert_eq!(trophy.winner, player_x),
+            state => panic!("no trophy: {:?}", state),
+        }
+
+        // A remote game that went another way fails the step, which says where
+        let mut local = local.clone();
+        local.set_board_from_str("XX_OO_X__").unwrap();
+        let err = verify_step(&local, &bank.game(&game_key)).unwrap_err();
+        assert_eq!(err.cells, [2, 6]);
+        assert_eq!(
+            err.to_string(),
+            "remote game differs from the local one:\n  \
+             game_state: local OMove, remote XWon\n  \
+             moves: local a1 a2 b1 b2 a3, remote a1 a2 b1 b2 c1\n  \
+             board: local XX_OO_X__, remote XXXOO____\n  \
+             cells that differ: c1 a3"
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use game::GameState;
+use solana_sdk::pubkey::Pubkey;
+use std;
+
+// The numbering of the variants is what goes on chain, new variants go at the end
+#[derive(Clone, Copy, Debug, PartialEq, Error)]
+pub enum ProgramError {
+    #[error("invalid input")]
+    InvalidInput,
+    #[error("game in progress")]
+    GameInProgress,
+    #[error("invalid move")]
+    InvalidMove,
+    #[error("invalid account userdata")]
+    InvalidUserdata,
+    #[error("timestamp is not newer than the last one")]
+    InvalidTimestamp,
+    #[error("not your turn")]
+    NotYourTurn,
+    #[error("player not found")]
+    PlayerNotFound,
+    #[error("trophy already minted")]
+    TrophyAlreadyMinted,
+    #[error("more than one history leads from one snapshot to the other")]
+    AmbiguousHistory,
+    #[error("snapshots are not of the same game")]
+    InconsistentSnapshots,
+    #[error("invalid game config")]
+    InvalidConfig,
+    #[error("solo games are not allowed")]
+    SoloNotAllowed,
+    #[error("game timed out")]
+    TimedOut,
+    #[error("invalid player name")]
+    InvalidName,
+    #[error("game uses the other kind of keep alive")]
+    WrongLiveness,
+    #[error("opponent has not timed out")]
+    NotTimedOut,
+    #[error("game is finished")]
+    GameFinished,
+    #[error("seat already taken")]
+    SeatTaken, // Someone else joined first, the context is the player holding seat O
+    #[error("not watching the game")]
+    NotWatching,
+    #[error("game is frozen for review")]
+    GameFrozen,
+    #[error("not the moderator of the dashboard")]
+    NotModerator,
+    #[error("time is not in the game's timeout unit")]
+    TimeoutUnitMismatch,
+    #[error("the handicap forbids X to open in the center")]
+    HandicapViolation,
+    #[error("move does not fit the game's variant")]
+    WrongVariant,
+    #[error("archive is full")]
+    ArchiveFull,
+    #[error("no opponent has joined yet")]
+    GameNotStarted,
+    #[error("token account holds another token")]
+    TokenMintMismatch,
+    #[error("token account belongs to someone else")]
+    TokenOwnerMismatch,
+    #[error("not enough tokens for the stake")]
+    InsufficientTokens,
+    #[error("invalid board state")]
+    InvalidBoardState,
+}
+
+impl ProgramError {
+    // This error, pointing at what the failing call was given
+    pub fn at(self, context: ErrorContext) -> GameError {
+        GameError {
+            kind: self,
+            context,
+        }
+    }
+
+    // The error code of the variant on chain
+    pub fn discriminant(&self) -> u32 {
+        *self as u32
+    }
+}
+
+// The argument of the failing call an error is about
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorContext {
+    Player(Pubkey),           // The player the call was made by or for
+    Coordinate(usize, usize), // The cell the call named
+    Timestamp(u64),           // The timestamp or sequence number the call carried
+    Outcome(GameState),       // The state the game is in, its outcome once finished
+    None,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameError {
+    pub kind: ProgramError,
+    pub context: ErrorContext,
+}
+
+impl From<ProgramError> for GameError {
+    fn from(kind: ProgramError) -> GameError {
+        kind.at(ErrorContext::None)
+    }
+}
+
+// On chain errors are just the ProgramError, numbered in declaration order
+impl From<GameError> for u64 {
+    fn from(err: GameError) -> u64 {
+        u64::from(err.kind.discriminant())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, GameError>;
+
+// For off chain tools working in anyhow::Result: wrap the GameError with what the tool was
+// doing when it happened
+#[cfg(feature = "anyhow")]
+pub trait GameResultExt<T> {
+    fn game_context(self, ctx: &str) -> anyhow::Result<T>;
+}
+
+#[cfg(feature = "anyhow")]
+impl<T> GameResultExt<T> for Result<T> {
+    fn game_context(self, ctx: &str) -> anyhow::Result<T> {
+        self.map_err(|err| anyhow::Error::new(err).context(ctx.to_string()))
+    }
+}
+
+impl std::fmt::Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.context {
+            ErrorContext::Player(player) => write!(f, "{} for player {}", self.kind, player),
+            ErrorContext::Coordinate(x, y) => write!(f, "{} at ({}, {})", self.kind, x, y),
+            ErrorContext::Timestamp(timestamp) => write!(f, "{} at {}", self.kind, timestamp),
+            ErrorContext::Outcome(outcome) => write!(f, "{} as {:?}", self.kind, outcome),
+            ErrorContext::None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+// A GameError is where the failure starts, there is no underlying error to chain to yet
+impl std::error::Error for GameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Every variant, in declaration order, with its message
+    const MESSAGES: [(ProgramError, &str); 30] = [
+        (ProgramError::InvalidInput, "invalid input"),
+        (ProgramError::GameInProgress, "game in progress"),
+        (ProgramError::InvalidMove, "invalid move"),
+        (ProgramError::InvalidUserdata, "invalid account userdata"),
+        (
+            ProgramError::InvalidTimestamp,
+            "timestamp is not newer than the last one",
+        ),
+        (ProgramError::NotYourTurn, "not your turn"),
+        (ProgramError::PlayerNotFound, "player not found"),
+        (ProgramError::TrophyAlreadyMinted, "trophy already minted"),
+        (
+            ProgramError::AmbiguousHistory,
+            "more than one history leads from one snapshot to the other",
+        ),
+        (
+            ProgramError::InconsistentSnapshots,
+            "snapshots are not of the same game",
+        ),
+        (ProgramError::InvalidConfig, "invalid game config"),
+        (ProgramError::SoloNotAllowed, "solo games are not allowed"),
+        (ProgramError::TimedOut, "game timed out"),
+        (ProgramError::InvalidName, "invalid player name"),
+        (
+            ProgramError::WrongLiveness,
+            "game uses the other kind of keep alive",
+        ),
+        (ProgramError::NotTimedOut, "opponent has not timed out"),
+        (ProgramError::GameFinished, "game is finished"),
+        (ProgramError::SeatTaken, "seat already taken"),
+        (ProgramError::NotWatching, "not watching the game"),
+        (ProgramError::GameFrozen, "game is frozen for review"),
+        (
+            ProgramError::NotModerator,
+            "not the moderator of the dashboard",
+        ),
+        (
+            ProgramError::TimeoutUnitMismatch,
+            "time is not in the game's timeout unit",
+        ),
+        (
+            ProgramError::HandicapViolation,
+            "the handicap forbids X to open in the center",
+        ),
+        (
+            ProgramError::WrongVariant,
+            "move does not fit the game's variant",
+        ),
+        (ProgramError::ArchiveFull, "archive is full"),
+        (ProgramError::GameNotStarted, "no opponent has joined yet"),
+        (
+            ProgramError::TokenMintMismatch,
+            "token account holds another token",
+        ),
+        (
+            ProgramError::TokenOwnerMismatch,
+            "token account belongs to someone else",
+        ),
+        (
+            ProgramError::InsufficientTokens,
+            "not enough tokens for the stake",
+        ),
+        (ProgramError::InvalidBoardState, "invalid board state"),
+    ];
+
+    #[test]
+    pub fn messages_and_discriminants() {
+        for (i, &(kind, message)) in MESSAGES.iter().enumerate() {
+            assert_eq!(kind.to_string(), message);
+            assert_eq!(kind.discriminant(), i as u32);
+            assert_eq!(u64::from(GameError::from(kind)), i as u64);
+        }
+    }
+
+    #[test]
+    pub fn on_chain_codes() {
+        // Deployed clients decode these, a variant keeps its code for good
+        let codes = [
+            (ProgramError::InvalidInput, 0),
+            (ProgramError::GameInProgress, 1),
+            (ProgramError::InvalidMove, 2),
+            (ProgramError::InvalidUserdata, 3),
+            (ProgramError::InvalidTimestamp, 4),
+            (ProgramError::NotYourTurn, 5),
+            (ProgramError::PlayerNotFound, 6),
+            (ProgramError::TrophyAlreadyMinted, 7),
+            (ProgramError::AmbiguousHistory, 8),
+            (ProgramError::InconsistentSnapshots, 9),
+            (ProgramError::InvalidConfig, 10),
+            (ProgramError::SoloNotAllowed, 11),
+            (ProgramError::TimedOut, 12),
+            (ProgramError::InvalidName, 13),
+            (ProgramError::WrongLiveness, 14),
+            (ProgramError::NotTimedOut, 15),
+            (ProgramError::GameFinished, 16),
+            (ProgramError::SeatTaken, 17),
+            (ProgramError::NotWatching, 18),
+            (ProgramError::GameFrozen, 19),
+            (ProgramError::NotModerator, 20),
+            (ProgramError::TimeoutUnitMismatch, 21),
+            (ProgramError::HandicapViolation, 22),
+            (ProgramError::WrongVariant, 23),
+            (ProgramError::ArchiveFull, 24),
+            (ProgramError::GameNotStarted, 25),
+            (ProgramError::TokenMintMismatch, 26),
+            (ProgramError::TokenOwnerMismatch, 27),
+            (ProgramError::InsufficientTokens, 28),
+            (ProgramError::InvalidBoardState, 29),
+        ];
+        assert_eq!(codes.len(), MESSAGES.len());
+        for &(kind, code) in codes.iter() {
+            assert_eq!(kind.discriminant(), code);
+            assert_eq!(u64::from(GameError::from(kind)), u64::from(code));
+        }
+    }
+
+    #[test]
+    pub fn display_and_box() {
+        let player = Pubkey::new(&[1; 32]);
+        let contexts = [
+            ErrorContext::Player(player),
+            ErrorContext::Coordinate(0, 3),
+            ErrorContext::Timestamp(7),
+            ErrorContext::Outcome(GameState::Draw),
+            ErrorContext::None,
+        ];
+        for &(kind, message) in MESSAGES.iter() {
+            for &context in contexts.iter() {
+                assert!(kind.at(context).to_string().starts_with(message));
+            }
+        }
+        assert_eq!(
+            ProgramError::InvalidMove
+                .at(ErrorContext::Coordinate(0, 3))
+                .to_string(),
+            "invalid move at (0, 3)"
+        );
+        assert_eq!(
+            ProgramError::NotYourTurn
+                .at(ErrorContext::Player(player))
+                .to_string(),
+            format!("not your turn for player {}", player)
+        );
+        assert_eq!(
+            ProgramError::GameFinished
+                .at(ErrorContext::Outcome(GameState::XWon))
+                .to_string(),
+            "game is finished as XWon"
+        );
+
+        let boxed: Box<dyn std::error::Error> = Box::new(GameError::from(ProgramError::TimedOut));
+        assert_eq!(boxed.to_string(), "game timed out");
+        assert!(boxed.source().is_none());
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    pub fn game_context() {
+        let player = Pubkey::new(&[1; 32]);
+        let result: Result<()> = Err(ProgramError::PlayerNotFound.at(ErrorContext::Player(player)));
+        let err = result.game_context("during join").unwrap_err();
+        assert_eq!(err.to_string(), "during join");
+        let message = format!("{:#}", err);
+        assert!(message.starts_with("during join: player not found for player "));
+        assert_eq!(
+            err.downcast_ref::<GameError>().unwrap().kind,
+            ProgramError::PlayerNotFound
+        );
+    }
+}
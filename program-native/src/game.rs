@@ -1,146 +1,1008 @@
+// The game engine.  The Game itself lives in `state`, the rules of play in `rules` and
+// keep alives and timeouts in `liveness`; this module gathers them up and adds the solver,
+// analysis and history on top
+pub use consts::*;
+pub use rules::*;
+pub use state::*;
+
 use result::{ProgramError, Result};
 use solana_sdk::pubkey::Pubkey;
+use std;
+use std::collections::HashMap;
+use std::sync::OnceLock;
 
-const BOARD_ITEM_FREE: u8 = 0; // Free slot
-const BOARD_ITEM_X: u8 = 1; // Player X
-const BOARD_ITEM_O: u8 = 2; // Player O
-
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
-pub enum GameState {
-    Waiting,
-    XMove,
-    OMove,
-    XWon,
-    OWon,
-    Draw,
+// Game theoretic value of a position for the player to move (1 = win, 0 = draw, -1 = loss),
+// and the number of moves left until the game ends when the winning side hurries and the
+// losing side stalls.  X always moves first, so the mark counts determine who is to move
+type Solution = (i8, u32);
+
+fn solve(board: &mut [u8; 9], memo: &mut HashMap<[u8; 9], Solution>) -> Solution {
+    if let Some(&solution) = memo.get(board) {
+        return solution;
+    }
+
+    let best = best_move(board, memo, None).map_or((0, 0), |(_, solution)| solution);
+    memo.insert(*board, best);
+    best
 }
-impl Default for GameState {
-    fn default() -> GameState {
-        GameState::Waiting
+
+// The board index of the best move for the player to move and the solution it leads to,
+// leaving out the `forbidden` cell
+fn best_move(
+    board: &mut [u8; 9],
+    memo: &mut HashMap<[u8; 9], Solution>,
+    forbidden: Option<usize>,
+) -> Option<(usize, Solution)> {
+    let free = board
+        .iter()
+        .filter(|&&item| item == BOARD_ITEM_FREE)
+        .count();
+    let x_or_o = if free % 2 == 1 {
+        BOARD_ITEM_X
+    } else {
+        BOARD_ITEM_O
+    };
+
+    let mut best: Option<(usize, Solution)> = None;
+    for i in 0..board.len() {
+        if board[i] != BOARD_ITEM_FREE || Some(i) == forbidden {
+            continue;
+        }
+        board[i] = x_or_o;
+        let solution = if find_line(Variant::Classic, board, x_or_o).is_some() {
+            (1, 1)
+        } else if free == 1 {
+            (0, 1)
+        } else {
+            let (value, depth) = solve(board, memo);
+            (-value, depth + 1)
+        };
+        board[i] = BOARD_ITEM_FREE;
+
+        best = match best {
+            Some(best) if !better_solution(solution, best.1) => Some(best),
+            _ => Some((i, solution)),
+        };
     }
+    best
 }
 
-#[repr(C)]
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
-pub struct Game {
-    keep_alive: [u64; 2],      // Keep alive timestamp for each player
-    pub game_state: GameState, // Current state of the game
-    player_x: Pubkey,          // Player who initialized the game
-    player_o: Pubkey,          // Player who joined the game
-    board: [u8; 9],            // Tracks the player moves (BOARD_ITEM_xyz)
+// Value of `board` for `x_or_o` to move (1 = win, 0 = draw, -1 = loss), whatever the mark
+// counts say
+fn value_for(variant: Variant, board: &mut [u8; 9], x_or_o: u8) -> i8 {
+    let other = if x_or_o == BOARD_ITEM_X {
+        BOARD_ITEM_O
+    } else {
+        BOARD_ITEM_X
+    };
+    if find_line(variant, board, other).is_some() {
+        return -1;
+    }
+    if is_full(board) {
+        return 0;
+    }
+    let mut best = -1;
+    for i in 0..board.len() {
+        if board[i] == BOARD_ITEM_FREE && best < 1 {
+            board[i] = x_or_o;
+            best = best.max(-value_for(variant, board, other));
+            board[i] = BOARD_ITEM_FREE;
+        }
+    }
+    best
 }
 
-impl Game {
-    pub fn create(player_x: &Pubkey) -> Game {
-        let mut game = Game::default();
-        game.player_x = *player_x;
-        assert_eq!(game.game_state, GameState::Waiting);
-        game
+// Append to `lines` every way of finishing the game from `board` with only optimal moves,
+// each after the moves of `line` that led there.  `forbidden` is a cell the first of the
+// moves may not take
+fn optimal_lines(
+    board: &mut [u8; 9],
+    forbidden: Option<usize>,
+    memo: &mut HashMap<[u8; 9], Solution>,
+    line: &mut Vec<Move>,
+    lines: &mut Vec<Vec<Move>>,
+) {
+    let x_or_o = mark_to_move(board);
+    let mut values = vec![];
+    for i in 0..board.len() {
+        if board[i] != BOARD_ITEM_FREE || Some(i) == forbidden {
+            continue;
+        }
+        board[i] = x_or_o;
+        let value = if find_line(Variant::Classic, board, x_or_o).is_some() {
+            1
+        } else if is_full(board) {
+            0
+        } else {
+            -solve(board, memo).0
+        };
+        board[i] = BOARD_ITEM_FREE;
+        values.push((i, value));
+    }
+    let best = match values.iter().map(|&(_, value)| value).max() {
+        Some(best) => best,
+        None => return lines.push(line.clone()),
+    };
+    for (i, value) in values {
+        if value != best {
+            continue;
+        }
+        let (x, y) = cell_coords(i).unwrap();
+        board[i] = x_or_o;
+        line.push(Move { x, y });
+        if find_line(Variant::Classic, board, x_or_o).is_some() {
+            lines.push(line.clone());
+        } else {
+            optimal_lines(board, None, memo, line, lines);
+        }
+        line.pop();
+        board[i] = BOARD_ITEM_FREE;
     }
+}
 
-    #[cfg(test)]
-    pub fn new(player_x: Pubkey, player_o: Pubkey) -> Game {
-        let mut game = Game::create(&player_x);
-        game.join(player_o, 1).unwrap();
-        game
+// How Game::human_explanation names each of WIN_LINES
+const LINE_NAMES: [&str; 8] = [
+    "the top row",
+    "the middle row",
+    "the bottom row",
+    "the left column",
+    "the middle column",
+    "the right column",
+    "the diagonal from the top left",
+    "the diagonal from the top right",
+];
+
+// Solution of a wild `board` for the player to move.  Either player may place either mark, so
+// unlike a classic board the solution does not depend on who is to move
+fn wild_solve(board: &mut [u8; 9], memo: &mut HashMap<[u8; 9], Solution>) -> Solution {
+    if let Some(&solution) = memo.get(board) {
+        return solution;
     }
 
-    pub fn join(self: &mut Game, player_o: Pubkey, timestamp: u64) -> Result<()> {
-        if self.game_state == GameState::Waiting {
-            self.player_o = player_o;
-            self.game_state = GameState::XMove;
+    let best = wild_best_move(board, memo, None).unwrap_or((0, 0));
+    memo.insert(*board, best);
+    best
+}
 
-            if timestamp <= self.keep_alive[1] {
-                Err(ProgramError::InvalidTimestamp)
+// best_move for a wild board, trying both marks in every cell but the `forbidden` one
+fn wild_best_move(
+    board: &mut [u8; 9],
+    memo: &mut HashMap<[u8; 9], Solution>,
+    forbidden: Option<usize>,
+) -> Option<Solution> {
+    let mut best: Option<Solution> = None;
+    for i in 0..board.len() {
+        for &mark in [BOARD_ITEM_X, BOARD_ITEM_O].iter() {
+            if board[i] != BOARD_ITEM_FREE || Some(i) == forbidden {
+                continue;
+            }
+            board[i] = mark;
+            let solution = if find_line(Variant::Wild, board, mark).is_some() {
+                (1, 1)
+            } else if is_full(board) {
+                (0, 1)
             } else {
-                self.keep_alive[1] = timestamp;
-                Ok(())
+                let (value, depth) = wild_solve(board, memo);
+                (-value, depth + 1)
+            };
+            board[i] = BOARD_ITEM_FREE;
+
+            best = match best {
+                Some(best) if !better_solution(solution, best) => Some(best),
+                _ => Some(solution),
+            };
+        }
+    }
+    best
+}
+
+// Whether some continuation of `board` with `x_or_o` to move, however badly played, completes
+// a line.  Wild players may place either mark
+fn line_reachable(variant: Variant, board: &mut [u8; 9], x_or_o: u8) -> bool {
+    let other = if x_or_o == BOARD_ITEM_X {
+        BOARD_ITEM_O
+    } else {
+        BOARD_ITEM_X
+    };
+    let either = [BOARD_ITEM_X, BOARD_ITEM_O];
+    let marks = match variant {
+        Variant::Classic => std::slice::from_ref(&x_or_o),
+        Variant::Wild => &either[..],
+    };
+    for i in 0..board.len() {
+        for &mark in marks.iter() {
+            if board[i] != BOARD_ITEM_FREE {
+                continue;
             }
-        } else {
-            Err(ProgramError::GameInProgress)
+            board[i] = mark;
+            let reachable =
+                find_line(variant, board, mark).is_some() || line_reachable(variant, board, other);
+            board[i] = BOARD_ITEM_FREE;
+            if reachable {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// Most moves until the game ends on a classic `board` the player to move has a forced win on,
+// when the winner only keeps the win in hand and the loser stalls, or None without a forced
+// win.  With `lost` the player to move is the one losing instead
+fn longest_win(
+    board: &mut [u8; 9],
+    lost: bool,
+    solver: &mut HashMap<[u8; 9], Solution>,
+    memo: &mut HashMap<([u8; 9], bool), Option<u32>>,
+) -> Option<u32> {
+    if let Some(&moves) = memo.get(&(*board, lost)) {
+        return moves;
+    }
+    let x_or_o = mark_to_move(board);
+    let mut longest = None;
+    for i in 0..board.len() {
+        if board[i] != BOARD_ITEM_FREE {
+            continue;
         }
+        board[i] = x_or_o;
+        let moves = if lost {
+            longest_win(board, false, solver, memo)
+        } else if find_line(Variant::Classic, board, x_or_o).is_some() {
+            Some(0)
+        } else if solve(board, solver).0 == -1 {
+            longest_win(board, true, solver, memo)
+        } else {
+            None
+        };
+        board[i] = BOARD_ITEM_FREE;
+        longest = longest.max(moves.map(|moves| moves + 1));
+    }
+    memo.insert((*board, lost), longest);
+    longest
+}
+
+fn better_solution(candidate: Solution, best: Solution) -> bool {
+    if candidate.0 != best.0 {
+        candidate.0 > best.0
+    } else if candidate.0 > 0 {
+        candidate.1 < best.1
+    } else {
+        candidate.1 > best.1
+    }
+}
+
+// Exhaustive solution of every position reachable from the empty board
+pub struct Solver {
+    memo: HashMap<[u8; 9], Solution>,
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        let mut memo = HashMap::new();
+        solve(&mut [BOARD_ITEM_FREE; 9], &mut memo);
+        Solver { memo }
     }
 
-    fn same(x_or_o: u8, triple: &[u8]) -> bool {
-        triple.iter().all(|&i| i == x_or_o)
+    // Value of the position for the player to move (1 = win, 0 = draw, -1 = loss), None once
+    // the game is over
+    pub fn value(&self, game: &Game) -> Option<i8> {
+        if game.is_finished() {
+            return None;
+        }
+        self.board_value(&game.board)
     }
 
-    pub fn next_move(self: &mut Game, player: Pubkey, x: usize, y: usize) -> Result<()> {
-        let board_index = y * 3 + x;
-        if board_index >= self.board.len() || self.board[board_index] != BOARD_ITEM_FREE {
-            Err(ProgramError::InvalidMove)?;
+    // Value of a bare board for the player the mark counts put to move, None once the board
+    // decides the game
+    pub fn board_value(&self, board: &[u8; 9]) -> Option<i8> {
+        if outcome(Variant::Classic, board).is_some() {
+            return None;
         }
+        Some(match self.memo.get(board) {
+            Some(&(value, _)) => value,
+            None => solve(&mut board.clone(), &mut HashMap::new()).0,
+        })
+    }
+}
+
+impl Default for Solver {
+    fn default() -> Solver {
+        Solver::new()
+    }
+}
+
+// Result of a position under optimal play, from the point of view of the player to move
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OptimalOutcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+// How a move compares with the best move available in the position.  A Mistake turns a draw
+// into a loss, a Blunder throws away a win
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MoveQuality {
+    Optimal,
+    Mistake,
+    Blunder,
+}
+
+// Suggested move for the player to move, as (x, y).  WinNow ends the game, BlockNow stops the
+// opponent from winning on their next move
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Hint {
+    WinNow(usize, usize),
+    BlockNow(usize, usize),
+    Optimal(usize, usize),
+    NoHint,
+}
+
+// What a move would do, worked out by Game::preview_move without making it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MovePreview {
+    pub game_state: GameState,    // The state the move leaves the game in
+    pub wins: bool,               // The move wins the game
+    pub opponent_wins_next: bool, // The opponent can then win with their reply
+    pub board: [u8; 9],           // The board after the move
+}
+
+// Up to symmetry there are only three different first moves
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OpeningClass {
+    Center,
+    Corner,
+    Edge,
+}
+
+// The named openings, X's first move then O's reply by board index, one for each class of
+// positions that are the same up to symmetry.  A plain reply is next to X's move, a far or
+// opposite one is not
+const NAMED_OPENINGS: [(usize, Option<usize>, &str); 15] = [
+    (4, None, "Center Opening"),
+    (0, None, "Corner Opening"),
+    (1, None, "Edge Opening"),
+    (4, Some(0), "Center-Corner Response"),
+    (4, Some(1), "Center-Edge Response"),
+    (0, Some(4), "Corner-Center Response"),
+    (0, Some(2), "Corner-Corner Response"),
+    (0, Some(8), "Corner-Opposite Corner Response"),
+    (0, Some(1), "Corner-Edge Response"),
+    (0, Some(5), "Corner-Far Edge Response"),
+    (1, Some(4), "Edge-Center Response"),
+    (1, Some(0), "Edge-Corner Response"),
+    (1, Some(6), "Edge-Far Corner Response"),
+    (1, Some(3), "Edge-Edge Response"),
+    (1, Some(7), "Edge-Opposite Edge Response"),
+];
 
-        let (x_or_o, won_state) = match self.game_state {
-            GameState::XMove => {
-                if player != self.player_x {
-                    return Err(ProgramError::PlayerNotFound);
+// NAMED_OPENINGS under every symmetry of the board, by board_as_u32 of the position.  Worked
+// out on first use
+fn opening_names() -> &'static [(u32, &'static str)] {
+    static NAMES: OnceLock<Vec<(u32, &'static str)>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        let mut names = vec![];
+        for &(x, o, name) in NAMED_OPENINGS.iter() {
+            for symmetry in SYMMETRIES.iter() {
+                let mut board = [BOARD_ITEM_FREE; 9];
+                board[symmetry[x]] = BOARD_ITEM_X;
+                if let Some(o) = o {
+                    board[symmetry[o]] = BOARD_ITEM_O;
                 }
-                self.game_state = GameState::OMove;
-                (BOARD_ITEM_X, GameState::XWon)
+                names.push((pack_board(&board), name));
+            }
+        }
+        names.sort();
+        names.dedup();
+        names
+    })
+}
+
+impl Game {
+    // The position just after player O joined, before any move was made
+    pub(crate) fn initial_position(&self) -> Result<Game> {
+        if self.game_state == GameState::Waiting {
+            Err(ProgramError::InvalidInput)?;
+        }
+        let mut game = Game::create(&self.player_x);
+        game.player_o = self.player_o;
+        game.game_state = GameState::XMove;
+        game.config.variant = self.config.variant;
+        Ok(game)
+    }
+
+    // Play the move at `board_index` again, placing `mark` there if the game is wild
+    fn replay_move(&mut self, board_index: u8, mark: u8) -> Result<()> {
+        let player = match self.game_state {
+            GameState::XMove => self.player_x,
+            _ => self.player_o,
+        };
+        let (x, y) = cell_coords(board_index as usize)?;
+        match (self.config.variant, Cell::from_item(mark)) {
+            (Variant::Wild, Some(mark)) => self.next_move_wild(player, x, y, mark),
+            _ => self.next_move(player, x, y),
+        }
+    }
+
+    // Reconstruct the position after the first `n` recorded moves (0 = just after join).
+    // Keep alive timestamps are not part of the history and are left unset
+    pub fn at_move(&self, n: usize) -> Result<Game> {
+        if n > self.move_count() {
+            Err(ProgramError::InvalidInput)?;
+        }
+        let mut game = self.initial_position()?;
+        for &board_index in &self.moves[..n] {
+            game.replay_move(board_index, self.board[board_index as usize])?;
+        }
+        Ok(game)
+    }
+
+    // Every position of the game from just after join up to the current one
+    pub fn iter_positions<'a>(&'a self) -> impl Iterator<Item = Game> + 'a {
+        (0..=self.move_count()).filter_map(move |n| self.at_move(n).ok())
+    }
+
+    // Optimal play solution for the current position, None once the game is over.  Wild
+    // boards are solved with both marks open to either player
+    fn solution(&self) -> Option<Solution> {
+        let mut board = self.board;
+        let forbidden = self.forbidden_cell();
+        if self.is_finished() {
+            None
+        } else if self.config.variant == Variant::Wild {
+            Some(wild_best_move(&mut board, &mut HashMap::new(), forbidden).unwrap_or((0, 0)))
+        } else if let Some(cell) = forbidden {
+            let best = best_move(&mut board, &mut HashMap::new(), Some(cell));
+            Some(best.map_or((0, 0), |(_, solution)| solution))
+        } else {
+            Some(solve(&mut board, &mut HashMap::new()))
+        }
+    }
+
+    // Number of moves until the game ends when both sides play optimally
+    pub fn terminal_depth(&self) -> u32 {
+        self.solution().map_or(0, |(_, depth)| depth)
+    }
+
+    // Most moves the player to move may take to win against any defense, making any move
+    // that keeps the win in hand rather than the quickest, or None without a forced win.
+    // The pessimistic counterpart of terminal_depth.  Classic games only, like hint
+    pub fn max_moves_to_win(&self) -> Option<u32> {
+        if self.config.variant != Variant::Classic
+            || self.outcome_with_optimal_play() != Some(OptimalOutcome::Win)
+        {
+            return None;
+        }
+        longest_win(
+            &mut self.board.clone(),
+            false,
+            &mut HashMap::new(),
+            &mut HashMap::new(),
+        )
+    }
+
+    // Every line of play from here to the end of the game in which each move keeps the best
+    // outcome for the player making it, in board index order of the moves.  A finished game
+    // has the one empty line, and wild games are not traced
+    pub fn trace_optimal_play(&self) -> Vec<Vec<Move>> {
+        let mut lines = vec![];
+        if self.config.variant != Variant::Classic {
+            return lines;
+        }
+        if self.is_finished() {
+            lines.push(vec![]);
+            return lines;
+        }
+        optimal_lines(
+            &mut self.board.clone(),
+            self.forbidden_cell(),
+            &mut HashMap::new(),
+            &mut vec![],
+            &mut lines,
+        );
+        lines
+    }
+
+    // Moves left until the board is full if the game can still end in a draw, whatever the
+    // players would have to do to get there.  None for a game that was won, aborted, or can
+    // only end with a winner
+    pub fn step_count_to_draw(&self) -> Option<usize> {
+        match self.game_state {
+            GameState::Draw => Some(0),
+            GameState::Waiting | GameState::XMove | GameState::OMove
+                if can_be_drawn(self.config.variant, &self.board) =>
+            {
+                Some(self.empty_cells() as usize)
             }
-            GameState::OMove => {
-                if player != self.player_o {
-                    return Err(ProgramError::PlayerNotFound);
+            _ => None,
+        }
+    }
+
+    // What the player to move gets if both players play perfectly from here.  A finished game
+    // is reported from the point of view of the player who would move next, so a won game is
+    // a Loss.  None for an aborted game, which has no outcome to play for
+    pub fn outcome_with_optimal_play(&self) -> Option<OptimalOutcome> {
+        Some(match self.game_state {
+            GameState::XWon | GameState::OWon => OptimalOutcome::Loss,
+            GameState::Draw => OptimalOutcome::Draw,
+            GameState::Aborted => return None,
+            _ => match self.solution() {
+                Some((1, _)) => OptimalOutcome::Win,
+                Some((-1, _)) => OptimalOutcome::Loss,
+                _ => OptimalOutcome::Draw,
+            },
+        })
+    }
+
+    pub fn move_quality(&self, x: usize, y: usize) -> Result<MoveQuality> {
+        let player = self.current_player().ok_or(ProgramError::NotYourTurn)?;
+        let mut next = self.clone();
+        next.next_move(player, x, y)?;
+
+        // The outcome for the player making the move, after making it
+        let outcome = match next.game_state {
+            GameState::XWon | GameState::OWon => OptimalOutcome::Win,
+            GameState::Draw => OptimalOutcome::Draw,
+            _ => match next.outcome_with_optimal_play() {
+                Some(OptimalOutcome::Win) => OptimalOutcome::Loss,
+                Some(OptimalOutcome::Loss) => OptimalOutcome::Win,
+                _ => OptimalOutcome::Draw,
+            },
+        };
+        Ok(match (self.outcome_with_optimal_play(), outcome) {
+            (Some(OptimalOutcome::Win), OptimalOutcome::Win) => MoveQuality::Optimal,
+            (Some(OptimalOutcome::Win), _) => MoveQuality::Blunder,
+            (Some(OptimalOutcome::Draw), OptimalOutcome::Loss) => MoveQuality::Mistake,
+            _ => MoveQuality::Optimal,
+        })
+    }
+
+    // Play `moves` in order in a new game between player_x and player_o
+    pub fn from_moves_iter<I>(player_x: Pubkey, player_o: Pubkey, moves: I) -> Result<Game>
+    where
+        I: IntoIterator<Item = Move>,
+    {
+        let mut game = Game::create(&player_x);
+        game.join(player_o, 1)?;
+        for m in moves {
+            let player = game.current_player().ok_or(ProgramError::NotYourTurn)?;
+            game.next_move(player, m.x, m.y)?;
+        }
+        Ok(game)
+    }
+
+    // The position before any move and after each of `moves`.  Frames stop at the first
+    // move that cannot be played
+    pub fn replay_animation_frames(
+        moves: &[Move],
+        player_x: Pubkey,
+        player_o: Pubkey,
+    ) -> Vec<Game> {
+        let mut game = match Game::from_moves_iter(player_x, player_o, std::iter::empty()) {
+            Ok(game) => game,
+            Err(_) => return vec![],
+        };
+        let mut frames = vec![game.clone()];
+        for m in moves {
+            match game.current_player() {
+                Some(player) if game.next_move(player, m.x, m.y).is_ok() => {
+                    frames.push(game.clone())
                 }
-                self.game_state = GameState::XMove;
-                (BOARD_ITEM_O, GameState::OWon)
+                _ => break,
             }
-            _ => {
-                return Err(ProgramError::NotYourTurn);
+        }
+        frames
+    }
+
+    // Symmetry class of a first move at (x, y), None if the cell is off the board
+    pub fn opening_class(x: usize, y: usize) -> Option<OpeningClass> {
+        if x >= 3 || y >= 3 {
+            return None;
+        }
+        Some(match (x == 1, y == 1) {
+            (true, true) => OpeningClass::Center,
+            (false, false) => OpeningClass::Corner,
+            _ => OpeningClass::Edge,
+        })
+    }
+
+    // The conventional name of the opening on the board after the first or second move, None
+    // before the first move, from the third on, and for boards no normal game opens with
+    pub fn opening_name(&self) -> Option<&'static str> {
+        let names = opening_names();
+        names
+            .binary_search_by_key(&self.board_as_u32(), |&(board, _)| board)
+            .ok()
+            .map(|i| names[i].1)
+    }
+
+    // Number of cells the boards of `a` and `b` differ in.  A game and the one a move later are
+    // 1 apart
+    pub fn board_distance(a: &Game, b: &Game) -> usize {
+        a.board
+            .iter()
+            .zip(b.board.iter())
+            .filter(|&(a, b)| a != b)
+            .count()
+    }
+
+    // Cells that (x, y) can be mapped onto by a symmetry that leaves the current board
+    // unchanged, including (x, y) itself, in board order
+    pub fn all_equivalent_moves(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let index = match cell_index(x, y) {
+            Ok(index) => index,
+            Err(_) => return vec![],
+        };
+        let mut cells: Vec<usize> = SYMMETRIES
+            .iter()
+            .filter(|symmetry| (0..9).all(|i| self.board[symmetry[i]] == self.board[i]))
+            .map(|symmetry| symmetry[index])
+            .collect();
+        cells.sort();
+        cells.dedup();
+        cells
+            .into_iter()
+            .filter_map(|i| cell_coords(i).ok())
+            .collect()
+    }
+
+    // One move from each class of available moves that are equivalent under symmetry
+    pub fn deduplicated_moves(&self) -> Vec<(usize, usize)> {
+        let mut moves: Vec<(usize, usize)> = vec![];
+        for (x, y) in self.available_moves() {
+            if !self
+                .all_equivalent_moves(x, y)
+                .iter()
+                .any(|cell| moves.contains(cell))
+            {
+                moves.push((x, y));
             }
+        }
+        moves
+    }
+
+    // Cells whose owner decides the game: taking the cell gets the player to move a different
+    // result under optimal play than leaving it to the opponent would.  Every winning move
+    // is one, unless another cell wins as well.  Classic games only, like hint
+    pub fn critical_cells(&self) -> Vec<usize> {
+        let (mine, theirs) = match self.marks_to_move() {
+            Some(marks) if self.config.variant == Variant::Classic => marks,
+            _ => return vec![],
         };
-        self.board[board_index] = x_or_o;
+        let variant = self.config.variant;
+        self.available_moves()
+            .into_iter()
+            .filter_map(|(x, y)| cell_index(x, y).ok())
+            .filter(|&i| {
+                let mut board = self.board;
+                board[i] = mine;
+                let taken = -value_for(variant, &mut board, theirs);
+                board[i] = theirs;
+                taken != value_for(variant, &mut board, mine)
+            })
+            .collect()
+    }
 
-        let winner =
-            // Check rows
-            Game::same(x_or_o, &self.board[0..3])
-            || Game::same(x_or_o, &self.board[3..6])
-            || Game::same(x_or_o, &self.board[6..9])
-            // Check columns
-            || Game::same(x_or_o, &[self.board[0], self.board[3], self.board[6]])
-            || Game::same(x_or_o, &[self.board[1], self.board[4], self.board[7]])
-            || Game::same(x_or_o, &[self.board[2], self.board[5], self.board[8]])
-            // Check both diagonals
-            || Game::same(x_or_o, &[self.board[0], self.board[4], self.board[8]])
-            || Game::same(x_or_o, &[self.board[2], self.board[4], self.board[6]]);
+    // Whether the game can only end in a draw, whatever either player does from here on.
+    // False once the game is over
+    pub fn is_drawing_forced(&self) -> bool {
+        match self.marks_to_move() {
+            Some((mine, _)) => !line_reachable(self.config.variant, &mut self.board.clone(), mine),
+            None => false,
+        }
+    }
 
-        if winner {
-            self.game_state = won_state;
-        } else if self.board.iter().all(|&p| p != BOARD_ITEM_FREE) {
-            self.game_state = GameState::Draw;
+    // Whether the player to move loses with optimal play, but would not if they could pass.
+    // An extra mark never hurts in a classic game, so only wild games get there, where every
+    // placement may set up a line for the opponent
+    pub fn is_zugzwang(&self) -> bool {
+        let (mine, theirs) = match self.marks_to_move() {
+            Some(marks) => marks,
+            None => return false,
+        };
+        let variant = self.config.variant;
+        let mut board = self.board;
+        match variant {
+            Variant::Classic => {
+                value_for(variant, &mut board, mine) == -1
+                    && value_for(variant, &mut board, theirs) < 1
+            }
+            // After a pass the opponent would be to move in the very same lost position
+            Variant::Wild => wild_solve(&mut board, &mut HashMap::new()).0 == -1,
         }
+    }
 
-        Ok(())
+    // What `player` moving at (x, y) would lead to, for previews in the client.  The move is
+    // made on a copy, by next_move itself, so it fails exactly when next_move would
+    pub fn preview_move(&self, player: Pubkey, x: usize, y: usize) -> Result<MovePreview> {
+        let mut after = self.clone();
+        after.next_move(player, x, y)?;
+        Ok(self.preview(after))
     }
 
-    pub fn keep_alive(self: &mut Game, player: Pubkey, timestamp: u64) -> Result<()> {
-        match self.game_state {
-            GameState::Waiting | GameState::XMove | GameState::OMove => {
-                if player == self.player_x {
-                    if timestamp <= self.keep_alive[0] {
-                        Err(ProgramError::InvalidTimestamp)?;
-                    }
-                    self.keep_alive[0] = timestamp;
-                } else if player == self.player_o {
-                    if timestamp <= self.keep_alive[1] {
-                        Err(ProgramError::InvalidTimestamp)?;
-                    }
-                    self.keep_alive[1] = timestamp;
-                } else {
-                    Err(ProgramError::PlayerNotFound)?;
+    // preview_move for a wild game, placing `mark` the way next_move_wild would
+    pub fn preview_move_wild(
+        &self,
+        player: Pubkey,
+        x: usize,
+        y: usize,
+        mark: Cell,
+    ) -> Result<MovePreview> {
+        let mut after = self.clone();
+        after.next_move_wild(player, x, y, mark)?;
+        Ok(self.preview(after))
+    }
+
+    fn preview(&self, after: Game) -> MovePreview {
+        let wins = match after.game_state {
+            GameState::XWon => self.game_state == GameState::XMove,
+            GameState::OWon => self.game_state == GameState::OMove,
+            _ => false,
+        };
+        // The hint knows nothing of wild games, where a line of either mark wins for whoever
+        // completes it
+        let opponent_wins_next = match self.config.variant {
+            Variant::Classic => matches!(after.hint(), Hint::WinNow(..)),
+            Variant::Wild => {
+                !after.is_finished()
+                    && [BOARD_ITEM_X, BOARD_ITEM_O]
+                        .iter()
+                        .any(|&mark| completing_cell(Variant::Wild, &after.board, mark).is_some())
+            }
+        };
+        MovePreview {
+            game_state: after.game_state,
+            wins,
+            opponent_wins_next,
+            board: after.board,
+        }
+    }
+
+    // The position in a few plain English sentences, for tutorials and test output: the
+    // threats of each mark, then whose turn it is or how the game ended
+    pub fn human_explanation(&self) -> String {
+        let mut sentences = vec![];
+        for &(mark, name) in [(BOARD_ITEM_X, "X"), (BOARD_ITEM_O, "O")].iter() {
+            let threats: Vec<&str> = self
+                .threats(mark)
+                .iter()
+                .filter_map(|line| WIN_LINES.iter().position(|l| l == line))
+                .map(|i| LINE_NAMES[i])
+                .collect();
+            let mut sentence = match threats.split_last() {
+                None => format!("{} has no immediate threats", name),
+                Some((last, [])) => {
+                    format!("{} has a two-in-a-row with one free cell in {}", name, last)
                 }
+                Some((last, rest)) => format!(
+                    "{} has two-in-a-rows with one free cell in {} and {}",
+                    name,
+                    rest.join(", "),
+                    last
+                ),
+            };
+            if self.potential_lines_for_player(mark) == 0 {
+                sentence.push_str(" and can no longer complete a line");
+            }
+            sentences.push(sentence + ".");
+        }
+        sentences.push(
+            match (self.game_state, self.current_player()) {
+                (GameState::XMove, Some(_)) => "It is X's turn.",
+                (GameState::OMove, Some(_)) => "It is O's turn.",
+                (GameState::XWon, _) => "X has won.",
+                (GameState::OWon, _) => "O has won.",
+                (GameState::Draw, _) => "The game is drawn.",
+                (GameState::Aborted, _) => "The game was aborted.",
+                _ => "The game is waiting for player O to join.",
+            }
+            .to_string(),
+        );
+        sentences.join(" ")
+    }
+
+    // Classic games only, the solver knows nothing of wild ones
+    pub fn hint(&self) -> Hint {
+        let (mine, theirs) = match self.marks_to_move() {
+            Some(marks) if self.config.variant == Variant::Classic => marks,
+            _ => return Hint::NoHint,
+        };
+        let completing = |x_or_o| {
+            completing_cell(self.config.variant, &self.board, x_or_o)
+                .and_then(|i| cell_coords(i).ok())
+        };
+        if let Some((x, y)) = completing(mine) {
+            return Hint::WinNow(x, y);
+        }
+        if let Some((x, y)) = completing(theirs) {
+            return Hint::BlockNow(x, y);
+        }
+        let best = best_move(
+            &mut self.board.clone(),
+            &mut HashMap::new(),
+            self.forbidden_cell(),
+        );
+        match best.and_then(|(i, _)| cell_coords(i).ok()) {
+            Some((x, y)) => Hint::Optimal(x, y),
+            None => Hint::NoHint,
+        }
+    }
+}
+
+impl Game {
+    // The transition table as a Graphviz digraph, for `dot -Tpng`.  Finished states are
+    // double circled and the reset edges out of them dashed
+    pub fn state_machine_diagram() -> String {
+        let mut dot = "digraph GameState {\n    rankdir=LR;\n".to_string();
+        for state in GameState::ALL.iter() {
+            let shape = match state {
+                GameState::Waiting | GameState::XMove | GameState::OMove => "circle",
+                _ => "doublecircle",
+            };
+            dot.push_str(&format!("    {:?} [shape={}];\n", state, shape));
+        }
+        for &(from, action, to) in transition_table() {
+            let style = if action == Action::Reset {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    {:?} -> {:?} [label=\"{}\"{}];\n",
+                from,
+                to,
+                action.name(),
+                style
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// Reconstruct the moves that turned the `older` snapshot of a game into the `newer` one from
+// the board difference alone.  Fails with InconsistentSnapshots if no legal sequence of moves
+// explains the difference, and with AmbiguousHistory if more than one does
+pub fn infer_moves(older: &Game, newer: &Game) -> Result<Vec<(Pubkey, usize, usize)>> {
+    if older.player_x != newer.player_x {
+        Err(ProgramError::InconsistentSnapshots)?;
+    }
+    let start = if older.game_state == GameState::Waiting {
+        if older.board.iter().any(|&item| item != BOARD_ITEM_FREE) {
+            Err(ProgramError::InconsistentSnapshots)?;
+        }
+        let mut start = older.clone();
+        start.player_o = newer.player_o;
+        start.game_state = GameState::XMove;
+        start
+    } else if older.player_o == newer.player_o {
+        older.clone()
+    } else {
+        return Err(ProgramError::InconsistentSnapshots.into());
+    };
+    if newer.game_state == GameState::Waiting {
+        return if start.board == newer.board && older.game_state == GameState::Waiting {
+            Ok(vec![])
+        } else {
+            Err(ProgramError::InconsistentSnapshots.into())
+        };
+    }
+
+    fn search(
+        position: &Game,
+        target: &Game,
+        moves: &mut Vec<(Pubkey, usize, usize)>,
+        found: &mut Vec<Vec<(Pubkey, usize, usize)>>,
+    ) {
+        if position.board == target.board {
+            if position.game_state == target.game_state {
+                found.push(moves.clone());
             }
-            // Ignore keep_alive when game is no longer in progress
-            GameState::XWon | GameState::OWon | GameState::Draw => {}
+            return;
+        }
+        let (player, x_or_o) = match position.game_state {
+            GameState::XMove => (position.player_x, BOARD_ITEM_X),
+            GameState::OMove => (position.player_o, BOARD_ITEM_O),
+            _ => return,
         };
-        Ok(())
+        for i in 0..position.board.len() {
+            if found.len() > 1 {
+                return;
+            }
+            if position.board[i] != BOARD_ITEM_FREE || target.board[i] != x_or_o {
+                continue;
+            }
+            let (x, y) = match cell_coords(i) {
+                Ok(cell) => cell,
+                Err(_) => continue,
+            };
+            let mut next = position.clone();
+            if next.next_move(player, x, y).is_ok() {
+                moves.push((player, x, y));
+                search(&next, target, moves, found);
+                moves.pop();
+            }
+        }
+    }
+
+    let mut found = vec![];
+    search(&start, newer, &mut vec![], &mut found);
+    match found.len() {
+        0 => Err(ProgramError::InconsistentSnapshots.into()),
+        1 => Ok(found.pop().unwrap()),
+        _ => Err(ProgramError::AmbiguousHistory.into()),
     }
 }
 
+// The game between the keys [1; 32] and [2; 32] that most tests use, after the moves at these
+// board indexes
+#[cfg(test)]
+pub fn play(moves: &[usize]) -> Game {
+    play_between(Pubkey::new(&[1; 32]), Pubkey::new(&[2; 32]), moves)
+}
+
+#[cfg(test)]
+pub fn play_between(player_x: Pubkey, player_o: Pubkey, moves: &[usize]) -> Game {
+    let moves = moves.iter().map(|&i| {
+        let (x, y) = cell_coords(i).unwrap();
+        Move { x, y }
+    });
+    Game::from_moves_iter(player_x, player_o, moves).unwrap()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use bincode;
+    use invariants;
+    use perfect_play;
+    use player_name::PlayerName;
+    use record::EndReason;
+    use result::{ErrorContext, GameError};
+    use serde_json;
+    use trophy::Trophy;
+
+    #[cfg(feature = "logging")]
+    mod capture {
+        use super::LOG_TARGET;
+        use log::{self, Log, Metadata, Record};
+        use std::cell::RefCell;
+        use std::sync::Once;
+
+        // Collects engine log records per test thread so parallel tests don't interleave
+        struct CaptureLogger;
+
+        thread_local!(static RECORDS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) });
+
+        impl Log for CaptureLogger {
+            fn enabled(&self, metadata: &Metadata) -> bool {
+                metadata.target() == LOG_TARGET
+            }
+
+            fn log(&self, record: &Record) {
+                if self.enabled(record.metadata()) {
+                    RECORDS.with(|records| records.borrow_mut().push(format!("{}", record.args())));
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: CaptureLogger = CaptureLogger;
+        static INIT: Once = Once::new();
+
+        pub fn start() {
+            INIT.call_once(|| {
+                log::set_logger(&LOGGER).unwrap();
+                log::set_max_level(log::LevelFilter::Trace);
+            });
+            RECORDS.with(|records| records.borrow_mut().clear());
+        }
+
+        pub fn records() -> Vec<String> {
+            RECORDS.with(|records| records.borrow().clone())
+        }
+    }
 
     #[test]
     pub fn column_1_x_wins() {
@@ -170,6 +1032,26 @@ mod test {
         assert_eq!(g.game_state, GameState::XWon);
     }
 
+    #[test]
+    pub fn display_board_with_coords() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        for &(x, y) in &[(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)] {
+            let player = g.current_player().unwrap();
+            g.next_move(player, x, y).unwrap();
+        }
+        assert_eq!(
+            g.display_board_with_coords(),
+            "  0 1 2
+0 X|O|
+  -+-+-
+1 X|O|
+  -+-+-
+2 X| |"
+        );
+    }
+
     #[test]
     pub fn right_diagonal_x_wins() {
         /*
@@ -274,6 +1156,30 @@ mod test {
         assert_eq!(g.game_state, GameState::Draw);
     }
 
+    #[test]
+    pub fn full_board_is_finished() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        for &(x, y) in &[
+            (0, 0),
+            (1, 1),
+            (0, 2),
+            (0, 1),
+            (2, 1),
+            (1, 0),
+            (1, 2),
+            (2, 2),
+            (2, 0),
+        ] {
+            let player = g.current_player().unwrap();
+            g.next_move(player, x, y).unwrap();
+        }
+        assert!(g.is_board_full());
+        assert!(g.is_finished());
+        assert!(g.clone().next_move(player_o, 0, 0).is_err());
+    }
+
     #[test]
     pub fn solo() {
         /*
@@ -293,4 +1199,2703 @@ mod test {
         g.next_move(player_x, 1, 0).unwrap();
         assert_eq!(g.game_state, GameState::XMove);
     }
+
+    #[test]
+    pub fn seat_of() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let stranger = Pubkey::new(&[3; 32]);
+
+        // Player O has no seat before joining
+        let mut g = Game::create(&player_x);
+        assert_eq!(g.seat_of(&player_x).unwrap(), Seat::X);
+        assert_eq!(
+            g.seat_of(&player_o).unwrap_err().kind,
+            ProgramError::PlayerNotFound
+        );
+        g.join(player_o, 1).unwrap();
+        for &(player, seat) in &[(player_x, Seat::X), (player_o, Seat::O)] {
+            assert_eq!(g.seat_of(&player).unwrap(), seat);
+            assert_eq!(g.player(seat), player);
+            let opponent = g.player(seat.opponent());
+            assert_eq!(g.seat_of(&opponent).unwrap(), seat.opponent());
+            assert_eq!(seat.opponent().opponent(), seat);
+            assert_ne!(seat.opponent(), seat);
+        }
+        let err = g.seat_of(&stranger).unwrap_err();
+        assert_eq!(
+            (err.kind, err.context),
+            (ProgramError::PlayerNotFound, ErrorContext::Player(stranger))
+        );
+
+        // A solo player sits in the seat whose turn it is, X's once the game is over
+        let mut g = Game::new(player_x, player_x);
+        assert_eq!(g.seat_of(&player_x).unwrap(), Seat::X);
+        g.next_move(player_x, 0, 0).unwrap();
+        assert_eq!(g.seat_of(&player_x).unwrap(), Seat::O);
+        g.keep_alive(player_x, 5).unwrap();
+        assert_eq!(g.keep_alive.get(Seat::O), 5);
+        assert!(!g.keep_alive.is_set(Seat::X));
+        for &i in [3, 1, 4].iter() {
+            let (x, y) = cell_coords(i).unwrap();
+            g.next_move(player_x, x, y).unwrap();
+        }
+        g.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(g.seat_of(&player_x).unwrap(), Seat::X);
+    }
+
+    #[test]
+    pub fn board_hash() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        let g = Game::new(player_x, player_o);
+        assert_eq!(g.board_hash(), 0xe604_823a_2490_29bf);
+
+        let mut a = Game::new(player_x, player_o);
+        let mut b = Game::new(player_x, player_o);
+        a.next_move(player_x, 1, 1).unwrap();
+        b.next_move(player_x, 1, 1).unwrap();
+        assert_eq!(a.board_hash(), b.board_hash());
+
+        for i in 0..9 {
+            let mut c = Game::new(player_x, player_o);
+            c.next_move(player_x, 1, 1).unwrap();
+            c.board[i] = if c.board[i] == BOARD_ITEM_X {
+                BOARD_ITEM_O
+            } else {
+                BOARD_ITEM_X
+            };
+            assert_ne!(a.board_hash(), c.board_hash());
+        }
+    }
+
+    #[test]
+    pub fn normalize_player_order() {
+        /*
+            X| |X
+            -+-+-
+             |O|
+            -+-+-
+             | |
+        */
+        let low = Pubkey::new(&[1; 32]);
+        let high = Pubkey::new(&[2; 32]);
+
+        let mut a = Game::new(low, high);
+        a.next_move(low, 0, 0).unwrap();
+        a.next_move(high, 1, 1).unwrap();
+        a.next_move(low, 2, 0).unwrap();
+        assert!(a.is_normalized());
+        assert_eq!(a.normalize_player_order(), a);
+
+        // The same game recorded with the seats swapped
+        let mut b = Game::new(high, low);
+        b.keep_alive = a.keep_alive;
+        b.keep_alive.swap();
+        b.game_state = GameState::XMove;
+        b.board[0] = BOARD_ITEM_O;
+        b.board[2] = BOARD_ITEM_O;
+        b.board[4] = BOARD_ITEM_X;
+        b.moves = a.moves;
+        b.move_count = a.move_count;
+        assert!(!b.is_normalized());
+        assert_ne!(a.board_hash(), b.board_hash());
+
+        let na = a.normalize_player_order();
+        let nb = b.normalize_player_order();
+        assert!(nb.is_normalized());
+        assert_eq!(na.board_hash(), nb.board_hash());
+        assert_eq!(na.player_x, nb.player_x);
+        assert_eq!(na, nb);
+    }
+
+    #[test]
+    pub fn finalize_with_trophy() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let game_pubkey = Pubkey::new(&[3; 32]);
+
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 0, 2).unwrap();
+        g.next_move(player_x, 1, 0).unwrap();
+        assert!(g.finalize_with_trophy(&game_pubkey, 10).is_err());
+        g.next_move(player_o, 1, 2).unwrap();
+        g.next_move(player_x, 0, 1).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        assert_eq!(g.game_state, GameState::OWon);
+
+        let trophy = g.finalize_with_trophy(&game_pubkey, 10).unwrap().unwrap();
+        assert_eq!(
+            trophy,
+            Trophy {
+                game: game_pubkey,
+                winner: player_o,
+                loser: player_x,
+                board: g.board,
+                timestamp: 10,
+            }
+        );
+        assert!(g.finalize_with_trophy(&game_pubkey, 11).is_err());
+    }
+
+    #[test]
+    pub fn errors_carry_context() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let stranger = Pubkey::new(&[7; 32]);
+        let mut g = Game::new(player_x, player_o);
+
+        let err = g.next_move(player_x, 0, 3).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidMove);
+        assert_eq!(err.context, ErrorContext::Coordinate(0, 3));
+
+        let err = g.next_move(stranger, 1, 1).unwrap_err();
+        assert_eq!(err.kind, ProgramError::PlayerNotFound);
+        assert_eq!(err.context, ErrorContext::Player(stranger));
+
+        let err = g.keep_alive(player_o, 1).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidTimestamp);
+        assert_eq!(err.context, ErrorContext::Timestamp(1));
+
+        let err = g.request_abort(stranger).unwrap_err();
+        assert_eq!(err.context, ErrorContext::Player(stranger));
+        let err = g.claim_timeout(player_o, 2).unwrap_err();
+        assert_eq!(err.kind, ProgramError::NotTimedOut);
+        assert_eq!(err.context, ErrorContext::Timestamp(2));
+        let err = g.swap_seats(player_o).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidInput);
+        assert_eq!(err.context, ErrorContext::Player(player_o));
+        let err = g.finalize_with_trophy(&stranger, 2).unwrap_err();
+        assert_eq!(err.kind, ProgramError::GameInProgress);
+        assert_eq!(err.context, ErrorContext::Outcome(GameState::XMove));
+
+        // Errors without anything to point at, and the on chain encoding, drop the context
+        let err = GameBuilder::new().build().unwrap_err();
+        assert_eq!(err.context, ErrorContext::None);
+        assert_eq!(
+            u64::from(ProgramError::PlayerNotFound.at(ErrorContext::Player(stranger))),
+            ProgramError::PlayerNotFound as u64
+        );
+    }
+
+    #[test]
+    pub fn finished_games_are_immutable() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        let mut x_won = Game::new(player_x, player_o);
+        for &(x, y) in &[(1, 1), (0, 0), (0, 1), (2, 0), (2, 1)] {
+            let player = x_won.current_player().unwrap();
+            x_won.next_move(player, x, y).unwrap();
+        }
+        let mut o_won = Game::new(player_x, player_o);
+        for &(x, y) in &[(0, 0), (0, 2), (1, 0), (1, 2), (0, 1), (2, 2)] {
+            let player = o_won.current_player().unwrap();
+            o_won.next_move(player, x, y).unwrap();
+        }
+        let mut draw = Game::new(player_x, player_o);
+        for &(x, y) in &[
+            (0, 0),
+            (1, 1),
+            (0, 2),
+            (0, 1),
+            (2, 1),
+            (1, 0),
+            (1, 2),
+            (2, 2),
+            (2, 0),
+        ] {
+            let player = draw.current_player().unwrap();
+            draw.next_move(player, x, y).unwrap();
+        }
+        let mut aborted = Game::new(player_x, player_o);
+        aborted.request_abort(player_x).unwrap();
+        aborted.confirm_abort(player_o).unwrap();
+        let finished = [
+            (x_won, GameState::XWon),
+            (o_won, GameState::OWon),
+            (draw, GameState::Draw),
+            (aborted, GameState::Aborted),
+        ];
+
+        for &(ref g, game_state) in finished.iter() {
+            assert_eq!(g.game_state, game_state);
+            let refused = |result: Result<()>| {
+                matches!(
+                    result,
+                    Err(GameError {
+                        kind: ProgramError::GameFinished,
+                        ..
+                    })
+                )
+            };
+            for &player in &[player_x, player_o] {
+                assert!(refused(g.clone().join(player, 5).map(|_| ())));
+                assert!(refused(g.clone().next_move(player, 2, 2)));
+                assert!(refused(g.clone().swap_seats(player)));
+                assert!(refused(g.clone().request_abort(player)));
+                assert!(refused(g.clone().confirm_abort(player)));
+                assert!(refused(g.clone().claim_timeout(player, 1_000)));
+                let name = PlayerName::new("late").unwrap();
+                assert!(refused(g.clone().set_name(player, name)));
+
+                // Heartbeats and starting over are still allowed
+                assert!(g.clone().keep_alive(player, 5).is_ok());
+                assert!(g.clone().reset(&player, 5).is_ok());
+            }
+            let trophy = g.clone().finalize_with_trophy(&Pubkey::new(&[3; 32]), 5);
+            let won = game_state == GameState::XWon || game_state == GameState::OWon;
+            assert_eq!(trophy.unwrap().is_some(), won);
+            assert!(g.validate().is_ok());
+
+            let mut tampered = g.clone();
+            tampered.board[8] = BOARD_ITEM_FREE;
+            tampered.board[6] = BOARD_ITEM_X;
+            assert!(tampered.validate().is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(all(debug_assertions, feature = "json"))]
+    #[should_panic(expected = "board of a finished game changed")]
+    pub fn encoding_a_tampered_finished_game() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        g.request_abort(player_x).unwrap();
+        g.confirm_abort(player_o).unwrap();
+        g.board[4] = BOARD_ITEM_X;
+        let _ = g.to_json();
+    }
+
+    #[test]
+    pub fn finalize_draw_without_trophy() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 1, 1).unwrap();
+        g.next_move(player_x, 0, 2).unwrap();
+        g.next_move(player_o, 0, 1).unwrap();
+        g.next_move(player_x, 2, 1).unwrap();
+        g.next_move(player_o, 1, 0).unwrap();
+        g.next_move(player_x, 1, 2).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        g.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(g.game_state, GameState::Draw);
+
+        assert_eq!(
+            g.finalize_with_trophy(&Pubkey::default(), 10).unwrap(),
+            None
+        );
+        assert!(!g.trophy_minted);
+    }
+
+    #[test]
+    pub fn to_flat_features() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+
+        let features = g.to_flat_features();
+        assert!(features[0..18].iter().all(|&f| f == 0.0));
+        assert!(features[18..27].iter().all(|&f| f == 1.0));
+
+        g.next_move(player_x, 1, 1).unwrap();
+        g.next_move(player_o, 0, 0).unwrap();
+        g.next_move(player_x, 2, 0).unwrap();
+        let features = g.to_flat_features();
+        assert_eq!(features.len(), 27);
+        assert!(features.iter().all(|&f| f == 0.0 || f == 1.0));
+        for i in 0..9 {
+            assert_eq!(features[i] + features[9 + i] + features[18 + i], 1.0);
+        }
+        assert_eq!(features[4], 1.0);
+        assert_eq!(features[2], 1.0);
+        assert_eq!(features[9], 1.0);
+    }
+
+    #[cfg(feature = "logging")]
+    #[test]
+    pub fn logging() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+
+        capture::start();
+        g.next_move(player_x, 0, 0).unwrap();
+        assert!(g.next_move(player_x, 1, 0).is_err());
+        assert_eq!(
+            capture::records(),
+            vec![
+                format!("next_move: player={} x=0 y=0 state=XMove", player_x),
+                "next_move: accepted state=OMove".to_string(),
+                format!("next_move: player={} x=1 y=0 state=OMove", player_x),
+                "next_move: rejected reason=PlayerNotFound state=OMove".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn builder() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        assert!(GameBuilder::new().player_o(player_o).build().is_err());
+        let err = GameBuilder::new().player_x(player_x).build().unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidTimestamp);
+
+        let g = GameBuilder::new()
+            .player_x(player_x)
+            .timestamp(1)
+            .build()
+            .unwrap();
+        assert_eq!(g, Game::try_create(&player_x, 1).unwrap());
+
+        // Joining requires a timestamp newer than the initial keep alive
+        assert!(GameBuilder::new()
+            .player_x(player_x)
+            .player_o(player_o)
+            .timestamp(1)
+            .build()
+            .is_err());
+
+        let config = GameConfig {
+            variant: Variant::Wild,
+            swap_rule: true,
+            timeout_unit: TimeoutUnit::Slots,
+            ..GameConfig::default()
+        };
+        let builder = || {
+            GameBuilder::new()
+                .player_x(player_x)
+                .player_o(player_o)
+                .invited_player(player_o)
+                .config(config)
+                .timeout(30)
+                .timestamp(5)
+                .join_timestamp(6)
+        };
+        let clock = ClockSnapshot::from_tick_height;
+        let config = GameConfig {
+            move_timeout: 30,
+            ..config
+        };
+        let mut expected = Game::create_at(&player_x, config, &clock(5)).unwrap();
+        expected.join_at(player_o, &clock(6)).unwrap();
+        assert_eq!(builder().build().unwrap(), expected);
+
+        // Only the invited player joins, and the config is checked like any other
+        let stranger = Pubkey::new(&[3; 32]);
+        let err = builder().player_o(stranger).build().unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::PlayerNotFound.at(ErrorContext::Player(stranger))
+        );
+        let err = builder().timeout(0).build().unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidConfig);
+    }
+
+    #[test]
+    pub fn validate() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        g.validate().unwrap();
+
+        g.next_move(player_x, 0, 0).unwrap();
+        g.validate().unwrap();
+
+        let mut bad = g.clone();
+        bad.board[8] = 3;
+        assert!(bad.validate().is_err());
+
+        let mut bad = g.clone();
+        bad.game_state = GameState::XMove;
+        assert!(bad.validate().is_err());
+
+        let mut bad = g.clone();
+        bad.board[1] = BOARD_ITEM_X;
+        assert!(bad.validate().is_err());
+    }
+
+    fn assert_same_position(a: &Game, b: &Game) {
+        assert_eq!(a.game_state, b.game_state);
+        assert_eq!(a.player_x, b.player_x);
+        assert_eq!(a.player_o, b.player_o);
+        assert_eq!(a.board, b.board);
+        assert_eq!(a.moves, b.moves);
+        assert_eq!(a.move_count, b.move_count);
+    }
+
+    #[test]
+    pub fn at_move() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 0, 2).unwrap();
+        g.next_move(player_x, 1, 0).unwrap();
+        g.next_move(player_o, 1, 2).unwrap();
+        g.next_move(player_x, 0, 1).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        assert_eq!(g.move_count(), 6);
+
+        assert_same_position(&g.at_move(0).unwrap(), &Game::new(player_x, player_o));
+        assert_same_position(&g.at_move(6).unwrap(), &g);
+        assert!(g.at_move(7).is_err());
+
+        let mut expected = Game::new(player_x, player_o);
+        expected.next_move(player_x, 0, 0).unwrap();
+        expected.next_move(player_o, 0, 2).unwrap();
+        assert_same_position(&g.at_move(2).unwrap(), &expected);
+
+        assert!(Game::create(&player_x).at_move(0).is_err());
+    }
+
+    #[test]
+    pub fn iter_positions() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 1, 1).unwrap();
+        g.next_move(player_x, 0, 2).unwrap();
+        g.next_move(player_o, 0, 1).unwrap();
+        g.next_move(player_x, 2, 1).unwrap();
+        g.next_move(player_o, 1, 0).unwrap();
+        g.next_move(player_x, 1, 2).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        g.next_move(player_x, 2, 0).unwrap();
+
+        let positions: Vec<Game> = g.iter_positions().collect();
+        assert_eq!(positions.len(), g.move_count() + 1);
+        for (n, position) in positions.iter().enumerate() {
+            position.validate().unwrap();
+            assert_eq!(position.move_count(), n);
+            assert_same_position(position, &g.at_move(n).unwrap());
+        }
+        assert_same_position(&positions[9], &g);
+        assert_eq!(positions[9].game_state, GameState::Draw);
+
+        assert_eq!(Game::create(&player_x).iter_positions().count(), 0);
+    }
+
+    #[test]
+    pub fn to_html_table() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+
+        let html = g.to_html_table();
+        assert!(html.starts_with("<table"));
+        assert!(!html.contains("<html"));
+        assert_eq!(html.matches("<td").count(), 9);
+        assert_eq!(html.matches("class=\"cell-free\">&nbsp;</td>").count(), 9);
+
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 1, 0).unwrap();
+        g.next_move(player_x, 0, 1).unwrap();
+        g.next_move(player_o, 1, 1).unwrap();
+        g.next_move(player_x, 0, 2).unwrap();
+        assert_eq!(g.winning_line(), Some([0, 3, 6]));
+
+        let html = g.to_html_table();
+        assert_eq!(html.matches("<td").count(), 9);
+        assert_eq!(html.matches("class=\"cell-x winning\">X</td>").count(), 3);
+        assert_eq!(html.matches("class=\"cell-o\">O</td>").count(), 2);
+        assert_eq!(html.matches("winning").count(), 3);
+        assert_eq!(html.matches("cell-free").count(), 4);
+    }
+
+    #[test]
+    pub fn infer_single_move() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let older = Game::new(player_x, player_o);
+        let mut newer = older.clone();
+        newer.next_move(player_x, 2, 1).unwrap();
+
+        assert_eq!(infer_moves(&older, &newer).unwrap(), vec![(player_x, 2, 1)]);
+        assert_eq!(infer_moves(&newer, &newer).unwrap(), vec![]);
+        assert_eq!(
+            infer_moves(&Game::create(&player_x), &newer).unwrap(),
+            vec![(player_x, 2, 1)]
+        );
+    }
+
+    #[test]
+    pub fn infer_unique_gap() {
+        /*
+            X|X|X
+            -+-+-
+            O|O|
+            -+-+-
+            O| |X
+        */
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut older = Game::new(player_x, player_o);
+        older.next_move(player_x, 0, 0).unwrap();
+        older.next_move(player_o, 0, 1).unwrap();
+        older.next_move(player_x, 1, 0).unwrap();
+        older.next_move(player_o, 1, 1).unwrap();
+
+        // Playing (2, 0) first would have ended the game, so X must have played (2, 2) first
+        let mut newer = older.clone();
+        newer.next_move(player_x, 2, 2).unwrap();
+        newer.next_move(player_o, 0, 2).unwrap();
+        newer.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(newer.game_state, GameState::XWon);
+
+        assert_eq!(
+            infer_moves(&older, &newer).unwrap(),
+            vec![(player_x, 2, 2), (player_o, 0, 2), (player_x, 2, 0)]
+        );
+    }
+
+    #[test]
+    pub fn infer_ambiguous_gap() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let older = Game::new(player_x, player_o);
+        let mut newer = older.clone();
+        newer.next_move(player_x, 0, 0).unwrap();
+        newer.next_move(player_o, 1, 1).unwrap();
+        newer.next_move(player_x, 2, 2).unwrap();
+
+        match infer_moves(&older, &newer) {
+            Err(GameError {
+                kind: ProgramError::AmbiguousHistory,
+                ..
+            }) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    pub fn infer_inconsistent_snapshots() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut older = Game::new(player_x, player_o);
+        older.next_move(player_x, 0, 0).unwrap();
+
+        // A snapshot of some other game
+        let mut other = Game::new(player_o, player_x);
+        other.next_move(player_o, 0, 0).unwrap();
+        other.next_move(player_x, 1, 1).unwrap();
+        match infer_moves(&older, &other) {
+            Err(GameError {
+                kind: ProgramError::InconsistentSnapshots,
+                ..
+            }) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // Time running backwards
+        match infer_moves(&older, &Game::new(player_x, player_o)) {
+            Err(GameError {
+                kind: ProgramError::InconsistentSnapshots,
+                ..
+            }) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    pub fn terminal_depth() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!(g.terminal_depth(), 9);
+        assert_eq!(Game::create(&player_x).terminal_depth(), 9);
+
+        /*
+            X|X|
+            -+-+-
+            O|O|
+            -+-+-
+             | |
+        */
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 0, 1).unwrap();
+        g.next_move(player_x, 1, 0).unwrap();
+        g.next_move(player_o, 1, 1).unwrap();
+        assert_eq!(g.terminal_depth(), 1);
+
+        g.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(g.terminal_depth(), 0);
+    }
+
+    #[test]
+    pub fn try_create() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let g = Game::try_create(&player_x, 7).unwrap();
+        assert_eq!(g.game_state, GameState::Waiting);
+        assert_eq!((g.created_at, g.keep_alive.get(Seat::X)), (7, 7));
+        assert_eq!(g.config, GameConfig::default());
+
+        // The creation time survives the account encoding and snapshots
+        let decoded: Game = bincode::deserialize(&bincode::serialize(&g).unwrap()).unwrap();
+        assert_eq!(decoded.created_at, 7);
+        assert_eq!(
+            Game::from_snapshot(&g.to_snapshot().unwrap())
+                .unwrap()
+                .created_at,
+            7
+        );
+
+        assert_eq!(
+            Game::try_create(&Pubkey::default(), 7),
+            Err(ProgramError::InvalidInput.at(ErrorContext::Player(Pubkey::default())))
+        );
+        assert_eq!(
+            Game::try_create(&player_x, 0),
+            Err(ProgramError::InvalidTimestamp.at(ErrorContext::Timestamp(0)))
+        );
+
+        // The program's constructor checks the same
+        let clock = ClockSnapshot {
+            slot: 3,
+            unix_timestamp: 0,
+        };
+        assert!(Game::create_at(&player_x, GameConfig::default(), &clock).is_err());
+        let slots = GameConfig {
+            timeout_unit: TimeoutUnit::Slots,
+            ..GameConfig::default()
+        };
+        let g = Game::create_at(&player_x, slots, &clock).unwrap();
+        assert_eq!(g.created_at, 3);
+        assert!(Game::create_at(&Pubkey::default(), slots, &clock).is_err());
+    }
+
+    #[test]
+    pub fn trace_optimal_play() {
+        // Every line replays to the end, and to the outcome perfect play promised
+        let replay = |g: &Game, line: &[Move]| {
+            let mut g = g.clone();
+            for m in line {
+                let player = g.current_player().unwrap();
+                g.next_move(player, m.x, m.y).unwrap();
+            }
+            g.game_state
+        };
+
+        let g = play(&[]);
+        let lines = g.trace_optimal_play();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Draw));
+        assert_eq!(lines.len(), 3584);
+        assert!(lines
+            .iter()
+            .all(|line| replay(&g, line) == GameState::Draw && line.len() == 9));
+
+        // Lost for O, who is to move
+        let g = play(&[0, 1, 4]);
+        let lines = g.trace_optimal_play();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Loss));
+        assert_eq!(lines.len(), 163);
+        assert!(lines.iter().all(|line| replay(&g, line) == GameState::XWon));
+
+        assert_eq!(play(&[0, 1, 3, 4, 6]).trace_optimal_play(), vec![vec![]]);
+    }
+
+    #[test]
+    pub fn max_moves_to_win() {
+        assert_eq!(play(&[]).max_moves_to_win(), None);
+        // Lost for O, who is to move
+        assert_eq!(play(&[0, 1, 4]).max_moves_to_win(), None);
+
+        // X wins on 6 at once, and anything else lets O win on 7
+        let g = play(&[0, 1, 3, 4]);
+        assert_eq!(g.max_moves_to_win(), Some(1));
+        assert_eq!(g.terminal_depth(), 1);
+
+        // X forks with 6 and wins in three moves, but may take as many as five
+        let g = play(&[0, 1, 4, 8]);
+        assert_eq!(g.terminal_depth(), 3);
+        assert_eq!(g.max_moves_to_win(), Some(5));
+
+        assert_eq!(play(&[0, 1, 3, 4, 6]).max_moves_to_win(), None);
+    }
+
+    #[test]
+    pub fn reset() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!(g.generation(), 0);
+
+        g.next_move(player_x, 0, 0).unwrap();
+        assert!(g.reset(&player_o, 10).is_err());
+        assert_eq!(g.generation(), 0);
+
+        g.next_move(player_o, 1, 1).unwrap();
+        g.next_move(player_x, 0, 2).unwrap();
+        g.next_move(player_o, 0, 1).unwrap();
+        g.next_move(player_x, 2, 1).unwrap();
+        g.next_move(player_o, 1, 0).unwrap();
+        g.next_move(player_x, 1, 2).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        g.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(g.game_state, GameState::Draw);
+        let seen = g.generation();
+
+        // Only the players start the next game in the account, whoever resets it is X
+        let stranger = Pubkey::new(&[3; 32]);
+        let err = g.clone().reset(&stranger, 10).unwrap_err();
+        assert_eq!(err.kind, ProgramError::PlayerNotFound);
+        g.reset(&player_o, 10).unwrap();
+        assert_eq!(g.player_x(), &player_o);
+        assert_eq!(g.game_state, GameState::Waiting);
+        assert_eq!(g.generation(), 1);
+        assert!(g.is_stale(seen));
+        assert!(!g.is_stale(g.generation()));
+        assert_eq!(g.board, [BOARD_ITEM_FREE; 9]);
+        assert_eq!(g.move_count(), 0);
+        assert_eq!(g.keep_alive, KeepAlives::from([10, 0]));
+        g.validate().unwrap();
+
+        // The new game plays like any other
+        g.join(player_x, 11).unwrap();
+        g.next_move(player_o, 1, 1).unwrap();
+        assert_eq!(g.game_state, GameState::OMove);
+        assert!(g.reset(&player_x, 12).is_err());
+    }
+
+    #[test]
+    pub fn reset_keeps_the_trophy_flag() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let game_pubkey = Pubkey::new(&[3; 32]);
+        let win = |g: &mut Game| {
+            for &(x, y) in &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+                let player = g.current_player().unwrap();
+                g.next_move(player, x, y).unwrap();
+            }
+        };
+        let mut g = Game::new(player_x, player_o);
+        win(&mut g);
+        assert!(g.finalize_with_trophy(&game_pubkey, 5).unwrap().is_some());
+
+        // Winning again in the reused account awards no second trophy
+        g.reset(&player_x, 6).unwrap();
+        g.join(player_x, 7).unwrap();
+        win(&mut g);
+        let err = g.finalize_with_trophy(&game_pubkey, 8).unwrap_err();
+        assert_eq!(err.kind, ProgramError::TrophyAlreadyMinted);
+        g.validate().unwrap();
+    }
+
+    #[test]
+    pub fn reset_keeps_the_config() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let clock = |slot, unix_timestamp| ClockSnapshot {
+            slot,
+            unix_timestamp,
+        };
+        let config = GameConfig {
+            move_timeout: 30,
+            variant: Variant::Wild,
+            timeout_unit: TimeoutUnit::Slots,
+            conduct_limit: 3,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_at(&player_x, config, &clock(100, 5_000)).unwrap();
+        g.join_at(player_o, &clock(101, 5_001)).unwrap();
+        g.request_abort(player_x).unwrap();
+        g.confirm_abort(player_o).unwrap();
+        assert_eq!(g.game_state, GameState::Aborted);
+
+        // A slot timed game is reset by the slot, never by a bare timestamp or at slot 0
+        let err = g.clone().reset(&player_o, 5_002).unwrap_err();
+        assert_eq!(err.kind, ProgramError::TimeoutUnitMismatch);
+        let err = g.clone().reset_at(&player_o, &clock(0, 5_002)).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidTimestamp);
+        g.reset_at(&player_o, &clock(102, 5_002)).unwrap();
+        assert_eq!(g.config(), &config);
+        assert_eq!(g.created_at, 102);
+        assert_eq!(g.keep_alive, KeepAlives::from([102, 0]));
+        g.join_at(player_x, &clock(103, 5_003)).unwrap();
+        g.next_move_wild(player_o, 0, 0, Cell::X).unwrap();
+        g.validate().unwrap();
+
+        // Sequence games keep no keep alive timestamps, after a reset as well
+        let config = GameConfig {
+            liveness: Liveness::Sequence,
+            heartbeat_limit: 2,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 1).unwrap();
+        g.join(player_o, 2).unwrap();
+        g.request_abort(player_x).unwrap();
+        g.confirm_abort(player_o).unwrap();
+        g.reset(&player_x, 3).unwrap();
+        assert_eq!(g.config().liveness, Liveness::Sequence);
+        assert_eq!(g.keep_alive, KeepAlives::default());
+    }
+
+    #[test]
+    pub fn outcome_with_optimal_play() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let g = Game::new(player_x, player_o);
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Draw));
+
+        /*
+            X|O|
+            -+-+-
+             |X|
+            -+-+-
+             | |
+        */
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 1, 0).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Loss));
+
+        // O blocks the diagonal, but X is one move from winning via the fork at (0, 2)
+        g.next_move(player_o, 2, 2).unwrap();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Win));
+        g.next_move(player_x, 0, 2).unwrap();
+        g.next_move(player_o, 0, 1).unwrap();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Win));
+        g.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Loss));
+
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 1, 1).unwrap();
+        g.next_move(player_x, 0, 2).unwrap();
+        g.next_move(player_o, 0, 1).unwrap();
+        g.next_move(player_x, 2, 1).unwrap();
+        g.next_move(player_o, 1, 0).unwrap();
+        g.next_move(player_x, 1, 2).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Draw));
+        g.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Draw));
+    }
+
+    #[test]
+    pub fn default_config() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let g = Game::create_with_config(&player_x, GameConfig::default(), 0).unwrap();
+        assert_eq!(g, Game::create(&player_x));
+        assert!(!g.has_timed_out(u64::MAX));
+
+        // Solo play is allowed by default
+        let mut g = Game::create(&player_x);
+        g.join(player_x, 1).unwrap();
+        assert_eq!(g.game_state, GameState::XMove);
+    }
+
+    #[test]
+    pub fn invalid_config() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let config = GameConfig {
+            move_timeout: 0,
+            ..GameConfig::default()
+        };
+        assert!(Game::create_with_config(&player_x, config, 0).is_err());
+
+        let config = GameConfig {
+            join_deadline: 0,
+            ..GameConfig::default()
+        };
+        assert!(Game::create_with_config(&player_x, config, 0).is_err());
+    }
+
+    #[test]
+    pub fn config_timeouts() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        let short = GameConfig {
+            move_timeout: 10,
+            join_deadline: 10,
+            ..GameConfig::default()
+        };
+        let long = GameConfig {
+            move_timeout: 100,
+            join_deadline: 100,
+            ..GameConfig::default()
+        };
+
+        let mut a = Game::create_with_config(&player_x, short, 5).unwrap();
+        let mut b = Game::create_with_config(&player_x, long, 5).unwrap();
+        assert!(!a.has_timed_out(15));
+        assert!(a.has_timed_out(16));
+        assert!(!b.has_timed_out(16));
+        assert!(a.join(player_o, 16).is_err());
+
+        let mut a = Game::create_with_config(&player_x, short, 5).unwrap();
+        a.join(player_o, 6).unwrap();
+        b.join(player_o, 6).unwrap();
+        a.next_move(player_x, 1, 1).unwrap();
+        b.next_move(player_x, 1, 1).unwrap();
+
+        // Player O last checked in when joining
+        assert!(!a.has_timed_out(16));
+        assert!(a.has_timed_out(17));
+        assert!(!b.has_timed_out(17));
+        assert!(b.has_timed_out(107));
+    }
+
+    #[test]
+    pub fn keep_alive_baseline() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::create(&player_x);
+        g.config.move_timeout = 10;
+        g.config.join_deadline = 10;
+
+        // Without a creation time there is no join deadline to miss
+        assert!(!g.keep_alive.is_set(Seat::X));
+        assert!(!g.has_timed_out(1_000));
+        g.join(player_o, 500).unwrap();
+
+        // Player X's first move is timed from the join
+        assert!(!g.has_timed_out(510));
+        assert!(g.has_timed_out(511));
+        g.keep_alive(player_x, 505).unwrap();
+        assert!(!g.has_timed_out(515));
+        assert!(g.has_timed_out(516));
+    }
+
+    #[test]
+    pub fn keep_alive_many() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let stranger = Pubkey::new(&[3; 32]);
+        let mut playing = Game::new(player_x, player_o);
+        let mut others = Game::new(player_o, stranger);
+        let mut finished = Game::new(player_x, player_o);
+        for &(x, y) in [(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)].iter() {
+            let player = finished.current_player().unwrap();
+            finished.next_move(player, x, y).unwrap();
+        }
+        let config = GameConfig {
+            liveness: Liveness::Sequence,
+            heartbeat_limit: 2,
+            ..GameConfig::default()
+        };
+        let mut sequenced = Game::create_with_config(&player_x, config, 0).unwrap();
+        sequenced.join(player_o, 1).unwrap();
+
+        let statuses = Game::keep_alive_many(
+            &mut [&mut playing, &mut others, &mut finished, &mut sequenced],
+            player_x,
+            10,
+        )
+        .unwrap();
+        assert_eq!(
+            statuses,
+            vec![
+                KeepAliveStatus::Applied,
+                KeepAliveStatus::NotAPlayer,
+                KeepAliveStatus::Finished,
+                KeepAliveStatus::Failed(ProgramError::WrongLiveness),
+            ]
+        );
+        assert_eq!(playing.keep_alive.get(Seat::X), 10);
+
+        // Retrying the batch changes nothing, whichever of its games it reaches
+        let before = playing.clone();
+        let statuses = Game::keep_alive_many(&mut [&mut playing, &mut others], player_x, 10);
+        assert_eq!(
+            statuses.unwrap(),
+            vec![KeepAliveStatus::AlreadyCurrent, KeepAliveStatus::NotAPlayer]
+        );
+        assert_eq!(playing, before);
+
+        // One game past the cap fails the whole batch before it touches any game
+        let mut games: Vec<Game> = (0..KEEP_ALIVE_BATCH_MAX + 1)
+            .map(|_| Game::new(player_x, player_o))
+            .collect();
+        let mut batch: Vec<&mut Game> = games.iter_mut().collect();
+        assert_eq!(
+            Game::keep_alive_many(&mut batch, player_x, 20)
+                .unwrap_err()
+                .kind,
+            ProgramError::InvalidInput
+        );
+        assert!(games.iter().all(|g| g.keep_alive.get(Seat::X) == 0));
+
+        let mut batch: Vec<&mut Game> = games.iter_mut().skip(1).collect();
+        let statuses = Game::keep_alive_many(&mut batch, player_x, 20).unwrap();
+        assert_eq!(
+            statuses,
+            vec![KeepAliveStatus::Applied; KEEP_ALIVE_BATCH_MAX]
+        );
+    }
+
+    #[test]
+    pub fn keep_alive_layout() {
+        let mut keep_alives = KeepAlives::default();
+        keep_alives.set(Seat::X, 3);
+        keep_alives.set(Seat::O, 4);
+        assert_eq!(<[u64; 2]>::from(keep_alives), [3, 4]);
+        assert_eq!(KeepAlives::from([3, 4]).get(Seat::O), 4);
+        assert_eq!(
+            bincode::serialize(&keep_alives).unwrap(),
+            bincode::serialize(&[3u64, 4]).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&keep_alives).unwrap(),
+            serde_json::to_string(&[3u64, 4]).unwrap()
+        );
+
+        keep_alives.swap();
+        assert_eq!(keep_alives.get(Seat::X), 4);
+        assert_eq!(keep_alives.get(Seat::O), 3);
+    }
+
+    #[test]
+    pub fn step_count_to_draw() {
+        let player_x = Pubkey::new(&[1; 32]);
+        assert_eq!(Game::create(&player_x).step_count_to_draw(), Some(9));
+        assert_eq!(play(&[]).step_count_to_draw(), Some(9));
+
+        /*
+            X|O|X
+            -+-+-
+            X|O|O
+            -+-+-
+            O|X|
+        */
+        let mut g = play(&[0, 1, 2, 4, 3, 5, 7, 6]);
+        assert_eq!(g.step_count_to_draw(), Some(1));
+        g.next_move(player_x, 2, 2).unwrap();
+        assert_eq!(g.game_state, GameState::Draw);
+        assert_eq!(g.step_count_to_draw(), Some(0));
+
+        // X's last free cell completes the diagonal
+        let g = play(&[0, 1, 4, 2, 5, 3, 7, 6]);
+        assert_eq!(g.game_state, GameState::XMove);
+        assert_eq!(g.step_count_to_draw(), None);
+
+        let g = play(&[0, 3, 1, 4, 2]);
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(g.step_count_to_draw(), None);
+    }
+
+    // y * 3 + x alone put (4, 1) on the board as (1, 2).  Every API taking coordinates must
+    // treat it as off the board instead
+    #[test]
+    pub fn no_coordinate_aliasing() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        for &(x, y) in &[(1, 0), (0, 2), (1, 1), (2, 2)] {
+            let player = g.current_player().unwrap();
+            g.next_move(player, x, y).unwrap();
+        }
+        // X to move, and (1, 2) would win for X and block O
+        assert!(g.is_winning_move(1, 2) && g.is_blocking_move(1, 2));
+
+        assert_eq!(
+            cell_index(4, 1).unwrap_err().kind,
+            ProgramError::InvalidMove
+        );
+        assert!(cell_coords(9).is_err());
+        assert!(!is_free(g.board(), 4, 1));
+        assert!(!g.is_winning_move(4, 1));
+        assert!(!g.is_blocking_move(4, 1));
+        assert!(g.all_equivalent_moves(4, 1).is_empty());
+        let before = g.clone();
+        let err = g.next_move(player_x, 4, 1).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidMove);
+        assert_eq!(err.context, ErrorContext::Coordinate(4, 1));
+        let err = g.apply_instruction_unchecked(4, 1).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidMove);
+        assert_eq!(g, before);
+        assert_eq!(cell_name(7), "b3");
+        assert_eq!(cell_name(9), "??");
+    }
+
+    #[test]
+    pub fn count_winning_lines() {
+        assert_eq!(play(&[]).count_winning_lines(), (0, 0));
+        assert_eq!(play(&[4, 0]).count_winning_lines(), (0, 0));
+
+        // X down the first column
+        let g = play(&[0, 1, 3, 4, 6]);
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(g.count_winning_lines(), (1, 0));
+
+        // O across the middle row
+        let g = play(&[0, 3, 1, 4, 8, 5]);
+        assert_eq!(g.game_state, GameState::OWon);
+        assert_eq!(g.count_winning_lines(), (0, 1));
+
+        // X's last move completes both diagonals
+        let g = play(&[0, 1, 2, 3, 6, 5, 8, 7, 4]);
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(g.count_winning_lines(), (2, 0));
+
+        let g = play(&[0, 1, 2, 4, 3, 5, 7, 6, 8]);
+        assert_eq!(g.game_state, GameState::Draw);
+        assert_eq!(g.count_winning_lines(), (0, 0));
+    }
+
+    #[test]
+    pub fn critical_cells() {
+        // Against a corner opening only the center holds the draw, and X taking it instead
+        // wins.  The opposite corner loses for O whoever holds it
+        let g = play(&[0]);
+        let critical = g.critical_cells();
+        assert!(critical.contains(&4));
+        assert!(!critical.contains(&8));
+        assert!(!g.is_winning_move(1, 1));
+
+        // X wins on 2 and O would on 6 or 7, so every cell left decides the game
+        let g = play(&[0, 3, 1, 4, 5, 8]);
+        assert_eq!(g.critical_cells(), vec![2, 6, 7]);
+        assert!(g.is_winning_move(2, 0));
+
+        let finished = play(&[0, 1, 3, 4, 6]);
+        assert!(finished.critical_cells().is_empty());
+    }
+
+    #[test]
+    pub fn is_in_winning_line() {
+        let marked =
+            |g: &Game| -> Vec<usize> { (0..12).filter(|&i| g.is_in_winning_line(i)).collect() };
+
+        // O across the middle row
+        let g = play(&[0, 3, 1, 4, 8, 5]);
+        assert_eq!(g.game_state, GameState::OWon);
+        assert_eq!(marked(&g), vec![3, 4, 5]);
+
+        // Only the line winning_line reports counts when the last move completes two
+        let g = play(&[0, 1, 2, 3, 6, 5, 8, 7, 4]);
+        assert_eq!(marked(&g).len(), 3);
+        assert_eq!(marked(&g), g.winning_line().unwrap().to_vec());
+
+        assert!(marked(&play(&[0, 4, 1])).is_empty());
+        let g = play(&[0, 1, 2, 4, 3, 5, 7, 6, 8]);
+        assert_eq!(g.game_state, GameState::Draw);
+        assert!(marked(&g).is_empty());
+    }
+
+    #[test]
+    pub fn potential_lines_for_player() {
+        let potential = |g: &Game| {
+            (
+                g.potential_lines_for_player(BOARD_ITEM_X),
+                g.potential_lines_for_player(BOARD_ITEM_O),
+            )
+        };
+        assert_eq!(potential(&play(&[])), (8, 8));
+        assert_eq!(play(&[]).potential_lines_for_player(BOARD_ITEM_FREE), 0);
+
+        // The center blocks four lines, a corner three and an edge two
+        assert_eq!(potential(&play(&[4])), (8, 4));
+        assert_eq!(potential(&play(&[0])), (8, 5));
+        assert_eq!(potential(&play(&[1])), (8, 6));
+        assert_eq!(potential(&play(&[4, 0])), (5, 4));
+        assert_eq!(potential(&play(&[1, 7])), (6, 6));
+
+        // X holds a cell of every line
+        let g = play(&[0, 1, 4, 2, 8]);
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(potential(&g), (4, 0));
+    }
+
+    #[test]
+    pub fn state_machine_diagram() {
+        let dot = Game::state_machine_diagram();
+        assert!(dot.starts_with("digraph"));
+        assert!(dot.trim_end().ends_with('}'));
+        for name in &[
+            "Waiting", "XMove", "OMove", "XWon", "OWon", "Draw", "Aborted",
+        ] {
+            assert!(dot.contains(&format!("    {} [shape=", name)));
+        }
+        assert!(dot.contains("Waiting -> XMove [label=\"join\"];"));
+        assert!(dot.contains("Draw [shape=doublecircle];"));
+        assert!(dot.contains("XMove [shape=circle];"));
+        assert_eq!(dot.matches(" -> ").count(), transition_table().len());
+    }
+
+    #[test]
+    pub fn transition_table_matches_the_engine() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            move_timeout: 100,
+            swap_rule: true,
+            ..GameConfig::default()
+        };
+        let game_in = |state: GameState| {
+            let mut g = Game::create_with_config(&player_x, config, 1).unwrap();
+            let moves: &[usize] = match state {
+                GameState::XWon => &[0, 3, 1, 4, 2],
+                GameState::OWon => &[0, 3, 1, 4, 8, 5],
+                GameState::Draw => &[0, 4, 8, 2, 6, 3, 5, 7, 1],
+                GameState::OMove => &[0],
+                _ => &[],
+            };
+            match state {
+                GameState::Waiting => {}
+                GameState::Aborted => g.request_abort(player_x).unwrap(),
+                _ => {
+                    g.join(player_o, 2).unwrap();
+                }
+            }
+            for &i in moves {
+                let (x, y) = cell_coords(i).unwrap();
+                let player = g.current_player().unwrap();
+                g.next_move(player, x, y).unwrap();
+            }
+            assert_eq!(g.game_state, state);
+            g
+        };
+        // Each action the way a player who may take it would
+        let perform = |g: &mut Game, action: Action| -> Result<()> {
+            match action {
+                Action::Join => g.join(player_o, 3).map(|_| ()),
+                Action::Move => {
+                    let player = g.current_player().unwrap_or(player_x);
+                    let i = g.board.iter().position(|&item| item == BOARD_ITEM_FREE);
+                    let (x, y) = cell_coords(i.unwrap_or(0)).unwrap();
+                    g.next_move(player, x, y)
+                }
+                Action::SetName => g.set_name(player_x, PlayerName::new("ab").unwrap()),
+                Action::Swap => g.swap_seats(player_o),
+                Action::Abort => g.request_abort(player_x),
+                Action::ClaimTimeout => {
+                    let claimant = if g.game_state == GameState::XMove {
+                        player_o
+                    } else {
+                        player_x
+                    };
+                    g.claim_timeout(claimant, 1000)
+                }
+                Action::KeepAlive => g.keep_alive(player_x, 50),
+                Action::Reset => g.reset(&player_x, 2000),
+                Action::Finalize => g
+                    .finalize_with_trophy(&Pubkey::new(&[9; 32]), 2000)
+                    .map(|_| ()),
+                Action::Freeze => g.freeze(Pubkey::new(&[7; 32])),
+            }
+        };
+
+        for &state in GameState::ALL.iter() {
+            for &action in Action::ALL.iter() {
+                let mut g = game_in(state);
+                let result = perform(&mut g, action);
+                assert_eq!(
+                    result.is_ok(),
+                    is_action_allowed(state, action),
+                    "{:?} in {:?}: {:?}",
+                    action,
+                    state,
+                    result
+                );
+                match result {
+                    Ok(()) => {
+                        assert!(transition_table().contains(&(state, action, g.game_state)));
+                        assert!(game_in(state).ensure_allowed(action, player_x).is_ok());
+                    }
+                    // The table refuses up front with the error the engine gives
+                    Err(err) => assert_eq!(
+                        game_in(state)
+                            .ensure_allowed(action, player_x)
+                            .unwrap_err()
+                            .kind,
+                        err.kind,
+                        "{:?} in {:?}",
+                        action,
+                        state
+                    ),
+                }
+            }
+            assert!(state
+                .allowed_actions()
+                .iter()
+                .all(|&action| is_action_allowed(state, action)));
+        }
+        assert_eq!(
+            GameState::Waiting.allowed_actions(),
+            &[
+                Action::Join,
+                Action::SetName,
+                Action::Abort,
+                Action::KeepAlive,
+                Action::Freeze
+            ]
+        );
+        assert_eq!(
+            GameState::Draw.allowed_actions(),
+            &[Action::KeepAlive, Action::Reset, Action::Finalize]
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    pub fn transition_table_json() {
+        let json: serde_json::Value = serde_json::from_str(&state_machine_json()).unwrap();
+        assert_eq!(json["states"].as_array().unwrap().len(), 7);
+        assert_eq!(
+            json["transitions"][0],
+            serde_json::json!({"from": "Waiting", "action": "Join", "to": "XMove"})
+        );
+        assert_eq!(
+            json["transitions"].as_array().unwrap().len(),
+            transition_table().len()
+        );
+    }
+
+    #[test]
+    pub fn play_before_join() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            move_timeout: 100,
+            swap_rule: true,
+            ..GameConfig::default()
+        };
+        let wild = GameConfig {
+            variant: Variant::Wild,
+            ..config
+        };
+        assert!(transition_table()
+            .iter()
+            .all(|&(from, action, _)| from != GameState::Waiting || !action.is_play()));
+
+        let mut g = Game::create_with_config(&player_x, config, 1).unwrap();
+        let mut w = Game::create_with_config(&player_x, wild, 1).unwrap();
+        let not_started = |result: Result<()>, player: Pubkey| {
+            assert_eq!(
+                result.unwrap_err(),
+                ProgramError::GameNotStarted.at(ErrorContext::Player(player))
+            );
+        };
+        not_started(g.next_move(player_x, 0, 0), player_x);
+        not_started(g.next_move(player_o, 0, 0), player_o);
+        not_started(w.next_move_wild(player_x, 0, 0, Cell::O), player_x);
+        not_started(g.swap_seats(player_o), player_o);
+        not_started(g.claim_timeout(player_x, 1000), player_x);
+        not_started(g.confirm_abort(player_x), player_x);
+        not_started(g.apply_instruction_unchecked(0, 0), player_x);
+        assert!(!g.record_invalid_attempt(player_x, ProgramError::NotYourTurn.into()));
+        // Keeping the game alive is how X keeps it open for an opponent
+        g.keep_alive(player_x, 50).unwrap();
+        assert_eq!(g.game_state, GameState::Waiting);
+        assert_eq!(g.move_count(), 0);
+
+        // Once O has joined the same calls are played, or refused as before
+        g.join(player_o, 60).unwrap();
+        w.join(player_o, 60).unwrap();
+        let kind = |result: Result<()>| result.unwrap_err().kind;
+        assert_eq!(
+            kind(g.next_move(player_o, 0, 0)),
+            ProgramError::PlayerNotFound
+        );
+        assert_eq!(kind(g.confirm_abort(player_x)), ProgramError::NotYourTurn);
+        assert_eq!(
+            kind(g.claim_timeout(player_o, 61)),
+            ProgramError::NotTimedOut
+        );
+        g.next_move(player_x, 0, 0).unwrap();
+        g.swap_seats(player_o).unwrap();
+        w.next_move_wild(player_x, 0, 0, Cell::O).unwrap();
+        assert_eq!(g.move_count(), 1);
+        assert_eq!(w.game_state, GameState::OMove);
+    }
+
+    #[test]
+    pub fn progress_counters() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!((g.empty_cells(), g.filled_cells()), (9, 0));
+        assert_eq!(g.progress(), (0, 9));
+
+        for (n, &i) in [0, 1, 2, 4, 3, 5, 7, 6, 8].iter().enumerate() {
+            let (x, y) = cell_coords(i).unwrap();
+            let player = g.current_player().unwrap();
+            g.next_move(player, x, y).unwrap();
+            let made = n as u8 + 1;
+            assert_eq!((g.empty_cells(), g.filled_cells()), (9 - made, made));
+            assert_eq!(g.progress(), (made, 9));
+            assert_eq!(g.is_board_full(), made == 9);
+            assert_eq!(g.game_state == GameState::Draw, g.is_board_full());
+            assert_eq!(g.step_count_to_draw(), Some(g.empty_cells() as usize));
+        }
+    }
+
+    #[test]
+    pub fn set_board_from_str() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        g.set_board_from_str("XO_______").unwrap();
+        assert_eq!(g.board[..3], [BOARD_ITEM_X, BOARD_ITEM_O, BOARD_ITEM_FREE]);
+        assert!(g.board[2..].iter().all(|&item| item == BOARD_ITEM_FREE));
+        assert_eq!(g.game_state, GameState::XMove);
+        assert_eq!(g.move_history(), [Move { x: 0, y: 0 }, Move { x: 1, y: 0 }]);
+
+        // X's column on the left
+        g.set_board_from_str("XOOXXOX__").unwrap();
+        assert_eq!(g.game_state, GameState::XWon);
+        g.set_board_from_str("XO__X____").unwrap();
+        assert_eq!(g.game_state, GameState::OMove);
+        g.next_move(player_o, 2, 2).unwrap();
+
+        // Boards no game reaches, and strings that are no board, leave the game alone
+        let before = g.clone();
+        for s in &["XXXXXXXXX", "XXX_OO_OO"] {
+            let err = g.set_board_from_str(s).unwrap_err();
+            assert_eq!(err.kind, ProgramError::InvalidBoardState);
+        }
+        for s in &["XO______", "XO________", "XO______x"] {
+            let err = g.set_board_from_str(s).unwrap_err();
+            assert_eq!(err.kind, ProgramError::InvalidInput);
+        }
+        assert_eq!(g, before);
+    }
+
+    #[test]
+    pub fn board_as_u32() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!(g.board_as_u32(), 0);
+        g.next_move(player_x, 1, 0).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        assert_eq!(g.board_as_u32(), 3 + 2 * 3u32.pow(8));
+
+        let mut legal = 0;
+        for encoded in 0..3u32.pow(9) {
+            let board = unpack_board(encoded).unwrap();
+            assert_eq!(pack_board(&board), encoded);
+            if let Ok(g) = Game::from_board_u32(encoded, player_x, player_o) {
+                assert_eq!(g.board_as_u32(), encoded);
+                assert_eq!(g.board, board);
+                legal += 1;
+            }
+        }
+        // Every position alternating play reaches, the empty board included
+        assert_eq!(legal, 5478);
+
+        let g = Game::from_board_u32(g.board_as_u32(), player_x, player_o).unwrap();
+        assert_eq!(g.game_state, GameState::XMove);
+        assert_eq!(g.move_history(), [Move { x: 1, y: 0 }, Move { x: 2, y: 2 }]);
+
+        // Past the last board, and boards no game reaches
+        for &encoded in &[3u32.pow(9), u32::MAX] {
+            assert_eq!(
+                Game::from_board_u32(encoded, player_x, player_o)
+                    .unwrap_err()
+                    .kind,
+                ProgramError::InvalidUserdata
+            );
+        }
+        assert!(Game::from_board_u32(1 + 3, player_x, player_o).is_err());
+    }
+
+    #[test]
+    pub fn position_index() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        // Every position play reaches, each board expanded once
+        let mut seen = HashMap::new();
+        let mut stack = vec![Game::new(player_x, player_o)];
+        while let Some(g) = stack.pop() {
+            if seen
+                .insert(g.board_as_u32(), g.position_index().unwrap())
+                .is_some()
+            {
+                continue;
+            }
+            if let Some(player) = g.current_player() {
+                for (x, y) in g.available_moves() {
+                    let mut next = g.clone();
+                    next.next_move(player, x, y).unwrap();
+                    stack.push(next);
+                }
+            }
+        }
+        assert_eq!(seen.len(), POSITION_COUNT);
+
+        let mut indices: Vec<u16> = seen.values().cloned().collect();
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices.len(), POSITION_COUNT);
+        assert_eq!(indices.last(), Some(&(POSITION_COUNT as u16 - 1)));
+        for (&encoded, &index) in &seen {
+            let g = Game::from_position_index(index, player_x, player_o).unwrap();
+            assert_eq!(g.board_as_u32(), encoded);
+            assert_eq!(g.position_index(), Some(index));
+        }
+
+        assert_eq!(Game::new(player_x, player_o).position_index(), Some(0));
+        assert!(Game::from_position_index(POSITION_COUNT as u16, player_x, player_o).is_err());
+    }
+
+    #[test]
+    pub fn legal_moves_bitmask() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!(g.legal_moves_bitmask(), 0b1_1111_1111);
+        assert_eq!(
+            Game::move_from_bitmask(g.legal_moves_bitmask()),
+            g.available_moves()
+        );
+
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 2, 1).unwrap();
+        let mask = g.legal_moves_bitmask();
+        assert_eq!(mask & 1, 0);
+        assert_eq!(mask, 0b1_1101_1110);
+        assert_eq!(Game::move_from_bitmask(mask), g.available_moves());
+
+        // Walking the set bits lowest first visits the cells in board order
+        let mut cells = vec![];
+        let mut rest = mask;
+        while rest != 0 {
+            let lowest = rest & rest.wrapping_neg();
+            cells.push(lowest.trailing_zeros() as usize);
+            rest ^= lowest;
+        }
+        assert_eq!(cells, [1, 2, 3, 4, 6, 7, 8]);
+        assert_eq!(Game::move_from_bitmask(0xfe00), vec![]);
+    }
+
+    #[test]
+    pub fn apply_instruction_unchecked() {
+        let mut g = Game::new(Pubkey::new(&[1; 32]), Pubkey::new(&[2; 32]));
+        for &(x, y) in &[(1, 1), (0, 0), (0, 1)] {
+            g.apply_instruction_unchecked(x, y).unwrap();
+        }
+        assert_eq!(g.game_state, GameState::OMove);
+        assert_eq!(g.board[3], BOARD_ITEM_X);
+        assert_eq!(g.move_count(), 3);
+
+        // Bounds and free cells are still checked
+        let err = g.apply_instruction_unchecked(1, 1).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidMove);
+        let err = g.apply_instruction_unchecked(3, 0).unwrap_err();
+        assert_eq!(err.context, ErrorContext::Coordinate(3, 0));
+
+        for &(x, y) in &[(2, 0), (2, 1)] {
+            g.apply_instruction_unchecked(x, y).unwrap();
+        }
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(g.winning_line(), Some([3, 4, 5]));
+        assert!(g.apply_instruction_unchecked(2, 2).is_err());
+        assert!(g.validate().is_ok());
+    }
+
+    #[test]
+    pub fn snapshot_hash() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut base = Game::new(player_x, player_o);
+        base.next_move(player_x, 1, 1).unwrap();
+        assert_eq!(base.snapshot_hash(), base.clone().snapshot_hash());
+
+        let mutations: [fn(&mut Game); 32] = [
+            |g| g.keep_alive.set(Seat::X, 7),
+            |g| g.keep_alive.set(Seat::O, 7),
+            |g| g.game_state = GameState::XMove,
+            |g| g.player_x = Pubkey::new(&[3; 32]),
+            |g| g.player_o = Pubkey::new(&[3; 32]),
+            |g| g.board[0] = BOARD_ITEM_O,
+            |g| g.board[4] = BOARD_ITEM_O,
+            |g| g.trophy_minted = true,
+            |g| g.moves[0] = 5,
+            |g| g.moves[8] = 5,
+            |g| g.move_count += 1,
+            |g| g.generation += 1,
+            |g| g.config.move_timeout = 7,
+            |g| g.config.join_deadline = 7,
+            |g| g.config.allow_solo = !g.config.allow_solo,
+            |g| g.config.swap_rule = !g.config.swap_rule,
+            |g| g.config.liveness = Liveness::Sequence,
+            |g| g.config.heartbeat_limit += 1,
+            |g| g.config.timeout_unit = TimeoutUnit::Slots,
+            |g| g.config.center_handicap = true,
+            |g| g.turn = Seat::O,
+            |g| g.config.conduct_limit = 3,
+            |g| g.invalid_attempts[1] = 1,
+            |g| g.names[0] = PlayerName::new("x").unwrap(),
+            |g| g.names[1] = PlayerName::new("o").unwrap(),
+            |g| g.idle_heartbeats[1] += 1,
+            |g| g.abort_requested[0] = true,
+            |g| g.final_board_hash += 1,
+            |g| g.frozen = true,
+            |g| g.created_at = 7,
+            |g| g.finished_at = 7,
+            |g| g.linked = true,
+        ];
+        for (i, mutate) in mutations.iter().enumerate() {
+            let mut g = base.clone();
+            mutate(&mut g);
+            assert_ne!(g, base, "mutation {} changed nothing", i);
+            assert_ne!(g.snapshot_hash(), base.snapshot_hash(), "mutation {}", i);
+        }
+    }
+
+    #[test]
+    pub fn join_race() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let late = Pubkey::new(&[3; 32]);
+        let mut g = Game::create(&player_x);
+
+        let joined = JoinOutcome {
+            seat: Seat::O,
+            opponent: player_x,
+        };
+        assert_eq!(g.join(player_o, 1).unwrap(), joined);
+
+        // The loser of the race is told who has the seat
+        let err = g.join(late, 1).unwrap_err();
+        assert_eq!(err.kind, ProgramError::SeatTaken);
+        assert_eq!(err.context, ErrorContext::Player(player_o));
+        let err = g.join(player_x, 1).unwrap_err();
+        assert_eq!(err.kind, ProgramError::SeatTaken);
+
+        // A repeated join by the same key is a no-op
+        let before = g.clone();
+        assert_eq!(g.join(player_o, 7).unwrap(), joined);
+        assert_eq!(g, before);
+
+        // Even the seated player cannot join a finished game
+        g.request_abort(player_x).unwrap();
+        g.confirm_abort(player_o).unwrap();
+        for &player in &[player_o, late] {
+            assert_eq!(
+                g.join(player, 8).unwrap_err().kind,
+                ProgramError::GameFinished
+            );
+        }
+    }
+
+    // Whichever terminal command lands first decides the game; the one that lost the race is
+    // refused and told the outcome it lost to, without touching the game
+    #[test]
+    pub fn terminal_transition_precedence() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            move_timeout: 10,
+            ..GameConfig::default()
+        };
+        let mut start = Game::create_with_config(&player_x, config, 1).unwrap();
+        start.join(player_o, 2).unwrap();
+        for &(player, x, y) in &[
+            (player_x, 0, 0),
+            (player_o, 1, 0),
+            (player_x, 0, 1),
+            (player_o, 1, 1),
+        ] {
+            start.next_move(player, x, y).unwrap();
+        }
+        start.request_abort(player_x).unwrap();
+
+        type Transition = fn(&mut Game, Pubkey, Pubkey) -> Result<()>;
+        let win: Transition = |g, player_x, _| g.next_move(player_x, 0, 2);
+        let claim: Transition = |g, _, player_o| g.claim_timeout(player_o, 1_000);
+        let confirm: Transition = |g, _, player_o| g.confirm_abort(player_o);
+        let abort: Transition = |g, player_x, _| g.request_abort(player_x);
+        let pairs = [
+            (win, claim, GameState::XWon),
+            (claim, win, GameState::OWon),
+            (confirm, claim, GameState::Aborted),
+            (claim, confirm, GameState::OWon),
+            (win, abort, GameState::XWon),
+            (claim, claim, GameState::OWon),
+        ];
+
+        for &(first, second, outcome) in pairs.iter() {
+            let mut g = start.clone();
+            first(&mut g, player_x, player_o).unwrap();
+            assert_eq!(g.game_state, outcome);
+            let landed = g.clone();
+            let err = second(&mut g, player_x, player_o).unwrap_err();
+            assert_eq!(err.kind, ProgramError::GameFinished);
+            assert_eq!(err.context, ErrorContext::Outcome(outcome));
+            assert_eq!(g, landed);
+        }
+    }
+
+    #[test]
+    pub fn freeze_for_review() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let moderator = Pubkey::new(&[8; 32]);
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 1, 1).unwrap();
+
+        g.freeze(moderator).unwrap();
+        assert!(g.is_frozen());
+        let frozen = g.clone();
+        let err = g.next_move(player_o, 0, 0).unwrap_err();
+        assert_eq!(err.kind, ProgramError::GameFrozen);
+        let name = PlayerName::new("rude").unwrap();
+        assert_eq!(
+            g.set_name(player_o, name).unwrap_err().kind,
+            ProgramError::GameFrozen
+        );
+        assert!(g.claim_timeout(player_x, u64::MAX).is_err());
+        g.keep_alive(player_o, 5).unwrap();
+        assert_eq!(g.board, frozen.board);
+
+        g.unfreeze(moderator).unwrap();
+        assert!(!g.is_frozen());
+        assert_eq!(g.game_state, GameState::OMove);
+        g.next_move(player_o, 0, 0).unwrap();
+
+        // Finished games stay as they are
+        let mut g = Game::new(player_x, player_o);
+        g.request_abort(player_x).unwrap();
+        g.confirm_abort(player_o).unwrap();
+        assert_eq!(
+            g.freeze(moderator).unwrap_err().kind,
+            ProgramError::GameFinished
+        );
+        assert!(!g.is_frozen());
+    }
+
+    #[test]
+    pub fn config_solo_and_swap() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        let mut config = GameConfig {
+            allow_solo: false,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        assert!(g.join(player_x, 1).is_err());
+        g.join(player_o, 1).unwrap();
+
+        // Without the swap rule, O has to reply to X's first move
+        g.next_move(player_x, 1, 1).unwrap();
+        assert!(g.swap_seats(player_o).is_err());
+
+        config.swap_rule = true;
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        g.join(player_o, 1).unwrap();
+        assert!(g.swap_seats(player_o).is_err());
+        g.next_move(player_x, 1, 1).unwrap();
+        assert!(g.swap_seats(player_x).is_err());
+        g.swap_seats(player_o).unwrap();
+
+        // The original player X now replies as O
+        assert_eq!(g.game_state, GameState::OMove);
+        assert!(g.next_move(player_o, 0, 0).is_err());
+        g.next_move(player_x, 0, 0).unwrap();
+        assert!(g.swap_seats(player_x).is_err());
+        g.next_move(player_o, 2, 2).unwrap();
+        g.validate().unwrap();
+    }
+
+    #[test]
+    pub fn center_handicap() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            center_handicap: true,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        g.join(player_o, 1).unwrap();
+        assert_eq!(g.forbidden_cell(), Some(4));
+        assert_eq!(
+            g.next_move(player_x, 1, 1),
+            Err(ProgramError::HandicapViolation.at(ErrorContext::Coordinate(1, 1)))
+        );
+        assert_eq!(g.move_count(), 0);
+
+        // Nothing suggests the center to open with
+        assert_eq!(g.available_moves().len(), 8);
+        assert!(!g.available_moves().contains(&(1, 1)));
+        match g.hint() {
+            Hint::Optimal(x, y) => assert_ne!((x, y), (1, 1)),
+            hint => panic!("{:?}", hint),
+        }
+        assert_ne!(perfect_play::suggest_move_from_table(&g), Some((1, 1)));
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Draw));
+        assert!(g.deduplicated_moves().iter().all(|&cell| cell != (1, 1)));
+
+        // From the second move on the center is an ordinary cell, X's included
+        g.next_move(player_x, 0, 0).unwrap();
+        assert_eq!(g.forbidden_cell(), None);
+        g.next_move(player_o, 2, 2).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        assert_eq!(g.board()[4], BOARD_ITEM_X);
+
+        // Without the handicap X may open in the center
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!(g.forbidden_cell(), None);
+        assert_eq!(g.available_moves().len(), 9);
+        g.next_move(player_x, 1, 1).unwrap();
+    }
+
+    #[test]
+    pub fn is_drawing_forced() {
+        // A draw with best play, but a careless move still loses
+        let g = play(&[]);
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Draw));
+        assert!(!g.is_drawing_forced());
+
+        // Every line left holds both marks, or the middle row X can only get two of
+        let g = play(&[0, 1, 2, 6, 7, 8]);
+        assert_eq!(g.available_moves().len(), 3);
+        assert!(g.is_drawing_forced());
+
+        // X on 6 and 8 would still win on 7 if O left it
+        let g = play(&[0, 4, 8, 3, 5, 2, 6]);
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Draw));
+        assert!(!g.is_drawing_forced());
+
+        // Over, whether drawn or won
+        assert!(!play(&[0, 4, 8, 3, 5, 2, 6, 7, 1]).is_drawing_forced());
+        assert!(!play(&[0, 1, 3, 4, 6]).is_drawing_forced());
+    }
+
+    #[test]
+    pub fn preview_move() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        // X completes the top row
+        let g = play(&[0, 3, 1, 4]);
+        let before = g.snapshot_hash();
+        let preview = g.preview_move(player_x, 2, 0).unwrap();
+        assert_eq!(preview.game_state, GameState::XWon);
+        assert!(preview.wins && !preview.opponent_wins_next);
+        assert_eq!(preview.board[..3], [BOARD_ITEM_X; 3]);
+
+        // Anything else leaves O the middle row
+        let preview = g.preview_move(player_x, 2, 2).unwrap();
+        assert_eq!(preview.game_state, GameState::OMove);
+        assert!(!preview.wins && preview.opponent_wins_next);
+        assert_eq!(preview.board[8], BOARD_ITEM_X);
+
+        // Illegal previews fail like the move would
+        let err = g.preview_move(player_x, 0, 0).unwrap_err();
+        assert_eq!(err, g.clone().next_move(player_x, 0, 0).unwrap_err());
+        assert_eq!(
+            g.preview_move(player_o, 2, 2).unwrap_err().kind,
+            ProgramError::PlayerNotFound
+        );
+        assert_eq!(g.snapshot_hash(), before);
+        assert_eq!(g, play(&[0, 3, 1, 4]));
+    }
+
+    #[test]
+    pub fn preview_move_wild() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            variant: Variant::Wild,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        g.join(player_o, 1).unwrap();
+        g.next_move_wild(player_x, 0, 0, Cell::O).unwrap();
+
+        // A second O in the top row lets X complete it with a third
+        let preview = g.preview_move_wild(player_o, 1, 0, Cell::O).unwrap();
+        assert_eq!(preview.game_state, GameState::XMove);
+        assert!(!preview.wins && preview.opponent_wins_next);
+        let preview = g.preview_move_wild(player_o, 2, 2, Cell::X).unwrap();
+        assert!(!preview.wins && !preview.opponent_wins_next);
+
+        // Completing a line of the other mark still wins
+        g.next_move_wild(player_o, 1, 0, Cell::O).unwrap();
+        let preview = g.preview_move_wild(player_x, 2, 0, Cell::O).unwrap();
+        assert_eq!(preview.game_state, GameState::XWon);
+        assert!(preview.wins && !preview.opponent_wins_next);
+
+        assert_eq!(
+            g.preview_move(player_x, 2, 0).unwrap_err().kind,
+            ProgramError::WrongVariant
+        );
+    }
+
+    #[test]
+    pub fn human_explanation() {
+        let player_x = Pubkey::new(&[1; 32]);
+        assert_eq!(
+            play(&[0, 4, 1]).human_explanation(),
+            "X has a two-in-a-row with one free cell in the top row. \
+             O has no immediate threats. It is O's turn."
+        );
+
+        // A fork: two threats at once, O can only block one
+        let g = play(&[0, 1, 4, 2, 6]);
+        assert_eq!(g.threats(BOARD_ITEM_X), vec![[0, 3, 6], [0, 4, 8]]);
+        assert!(g.human_explanation().starts_with(
+            "X has two-in-a-rows with one free cell in the left column and the diagonal from \
+             the top left."
+        ));
+        assert!(g.human_explanation().ends_with("It is O's turn."));
+
+        assert_eq!(
+            play(&[0, 4, 8, 2, 6, 3, 5, 7, 1]).human_explanation(),
+            "X has no immediate threats and can no longer complete a line. \
+             O has no immediate threats and can no longer complete a line. The game is drawn."
+        );
+        assert!(play(&[0, 3, 1, 4, 2])
+            .human_explanation()
+            .ends_with("O has a two-in-a-row with one free cell in the middle row. X has won."));
+        assert!(Game::create(&player_x)
+            .human_explanation()
+            .ends_with("waiting for player O to join."));
+    }
+
+    #[test]
+    pub fn is_zugzwang() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        // Classic positions are lost by the marks on the board, never by having to move
+        let mut g = Game::new(player_x, player_o);
+        assert!(!g.is_zugzwang());
+        for &(x, y) in [(0, 0), (1, 0), (1, 1)].iter() {
+            let player = g.current_player().unwrap();
+            g.next_move(player, x, y).unwrap();
+        }
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Loss));
+        assert!(!g.is_zugzwang());
+
+        // X on the edges and O in the center: any mark in any corner gives the opponent a
+        // line to complete, while a pass would leave them in the same trouble
+        let config = GameConfig {
+            variant: Variant::Wild,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        g.join(player_o, 1).unwrap();
+        for &(x, y, mark) in [
+            (1, 0, Cell::X),
+            (1, 1, Cell::O),
+            (0, 1, Cell::X),
+            (2, 1, Cell::X),
+        ]
+        .iter()
+        {
+            assert!(!g.is_zugzwang());
+            let player = g.current_player().unwrap();
+            g.next_move_wild(player, x, y, mark).unwrap();
+        }
+        g.next_move_wild(player_x, 1, 2, Cell::X).unwrap();
+        assert_eq!(g.current_player(), Some(player_o));
+        assert!(g.is_zugzwang());
+
+        g.next_move_wild(player_o, 0, 0, Cell::O).unwrap();
+        assert!(!g.is_zugzwang());
+        g.next_move_wild(player_x, 2, 2, Cell::O).unwrap();
+        assert_eq!(g.game_state, GameState::XWon);
+        assert!(!g.is_zugzwang());
+    }
+
+    #[test]
+    pub fn invalid_attempts() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let stranger = Pubkey::new(&[3; 32]);
+        let attempt = |g: &mut Game, player: Pubkey, x: usize, y: usize| {
+            let err = g.next_move(player, x, y).unwrap_err();
+            g.record_invalid_attempt(player, err)
+        };
+
+        // Occupied cells and moves out of turn count, against whoever made them
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 1, 1).unwrap();
+        assert!(attempt(&mut g, player_o, 1, 1));
+        assert!(attempt(&mut g, player_x, 0, 0));
+        assert_eq!(
+            (g.invalid_attempts(Seat::X), g.invalid_attempts(Seat::O)),
+            (1, 1)
+        );
+        // Off the board, from outside the game, or after the game is over they do not
+        assert!(!attempt(&mut g, player_o, 3, 0));
+        assert!(!attempt(&mut g, stranger, 0, 0));
+        for _ in 0..5 {
+            assert!(attempt(&mut g, player_o, 1, 1));
+        }
+        assert_eq!(g.invalid_attempts(Seat::O), 6);
+        // The rule is off by default, so nothing is forfeited
+        assert_eq!(g.game_state, GameState::OMove);
+        assert_eq!(g.forfeited_by_conduct(), None);
+        g.invalid_attempts[1] = u32::MAX;
+        assert!(attempt(&mut g, player_o, 1, 1));
+        assert_eq!(g.invalid_attempts(Seat::O), u32::MAX);
+
+        // With a limit of three the third rejected move loses the game, whoever is to move
+        let config = GameConfig {
+            conduct_limit: 3,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 1).unwrap();
+        g.join(player_o, 1).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        assert!(attempt(&mut g, player_x, 0, 0));
+        assert!(attempt(&mut g, player_x, 2, 2));
+        assert!(attempt(&mut g, player_o, 1, 1));
+        assert_eq!(g.game_state, GameState::OMove);
+        assert!(attempt(&mut g, player_x, 1, 1));
+        assert_eq!(g.game_state, GameState::OWon);
+        assert_eq!(g.forfeited_by_conduct(), Some(Seat::X));
+        g.validate().unwrap();
+        invariants::check(&g).unwrap();
+        assert_eq!(
+            g.finalize_record(&stranger).unwrap().end_reason,
+            EndReason::ForfeitByConduct
+        );
+        assert!(!attempt(&mut g, player_o, 1, 1));
+        assert_eq!(g.invalid_attempts(Seat::O), 1);
+    }
+
+    #[test]
+    pub fn wild_solution() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            variant: Variant::Wild,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        g.join(player_o, 1).unwrap();
+
+        // Two Os on the diagonal: X moves next, whatever the mark counts say, and wins with an O
+        g.next_move_wild(player_x, 0, 0, Cell::O).unwrap();
+        g.next_move_wild(player_o, 1, 1, Cell::O).unwrap();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Win));
+        assert_eq!(g.terminal_depth(), 1);
+
+        // Leaving the line open hands the same win to O
+        g.next_move_wild(player_x, 2, 0, Cell::X).unwrap();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Win));
+        assert_eq!(g.terminal_depth(), 1);
+        g.next_move_wild(player_o, 2, 2, Cell::O).unwrap();
+        assert_eq!(g.game_state, GameState::OWon);
+        assert_eq!(g.terminal_depth(), 0);
+    }
+
+    #[test]
+    pub fn wild_variant() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            variant: Variant::Wild,
+            ..GameConfig::default()
+        };
+        let wild = || {
+            let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+            g.join(player_o, 1).unwrap();
+            g
+        };
+
+        // Turns alternate whatever marks the players place
+        let mut g = wild();
+        g.next_move_wild(player_x, 0, 0, Cell::O).unwrap();
+        assert_eq!((g.current_player(), g.turn), (Some(player_o), Seat::O));
+        g.next_move_wild(player_o, 1, 1, Cell::O).unwrap();
+        assert_eq!((g.current_player(), g.turn), (Some(player_x), Seat::X));
+        assert_eq!(
+            g.next_move_wild(player_o, 2, 2, Cell::X),
+            Err(ProgramError::PlayerNotFound.at(ErrorContext::Player(player_o)))
+        );
+        g.next_move_wild(player_x, 1, 0, Cell::X).unwrap();
+        g.validate().unwrap();
+        invariants::check(&g).unwrap();
+
+        // O completes the line of O marks X started, and wins with it
+        g.next_move_wild(player_o, 2, 2, Cell::O).unwrap();
+        assert_eq!(g.game_state, GameState::OWon);
+        assert_eq!(g.winning_line(), Some([0, 4, 8]));
+        g.validate().unwrap();
+        invariants::check(&g).unwrap();
+        assert_eq!(g.at_move(4).unwrap().game_state, GameState::OWon);
+        assert_eq!(g.at_move(3).unwrap().board, {
+            let mut board = g.board;
+            board[8] = BOARD_ITEM_FREE;
+            board
+        });
+
+        // A full board without a line of either mark is a draw
+        let mut g = wild();
+        let cells = [
+            (1, Cell::O),
+            (0, Cell::X),
+            (2, Cell::X),
+            (4, Cell::O),
+            (6, Cell::O),
+        ];
+        let more = [(3, Cell::X), (5, Cell::O), (8, Cell::X), (7, Cell::X)];
+        for &(i, mark) in cells.iter().chain(more.iter()) {
+            let (x, y) = cell_coords(i).unwrap();
+            let player = g.current_player().unwrap();
+            g.next_move_wild(player, x, y, mark).unwrap();
+        }
+        assert_eq!(g.game_state, GameState::Draw);
+        g.validate().unwrap();
+        invariants::check(&g).unwrap();
+
+        // Each variant only takes its own kind of move
+        let mut g = wild();
+        assert_eq!(
+            g.next_move(player_x, 0, 0).map_err(|err| err.kind),
+            Err(ProgramError::WrongVariant)
+        );
+        assert_eq!(
+            g.next_move_wild(player_x, 0, 0, Cell::Free),
+            Err(ProgramError::InvalidMove.at(ErrorContext::Coordinate(0, 0)))
+        );
+        assert_eq!(g.hint(), Hint::NoHint);
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!(
+            g.next_move_wild(player_x, 0, 0, Cell::X)
+                .map_err(|err| err.kind),
+            Err(ProgramError::WrongVariant)
+        );
+        assert_eq!(g.move_count(), 0);
+    }
+
+    #[test]
+    pub fn move_quality() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!(g.move_quality(1, 1).unwrap(), MoveQuality::Optimal);
+        assert_eq!(g.move_quality(0, 0).unwrap(), MoveQuality::Optimal);
+        assert!(g.move_quality(0, 3).is_err());
+
+        // An edge reply to X's corner opening loses for O
+        g.next_move(player_x, 0, 0).unwrap();
+        assert_eq!(g.current_player(), Some(player_o));
+        assert_eq!(g.move_quality(1, 1).unwrap(), MoveQuality::Optimal);
+        assert_eq!(g.move_quality(1, 0).unwrap(), MoveQuality::Mistake);
+        assert!(g.move_quality(0, 0).is_err());
+
+        /*
+            X|O|
+            -+-+-
+             |X|
+            -+-+-
+             | |O
+        */
+        g.next_move(player_o, 1, 0).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        assert_eq!(g.outcome_with_optimal_play(), Some(OptimalOutcome::Win));
+        assert_eq!(g.move_quality(0, 2).unwrap(), MoveQuality::Optimal);
+        assert_eq!(g.move_quality(1, 2).unwrap(), MoveQuality::Blunder);
+
+        g.next_move(player_x, 0, 2).unwrap();
+        g.next_move(player_o, 0, 1).unwrap();
+        assert_eq!(g.move_quality(2, 0).unwrap(), MoveQuality::Optimal);
+        g.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(g.current_player(), None);
+        assert!(g.move_quality(2, 1).is_err());
+    }
+
+    #[test]
+    pub fn set_name() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::create(&player_x);
+        let name = PlayerName::new("bob").unwrap();
+
+        assert!(g.set_name(player_o, name).is_err());
+        g.set_name(player_x, name).unwrap();
+        g.join(player_o, 1).unwrap();
+        g.set_name(player_o, PlayerName::new("bob").unwrap())
+            .unwrap();
+        assert_eq!(g.names()[0], g.names()[1]);
+        assert!(g.set_name(Pubkey::new(&[3; 32]), name).is_err());
+        assert!(g.set_name(player_o, PlayerName::default()).is_err());
+    }
+
+    #[test]
+    pub fn hint() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!(g.hint(), Hint::Optimal(0, 0));
+
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        g.next_move(player_x, 1, 0).unwrap();
+        // O has to block the top row
+        assert!(g.is_blocking_move(2, 0));
+        assert!(!g.is_winning_move(2, 0));
+        assert_eq!(g.hint(), Hint::BlockNow(2, 0));
+
+        g.next_move(player_o, 0, 1).unwrap();
+        // X can win on the top row
+        assert!(g.is_winning_move(2, 0));
+        assert_eq!(g.hint(), Hint::WinNow(2, 0));
+
+        g.next_move(player_x, 2, 0).unwrap();
+        assert_eq!(g.game_state, GameState::XWon);
+        assert_eq!(g.hint(), Hint::NoHint);
+        assert!(!g.is_winning_move(1, 1));
+    }
+
+    #[test]
+    pub fn liveness_status() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            move_timeout: 100,
+            join_deadline: 50,
+            ..GameConfig::default()
+        };
+        let warning = WarningConfig::default();
+        let status = |g: &Game, now| g.liveness_status(now, &warning);
+        let remaining = |remaining| PlayerLiveness::Warning { remaining };
+
+        // Only the join deadline applies while Waiting, counted from creation
+        let mut g = Game::create_with_config(&player_x, config, 10).unwrap();
+        assert_eq!(status(&g, 49), PlayerLiveness::Fresh);
+        assert_eq!(status(&g, 50), remaining(10));
+        assert_eq!(status(&g, 60), remaining(0));
+        assert!(!g.has_timed_out(60));
+        assert_eq!(status(&g, 61), PlayerLiveness::Claimable);
+        assert!(g.has_timed_out(61));
+        assert_eq!(status(&g, 5), PlayerLiveness::Fresh);
+
+        // Player X's move is timed from their last keep alive
+        g.join(player_o, 20).unwrap();
+        g.keep_alive(player_x, 30).unwrap();
+        assert_eq!(status(&g, 109), PlayerLiveness::Fresh);
+        assert_eq!(status(&g, 110), remaining(20));
+        assert_eq!(status(&g, 130), remaining(0));
+        assert_eq!(status(&g, 131), PlayerLiveness::Claimable);
+        assert!(g.claim_timeout(player_o, 130).is_err());
+
+        // A clock behind the last keep alive has seen no time pass, whatever the threshold
+        let always = WarningConfig { warning_percent: 0 };
+        assert_eq!(g.liveness_status(30, &always), remaining(100));
+        assert_eq!(g.liveness_status(29, &always), PlayerLiveness::Fresh);
+        assert_eq!(status(&g, 0), PlayerLiveness::Fresh);
+        let last_moment = WarningConfig {
+            warning_percent: 255,
+        };
+        assert_eq!(g.liveness_status(129, &last_moment), PlayerLiveness::Fresh);
+        assert_eq!(g.liveness_status(130, &last_moment), remaining(0));
+
+        // Finished games have nobody to wait on
+        g.claim_timeout(player_o, 131).unwrap();
+        assert_eq!(status(&g, 131), PlayerLiveness::Fresh);
+        assert_eq!(g.liveness_status(u64::MAX, &always), PlayerLiveness::Fresh);
+
+        // No timeout at all still counts down without overflowing
+        let g = Game::create_with_config(&player_x, GameConfig::default(), 1).unwrap();
+        assert_eq!(status(&g, u64::MAX / 2), PlayerLiveness::Fresh);
+        assert_eq!(status(&g, u64::MAX), remaining(1));
+    }
+
+    #[test]
+    pub fn slot_timeouts() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let clock = |slot, unix_timestamp| ClockSnapshot {
+            slot,
+            unix_timestamp,
+        };
+        let slots = GameConfig {
+            move_timeout: 10,
+            timeout_unit: TimeoutUnit::Slots,
+            ..GameConfig::default()
+        };
+
+        // Slots run on while the validator clock stalls
+        let mut g = Game::create_at(&player_x, slots, &clock(100, 5_000)).unwrap();
+        g.join_at(player_o, &clock(101, 5_000)).unwrap();
+        assert!(!g.has_timed_out_at(&clock(110, 5_000)));
+        assert!(g.has_timed_out_at(&clock(111, 5_000)));
+        assert!(g.claim_timeout_at(player_o, &clock(110, 9_999)).is_err());
+        g.claim_timeout_at(player_o, &clock(111, 5_000)).unwrap();
+        assert_eq!(g.game_state, GameState::OWon);
+
+        // The same clock readings leave a game timed in seconds alone
+        let seconds = GameConfig {
+            timeout_unit: TimeoutUnit::Seconds,
+            ..slots
+        };
+        let mut g = Game::create_at(&player_x, seconds, &clock(100, 5_000)).unwrap();
+        g.join_at(player_o, &clock(101, 5_001)).unwrap();
+        assert!(g.claim_timeout_at(player_o, &clock(1_000, 5_010)).is_err());
+        g.claim_timeout(player_o, 5_011).unwrap();
+
+        // Bare timestamps are seconds, which a slot timed game refuses
+        let unit_error = |result: Result<()>| result.unwrap_err().kind;
+        assert_eq!(
+            Game::create_with_config(&player_x, slots, 1)
+                .unwrap_err()
+                .kind,
+            ProgramError::TimeoutUnitMismatch
+        );
+        let mut g = Game::create_at(&player_x, slots, &clock(100, 5_000)).unwrap();
+        assert_eq!(
+            g.join(player_o, 101).unwrap_err().kind,
+            ProgramError::TimeoutUnitMismatch
+        );
+        g.join_at(player_o, &clock(101, 5_000)).unwrap();
+        assert_eq!(
+            unit_error(g.keep_alive(player_x, 102)),
+            ProgramError::TimeoutUnitMismatch
+        );
+        assert_eq!(
+            unit_error(g.claim_timeout(player_o, 200)),
+            ProgramError::TimeoutUnitMismatch
+        );
+
+        // A slot going backwards is an ordinary stale keep alive, and never times anyone out
+        g.keep_alive_at(player_x, &clock(105, 5_000)).unwrap();
+        assert_eq!(
+            unit_error(g.keep_alive_at(player_x, &clock(104, 6_000))),
+            ProgramError::InvalidTimestamp
+        );
+        assert!(!g.has_timed_out_at(&clock(0, 6_000)));
+        assert!(g.claim_timeout_at(player_o, &clock(3, 6_000)).is_err());
+        assert_eq!(g.game_state, GameState::XMove);
+    }
+
+    #[test]
+    pub fn timestamp_claim_timeout() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            move_timeout: 10,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        g.join(player_o, 1).unwrap();
+        g.keep_alive(player_x, 1).unwrap();
+
+        // X is to move, only O may claim and only once X's keep alive is stale
+        assert!(g.claim_timeout(player_x, 20).is_err());
+        assert!(g.claim_timeout(player_o, 11).is_err());
+        assert!(g.keep_alive_seq(player_x, 2).is_err());
+        g.claim_timeout(player_o, 12).unwrap();
+        assert_eq!(g.game_state, GameState::OWon);
+        assert_eq!(g.winning_line(), None);
+        assert!(g.validate().is_ok());
+    }
+
+    #[test]
+    pub fn sequence_liveness() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            liveness: Liveness::Sequence,
+            heartbeat_limit: 2,
+            ..GameConfig::default()
+        };
+        assert!(GameConfig {
+            heartbeat_limit: 0,
+            ..config
+        }
+        .validate()
+        .is_err());
+
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        g.join(player_o, 1).unwrap();
+
+        // The two liveness modes cannot be mixed
+        assert!(g.keep_alive(player_x, 5).is_err());
+
+        // Sequence numbers must strictly increase
+        g.keep_alive_seq(player_o, 1).unwrap();
+        assert!(g.keep_alive_seq(player_o, 1).is_err());
+        assert!(g.keep_alive_seq(player_o, 0).is_err());
+        assert!(g.claim_timeout(player_o, 1_000).is_err());
+
+        // X answering resets O's count
+        g.keep_alive_seq(player_x, 1).unwrap();
+        g.keep_alive_seq(player_o, 2).unwrap();
+        assert!(g.claim_timeout(player_o, 0).is_err());
+        g.keep_alive_seq(player_o, 7).unwrap();
+        assert!(g.claim_timeout(player_x, 0).is_err());
+        g.claim_timeout(player_o, 0).unwrap();
+        assert_eq!(g.game_state, GameState::OWon);
+
+        // A move also counts as an answer
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        g.join(player_o, 1).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        g.keep_alive_seq(player_x, 1).unwrap();
+        g.next_move(player_o, 0, 0).unwrap();
+        g.keep_alive_seq(player_x, 2).unwrap();
+        assert!(g.claim_timeout(player_o, 0).is_err());
+        assert!(g.claim_timeout(player_x, 0).is_err());
+    }
+
+    #[test]
+    pub fn abort() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        // Full handshake
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 1, 1).unwrap();
+        assert!(g.confirm_abort(player_x).is_err());
+        g.request_abort(player_o).unwrap();
+        assert!(g.confirm_abort(player_o).is_err());
+        assert_eq!(g.game_state, GameState::OMove);
+        g.confirm_abort(player_x).unwrap();
+        assert_eq!(g.game_state, GameState::Aborted);
+        assert!(g.validate().is_ok());
+        assert_eq!(g.finalize_with_trophy(&player_x, 1).unwrap(), None);
+        assert_eq!(g.outcome_with_optimal_play(), None);
+
+        // Abort after the game is over
+        assert!(g.request_abort(player_x).is_err());
+        assert!(g.confirm_abort(player_x).is_err());
+        assert!(g.next_move(player_o, 0, 0).is_err());
+
+        // A move cancels the request
+        let mut g = Game::new(player_x, player_o);
+        g.request_abort(player_x).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        assert!(g.confirm_abort(player_o).is_err());
+        assert_eq!(g.game_state, GameState::OMove);
+
+        // Player X may abort a Waiting game alone, but nobody else can
+        let mut g = Game::create(&player_x);
+        assert!(g.request_abort(player_o).is_err());
+        assert!(g.confirm_abort(player_x).is_err());
+        g.request_abort(player_x).unwrap();
+        assert_eq!(g.game_state, GameState::Aborted);
+        assert!(g.join(player_o, 1).is_err());
+    }
+
+    #[test]
+    pub fn opening_class() {
+        assert_eq!(Game::opening_class(1, 1), Some(OpeningClass::Center));
+        for &(x, y) in &[(0, 0), (2, 0), (0, 2), (2, 2)] {
+            assert_eq!(Game::opening_class(x, y), Some(OpeningClass::Corner));
+        }
+        for &(x, y) in &[(1, 0), (0, 1), (2, 1), (1, 2)] {
+            assert_eq!(Game::opening_class(x, y), Some(OpeningClass::Edge));
+        }
+        assert_eq!(Game::opening_class(3, 0), None);
+        assert_eq!(Game::opening_class(0, 3), None);
+    }
+
+    #[test]
+    pub fn opening_name() {
+        assert_eq!(play(&[]).opening_name(), None);
+        let mut seen = HashMap::new();
+        for first in 0..9 {
+            let (x, y) = cell_coords(first).unwrap();
+            let name = match Game::opening_class(x, y).unwrap() {
+                OpeningClass::Center => "Center Opening",
+                OpeningClass::Corner => "Corner Opening",
+                OpeningClass::Edge => "Edge Opening",
+            };
+            assert_eq!(play(&[first]).opening_name(), Some(name));
+            for reply in (0..9).filter(|&reply| reply != first) {
+                let name = play(&[first, reply]).opening_name().unwrap();
+                *seen.entry(name).or_insert(0) += 1;
+                for &next in [0, 4, 8]
+                    .iter()
+                    .filter(|&&next| next != first && next != reply)
+                {
+                    assert_eq!(play(&[first, reply, next]).opening_name(), None);
+                }
+            }
+        }
+        // Every named reply, the images of each under the symmetries of the board included
+        assert_eq!(seen.len(), 12);
+        assert_eq!(seen.values().sum::<usize>(), 72);
+        assert_eq!(seen["Center-Corner Response"], 4);
+        assert_eq!(seen["Corner-Opposite Corner Response"], 4);
+        assert_eq!(seen["Corner-Edge Response"], 8);
+        assert_eq!(seen["Edge-Opposite Edge Response"], 4);
+
+        assert_eq!(play(&[0, 1]).opening_name(), Some("Corner-Edge Response"));
+        assert_eq!(
+            play(&[0, 5]).opening_name(),
+            Some("Corner-Far Edge Response")
+        );
+        assert_eq!(play(&[8, 2]).opening_name(), Some("Corner-Corner Response"));
+        assert_eq!(
+            play(&[7, 0]).opening_name(),
+            Some("Edge-Far Corner Response")
+        );
+        assert_eq!(play(&[5, 7]).opening_name(), Some("Edge-Edge Response"));
+    }
+
+    #[test]
+    pub fn board_distance() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut a = Game::new(player_x, player_o);
+        let mut b = a.clone();
+        assert_eq!(Game::board_distance(&a, &b), 0);
+        b.next_move(player_x, 1, 1).unwrap();
+        assert_eq!(Game::board_distance(&a, &b), 1);
+        a.next_move(player_x, 0, 0).unwrap();
+        assert_eq!(Game::board_distance(&a, &b), 2);
+
+        // Every cell filled on one board and free on the other, or holding the other mark
+        a.set_board_from_str("XOXXOOOXX").unwrap();
+        b.set_board_from_str("_________").unwrap();
+        assert_eq!(Game::board_distance(&a, &b), 9);
+        b.board = a.board;
+        for item in b.board.iter_mut() {
+            *item = BOARD_ITEM_X + BOARD_ITEM_O - *item;
+        }
+        assert_eq!(Game::board_distance(&a, &b), 9);
+        assert_eq!(Game::board_distance(&b, &a), 9);
+        b.set_board_from_str("XO_X_____").unwrap();
+        assert_eq!(Game::board_distance(&a, &b), 6);
+        assert_eq!(Game::board_distance(&b, &a), 6);
+    }
+
+    #[test]
+    pub fn all_equivalent_moves() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        let corners = vec![(0, 0), (2, 0), (0, 2), (2, 2)];
+        assert_eq!(g.all_equivalent_moves(0, 0), corners);
+        assert_eq!(g.all_equivalent_moves(2, 2), corners);
+        assert_eq!(g.all_equivalent_moves(1, 1), vec![(1, 1)]);
+        assert_eq!(g.all_equivalent_moves(1, 0).len(), 4);
+        assert_eq!(g.all_equivalent_moves(3, 0), vec![]);
+
+        g.next_move(player_x, 1, 1).unwrap();
+        assert_eq!(g.all_equivalent_moves(0, 0), corners);
+
+        g.next_move(player_o, 0, 0).unwrap();
+        assert_eq!(g.all_equivalent_moves(2, 0), vec![(2, 0), (0, 2)]);
+        assert_eq!(g.all_equivalent_moves(2, 2), vec![(2, 2)]);
+
+        g.next_move(player_x, 1, 0).unwrap();
+        assert_eq!(g.all_equivalent_moves(2, 0), vec![(2, 0)]);
+    }
+
+    #[test]
+    pub fn deduplicated_moves() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        assert_eq!(g.available_moves().len(), 9);
+        assert_eq!(g.deduplicated_moves(), vec![(0, 0), (1, 0), (1, 1)]);
+
+        g.next_move(player_x, 1, 1).unwrap();
+        assert_eq!(g.deduplicated_moves(), vec![(0, 0), (1, 0)]);
+
+        g.next_move(player_o, 0, 0).unwrap();
+        let moves = g.deduplicated_moves();
+        assert!(moves.len() < g.available_moves().len());
+        for (x, y) in g.available_moves() {
+            // Every pruned move is represented by exactly one of the remaining moves
+            let equivalent = g.all_equivalent_moves(x, y);
+            assert_eq!(moves.iter().filter(|m| equivalent.contains(m)).count(), 1);
+        }
+
+        g.next_move(player_x, 1, 0).unwrap();
+        assert_eq!(g.deduplicated_moves(), g.available_moves());
+    }
+
+    #[test]
+    pub fn replay_animation_frames() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let moves = [
+            Move { x: 1, y: 1 },
+            Move { x: 0, y: 0 },
+            Move { x: 2, y: 0 },
+            Move { x: 0, y: 2 },
+            Move { x: 0, y: 1 },
+        ];
+        let frames = Game::replay_animation_frames(&moves, player_x, player_o);
+        assert_eq!(frames.len(), moves.len() + 1);
+
+        let occupied = |g: &Game| {
+            g.board
+                .iter()
+                .filter(|&&item| item != BOARD_ITEM_FREE)
+                .count()
+        };
+        assert_eq!(occupied(&frames[0]), 0);
+        for pair in frames.windows(2) {
+            assert_eq!(occupied(&pair[1]), occupied(&pair[0]) + 1);
+        }
+        let last = Game::from_moves_iter(player_x, player_o, moves.iter().cloned()).unwrap();
+        assert_eq!(frames.last(), Some(&last));
+
+        // An illegal move ends the replay
+        let frames = Game::replay_animation_frames(&[moves[0], moves[0]], player_x, player_o);
+        assert_eq!(frames.len(), 2);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    pub fn json_round_trip() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 1, 1).unwrap();
+
+        let json = g.to_json().unwrap();
+        assert_eq!(Game::from_json(&json).unwrap(), g);
+        assert!(Game::from_json("{}").is_err());
+
+        g.board[0] = BOARD_ITEM_X;
+        assert!(Game::from_json(&g.to_json().unwrap()).is_err());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    pub fn msgpack_round_trip() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 1, 1).unwrap();
+        g.next_move(player_o, 0, 2).unwrap();
+
+        let bytes = g.to_msgpack().unwrap();
+        assert!(bytes.len() < serde_json::to_string(&g).unwrap().len());
+        assert_eq!(Game::from_msgpack(&bytes).unwrap(), g);
+        assert!(Game::from_msgpack(&[]).is_err());
+        assert!(Game::from_msgpack(&bytes[..bytes.len() / 2]).is_err());
+
+        g.board[0] = BOARD_ITEM_X;
+        assert!(Game::from_msgpack(&g.to_msgpack().unwrap()).is_err());
+    }
+
+    #[test]
+    pub fn solver_board_value() {
+        let solver = Solver::new();
+        assert_eq!(solver.board_value(&[BOARD_ITEM_FREE; 9]), Some(0));
+
+        // O answering a corner with an adjacent edge loses
+        let mut board = [BOARD_ITEM_FREE; 9];
+        board[0] = BOARD_ITEM_X;
+        board[1] = BOARD_ITEM_O;
+        assert_eq!(solver.board_value(&board), Some(1));
+
+        board[3] = BOARD_ITEM_X;
+        board[4] = BOARD_ITEM_O;
+        board[6] = BOARD_ITEM_X;
+        assert_eq!(solver.board_value(&board), None);
+    }
 }
@@ -0,0 +1,376 @@
+use consts::{BOARD_ITEM_FREE, BOARD_ITEM_O, BOARD_ITEM_X};
+use game::{Game, GameState, Seat, Variant};
+#[cfg(feature = "testing")]
+use proptest::prelude::*;
+use rules::{find_line, is_full};
+use solana_sdk::pubkey::Pubkey;
+
+// The properties every reachable Game has.  check() and check_transition() report the first
+// one a game breaks
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Invariant {
+    KnownCells,        // Every cell is free, X or O
+    MarkBalance,       // X has as many marks as O, or one more, unless the game is wild
+    StateMatchesBoard, // The game state agrees with the lines and free cells of the board
+    MoveHistory,       // The recorded moves are the marks on the board, X and O alternating
+    KeepAlive,         // Nothing is recorded for player O before they join
+    TrophyForWinner,   // Only a won game can have minted a trophy, until the account is reset
+    TerminalImmutable, // A finished game keeps its board, moves and outcome until it is reset
+}
+
+pub fn check(game: &Game) -> Result<(), Invariant> {
+    let board = &game.board;
+    if board
+        .iter()
+        .any(|&item| item != BOARD_ITEM_FREE && item != BOARD_ITEM_X && item != BOARD_ITEM_O)
+    {
+        Err(Invariant::KnownCells)?;
+    }
+
+    let count = |x_or_o| board.iter().filter(|&&item| item == x_or_o).count();
+    let (x_count, o_count) = (count(BOARD_ITEM_X), count(BOARD_ITEM_O));
+    let wild = game.config.variant == Variant::Wild;
+    if !wild && x_count != o_count && x_count != o_count + 1 {
+        Err(Invariant::MarkBalance)?;
+    }
+
+    let variant = game.config.variant;
+    let x_line = find_line(variant, board, BOARD_ITEM_X).is_some();
+    let o_line = find_line(variant, board, BOARD_ITEM_O).is_some();
+    // Wild players place either mark, so only the number of moves tells who is to move, and a
+    // line goes to whoever made the last one
+    let (x_to_move, x_won, o_won) = if wild {
+        let even = (x_count + o_count) % 2 == 0;
+        let line = x_line || o_line;
+        (even, line && !even, line && even)
+    } else {
+        (x_count == o_count, x_line, o_line)
+    };
+    let forfeit = game.forfeited_by_conduct().is_some();
+    let matches_board = match game.game_state {
+        GameState::Waiting => x_count + o_count == 0,
+        GameState::XMove => x_to_move && !x_won && !o_won,
+        GameState::OMove => !x_to_move && !x_won && !o_won && !is_full(board),
+        // Won by timeout or forfeit if the winner has no line, and by forfeit with either
+        // player to move
+        GameState::XWon => (!x_to_move || forfeit) && !o_won && (x_won || !is_full(board)),
+        GameState::OWon => (x_to_move || forfeit) && !x_won && (o_won || !is_full(board)),
+        GameState::Draw => is_full(board) && !x_won && !o_won,
+        GameState::Aborted => !x_won && !o_won,
+    };
+    if !matches_board {
+        Err(Invariant::StateMatchesBoard)?;
+    }
+
+    let moves = &game.moves[..game.move_count as usize];
+    let alternating = moves.iter().enumerate().all(|(i, &board_index)| {
+        let x_or_o = if i % 2 == 0 {
+            BOARD_ITEM_X
+        } else {
+            BOARD_ITEM_O
+        };
+        let placed = match board.get(board_index as usize) {
+            Some(&item) if wild => item != BOARD_ITEM_FREE,
+            item => item == Some(&x_or_o),
+        };
+        placed && !moves[..i].contains(&board_index)
+    });
+    if moves.len() != x_count + o_count || !alternating {
+        Err(Invariant::MoveHistory)?;
+    }
+
+    if game.game_state == GameState::Waiting
+        && (game.player_o != Pubkey::default()
+            || game.keep_alive.is_set(Seat::O)
+            || game.idle_heartbeats[1] != 0
+            || game.abort_requested.contains(&true))
+    {
+        Err(Invariant::KeepAlive)?;
+    }
+
+    // The flag belongs to the account and outlives a reset, see Game::reset
+    let won = game.game_state == GameState::XWon || game.game_state == GameState::OWon;
+    if game.trophy_minted && !won && game.generation == 0 {
+        Err(Invariant::TrophyForWinner)?;
+    }
+    Ok(())
+}
+
+// Invariants of a single API call that turned `before` into `after`: the moves already made
+// stay made, and a finished game stays as it ended.  Resetting the account starts over
+pub fn check_transition(before: &Game, after: &Game) -> Result<(), Invariant> {
+    check(after)?;
+    if before.generation != after.generation {
+        return Ok(());
+    }
+    let made = before.move_count as usize;
+    if after.move_count < before.move_count || after.moves[..made] != before.moves[..made] {
+        Err(Invariant::MoveHistory)?;
+    }
+    if before.is_finished()
+        && (after.board != before.board
+            || after.move_count != before.move_count
+            || after.game_state != before.game_state)
+    {
+        Err(Invariant::TerminalImmutable)?;
+    }
+    Ok(())
+}
+
+// Players of the generated games
+#[cfg(feature = "testing")]
+pub const PLAYER_X: [u8; 32] = [1; 32];
+#[cfg(feature = "testing")]
+pub const PLAYER_O: [u8; 32] = [2; 32];
+
+// Games reached through the public API: a random config, then a random prefix of legal moves.
+// The moves are picked among the available ones, so every generated game is reachable
+#[cfg(feature = "testing")]
+pub fn reachable_game() -> BoxedStrategy<Game> {
+    use game::{GameConfig, Liveness};
+
+    (
+        any::<bool>(),
+        any::<bool>(),
+        any::<bool>(),
+        any::<bool>(),
+        proptest::option::of(1u8..4),
+        proptest::collection::vec(0usize..9, 0..10),
+    )
+        .prop_map(
+            |(join, solo, swap_rule, center_handicap, heartbeat_limit, choices)| {
+                let player_x = Pubkey::new(&PLAYER_X);
+                let player_o = if solo {
+                    player_x
+                } else {
+                    Pubkey::new(&PLAYER_O)
+                };
+                let config = GameConfig {
+                    swap_rule,
+                    liveness: heartbeat_limit.map_or(Liveness::Timestamp, |_| Liveness::Sequence),
+                    heartbeat_limit: heartbeat_limit.unwrap_or(0),
+                    center_handicap,
+                    ..GameConfig::default()
+                };
+                let mut game = Game::create_with_config(&player_x, config, 1).unwrap();
+                if !join {
+                    return game;
+                }
+                game.join(player_o, 2).unwrap();
+                for choice in choices {
+                    let moves = game.available_moves();
+                    let player = match game.current_player() {
+                        Some(player) if !moves.is_empty() => player,
+                        _ => break,
+                    };
+                    let (x, y) = moves[choice % moves.len()];
+                    game.next_move(player, x, y).unwrap();
+                }
+                game
+            },
+        )
+        .boxed()
+}
+
+#[cfg(feature = "testing")]
+impl Arbitrary for Game {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Game>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<Game> {
+        reachable_game()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn check_finds_broken_games() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 1, 1).unwrap();
+        assert_eq!(check(&g), Ok(()));
+
+        let mut broken = g.clone();
+        broken.board[0] = BOARD_ITEM_X;
+        assert_eq!(check(&broken), Err(Invariant::MarkBalance));
+
+        let mut broken = g.clone();
+        broken.game_state = GameState::XMove;
+        assert_eq!(check(&broken), Err(Invariant::StateMatchesBoard));
+
+        let mut broken = g.clone();
+        broken.moves[0] = 0;
+        assert_eq!(check(&broken), Err(Invariant::MoveHistory));
+
+        let mut broken = g.clone();
+        broken.trophy_minted = true;
+        assert_eq!(check(&broken), Err(Invariant::TrophyForWinner));
+
+        let mut waiting = Game::create(&player_x);
+        assert_eq!(check(&waiting), Ok(()));
+        waiting.keep_alive.set(Seat::O, 5);
+        assert_eq!(check(&waiting), Err(Invariant::KeepAlive));
+
+        let mut finished = g.clone();
+        finished.game_state = GameState::Aborted;
+        let mut after = finished.clone();
+        after.game_state = GameState::XMove;
+        after.board[1] = BOARD_ITEM_O;
+        after.moves[1] = 1;
+        after.move_count = 2;
+        assert_eq!(
+            check_transition(&finished, &after),
+            Err(Invariant::TerminalImmutable)
+        );
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod proptest_test {
+    use super::*;
+    use player_name::PlayerName;
+    use program_state::State;
+    use result::ProgramError;
+    use rules::{cell_coords, cell_index};
+    use simple_serde::SimpleSerde;
+    use std::mem::size_of;
+
+    // One call of the Game API, by player X (false) or player O (true)
+    #[derive(Clone, Debug)]
+    enum Call {
+        Join(bool, u64),
+        Move(bool, usize, usize),
+        KeepAlive(bool, u64),
+        KeepAliveSeq(bool, u64),
+        SetName(bool),
+        Swap(bool),
+        ClaimTimeout(bool, u64),
+        RequestAbort(bool),
+        ConfirmAbort(bool),
+        Reset(bool, u64),
+        FinalizeWithTrophy,
+    }
+
+    fn call() -> BoxedStrategy<Call> {
+        prop_oneof![
+            (any::<bool>(), 0u64..200).prop_map(|(o, t)| Call::Join(o, t)),
+            (any::<bool>(), 0usize..4, 0usize..4).prop_map(|(o, x, y)| Call::Move(o, x, y)),
+            (any::<bool>(), 0u64..200).prop_map(|(o, t)| Call::KeepAlive(o, t)),
+            (any::<bool>(), 0u64..200).prop_map(|(o, s)| Call::KeepAliveSeq(o, s)),
+            any::<bool>().prop_map(Call::SetName),
+            any::<bool>().prop_map(Call::Swap),
+            (any::<bool>(), 0u64..200).prop_map(|(o, t)| Call::ClaimTimeout(o, t)),
+            any::<bool>().prop_map(Call::RequestAbort),
+            any::<bool>().prop_map(Call::ConfirmAbort),
+            (any::<bool>(), 0u64..200).prop_map(|(o, t)| Call::Reset(o, t)),
+            Just(Call::FinalizeWithTrophy),
+        ]
+        .boxed()
+    }
+
+    fn apply(game: &mut Game, call: &Call) -> bool {
+        let player = |o: bool| Pubkey::new(if o { &PLAYER_O } else { &PLAYER_X });
+        match *call {
+            Call::Join(o, t) => game.join(player(o), t).is_ok(),
+            Call::Move(o, x, y) => game.next_move(player(o), x, y).is_ok(),
+            Call::KeepAlive(o, t) => game.keep_alive(player(o), t).is_ok(),
+            Call::KeepAliveSeq(o, s) => game.keep_alive_seq(player(o), s).is_ok(),
+            Call::SetName(o) => game
+                .set_name(player(o), PlayerName::new("prop").unwrap())
+                .is_ok(),
+            Call::Swap(o) => game.swap_seats(player(o)).is_ok(),
+            Call::ClaimTimeout(o, t) => game.claim_timeout(player(o), t).is_ok(),
+            Call::RequestAbort(o) => game.request_abort(player(o)).is_ok(),
+            Call::ConfirmAbort(o) => game.confirm_abort(player(o)).is_ok(),
+            Call::Reset(o, t) => game.reset(&player(o), t).is_ok(),
+            Call::FinalizeWithTrophy => {
+                game.finalize_with_trophy(&Pubkey::new(&[9; 32]), 0).is_ok()
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn cell_index_and_cell_coords_are_inverses(x in 0usize..12, y in 0usize..12) {
+            match cell_index(x, y) {
+                Ok(index) => {
+                    prop_assert!(x < 3 && y < 3);
+                    prop_assert_eq!(cell_coords(index).unwrap(), (x, y));
+                }
+                Err(err) => {
+                    prop_assert!(x >= 3 || y >= 3);
+                    prop_assert_eq!(err.kind, ProgramError::InvalidMove);
+                }
+            }
+            let index = y * 12 + x;
+            match cell_coords(index) {
+                Ok((x, y)) => prop_assert_eq!(cell_index(x, y).unwrap(), index),
+                Err(_) => prop_assert!(index >= 9),
+            }
+        }
+
+        #[test]
+        fn lenient_decoding_never_panics(
+            game in any::<Game>(),
+            edits in proptest::collection::vec((any::<usize>(), any::<u8>()), 0..8),
+            len in 0usize..300,
+        ) {
+            let mut userdata = vec![0; size_of::<State>()];
+            State::Game(game).serialize(&mut userdata).unwrap();
+            for &(i, byte) in &edits {
+                let i = i % userdata.len();
+                userdata[i] = byte;
+            }
+            userdata.resize(len, 0);
+            Game::deserialize_lenient(&userdata);
+        }
+
+        #[test]
+        fn generated_games_hold_invariants(game in any::<Game>()) {
+            prop_assert_eq!(check(&game), Ok(()));
+            prop_assert!(game.validate().is_ok());
+        }
+
+        #[test]
+        fn api_calls_preserve_invariants(
+            game in any::<Game>(),
+            calls in proptest::collection::vec(call(), 0..20),
+        ) {
+            let mut game = game;
+            for call in &calls {
+                let before = game.clone();
+                apply(&mut game, call);
+                prop_assert_eq!(check_transition(&before, &game), Ok(()), "after {:?}", call);
+            }
+        }
+
+        #[test]
+        fn serialization_round_trips(game in any::<Game>()) {
+            let json = game.to_json().unwrap();
+            prop_assert_eq!(&Game::from_json(&json).unwrap(), &game);
+
+            let mut userdata = vec![0; size_of::<State>()];
+            State::Game(game.clone()).serialize(&mut userdata).unwrap();
+            match State::deserialize(&userdata).unwrap() {
+                State::Game(decoded) => prop_assert_eq!(decoded, game),
+                state => prop_assert!(false, "decoded {:?}", state),
+            }
+        }
+
+        #[test]
+        fn cells_are_taken_once(game in any::<Game>(), x in 0usize..3, y in 0usize..3) {
+            let mut game = game;
+            if let Some(player) = game.current_player() {
+                if game.next_move(player, x, y).is_ok() {
+                    for player in &[Pubkey::new(&PLAYER_X), Pubkey::new(&PLAYER_O)] {
+                        prop_assert!(game.clone().next_move(*player, x, y).is_err());
+                    }
+                }
+            }
+        }
+    }
+}
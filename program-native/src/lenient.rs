@@ -0,0 +1,431 @@
+// Best-effort decoding of game accounts for support tooling.  The strict path,
+// batch::decode_game, refuses a dump at its first problem; this one carries on and reports
+// what it had to patch up so the rest of the account can still be looked at
+use bincode;
+use game::{
+    outcome, Game, GameConfig, GameState, KeepAlives, Liveness, Seat, TimeoutUnit, Variant,
+    BOARD_ITEM_FREE, BOARD_ITEM_O, BOARD_ITEM_X,
+};
+use player_name::{PlayerName, MAX_NAME_LEN};
+use result::ProgramError;
+use solana_sdk::pubkey::Pubkey;
+use std;
+
+// Something deserialize_lenient had to work around, with what it did about it
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodeDiagnostic {
+    NotAGame(u32), // Tag other than State::Game, read on anyway
+    Truncated { offset: usize, field: &'static str }, // Input ended, later fields are defaults
+    UnknownGameState(u32), // Replaced by the state the board implies
+    InvalidBoardItem { cell: usize, item: u8 }, // Cleared
+    InvalidBool { field: &'static str, value: u8 }, // Taken as true
+    UnknownVariant { field: &'static str, value: u32 }, // Replaced by the default
+    InvalidName { seat: usize }, // Cleared
+    InvalidMoveCount(u8), // Replaced by the number of marks
+    ImplausibleKeepAlive { seat: usize, value: u64 }, // Outside created_at..finished_at, kept
+    Inconsistent(ProgramError), // The recovered game fails Game::validate
+}
+
+impl std::fmt::Display for DecodeDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            DecodeDiagnostic::NotAGame(tag) => write!(f, "state tag {} is not a game", tag),
+            DecodeDiagnostic::Truncated { offset, field } => {
+                write!(f, "input ends at byte {} in {}", offset, field)
+            }
+            DecodeDiagnostic::UnknownGameState(value) => {
+                write!(f, "unknown game state {}, inferred from the board", value)
+            }
+            DecodeDiagnostic::InvalidBoardItem { cell, item } => {
+                write!(f, "board item {} in cell {}, cleared", item, cell)
+            }
+            DecodeDiagnostic::InvalidBool { field, value } => {
+                write!(f, "{} is {}, taken as true", field, value)
+            }
+            DecodeDiagnostic::UnknownVariant { field, value } => {
+                write!(f, "unknown {} {}, using the default", field, value)
+            }
+            DecodeDiagnostic::InvalidName { seat } => {
+                write!(f, "name {} is invalid, cleared", seat)
+            }
+            DecodeDiagnostic::InvalidMoveCount(count) => {
+                write!(f, "move count {}, using the number of marks", count)
+            }
+            DecodeDiagnostic::ImplausibleKeepAlive { seat, value } => {
+                write!(
+                    f,
+                    "keep alive {} of seat {} is outside the game",
+                    value, seat
+                )
+            }
+            DecodeDiagnostic::Inconsistent(kind) => write!(f, "recovered game: {}", kind),
+        }
+    }
+}
+
+// Reads the fields of a bincode encoded game one at a time.  Past the end of the input every
+// read is None, after a single Truncated diagnostic
+struct Reader<'a> {
+    src: &'a [u8],
+    offset: usize,
+    truncated: bool,
+    diagnostics: Vec<DecodeDiagnostic>,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, field: &'static str, len: usize) -> Option<&'a [u8]> {
+        if self.truncated {
+            return None;
+        }
+        match self.src.get(self.offset..self.offset + len) {
+            Some(bytes) => {
+                self.offset += len;
+                Some(bytes)
+            }
+            None => {
+                self.diagnostics.push(DecodeDiagnostic::Truncated {
+                    offset: self.offset,
+                    field,
+                });
+                self.truncated = true;
+                None
+            }
+        }
+    }
+
+    fn u8(&mut self, field: &'static str) -> Option<u8> {
+        self.take(field, 1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self, field: &'static str) -> Option<u32> {
+        let mut word = [0; 4];
+        word.copy_from_slice(self.take(field, 4)?);
+        Some(u32::from_le_bytes(word))
+    }
+
+    fn u64(&mut self, field: &'static str) -> Option<u64> {
+        let mut word = [0; 8];
+        word.copy_from_slice(self.take(field, 8)?);
+        Some(u64::from_le_bytes(word))
+    }
+
+    fn bool(&mut self, field: &'static str) -> Option<bool> {
+        let value = self.u8(field)?;
+        if value > 1 {
+            self.diagnostics
+                .push(DecodeDiagnostic::InvalidBool { field, value });
+        }
+        Some(value != 0)
+    }
+
+    fn pubkey(&mut self, field: &'static str) -> Option<Pubkey> {
+        self.take(field, 32).map(Pubkey::new)
+    }
+
+    // One of the `variants` by its index, the first one when the index is unknown
+    fn variant<T: Copy>(&mut self, field: &'static str, value: Option<u32>, variants: &[T]) -> T {
+        match value {
+            Some(value) => match variants.get(value as usize) {
+                Some(&variant) => variant,
+                None => {
+                    self.diagnostics
+                        .push(DecodeDiagnostic::UnknownVariant { field, value });
+                    variants[0]
+                }
+            },
+            None => variants[0],
+        }
+    }
+}
+
+impl Game {
+    // Decode a game account dump as far as it goes.  None only when the input ends before the
+    // players and board; otherwise the game holds every field that could be read, with the
+    // repairs the diagnostics describe.  Never panics, whatever the input
+    pub fn deserialize_lenient(src: &[u8]) -> (Option<Game>, Vec<DecodeDiagnostic>) {
+        let mut reader = Reader {
+            src,
+            offset: 0,
+            truncated: false,
+            diagnostics: vec![],
+        };
+        let game = Game::read_lenient(&mut reader);
+        (game, reader.diagnostics)
+    }
+
+    fn read_lenient(reader: &mut Reader) -> Option<Game> {
+        let tag = reader.u32("state tag")?;
+        if tag != 2 {
+            reader.diagnostics.push(DecodeDiagnostic::NotAGame(tag));
+        }
+        let mut game = Game {
+            keep_alive: KeepAlives::from([reader.u64("keep alive")?, reader.u64("keep alive")?]),
+            ..Game::default()
+        };
+        let game_state = reader.u32("game state")?;
+        game.player_x = reader.pubkey("player x")?;
+        game.player_o = reader.pubkey("player o")?;
+        let board = reader.take("board", 9)?;
+        for (cell, &item) in board.iter().enumerate() {
+            if item == BOARD_ITEM_FREE || item == BOARD_ITEM_X || item == BOARD_ITEM_O {
+                game.board[cell] = item;
+            } else {
+                reader
+                    .diagnostics
+                    .push(DecodeDiagnostic::InvalidBoardItem { cell, item });
+            }
+        }
+        game.game_state = match GameState::ALL.get(game_state as usize) {
+            Some(&game_state) => game_state,
+            None => {
+                reader
+                    .diagnostics
+                    .push(DecodeDiagnostic::UnknownGameState(game_state));
+                implied_state(&game)
+            }
+        };
+
+        // Everything from here on keeps its default once the input runs out
+        let marks = game.filled_cells();
+        game.trophy_minted = reader.bool("trophy minted").unwrap_or(false);
+        if let Some(moves) = reader.take("moves", 9) {
+            game.moves.copy_from_slice(moves);
+        }
+        game.move_count = match reader.u8("move count") {
+            Some(count) if count > 9 => {
+                reader
+                    .diagnostics
+                    .push(DecodeDiagnostic::InvalidMoveCount(count));
+                marks
+            }
+            Some(count) => count,
+            None => marks,
+        };
+        game.generation = reader.u32("generation").unwrap_or(0);
+
+        let defaults = GameConfig::default();
+        let move_timeout = reader.u64("move timeout");
+        let join_deadline = reader.u64("join deadline");
+        let variant = reader.u32("variant");
+        let allow_solo = reader.bool("allow solo");
+        let swap_rule = reader.bool("swap rule");
+        let liveness = reader.u32("liveness");
+        let heartbeat_limit = reader.u8("heartbeat limit");
+        let timeout_unit = reader.u8("timeout unit");
+        let center_handicap = reader.bool("center handicap");
+        let conduct_limit = reader.u32("conduct limit");
+        game.config = GameConfig {
+            move_timeout: move_timeout.unwrap_or(defaults.move_timeout),
+            join_deadline: join_deadline.unwrap_or(defaults.join_deadline),
+            variant: reader.variant("variant", variant, &[Variant::Classic, Variant::Wild]),
+            allow_solo: allow_solo.unwrap_or(defaults.allow_solo),
+            swap_rule: swap_rule.unwrap_or(defaults.swap_rule),
+            liveness: reader.variant(
+                "liveness",
+                liveness,
+                &[Liveness::Timestamp, Liveness::Sequence],
+            ),
+            heartbeat_limit: heartbeat_limit.unwrap_or(defaults.heartbeat_limit),
+            timeout_unit: reader.variant(
+                "timeout unit",
+                timeout_unit.map(u32::from),
+                &[TimeoutUnit::Seconds, TimeoutUnit::Slots],
+            ),
+            center_handicap: center_handicap.unwrap_or(defaults.center_handicap),
+            conduct_limit: conduct_limit.unwrap_or(defaults.conduct_limit),
+        };
+
+        for seat in 0..2 {
+            if let Some(bytes) = reader.take("names", 1 + MAX_NAME_LEN) {
+                match bincode::deserialize::<PlayerName>(bytes) {
+                    Ok(ref name) if !name.is_set() || name.validate().is_ok() => {
+                        game.names[seat] = *name
+                    }
+                    _ => reader
+                        .diagnostics
+                        .push(DecodeDiagnostic::InvalidName { seat }),
+                }
+            }
+        }
+        if let Some(idle_heartbeats) = reader.take("idle heartbeats", 2) {
+            game.idle_heartbeats.copy_from_slice(idle_heartbeats);
+        }
+        for seat in 0..2 {
+            game.abort_requested[seat] = reader.bool("abort requested").unwrap_or(false);
+        }
+        game.final_board_hash = reader.u32("final board hash").unwrap_or(0);
+        game.frozen = reader.bool("frozen").unwrap_or(false);
+        game.created_at = reader.u64("created at").unwrap_or(0);
+        game.finished_at = reader.u64("finished at").unwrap_or(0);
+        let turn = reader.u32("turn");
+        game.turn = reader.variant("turn", turn, &[Seat::X, Seat::O]);
+        for seat in 0..2 {
+            if let Some(attempts) = reader.u32("invalid attempts") {
+                game.invalid_attempts[seat] = attempts;
+            }
+        }
+        game.linked = reader.bool("linked").unwrap_or(false);
+
+        if game.config.liveness == Liveness::Timestamp {
+            let keep_alives: [u64; 2] = game.keep_alive.into();
+            for (seat, &value) in keep_alives.iter().enumerate() {
+                let finished = game.finished_at != 0 && value > game.finished_at;
+                if value != 0 && (value < game.created_at || finished) {
+                    reader
+                        .diagnostics
+                        .push(DecodeDiagnostic::ImplausibleKeepAlive { seat, value });
+                }
+            }
+        }
+        if let Err(err) = game.validate() {
+            reader
+                .diagnostics
+                .push(DecodeDiagnostic::Inconsistent(err.kind));
+        }
+        Some(game)
+    }
+}
+
+// The state a board with no trustworthy state of its own is most likely in
+fn implied_state(game: &Game) -> GameState {
+    match outcome(Variant::Classic, &game.board) {
+        Some(game_state) => game_state,
+        None if game.filled_cells() == 0 && game.player_o == Pubkey::default() => {
+            GameState::Waiting
+        }
+        None if game.filled_cells().is_multiple_of(2) => GameState::XMove,
+        None => GameState::OMove,
+    }
+}
+
+// What an inspect command prints for an account dump: the diagnostics, then the board of
+// whatever could be recovered
+pub fn inspect(src: &[u8]) -> String {
+    let (game, diagnostics) = Game::deserialize_lenient(src);
+    let mut report: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| format!("warning: {}", diagnostic))
+        .collect();
+    match game {
+        Some(game) => {
+            report.push(format!(
+                "state: {:?}, moves: {}",
+                game.game_state,
+                game.move_notation()
+            ));
+            report.push(game.display_board_with_coords());
+        }
+        None => report.push("no game could be recovered".to_string()),
+    }
+    report.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use batch::decode_game;
+    use program_state::State;
+    use rng::{GameRng, SplitMix64};
+    use simple_serde::SimpleSerde;
+    use std::mem::size_of;
+
+    // Offsets into the userdata of a State::Game
+    const GAME_STATE: usize = 4 + 16;
+    const BOARD: usize = GAME_STATE + 4 + 32 + 32;
+    const MOVE_COUNT: usize = BOARD + 9 + 1 + 9;
+
+    fn userdata() -> (Game, Vec<u8>) {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut game = Game::new(player_x, player_o);
+        game.next_move(player_x, 1, 1).unwrap();
+        game.next_move(player_o, 0, 0).unwrap();
+        let mut userdata = vec![0; size_of::<State>()];
+        State::Game(game.clone()).serialize(&mut userdata).unwrap();
+        (game, userdata)
+    }
+
+    #[test]
+    pub fn clean_account() {
+        let (game, userdata) = userdata();
+        assert_eq!(Game::deserialize_lenient(&userdata), (Some(game), vec![]));
+    }
+
+    #[test]
+    pub fn corrupted_fields() {
+        let (game, mut userdata) = userdata();
+        userdata[GAME_STATE] = 9;
+        userdata[BOARD + 8] = 7;
+        userdata[MOVE_COUNT] = 200;
+        let (recovered, diagnostics) = Game::deserialize_lenient(&userdata);
+        let recovered = recovered.unwrap();
+        assert_eq!(recovered.game_state, GameState::XMove);
+        assert_eq!(recovered.board(), game.board());
+        assert_eq!(recovered.move_count(), 2);
+        assert_eq!(recovered.player_o(), game.player_o());
+        assert_eq!(
+            diagnostics,
+            vec![
+                DecodeDiagnostic::InvalidBoardItem { cell: 8, item: 7 },
+                DecodeDiagnostic::UnknownGameState(9),
+                DecodeDiagnostic::InvalidMoveCount(200),
+            ]
+        );
+        assert!(decode_game(&userdata).is_err());
+
+        let report = inspect(&userdata);
+        assert!(report.starts_with("warning: board item 7 in cell 8, cleared\n"));
+        assert!(report.contains("state: XMove, moves: b2 a1"));
+        assert!(report.ends_with(&game.display_board_with_coords()));
+    }
+
+    #[test]
+    pub fn truncated_and_implausible() {
+        let (game, mut userdata) = userdata();
+        assert_eq!(Game::deserialize_lenient(&userdata[..BOARD]).0, None);
+
+        let (recovered, diagnostics) = Game::deserialize_lenient(&userdata[..BOARD + 9]);
+        assert_eq!(recovered.unwrap().board(), game.board());
+        // Without the move history the board cannot be checked against it
+        assert_eq!(
+            diagnostics,
+            vec![
+                DecodeDiagnostic::Truncated {
+                    offset: BOARD + 9,
+                    field: "trophy minted"
+                },
+                DecodeDiagnostic::Inconsistent(ProgramError::InvalidBoardState),
+            ]
+        );
+
+        // A keep alive from before the game was created
+        let mut created = game.clone();
+        created.created_at = 50;
+        created.keep_alive = KeepAlives::from([10, 60]);
+        State::Game(created).serialize(&mut userdata).unwrap();
+        let (_, diagnostics) = Game::deserialize_lenient(&userdata);
+        assert_eq!(
+            diagnostics,
+            vec![DecodeDiagnostic::ImplausibleKeepAlive { seat: 0, value: 10 }]
+        );
+    }
+
+    // Random bytes, and a real account with random bytes overwritten, never make it panic
+    #[test]
+    pub fn fuzz() {
+        let (_, userdata) = userdata();
+        let mut rng = SplitMix64::from_seed(7);
+        for _ in 0..2_000 {
+            let len = (rng.next_u64() % 300) as usize;
+            let random: Vec<u8> = (0..len).map(|_| rng.next_u64() as u8).collect();
+            Game::deserialize_lenient(&random);
+
+            let mut corrupted = userdata.clone();
+            for _ in 0..rng.next_u64() % 8 {
+                let i = (rng.next_u64() as usize) % corrupted.len();
+                corrupted[i] = rng.next_u64() as u8;
+            }
+            let _ = inspect(&corrupted[..(rng.next_u64() as usize) % corrupted.len()]);
+        }
+    }
+}
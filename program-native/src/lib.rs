@@ -1,20 +1,88 @@
+#[cfg(feature = "anyhow")]
+extern crate anyhow;
+#[cfg(feature = "client")]
+extern crate base64;
 extern crate bincode;
+#[cfg(any(feature = "client", feature = "wasm"))]
+extern crate bs58;
+#[cfg(feature = "gif")]
+extern crate gif;
+// pyo3's and prost's macros name ::core, which edition 2015 looks up in the crate root
+#[cfg(any(feature = "protobuf", feature = "python"))]
+extern crate core;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "testing")]
+extern crate proptest;
+#[cfg(feature = "protobuf")]
+extern crate prost;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "msgpack")]
+extern crate rmp_serde;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[cfg(any(test, feature = "json"))]
+extern crate serde_json;
 extern crate simple_logger;
 #[macro_use]
 extern crate solana_sdk;
+#[macro_use]
+extern crate thiserror;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(all(test, target_arch = "wasm32"))]
+extern crate wasm_bindgen_test;
 
-mod dashboard;
-mod game;
+pub mod analysis;
+#[cfg(feature = "gif")]
+pub mod animation;
+pub mod archive;
+pub mod audit;
+pub mod batch;
+#[cfg(feature = "client")]
+pub mod client;
+#[macro_use]
+mod consts;
+pub mod dashboard;
+#[cfg(feature = "client")]
+pub mod demo;
+mod errors;
+pub mod game;
+pub mod invariants;
+pub mod lenient;
+pub mod linked_match;
+mod liveness;
 mod logger;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod minimax;
+pub mod perfect_play;
+pub mod player_name;
+pub mod player_stats;
 mod program_command;
 mod program_state;
-mod result;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod record;
+pub mod result;
+pub mod rng;
+mod rules;
+pub mod sim;
 mod simple_serde;
+pub mod snapshot;
+mod state;
+pub mod tournament;
+pub mod trophy;
+pub mod upgrade;
+pub mod view;
+pub mod wager;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watch_list;
 
 use program_command::Command;
 use program_state::State;
@@ -22,6 +90,7 @@ use result::{ProgramError, Result as ProgramResult};
 use simple_serde::SimpleSerde;
 use solana_sdk::account::KeyedAccount;
 use solana_sdk::pubkey::Pubkey;
+use std::mem::size_of;
 
 fn expect_n_accounts(info: &mut [KeyedAccount], n: usize) -> ProgramResult<()> {
     if info.len() < n {
@@ -30,7 +99,7 @@ fn expect_n_accounts(info: &mut [KeyedAccount], n: usize) -> ProgramResult<()> {
             n,
             info.len()
         );
-        Err(ProgramError::InvalidInput)
+        Err(ProgramError::InvalidInput.into())
     } else {
         Ok(())
     }
@@ -43,7 +112,7 @@ fn fund_next_move(
 ) -> ProgramResult<()> {
     if info[dashboard_index].account.lamports <= 1 {
         error!("Dashboard is out of lamports");
-        Err(ProgramError::InvalidInput)
+        Err(ProgramError::InvalidInput.into())
     } else {
         if info[user_or_game_index].account.lamports != 0 {
             debug!("user_or_game still has lamports");
@@ -55,9 +124,128 @@ fn fund_next_move(
     }
 }
 
+// The engine action a game account command performs, None for the ones that leave the game
+// alone
+fn command_action(command: &Command) -> Option<game::Action> {
+    match command {
+        Command::Join => Some(game::Action::Join),
+        Command::Move(..) | Command::MoveWild(..) => Some(game::Action::Move),
+        Command::KeepAlive | Command::KeepAliveSeq(_) => Some(game::Action::KeepAlive),
+        Command::Reset => Some(game::Action::Reset),
+        Command::Swap => Some(game::Action::Swap),
+        Command::SetName(_) => Some(game::Action::SetName),
+        Command::ClaimTimeout => Some(game::Action::ClaimTimeout),
+        Command::RequestAbort | Command::ConfirmAbort => Some(game::Action::Abort),
+        Command::FinalizeWithTrophy => Some(game::Action::Finalize),
+        Command::Freeze | Command::Unfreeze => Some(game::Action::Freeze),
+        _ => None,
+    }
+}
+
+// Add the finished game in info[index] to the archive in info[at] of the dashboard in info[1].
+// A full archive only costs the game its archive entry
+fn archive_game(
+    info: &mut [KeyedAccount],
+    at: usize,
+    index: usize,
+    game: &game::Game,
+) -> ProgramResult<()> {
+    expect_n_accounts(info, at + 1)?;
+    if info[1].account.owner != info[at].account.owner {
+        error!("Invalid archive account");
+        Err(ProgramError::InvalidInput)?;
+    }
+    let mut archive_state = State::deserialize(&info[at].account.userdata)?;
+    match archive_state {
+        State::Archive(ref mut archive) if archive.dashboard() == info[1].unsigned_key() => {
+            let record = game.finalize_record(info[index].unsigned_key())?;
+            match archive.append(archive::ArchiveEntry::from_record(&record)) {
+                Err(ref err) if err.kind == ProgramError::ArchiveFull => {
+                    warn!(
+                        "Archive is full, {} is not archived",
+                        info[index].unsigned_key()
+                    );
+                    return Ok(());
+                }
+                result => result,
+            }
+        }
+        _ => {
+            error!("Invalid archive state: {:?}", archive_state);
+            Err(ProgramError::InvalidInput.into())
+        }
+    }?;
+    archive_state.serialize(&mut info[at].account.userdata)?;
+    Ok(())
+}
+
+// Settle the linked match in info[3] for the finished game in info[2].  When the game won the
+// match its sibling in info[4] is voided right away, so the sibling cannot be played on after
+// the match was decided, and the voided sibling is returned
+fn resolve_linked_match(
+    info: &mut [KeyedAccount],
+    game: &game::Game,
+    dashboard_state: &mut State,
+    clock: &game::ClockSnapshot,
+) -> ProgramResult<Option<game::Game>> {
+    if info[2].account.owner != info[3].account.owner
+        || info[2].account.owner != info[4].account.owner
+    {
+        error!("Invalid linked match accounts");
+        Err(ProgramError::InvalidInput)?;
+    }
+    let mut match_state = State::deserialize(&info[3].account.userdata)?;
+    let resolution = match match_state {
+        State::LinkedMatch(ref mut linked_match) => {
+            match linked_match.board(info[2].unsigned_key()) {
+                Some(which) if linked_match.games()[1 - which] == *info[4].unsigned_key() => {
+                    Ok(linked_match.on_game_finished(which, game.game_state))
+                }
+                _ => {
+                    error!("Games not linked by {}", info[3].unsigned_key());
+                    Err(ProgramError::InvalidInput)
+                }
+            }
+        }
+        _ => {
+            error!("Invalid linked match state: {:?}", match_state);
+            Err(ProgramError::InvalidInput)
+        }
+    }?;
+
+    let mut voided = None;
+    if let linked_match::MatchResolution::VoidSibling(_) = resolution {
+        let mut sibling_state = State::deserialize(&info[4].account.userdata)?;
+        match sibling_state {
+            State::Game(ref mut sibling) => {
+                sibling.void_as_sibling()?;
+                sibling.set_finished_at(clock.reading(sibling.config().timeout_unit));
+                match *dashboard_state {
+                    State::Dashboard(ref mut dashboard) => {
+                        dashboard.update(info[4].unsigned_key(), sibling)
+                    }
+                    _ => Err(ProgramError::InvalidInput.into()),
+                }
+            }
+            _ => {
+                error!("Invalid sibling game state: {:?}", sibling_state);
+                Err(ProgramError::InvalidInput.into())
+            }
+        }?;
+        sibling_state.serialize(&mut info[4].account.userdata)?;
+        if let State::Game(sibling) = sibling_state {
+            voided = Some(sibling);
+        }
+    }
+    info!("linked match {}: {:?}", info[3].unsigned_key(), resolution);
+    match_state.serialize(&mut info[3].account.userdata)?;
+    Ok(voided)
+}
+
 fn process_instruction(info: &mut [KeyedAccount], input: &[u8], tick_height: u64) -> ProgramResult<()> {
     let command = Command::deserialize(input)?;
     debug!("entrypoint: command={:?}", command);
+    let clock = game::ClockSnapshot::from_tick_height(tick_height);
 
     if command == Command::InitDashboard {
         expect_n_accounts(info, 1)?;
@@ -65,7 +253,13 @@ fn process_instruction(info: &mut [KeyedAccount], input: &[u8], tick_height: u64
 
         match dashboard_state {
             State::Uninitialized => {
-                dashboard_state = State::Dashboard(Default::default());
+                // An optional second account is the moderator of the dashboard
+                dashboard_state = State::Dashboard(match info.get(1) {
+                    Some(moderator) => {
+                        dashboard::Dashboard::with_moderator(moderator.unsigned_key())
+                    }
+                    None => Default::default(),
+                });
                 Ok(())
             }
             _ => {
@@ -107,6 +301,38 @@ fn process_instruction(info: &mut [KeyedAccount], input: &[u8], tick_height: u64
         return fund_next_move(info, 0, 1);
     }
 
+    // Only the dashboard's own key signs for its archive, and it gets one
+    if command == Command::InitArchive {
+        expect_n_accounts(info, 2)?;
+        let mut dashboard_state = State::deserialize(&info[0].account.userdata)?;
+        match dashboard_state {
+            State::Dashboard(ref mut dashboard) => dashboard.set_archive(),
+            _ => {
+                error!(
+                    "Invalid dashboard state for InitArchive: {:?}",
+                    dashboard_state
+                );
+                Err(ProgramError::InvalidInput.into())
+            }
+        }?;
+        if info[0].account.owner != info[1].account.owner {
+            error!("Invalid archive account for InitArchive");
+            Err(ProgramError::InvalidInput)?;
+        }
+        let archive_state = State::deserialize(&info[1].account.userdata)?;
+        match archive_state {
+            State::Uninitialized => Ok(()),
+            _ => {
+                error!("Invalid archive state for InitArchive: {:?}", archive_state);
+                Err(ProgramError::InvalidInput)
+            }
+        }?;
+        let archive = archive::Archive::new(info[0].unsigned_key(), info[1].account.userdata.len());
+        State::Archive(archive).serialize(&mut info[1].account.userdata)?;
+        dashboard_state.serialize(&mut info[0].account.userdata)?;
+        return Ok(());
+    }
+
     expect_n_accounts(info, 3)?;
     let mut dashboard_state = State::deserialize(&info[1].account.userdata)?;
     match dashboard_state {
@@ -117,7 +343,12 @@ fn process_instruction(info: &mut [KeyedAccount], input: &[u8], tick_height: u64
         }
     }?;
 
-    if command == Command::InitGame {
+    let init_game_config = match command {
+        Command::InitGame => Some(game::GameConfig::default()),
+        Command::InitGameWithConfig(config) => Some(config),
+        _ => None,
+    };
+    if let Some(config) = init_game_config {
         let mut game_state = State::deserialize(&info[0].account.userdata)?;
 
         if info[0].account.owner != info[1].account.owner {
@@ -131,17 +362,17 @@ fn process_instruction(info: &mut [KeyedAccount], input: &[u8], tick_height: u64
 
         match game_state {
             State::Uninitialized => {
-                let game = game::Game::create(&info[2].unsigned_key());
+                let game = game::Game::create_at(info[2].unsigned_key(), config, &clock)?;
                 match dashboard_state {
                     State::Dashboard(ref mut dashboard) => {
-                        dashboard.update(&info[1].unsigned_key(), &game)
+                        dashboard.update(info[0].unsigned_key(), &game)
                     }
                     _ => {
                         error!(
                             "Invalid dashboard state for InitGame: {:?}",
                             dashboard_state
                         );
-                        Err(ProgramError::InvalidInput)
+                        Err(ProgramError::InvalidInput.into())
                     }
                 }?;
                 game_state = State::Game(game);
@@ -159,6 +390,101 @@ fn process_instruction(info: &mut [KeyedAccount], input: &[u8], tick_height: u64
         return fund_next_move(info, 1, 2);
     }
 
+    if command == Command::Upgrade {
+        if info[1].account.owner != info[2].account.owner {
+            error!("Invalid game account for Upgrade");
+            Err(ProgramError::InvalidInput)?;
+        }
+        let game = match upgrade::upgrade_game(&info[2].account.userdata)? {
+            Some(game) => game,
+            None => return Ok(()), // Already in the current layout
+        };
+
+        // Account data cannot grow on this runtime, so a game account too small for the
+        // current layout moves to the uninitialized account passed after it
+        let target = if info[2].account.userdata.len() >= size_of::<State>() {
+            2
+        } else {
+            expect_n_accounts(info, 4)?;
+            if info[2].account.owner != info[3].account.owner {
+                error!("Invalid new game account for Upgrade");
+                Err(ProgramError::InvalidInput)?;
+            }
+            let new_game_state = State::deserialize(&info[3].account.userdata)?;
+            match new_game_state {
+                State::Uninitialized => Ok(()),
+                _ => {
+                    error!("Invalid new game state for Upgrade: {:?}", new_game_state);
+                    Err(ProgramError::InvalidInput)
+                }
+            }?;
+            for byte in info[2].account.userdata.iter_mut() {
+                *byte = 0;
+            }
+            3
+        };
+
+        match dashboard_state {
+            State::Dashboard(ref mut dashboard) => {
+                dashboard.record_upgrade(info[2].unsigned_key(), info[target].unsigned_key());
+                Ok(())
+            }
+            _ => {
+                error!("Invalid dashboard state for Upgrade: {:?}", dashboard_state);
+                Err(ProgramError::InvalidInput)
+            }
+        }?;
+        info!(
+            "game {} upgraded into {}",
+            info[2].unsigned_key(),
+            info[target].unsigned_key()
+        );
+        State::Game(game).serialize(&mut info[target].account.userdata)?;
+        dashboard_state.serialize(&mut info[1].account.userdata)?;
+        return Ok(());
+    }
+
+    // The games of a KeepAliveMany follow the dashboard.  Games the keep alive does not apply
+    // to are logged and skipped, only accounts that hold no game fail the instruction
+    if command == Command::KeepAliveMany {
+        let games = &info[2..];
+        if games.len() > game::KEEP_ALIVE_BATCH_MAX {
+            error!("Too many games for KeepAliveMany: {}", games.len());
+            Err(ProgramError::InvalidInput)?;
+        }
+        if info[0].account.owner != info[1].account.owner || !info[0].account.userdata.is_empty() {
+            error!("Invalid player account for KeepAliveMany");
+            Err(ProgramError::InvalidInput)?;
+        }
+        let mut game_states = vec![];
+        for account in games.iter() {
+            if account.account.owner != info[1].account.owner {
+                error!("Invalid game account for KeepAliveMany");
+                Err(ProgramError::InvalidInput)?;
+            }
+            match State::deserialize(&account.account.userdata)? {
+                State::Game(game) => game_states.push(game),
+                state => {
+                    error!("Invalid game state for KeepAliveMany: {:?}", state);
+                    Err(ProgramError::InvalidInput)?;
+                }
+            }
+        }
+
+        let player = *info[0].signer_key().unwrap();
+        let statuses = {
+            let mut batch: Vec<&mut game::Game> = game_states.iter_mut().collect();
+            game::Game::keep_alive_many_at(&mut batch, player, &clock)?
+        };
+        for (i, (game, status)) in game_states.into_iter().zip(statuses).enumerate() {
+            info!("keep alive {}: {:?}", info[2 + i].unsigned_key(), status);
+            if status == game::KeepAliveStatus::Applied {
+                State::Game(game).serialize(&mut info[2 + i].account.userdata)?;
+            }
+        }
+        return fund_next_move(info, 1, 0);
+    }
+
     let mut game_state = State::deserialize(&info[2].account.userdata)?;
     if info[0].account.owner != info[1].account.owner || info[0].account.userdata.len() != 0 {
         error!("Invalid player account");
@@ -169,36 +495,238 @@ fn process_instruction(info: &mut [KeyedAccount], input: &[u8], tick_height: u64
         Err(ProgramError::InvalidInput)?;
     }
 
+    if command == Command::FinalizeWithTrophy {
+        expect_n_accounts(info, 4)?;
+        if info[2].account.owner != info[3].account.owner {
+            error!("Invalid trophy account for FinalizeWithTrophy");
+            Err(ProgramError::InvalidInput)?;
+        }
+        let trophy_state = State::deserialize(&info[3].account.userdata)?;
+        match trophy_state {
+            State::Uninitialized => Ok(()),
+            _ => {
+                error!(
+                    "Invalid trophy state for FinalizeWithTrophy: {:?}",
+                    trophy_state
+                );
+                Err(ProgramError::InvalidInput)
+            }
+        }?;
+
+        let trophy = match game_state {
+            // Only the players settle their game and choose the account its trophy goes to
+            State::Game(ref mut game) => game
+                .seat_of(info[0].signer_key().unwrap())
+                .and_then(|_| game.finalize_with_trophy(info[2].unsigned_key(), tick_height)),
+            _ => {
+                error!(
+                    "Invalid game state for FinalizeWithTrophy: {:?}",
+                    game_state
+                );
+                Err(ProgramError::InvalidInput.into())
+            }
+        }?;
+
+        game_state.serialize(&mut info[2].account.userdata)?;
+        if let Some(trophy) = trophy {
+            State::Trophy(trophy).serialize(&mut info[3].account.userdata)?;
+        }
+        return fund_next_move(info, 1, 0);
+    }
+
+    if command == Command::Watch || command == Command::Unwatch {
+        expect_n_accounts(info, 4)?;
+        if info[2].account.owner != info[3].account.owner {
+            error!("Invalid watch list account for {:?}", command);
+            Err(ProgramError::InvalidInput)?;
+        }
+        match game_state {
+            State::Game(_) => Ok(()),
+            _ => {
+                error!("Invalid game state for {:?}: {:?}", command, game_state);
+                Err(ProgramError::InvalidInput)
+            }
+        }?;
+
+        // The first watcher of a game sets up its watch list
+        let mut watch_list_state = State::deserialize(&info[3].account.userdata)?;
+        if let State::Uninitialized = watch_list_state {
+            watch_list_state = State::WatchList(watch_list::WatchList::new(info[2].unsigned_key()));
+        }
+        match watch_list_state {
+            State::WatchList(ref mut watch_list) if watch_list.game() == info[2].unsigned_key() => {
+                let watcher = *info[0].unsigned_key();
+                if command == Command::Watch {
+                    watch_list.watch(watcher)
+                } else {
+                    watch_list.unwatch(watcher)
+                }
+            }
+            _ => {
+                error!(
+                    "Invalid watch list state for {:?}: {:?}",
+                    command, watch_list_state
+                );
+                Err(ProgramError::InvalidInput.into())
+            }
+        }?;
+
+        // Watching is not a move, so the dashboard does not fund it
+        watch_list_state.serialize(&mut info[3].account.userdata)?;
+        return Ok(());
+    }
+
+    if command == Command::LinkGames {
+        expect_n_accounts(info, 5)?;
+        if info[2].account.owner != info[3].account.owner
+            || info[2].account.owner != info[4].account.owner
+        {
+            error!("Invalid accounts for LinkGames");
+            Err(ProgramError::InvalidInput)?;
+        }
+        let match_state = State::deserialize(&info[3].account.userdata)?;
+        match match_state {
+            State::Uninitialized => Ok(()),
+            _ => {
+                error!(
+                    "Invalid linked match state for LinkGames: {:?}",
+                    match_state
+                );
+                Err(ProgramError::InvalidInput)
+            }
+        }?;
+        let mut sibling_state = State::deserialize(&info[4].account.userdata)?;
+        let linked_match = match (&game_state, &sibling_state) {
+            (State::Game(ref game), State::Game(ref sibling)) => linked_match::LinkedMatch::link(
+                info[0].signer_key().unwrap(),
+                [
+                    (info[2].unsigned_key(), game),
+                    (info[4].unsigned_key(), sibling),
+                ],
+            ),
+            _ => {
+                error!(
+                    "Invalid game states for LinkGames: {:?} {:?}",
+                    game_state, sibling_state
+                );
+                Err(ProgramError::InvalidInput.into())
+            }
+        }?;
+
+        // From now on commands on either board have to pass the match along
+        if let State::Game(ref mut game) = game_state {
+            game.set_linked();
+        }
+        if let State::Game(ref mut sibling) = sibling_state {
+            sibling.set_linked();
+        }
+        State::LinkedMatch(linked_match).serialize(&mut info[3].account.userdata)?;
+        game_state.serialize(&mut info[2].account.userdata)?;
+        sibling_state.serialize(&mut info[4].account.userdata)?;
+        return fund_next_move(info, 1, 0);
+    }
+
+    let mut finished = false;
     match game_state {
         State::Game(ref mut game) => {
+            if game.is_linked() {
+                expect_n_accounts(info, 5)?;
+            }
             let player = info[0].signer_key().unwrap();
-            match command {
+            let was_finished = game.is_finished();
+            // The engine only sees actions the transition table allows
+            if let Some(action) = command_action(&command) {
+                game.ensure_allowed(action, *player)?;
+            }
+            let result = match command {
                 Command::Advertise => Ok(()), // Nothing to do here beyond the dashboard_update() below
-                Command::Join => game.join(*player, tick_height),
+                Command::Join => game.join_at(*player, &clock).map(|_| ()),
                 Command::Move(x, y) => game.next_move(*player, x as usize, y as usize),
-                Command::KeepAlive => game.keep_alive(*player, tick_height),
+                Command::MoveWild(x, y, mark) => {
+                    game.next_move_wild(*player, x as usize, y as usize, mark)
+                }
+                Command::KeepAlive => game.keep_alive_at(*player, &clock),
+                Command::Reset => game.reset_at(player, &clock),
+                Command::Swap => game.swap_seats(*player),
+                Command::SetName(name) => game.set_name(*player, name),
+                Command::KeepAliveSeq(seq) => game.keep_alive_seq(*player, seq),
+                Command::ClaimTimeout => game.claim_timeout_at(*player, &clock),
+                Command::RequestAbort => game.request_abort(*player),
+                Command::ConfirmAbort => game.confirm_abort(*player),
+                Command::Freeze | Command::Unfreeze => {
+                    match dashboard_state {
+                        State::Dashboard(ref dashboard) => dashboard.check_moderator(player),
+                        _ => Err(ProgramError::InvalidInput.into()),
+                    }?;
+                    if command == Command::Freeze {
+                        game.freeze(*player)
+                    } else {
+                        game.unfreeze(*player)
+                    }
+                }
                 _ => {
                     error!("invalid command for State::Game");
-                    Err(ProgramError::InvalidInput)
+                    Err(ProgramError::InvalidInput.into())
                 }
+            };
+            // Under a conduct limit a move rejected for the player's own conduct counts against
+            // them, and the instruction succeeds so the runtime keeps the count
+            let is_move = matches!(command, Command::Move(..) | Command::MoveWild(..));
+            match result {
+                Err(err)
+                    if is_move
+                        && game.config().conduct_limit > 0
+                        && game.record_invalid_attempt(*player, err) =>
+                {
+                    warn!("move rejected for conduct: {}", err);
+                    Ok(())
+                }
+                result => result,
             }?;
+            if !was_finished && game.is_finished() {
+                game.set_finished_at(clock.reading(game.config().timeout_unit));
+                finished = true;
+            }
 
             match dashboard_state {
                 State::Dashboard(ref mut dashboard) => {
-                    dashboard.update(&info[1].unsigned_key(), &game)
+                    dashboard.update(info[2].unsigned_key(), game)
                 }
                 _ => {
                     error!("Invalid dashboard stat: {:?}", dashboard_state);
-                    Err(ProgramError::InvalidInput)
+                    Err(ProgramError::InvalidInput.into())
                 }
             }
         }
         _ => {
             error!("Invalid game state: {:?}", game_state);
-            Err(ProgramError::InvalidInput)
+            Err(ProgramError::InvalidInput.into())
         }
     }?;
 
+    if let State::Game(ref game) = game_state {
+        game.debug_assert_final_board();
+        // Commands on a board of a linked match pass the match and the other board after it
+        let voided = if game.is_linked() && game.is_finished() {
+            resolve_linked_match(info, game, &mut dashboard_state, &clock)?
+        } else {
+            None
+        };
+        // The archive of the dashboard comes last, for instructions that finish a game
+        let has_archive = match dashboard_state {
+            State::Dashboard(ref dashboard) => dashboard.has_archive(),
+            _ => false,
+        };
+        if has_archive {
+            let at = if game.is_linked() { 5 } else { 3 };
+            if finished {
+                archive_game(info, at, 2, game)?;
+            }
+            if let Some(sibling) = voided {
+                archive_game(info, at, 4, &sibling)?;
+            }
+        }
+    }
     dashboard_state.serialize(&mut info[1].account.userdata)?;
     game_state.serialize(&mut info[2].account.userdata)?;
     // Distribute funds to the player for their next transaction
@@ -219,10 +747,18 @@ fn entrypoint(
         return Err(ProgramError::InvalidInput);
     }
 
-    match process_instruction(keyed_accounts, data, tick_height) {
+    #[cfg(feature = "metrics")]
+    let (result, _) = metrics::measure(&metrics::ElapsedMeter::new(), data, || {
+        process_instruction(keyed_accounts, data, tick_height)
+    });
+    #[cfg(not(feature = "metrics"))]
+    let result = process_instruction(keyed_accounts, data, tick_height);
+
+    match result {
         Err(err) => {
-            error!("{:?}", err);
-            Err(err)
+            error!("{}", err);
+            // The runtime only takes the ProgramError
+            Err(err.kind)
         }
         _ => Ok(()),
     }
@@ -0,0 +1,310 @@
+use game::{Game, GameState};
+use result::{ErrorContext, ProgramError, Result};
+use solana_sdk::pubkey::Pubkey;
+
+// Where a linked match stands
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum MatchStatus {
+    Playing, // Neither board has been won yet
+    Won(u8), // Decided by a win on this board
+    Drawn,   // Both boards finished without a winner
+}
+
+// What the processing layer has to do once a board of the match finished
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MatchResolution {
+    Pending,            // The other board is still being played and decides the match
+    VoidSibling(usize), // This board won the match, the other board has to be aborted
+    Won,                // This board won the match after the other ended without a winner
+    Drawn,              // Neither board had a winner
+    AlreadyResolved,    // Nothing changed, the match was over or this board recorded before
+}
+
+// Two games between the same two players with their colors swapped, played at the same time.
+// The first win on either board takes the match and voids the other board, while a draw on one
+// board leaves the match to the other
+#[repr(C)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LinkedMatch {
+    games: [Pubkey; 2],               // Game accounts of boards 0 and 1
+    outcomes: [Option<GameState>; 2], // How each board ended, once it has
+    status: MatchStatus,
+}
+
+impl LinkedMatch {
+    // Link two fresh boards, neither of them linked before.  `player` has to play in them, and
+    // whoever is X on one board has to be O on the other
+    pub fn link(player: &Pubkey, boards: [(&Pubkey, &Game); 2]) -> Result<LinkedMatch> {
+        let [(key_a, a), (key_b, b)] = boards;
+        if key_a == key_b {
+            Err(ProgramError::InvalidInput)?;
+        }
+        for (_, game) in &boards {
+            if game.game_state != GameState::XMove || game.move_count() != 0 {
+                Err(ProgramError::GameInProgress)?;
+            }
+            if game.player_x() == game.player_o() {
+                Err(ProgramError::SoloNotAllowed)?;
+            }
+            if game.is_linked() {
+                Err(ProgramError::InvalidInput)?;
+            }
+        }
+        if a.player_x() != b.player_o() || a.player_o() != b.player_x() {
+            Err(ProgramError::PlayerNotFound.at(ErrorContext::Player(*b.player_x())))?;
+        }
+        if player != a.player_x() && player != a.player_o() {
+            Err(ProgramError::PlayerNotFound.at(ErrorContext::Player(*player)))?;
+        }
+        Ok(LinkedMatch {
+            games: [*key_a, *key_b],
+            outcomes: [None; 2],
+            status: MatchStatus::Playing,
+        })
+    }
+
+    pub fn games(&self) -> &[Pubkey; 2] {
+        &self.games
+    }
+
+    pub fn status(&self) -> MatchStatus {
+        self.status
+    }
+
+    // Which board of the match `game` is
+    pub fn board(&self, game: &Pubkey) -> Option<usize> {
+        self.games.iter().position(|key| key == game)
+    }
+
+    // Record how board `which` ended.  Reporting a board twice, or after the match is over,
+    // changes nothing, so the processing layer may report a finished board on every command
+    pub fn on_game_finished(&mut self, which: usize, outcome: GameState) -> MatchResolution {
+        let other = 1 - which;
+        if self.status != MatchStatus::Playing || self.outcomes[which].is_some() {
+            return MatchResolution::AlreadyResolved;
+        }
+        match outcome {
+            GameState::XWon | GameState::OWon => {
+                self.outcomes[which] = Some(outcome);
+                self.status = MatchStatus::Won(which as u8);
+                if self.outcomes[other].is_some() {
+                    MatchResolution::Won
+                } else {
+                    self.outcomes[other] = Some(GameState::Aborted);
+                    MatchResolution::VoidSibling(other)
+                }
+            }
+            GameState::Draw | GameState::Aborted => {
+                self.outcomes[which] = Some(outcome);
+                if self.outcomes[other].is_some() {
+                    self.status = MatchStatus::Drawn;
+                    MatchResolution::Drawn
+                } else {
+                    MatchResolution::Pending
+                }
+            }
+            // The board is still being played
+            _ => MatchResolution::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::Move;
+    use program_command::Command;
+    use program_state::State;
+    use simple_serde::SimpleSerde;
+    use solana_sdk::account::{Account, KeyedAccount};
+    use std::mem::size_of;
+
+    fn boards() -> (Game, Game) {
+        let (player_1, player_2) = (Pubkey::new(&[1; 32]), Pubkey::new(&[2; 32]));
+        (Game::new(player_1, player_2), Game::new(player_2, player_1))
+    }
+
+    fn linked(a: &Game, b: &Game) -> LinkedMatch {
+        let (key_a, key_b) = (Pubkey::new(&[3; 32]), Pubkey::new(&[4; 32]));
+        LinkedMatch::link(a.player_x(), [(&key_a, a), (&key_b, b)]).unwrap()
+    }
+
+    fn play(game: &mut Game, moves: &[(usize, usize)]) {
+        for &(x, y) in moves {
+            let player = game.current_player().unwrap();
+            game.next_move(player, x, y).unwrap();
+        }
+    }
+
+    #[test]
+    pub fn win_on_one_board_voids_the_other() {
+        let (mut a, mut b) = boards();
+        let mut linked_match = linked(&a, &b);
+        play(&mut b, &[(1, 1), (0, 0)]);
+        play(&mut a, &[(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)]);
+        assert_eq!(a.game_state, GameState::XWon);
+
+        assert_eq!(
+            linked_match.on_game_finished(0, a.game_state),
+            MatchResolution::VoidSibling(1)
+        );
+        assert_eq!(linked_match.status(), MatchStatus::Won(0));
+        b.void_as_sibling().unwrap();
+        assert_eq!(b.game_state, GameState::Aborted);
+
+        // Late moves on the voided board are rejected, and reporting it changes nothing
+        let player = *b.player_x();
+        assert_eq!(
+            b.next_move(player, 2, 2).map_err(|err| err.kind),
+            Err(ProgramError::GameFinished)
+        );
+        assert_eq!(
+            linked_match.on_game_finished(1, b.game_state),
+            MatchResolution::AlreadyResolved
+        );
+        assert_eq!(linked_match.status(), MatchStatus::Won(0));
+    }
+
+    #[test]
+    pub fn draws_on_both_boards_draw_the_match() {
+        let draw = [
+            Move { x: 0, y: 0 },
+            Move { x: 1, y: 1 },
+            Move { x: 2, y: 2 },
+            Move { x: 0, y: 1 },
+            Move { x: 2, y: 1 },
+            Move { x: 2, y: 0 },
+            Move { x: 0, y: 2 },
+            Move { x: 1, y: 2 },
+            Move { x: 1, y: 0 },
+        ];
+        let (a, b) = boards();
+        let mut linked_match = linked(&a, &b);
+        let a = Game::from_moves_iter(*a.player_x(), *a.player_o(), draw.iter().cloned()).unwrap();
+        let b = Game::from_moves_iter(*b.player_x(), *b.player_o(), draw.iter().cloned()).unwrap();
+        assert_eq!(
+            (a.game_state, b.game_state),
+            (GameState::Draw, GameState::Draw)
+        );
+
+        // A draw on one board leaves the match to the other
+        assert_eq!(
+            linked_match.on_game_finished(1, b.game_state),
+            MatchResolution::Pending
+        );
+        assert_eq!(linked_match.status(), MatchStatus::Playing);
+        assert_eq!(
+            linked_match.on_game_finished(1, b.game_state),
+            MatchResolution::AlreadyResolved
+        );
+        assert_eq!(
+            linked_match.on_game_finished(0, a.game_state),
+            MatchResolution::Drawn
+        );
+        assert_eq!(linked_match.status(), MatchStatus::Drawn);
+    }
+
+    #[test]
+    pub fn win_after_a_draw_takes_the_match() {
+        let (a, b) = boards();
+        let mut linked_match = linked(&a, &b);
+        linked_match.on_game_finished(0, GameState::Draw);
+        assert_eq!(
+            linked_match.on_game_finished(1, GameState::OWon),
+            MatchResolution::Won
+        );
+        assert_eq!(linked_match.status(), MatchStatus::Won(1));
+    }
+
+    #[test]
+    pub fn link_only_swapped_fresh_boards() {
+        let (a, b) = boards();
+        let (key_a, key_b) = (Pubkey::new(&[3; 32]), Pubkey::new(&[4; 32]));
+        let player = *a.player_x();
+        let linked_match = linked(&a, &b);
+        assert_eq!(linked_match.games(), &[key_a, key_b]);
+        assert_eq!(linked_match.board(&key_b), Some(1));
+        assert_eq!(linked_match.board(&player), None);
+
+        // Same colors on both boards
+        let err = LinkedMatch::link(&player, [(&key_a, &a), (&key_b, &a)]).unwrap_err();
+        assert_eq!(err.kind, ProgramError::PlayerNotFound);
+        // One board twice
+        let err = LinkedMatch::link(&player, [(&key_a, &a), (&key_a, &b)]).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidInput);
+        // Someone else's boards
+        let err = LinkedMatch::link(&key_a, [(&key_a, &a), (&key_b, &b)]).unwrap_err();
+        assert_eq!(err.kind, ProgramError::PlayerNotFound);
+        // A board already being played
+        let mut started = b.clone();
+        play(&mut started, &[(1, 1)]);
+        let err = LinkedMatch::link(&player, [(&key_a, &a), (&key_b, &started)]).unwrap_err();
+        assert_eq!(err.kind, ProgramError::GameInProgress);
+        // A board of another match
+        let mut relinked = b.clone();
+        relinked.set_linked();
+        let err = LinkedMatch::link(&player, [(&key_a, &a), (&key_b, &relinked)]).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidInput);
+    }
+
+    #[test]
+    pub fn linked_boards_carry_the_match() {
+        let program_id = Pubkey::new(&[9; 32]);
+        let account = |tokens, space| Account::new(tokens, space, program_id);
+        let (a, b) = boards();
+        let mut accounts = [
+            (*a.player_x(), account(0, 0)),
+            (Pubkey::new(&[5; 32]), account(100, size_of::<State>())),
+            (Pubkey::new(&[3; 32]), account(0, size_of::<State>())),
+            (Pubkey::new(&[6; 32]), account(0, size_of::<State>())),
+            (Pubkey::new(&[4; 32]), account(0, size_of::<State>())),
+        ];
+        State::Dashboard(Default::default())
+            .serialize(&mut accounts[1].1.userdata)
+            .unwrap();
+        State::Game(a.clone())
+            .serialize(&mut accounts[2].1.userdata)
+            .unwrap();
+        State::Game(b)
+            .serialize(&mut accounts[4].1.userdata)
+            .unwrap();
+
+        // Run `command` signed by `player` with the first `n` accounts
+        let mut process = |player: Pubkey, command: Command, n: usize| {
+            accounts[0].0 = player;
+            let mut data = vec![0; size_of::<Command>()];
+            command.serialize(&mut data).unwrap();
+            let mut info: Vec<KeyedAccount> = accounts[..n]
+                .iter_mut()
+                .enumerate()
+                .map(|(i, &mut (ref key, ref mut account))| KeyedAccount::new(key, i == 0, account))
+                .collect();
+            let result = ::process_instruction(&mut info, &data, 1);
+            let states: Vec<State> = accounts
+                .iter()
+                .map(|(_, account)| State::deserialize(&account.userdata).unwrap_or_default())
+                .collect();
+            result.map(|_| states)
+        };
+        let (player_x, player_o) = (*a.player_x(), *a.player_o());
+        process(player_x, Command::LinkGames, 5).unwrap();
+
+        // A board of the match cannot be played without the match
+        let err = process(player_x, Command::Move(0, 0), 3).unwrap_err();
+        assert_eq!(err.kind, ProgramError::InvalidInput);
+
+        for &(player, x, y) in [(player_x, 0, 0), (player_o, 1, 0), (player_x, 0, 1)].iter() {
+            process(player, Command::Move(x, y), 5).unwrap();
+        }
+        process(player_o, Command::Move(1, 1), 5).unwrap();
+        let states = process(player_x, Command::Move(0, 2), 5).unwrap();
+        match (&states[2], &states[3], &states[4]) {
+            (State::Game(a), State::LinkedMatch(linked_match), State::Game(b)) => {
+                assert_eq!(a.game_state, GameState::XWon);
+                assert_eq!(linked_match.status(), MatchStatus::Won(0));
+                assert_eq!(b.game_state, GameState::Aborted);
+            }
+            states => panic!("unexpected states: {:?}", states),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use result::{ErrorContext, ProgramError, Result};
+use rules::Action;
+use solana_sdk::pubkey::Pubkey;
+use state::{
+    ClockSnapshot, Game, GameState, KeepAliveStatus, Liveness, Now, PlayerLiveness, Seat,
+    WarningConfig, KEEP_ALIVE_BATCH_MAX,
+};
+
+impl Game {
+    // Time since the player the game is waiting on was last seen, and the timeout that applies:
+    // the join deadline while Waiting, the move timeout of the player to move otherwise.  None
+    // when nobody can time out, including when `now` is behind the last keep alive
+    fn idle_time(&self, now: u64) -> Option<(u64, u64)> {
+        if self.config.liveness != Liveness::Timestamp {
+            return None;
+        }
+        let (seat, timeout) = match self.game_state {
+            GameState::Waiting => (Seat::X, self.config.join_deadline),
+            GameState::XMove => (Seat::X, self.config.move_timeout),
+            GameState::OMove => (Seat::O, self.config.move_timeout),
+            GameState::XWon | GameState::OWon | GameState::Draw | GameState::Aborted => {
+                return None
+            }
+        };
+        // A game created without a timestamp starts player X's clock when player O joins,
+        // and until then has nothing to time out from
+        let last_seen = if self.keep_alive.is_set(seat) {
+            self.keep_alive.get(seat)
+        } else if self.game_state == GameState::Waiting {
+            return None;
+        } else {
+            self.keep_alive.get(Seat::O)
+        };
+        now.checked_sub(last_seen).map(|idle| (idle, timeout))
+    }
+
+    // How close the player the game is waiting on is to timing out, for countdowns.  Only
+    // Timestamp liveness games count down, and `now` is in the game's timeout unit
+    pub fn liveness_status(&self, now: u64, config: &WarningConfig) -> PlayerLiveness {
+        match self.idle_time(now) {
+            Some((idle, timeout)) if idle > timeout => PlayerLiveness::Claimable,
+            Some((idle, timeout)) if idle >= config.warning_after(timeout) => {
+                PlayerLiveness::Warning {
+                    remaining: timeout - idle,
+                }
+            }
+            _ => PlayerLiveness::Fresh,
+        }
+    }
+
+    // True once the player the game is waiting on has gone quiet for longer than the
+    // configured timeout: player O joining a Waiting game, or the player to move otherwise
+    // Only meaningful in Timestamp liveness mode, Sequence mode games never time out by the clock.
+    // `now` is in the game's timeout unit
+    pub fn has_timed_out(&self, now: u64) -> bool {
+        self.liveness_status(now, &WarningConfig::default()) == PlayerLiveness::Claimable
+    }
+
+    pub fn has_timed_out_at(&self, clock: &ClockSnapshot) -> bool {
+        self.has_timed_out(clock.reading(self.config.timeout_unit))
+    }
+
+    pub fn keep_alive(self: &mut Game, player: Pubkey, timestamp: u64) -> Result<()> {
+        self.keep_alive_with_clock(player, Now::Seconds(timestamp))
+    }
+
+    pub fn keep_alive_at(self: &mut Game, player: Pubkey, clock: &ClockSnapshot) -> Result<()> {
+        self.keep_alive_with_clock(player, Now::Clock(*clock))
+    }
+
+    fn keep_alive_with_clock(self: &mut Game, player: Pubkey, now: Now) -> Result<()> {
+        game_log!(
+            "keep_alive: player={} now={:?} state={:?}",
+            player,
+            now,
+            self.game_state
+        );
+        let result = self.apply_keep_alive(player, now);
+        self.log_outcome("keep_alive", &result);
+        result
+    }
+
+    fn apply_keep_alive(self: &mut Game, player: Pubkey, now: Now) -> Result<()> {
+        self.ensure_mutable(Action::KeepAlive)?;
+        if self.config.liveness != Liveness::Timestamp {
+            Err(ProgramError::WrongLiveness)?;
+        }
+        let timestamp = self.reading(now)?;
+        match self.game_state {
+            GameState::Waiting | GameState::XMove | GameState::OMove => {
+                let seat = self.seat_of(&player)?;
+                if timestamp <= self.keep_alive.get(seat) {
+                    Err(ProgramError::InvalidTimestamp.at(ErrorContext::Timestamp(timestamp)))?;
+                }
+                self.keep_alive.set(seat, timestamp);
+            }
+            // Ignore keep_alive when game is no longer in progress
+            GameState::XWon | GameState::OWon | GameState::Draw | GameState::Aborted => {}
+        };
+        Ok(())
+    }
+
+    // Keep `player` alive in each of up to KEEP_ALIVE_BATCH_MAX games.  A game the keep alive
+    // does not apply to gets its status and is left alone instead of failing the batch, and
+    // sending the same batch again changes nothing
+    pub fn keep_alive_many(
+        games: &mut [&mut Game],
+        player: Pubkey,
+        timestamp: u64,
+    ) -> Result<Vec<KeepAliveStatus>> {
+        Game::keep_alive_many_with_clock(games, player, Now::Seconds(timestamp))
+    }
+
+    pub fn keep_alive_many_at(
+        games: &mut [&mut Game],
+        player: Pubkey,
+        clock: &ClockSnapshot,
+    ) -> Result<Vec<KeepAliveStatus>> {
+        Game::keep_alive_many_with_clock(games, player, Now::Clock(*clock))
+    }
+
+    fn keep_alive_many_with_clock(
+        games: &mut [&mut Game],
+        player: Pubkey,
+        now: Now,
+    ) -> Result<Vec<KeepAliveStatus>> {
+        if games.len() > KEEP_ALIVE_BATCH_MAX {
+            Err(ProgramError::InvalidInput)?;
+        }
+        let statuses = games
+            .iter_mut()
+            .map(|game| {
+                if game.is_finished() {
+                    return KeepAliveStatus::Finished;
+                }
+                match game.keep_alive_with_clock(player, now) {
+                    Ok(()) => KeepAliveStatus::Applied,
+                    Err(err) => match err.kind {
+                        ProgramError::PlayerNotFound => KeepAliveStatus::NotAPlayer,
+                        ProgramError::InvalidTimestamp => KeepAliveStatus::AlreadyCurrent,
+                        kind => KeepAliveStatus::Failed(kind),
+                    },
+                }
+            })
+            .collect();
+        Ok(statuses)
+    }
+
+    pub fn keep_alive_seq(self: &mut Game, player: Pubkey, seq: u64) -> Result<()> {
+        game_log!(
+            "keep_alive_seq: player={} seq={} state={:?}",
+            player,
+            seq,
+            self.game_state
+        );
+        let result = self.apply_keep_alive_seq(player, seq);
+        self.log_outcome("keep_alive_seq", &result);
+        result
+    }
+
+    fn apply_keep_alive_seq(self: &mut Game, player: Pubkey, seq: u64) -> Result<()> {
+        self.ensure_mutable(Action::KeepAlive)?;
+        if self.config.liveness != Liveness::Sequence {
+            Err(ProgramError::WrongLiveness)?;
+        }
+        if self.is_finished() {
+            // Ignore keep_alive when game is no longer in progress
+            return Ok(());
+        }
+        let seat = self.seat_of(&player)?;
+        if seq <= self.keep_alive.get(seat) {
+            Err(ProgramError::InvalidTimestamp.at(ErrorContext::Timestamp(seq)))?;
+        }
+        self.keep_alive.set(seat, seq);
+        self.opponent_acted(seat);
+        let idle = &mut self.idle_heartbeats[seat.index()];
+        *idle = idle.saturating_add(1);
+        Ok(())
+    }
+
+    // The player in `seat` did something, so their opponent's heartbeats are no longer
+    // unanswered
+    pub(crate) fn opponent_acted(&mut self, seat: Seat) {
+        self.idle_heartbeats[seat.opponent().index()] = 0;
+    }
+
+    // The player waiting on their opponent wins if the opponent has stopped responding
+    pub fn claim_timeout(self: &mut Game, player: Pubkey, now: u64) -> Result<()> {
+        self.claim_timeout_with_clock(player, Now::Seconds(now))
+    }
+
+    pub fn claim_timeout_at(self: &mut Game, player: Pubkey, clock: &ClockSnapshot) -> Result<()> {
+        self.claim_timeout_with_clock(player, Now::Clock(*clock))
+    }
+
+    fn claim_timeout_with_clock(self: &mut Game, player: Pubkey, now: Now) -> Result<()> {
+        self.ensure_playable(Action::ClaimTimeout, player)?;
+        let now = self.reading(now)?;
+        // The player waiting on the opponent claims; a solo player is never waiting
+        let claimant = match self.seat_to_move() {
+            Some(to_move) => to_move.opponent(),
+            None => Err(ProgramError::NotYourTurn)?,
+        };
+        if self.seat_of(&player).ok() != Some(claimant) {
+            Err(ProgramError::NotYourTurn.at(ErrorContext::Player(player)))?;
+        }
+        let timed_out = match self.config.liveness {
+            Liveness::Timestamp => self.has_timed_out(now),
+            Liveness::Sequence => {
+                self.idle_heartbeats[claimant.index()] >= self.config.heartbeat_limit
+            }
+        };
+        if !timed_out {
+            Err(ProgramError::NotTimedOut.at(ErrorContext::Timestamp(now)))?;
+        }
+        self.finish(match claimant {
+            Seat::X => GameState::XWon,
+            Seat::O => GameState::OWon,
+        });
+        Ok(())
+    }
+}
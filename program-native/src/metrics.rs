@@ -0,0 +1,162 @@
+use std::time::Instant;
+
+// Source of the compute units left for the current instruction
+pub trait ComputeMeter {
+    fn remaining_units(&self) -> u64;
+}
+
+// Native stand in for the runtime's compute meter, one unit per nanosecond since the meter
+// was created.  solana-sdk 0.11 has no compute meter the program could ask instead
+pub struct ElapsedMeter {
+    start: Instant,
+}
+
+impl ElapsedMeter {
+    pub fn new() -> ElapsedMeter {
+        ElapsedMeter {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for ElapsedMeter {
+    fn default() -> ElapsedMeter {
+        ElapsedMeter::new()
+    }
+}
+
+impl ComputeMeter for ElapsedMeter {
+    fn remaining_units(&self) -> u64 {
+        let elapsed = self.start.elapsed();
+        u64::MAX - (elapsed.as_secs() * 1_000_000_000 + u64::from(elapsed.subsec_nanos()))
+    }
+}
+
+// Run the handler for the instruction `input`, logging one line with its command tag and the
+// units it consumed.  Returns the handler's result and the units
+pub fn measure<M, T, F>(meter: &M, input: &[u8], handler: F) -> (T, u64)
+where
+    M: ComputeMeter,
+    F: FnOnce() -> T,
+{
+    let before = meter.remaining_units();
+    let result = handler();
+    let units = before.saturating_sub(meter.remaining_units());
+    // The first byte of the instruction is the Command's variant index
+    let tag = input.first().cloned().unwrap_or_default();
+    info!("metrics: command={} units={}", tag, units);
+    (result, units)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use program_command::Command;
+    use program_state::State;
+    use simple_serde::SimpleSerde;
+    use solana_sdk::account::{Account, KeyedAccount};
+    use solana_sdk::pubkey::Pubkey;
+    use std::cell::Cell;
+    use std::mem::size_of;
+
+    struct FixedMeter {
+        remaining: Cell<u64>,
+    }
+
+    impl ComputeMeter for FixedMeter {
+        fn remaining_units(&self) -> u64 {
+            let remaining = self.remaining.get();
+            self.remaining.set(remaining - 7);
+            remaining
+        }
+    }
+
+    #[test]
+    pub fn measure_reports_consumed_units() {
+        let meter = FixedMeter {
+            remaining: Cell::new(100),
+        };
+        assert_eq!(measure(&meter, &[6, 0, 0, 0], || 42), (42, 7));
+    }
+
+    // Process `command` against `accounts`, the first of which signs, returning the units
+    // it took
+    fn run(accounts: Vec<&mut (Pubkey, Account)>, command: Command, tick_height: u64) -> u64 {
+        let mut data = vec![0; size_of::<Command>()];
+        command.serialize(&mut data).unwrap();
+        let mut info: Vec<KeyedAccount> = accounts
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, account))| KeyedAccount::new(key, i == 0, account))
+            .collect();
+        let meter = ElapsedMeter::new();
+        let (result, units) = measure(&meter, &data, || {
+            ::process_instruction(&mut info, &data, tick_height)
+        });
+        result.unwrap();
+        units
+    }
+
+    // Generous ceilings that still catch a handler getting drastically more expensive
+    fn budget(command: &Command) -> u64 {
+        match command {
+            Command::Move(_, _) => 2_000_000,
+            _ => 5_000_000,
+        }
+    }
+
+    #[test]
+    pub fn command_budgets() {
+        let program_id = Pubkey::new(&[9; 32]);
+        let account = |tokens, space| Account::new(tokens, space, program_id);
+
+        // The cheapest of a few runs, so a busy machine does not fail the test
+        let mut cheapest: Vec<(Command, u64)> = vec![];
+        for _ in 0..5 {
+            let mut dashboard = (Pubkey::new(&[3; 32]), account(100, size_of::<State>()));
+            let mut game = (Pubkey::new(&[4; 32]), account(0, size_of::<State>()));
+            let mut player_x = (Pubkey::new(&[1; 32]), account(0, 0));
+            let mut player_o = (Pubkey::new(&[2; 32]), account(0, 0));
+            let mut units = vec![];
+
+            let accounts = vec![&mut dashboard];
+            units.push((
+                Command::InitDashboard,
+                run(accounts, Command::InitDashboard, 1),
+            ));
+            let accounts = vec![&mut game, &mut dashboard, &mut player_x];
+            units.push((Command::InitGame, run(accounts, Command::InitGame, 1)));
+            let accounts = vec![&mut player_o, &mut dashboard, &mut game];
+            units.push((Command::Join, run(accounts, Command::Join, 2)));
+
+            // X wins along the middle row
+            for (i, &(x, y)) in [(1, 1), (0, 0), (0, 1), (2, 0), (2, 1)].iter().enumerate() {
+                let player = if i % 2 == 0 {
+                    &mut player_x
+                } else {
+                    &mut player_o
+                };
+                let accounts = vec![player, &mut dashboard, &mut game];
+                units.push((Command::Move(x, y), run(accounts, Command::Move(x, y), 3)));
+            }
+
+            if cheapest.is_empty() {
+                cheapest = units;
+            } else {
+                for (best, (_, units)) in cheapest.iter_mut().zip(units) {
+                    best.1 = best.1.min(units);
+                }
+            }
+        }
+
+        for (command, units) in cheapest {
+            assert!(
+                units < budget(&command),
+                "{:?} took {} units, over its budget of {}",
+                command,
+                units,
+                budget(&command)
+            );
+        }
+    }
+}
@@ -0,0 +1,46 @@
+// The exhaustive classic minimax the perfect play table is generated with.  build.rs includes
+// this file and consts.rs as they are, so the table and the runtime share one set of rules and
+// one board encoding; nothing here may reach further into the crate
+use consts::{BOARD_ITEM_FREE, BOARD_ITEM_O, BOARD_ITEM_X, WIN_LINES};
+use std::collections::BTreeMap;
+
+// The board as a base 3 number, board[i] * 3^i summed over the cells
+pub fn pack_board(board: &[u8]) -> u32 {
+    board
+        .iter()
+        .rev()
+        .fold(0, |encoded, &item| encoded * 3 + u32::from(item))
+}
+
+// Minimax value of the board for `to_move` (1 = win, 0 = draw, -1 = loss), recorded by packed
+// board for the board and every board reachable from it
+pub fn minimax(board: &mut [u8; 9], to_move: u8, values: &mut BTreeMap<u32, i8>) -> i8 {
+    let packed = pack_board(board);
+    if let Some(&value) = values.get(&packed) {
+        return value;
+    }
+    let opponent = if to_move == BOARD_ITEM_X {
+        BOARD_ITEM_O
+    } else {
+        BOARD_ITEM_X
+    };
+    let value = if WIN_LINES
+        .iter()
+        .any(|line| line.iter().all(|&i| board[i] == opponent))
+    {
+        -1
+    } else {
+        let mut best = None;
+        for i in 0..board.len() {
+            if board[i] == BOARD_ITEM_FREE {
+                board[i] = to_move;
+                let value = -minimax(board, opponent, values);
+                best = Some(best.map_or(value, |best: i8| best.max(value)));
+                board[i] = BOARD_ITEM_FREE;
+            }
+        }
+        best.unwrap_or(0)
+    };
+    values.insert(packed, value);
+    value
+}
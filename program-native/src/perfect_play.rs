@@ -0,0 +1,141 @@
+use game::{Game, POSITION_COUNT};
+
+// Minimax value of every reachable position for the player to move (1 = win, 0 = draw,
+// -1 = loss), indexed by Game::position_index.  build.rs solves the game to generate it
+pub static PERFECT_PLAY_TABLE: [i8; POSITION_COUNT] = signed(include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/perfect_play.bin"
+)));
+
+const fn signed(bytes: &[u8; POSITION_COUNT]) -> [i8; POSITION_COUNT] {
+    let mut table = [0; POSITION_COUNT];
+    let mut i = 0;
+    while i < POSITION_COUNT {
+        table[i] = bytes[i] as i8;
+        i += 1;
+    }
+    table
+}
+
+// Value of the game's board for the player to move under perfect play, in one lookup, or
+// None for a board alternating play cannot reach, which only a wild game holds.  Once a line
+// is on the board the player left to move has lost, and a full board without one is a draw.
+// Decided boards are in the table too, so this is the endgame probe as well: a search stops
+// on the score it gives instead of asking is_finished
+pub fn solve(game: &Game) -> Option<i8> {
+    game.position_index()
+        .map(|index| PERFECT_PLAY_TABLE[index as usize])
+}
+
+// The move that leaves the player to move the best score in PERFECT_PLAY_TABLE, the lowest
+// board index among equally good ones.  None when no move can be played
+pub fn suggest_move_from_table(game: &Game) -> Option<(usize, usize)> {
+    let player = game.current_player()?;
+    let mut best: Option<((usize, usize), i8)> = None;
+    for (x, y) in game.available_moves() {
+        let mut next = game.clone();
+        if next.next_move(player, x, y).is_err() {
+            continue;
+        }
+        // The table scores the position for the opponent, who moves next
+        let score = match solve(&next) {
+            Some(score) => -score,
+            None => continue,
+        };
+        if best.is_none_or(|(_, best)| score > best) {
+            best = Some(((x, y), score));
+        }
+    }
+    best.map(|(cell, _)| cell)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::{outcome, Cell, GameConfig, GameState, Move, Solver, Variant, BOARD_ITEM_X};
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    pub fn start_is_a_draw() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        assert_eq!(solve(&Game::new(player_x, player_o)), Some(0));
+    }
+
+    #[test]
+    pub fn probe_unreachable_board() {
+        let mut g = Game::new(Pubkey::new(&[1; 32]), Pubkey::new(&[2; 32]));
+        assert_eq!(solve(&g), Some(0));
+        g.board = [BOARD_ITEM_X; 9];
+        assert_eq!(g.position_index(), None);
+        assert_eq!(solve(&g), None);
+    }
+
+    #[test]
+    pub fn wild_board_outside_the_table() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            variant: Variant::Wild,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 0).unwrap();
+        g.join(player_o, 1).unwrap();
+        // Two Os and no X, which alternating play never reaches
+        g.next_move_wild(player_x, 0, 0, Cell::O).unwrap();
+        g.next_move_wild(player_o, 1, 0, Cell::O).unwrap();
+        assert_eq!(g.position_index(), None);
+        assert_eq!(solve(&g), None);
+    }
+
+    #[test]
+    pub fn suggestions() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+
+        // X takes the open end of the top row rather than blocking O
+        let moves = [(0, 0), (0, 1), (1, 0), (1, 1)].iter();
+        let g = Game::from_moves_iter(player_x, player_o, moves.map(|&(x, y)| Move { x, y }));
+        assert_eq!(suggest_move_from_table(&g.unwrap()), Some((2, 0)));
+
+        // Every suggestion keeps the best result the position offers, so a drawing move is
+        // never passed over for a losing one
+        let mut suggested = 0;
+        for index in 0..POSITION_COUNT as u16 {
+            let g = Game::from_position_index(index, player_x, player_o).unwrap();
+            match suggest_move_from_table(&g) {
+                Some((x, y)) => {
+                    let mut next = g.clone();
+                    next.next_move(g.current_player().unwrap(), x, y).unwrap();
+                    assert_eq!(solve(&next).map(|score| -score), solve(&g));
+                    suggested += 1;
+                }
+                None => assert!(g.available_moves().is_empty()),
+            }
+        }
+        assert!(suggested > 0);
+
+        let mut g = Game::new(player_x, player_o);
+        g.request_abort(player_x).unwrap();
+        g.confirm_abort(player_o).unwrap();
+        assert_eq!(suggest_move_from_table(&g), None);
+    }
+
+    #[test]
+    pub fn table_matches_solver() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let solver = Solver::new();
+        for index in 0..POSITION_COUNT as u16 {
+            let g = Game::from_position_index(index, player_x, player_o).unwrap();
+            let board = g.board();
+            // The solver has no value for decided boards, a line there is the mover's loss
+            let expected = match solver.board_value(board) {
+                Some(value) => value,
+                None if outcome(Variant::Classic, board) == Some(GameState::Draw) => 0,
+                None => -1,
+            };
+            assert_eq!(solve(&g), Some(expected));
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use result::{ProgramError, Result};
+use std;
+
+pub const MAX_NAME_LEN: usize = 16;
+
+// Display name of a player: up to MAX_NAME_LEN bytes of UTF-8.  Names are only for display,
+// so two players may share one; their pubkeys tell them apart
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct PlayerName {
+    len: u8,                   // Number of bytes in use, 0 if no name was set
+    bytes: [u8; MAX_NAME_LEN], // UTF-8 encoded name
+}
+
+impl PlayerName {
+    pub fn new(name: &str) -> Result<PlayerName> {
+        PlayerName::from_bytes(name.as_bytes())
+    }
+
+    pub fn from_bytes(name: &[u8]) -> Result<PlayerName> {
+        if name.len() > MAX_NAME_LEN {
+            Err(ProgramError::InvalidName)?;
+        }
+        let mut bytes = [0; MAX_NAME_LEN];
+        bytes[..name.len()].copy_from_slice(name);
+        let player_name = PlayerName {
+            len: name.len() as u8,
+            bytes,
+        };
+        player_name.validate()?;
+        Ok(player_name)
+    }
+
+    // Names arriving in an instruction are only structurally decoded, so check them again
+    // before storing
+    pub fn validate(&self) -> Result<()> {
+        let len = self.len as usize;
+        if len > MAX_NAME_LEN {
+            Err(ProgramError::InvalidName)?;
+        }
+        let name =
+            std::str::from_utf8(&self.bytes[..len]).map_err(|_| ProgramError::InvalidName)?;
+        if name.trim().is_empty() || name.chars().any(char::is_control) {
+            Err(ProgramError::InvalidName)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.len != 0
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        if !self.is_set() {
+            return None;
+        }
+        self.bytes
+            .get(..self.len as usize)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bincode;
+
+    #[test]
+    pub fn round_trip() {
+        let name = PlayerName::new("Tic Tac 💥").unwrap();
+        assert_eq!(name.as_str(), Some("Tic Tac 💥"));
+
+        let bytes = bincode::serialize(&name).unwrap();
+        assert_eq!(bytes.len(), 1 + MAX_NAME_LEN);
+        let decoded: PlayerName = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(decoded, name);
+        assert_eq!(decoded.as_str(), Some("Tic Tac 💥"));
+
+        assert_eq!(PlayerName::default().as_str(), None);
+    }
+
+    #[test]
+    pub fn invalid() {
+        assert!(PlayerName::new("sixteen bytes ok").is_ok());
+        assert!(PlayerName::new("seventeen bytes!!").is_err());
+        assert!(PlayerName::new("").is_err());
+        assert!(PlayerName::new("   ").is_err());
+        assert!(PlayerName::new("tab\tname").is_err());
+        assert!(PlayerName::from_bytes(&[0x66, 0x6f, 0xff]).is_err());
+
+        let mut bytes = bincode::serialize(&PlayerName::new("abc").unwrap()).unwrap();
+        bytes[2] = 0xc3; // Start of a two byte sequence that never finishes
+        let decoded: PlayerName = bincode::deserialize(&bytes).unwrap();
+        assert!(decoded.validate().is_err());
+        assert_eq!(decoded.as_str(), None);
+    }
+}
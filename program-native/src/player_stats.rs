@@ -0,0 +1,184 @@
+use game::{cell_coords, cell_name, GameState};
+use record::FinalizedGame;
+#[cfg(feature = "json")]
+use result::{ProgramError, Result};
+#[cfg(feature = "json")]
+use serde_json;
+use solana_sdk::pubkey::Pubkey;
+
+// A player's results over the games finalized for them, kept up to date from the FinalizedGame
+// of each.  The opening counters only count the games the player made the first move in
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct PlayerStats {
+    pub player: Pubkey,
+    pub games: u32,             // Finished games played, aborted ones aside
+    pub wins: u32,              // Games won
+    pub draws: u32,             // Games drawn
+    pub losses: u32,            // Games lost
+    pub first_moves: [u16; 9],  // Games opened, by board index of the first move
+    pub opening_wins: [u16; 9], // Games opened and won, by the same index
+}
+
+impl PlayerStats {
+    pub fn new(player: Pubkey) -> PlayerStats {
+        PlayerStats {
+            player,
+            ..PlayerStats::default()
+        }
+    }
+
+    // Count a finalized game.  Games the player was not in and aborted games leave the stats
+    // alone.  The first move is the player's when they hold seat X in the record, which under
+    // the swap rule is the player who took the move over
+    pub fn record_game(&mut self, record: &FinalizedGame) {
+        let is_x = record.player_x == self.player;
+        if !is_x && record.player_o != self.player || record.outcome == GameState::Aborted {
+            return;
+        }
+        let won = match record.outcome {
+            GameState::XWon => is_x,
+            GameState::OWon => !is_x || record.player_o == self.player,
+            _ => false,
+        };
+        self.games = self.games.saturating_add(1);
+        if record.outcome == GameState::Draw {
+            self.draws = self.draws.saturating_add(1);
+        } else if won {
+            self.wins = self.wins.saturating_add(1);
+        } else {
+            self.losses = self.losses.saturating_add(1);
+        }
+
+        if is_x && record.move_count > 0 {
+            let first = usize::from(record.moves[0]).min(8);
+            self.first_moves[first] = self.first_moves[first].saturating_add(1);
+            if record.outcome == GameState::XWon {
+                self.opening_wins[first] = self.opening_wins[first].saturating_add(1);
+            }
+        }
+    }
+
+    // The cell the player opens on most, the first in board index order among equals.  None
+    // until the player opened a game
+    pub fn favorite_opening(&self) -> Option<(usize, usize)> {
+        self.favorite_cell().and_then(|i| cell_coords(i).ok())
+    }
+
+    fn favorite_cell(&self) -> Option<usize> {
+        let (i, &plays) = self
+            .first_moves
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|&(_, &plays)| plays)?;
+        if plays == 0 {
+            return None;
+        }
+        Some(i)
+    }
+
+    pub fn view(&self) -> PlayerStatsView {
+        PlayerStatsView {
+            player: self.player.to_string(),
+            games: self.games,
+            wins: self.wins,
+            draws: self.draws,
+            losses: self.losses,
+            first_moves: self.first_moves,
+            opening_wins: self.opening_wins,
+            favorite_opening: self.favorite_cell().map(cell_name),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(&self.view()).map_err(|_| ProgramError::InvalidUserdata.into())
+    }
+}
+
+// Profile page rendering of PlayerStats, the favorite opening in Game::move_notation form
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PlayerStatsView {
+    pub player: String,
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub first_moves: [u16; 9],
+    pub opening_wins: [u16; 9],
+    pub favorite_opening: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::play_between;
+    use serde_json;
+
+    fn players() -> (Pubkey, Pubkey) {
+        (Pubkey::new(&[1; 32]), Pubkey::new(&[2; 32]))
+    }
+
+    fn finalized(player_x: Pubkey, player_o: Pubkey, moves: &[usize]) -> FinalizedGame {
+        play_between(player_x, player_o, moves)
+            .finalize_record(&Pubkey::new(&[3; 32]))
+            .unwrap()
+    }
+
+    #[test]
+    pub fn openings() {
+        let (player_x, player_o) = players();
+        let mut stats = PlayerStats::new(player_x);
+        assert_eq!(stats.favorite_opening(), None);
+
+        // A draw, a win and a loss opened in the center, a draw opened in a corner
+        stats.record_game(&finalized(player_x, player_o, &[4, 0, 2, 6, 3, 5, 1, 7, 8]));
+        stats.record_game(&finalized(player_x, player_o, &[4, 1, 0, 2, 8]));
+        stats.record_game(&finalized(player_x, player_o, &[4, 0, 5, 3, 2, 6]));
+        stats.record_game(&finalized(player_x, player_o, &[0, 4, 8, 2, 6, 3, 5, 7, 1]));
+        assert_eq!(
+            (stats.games, stats.wins, stats.draws, stats.losses),
+            (4, 1, 2, 1)
+        );
+        assert_eq!(stats.first_moves[4], 3);
+        assert_eq!(stats.opening_wins[4], 1);
+        // The draw counts as a play of its opening, not as a win
+        assert_eq!((stats.first_moves[0], stats.opening_wins[0]), (1, 0));
+        assert_eq!(stats.favorite_opening(), Some((1, 1)));
+
+        // Games as O count in the results but not in the openings
+        let openings = stats.first_moves;
+        stats.record_game(&finalized(player_o, player_x, &[0, 4, 1, 3, 8, 5]));
+        assert_eq!((stats.games, stats.wins), (5, 2));
+        assert_eq!(stats.first_moves, openings);
+
+        // Nor do games of other players
+        stats.record_game(&finalized(
+            player_o,
+            Pubkey::new(&[4; 32]),
+            &[8, 0, 7, 1, 6],
+        ));
+        assert_eq!(stats.games, 5);
+
+        let json = serde_json::to_string(&stats.view()).unwrap();
+        assert!(json.contains(r#""favorite_opening":"b2""#), "{}", json);
+    }
+
+    #[test]
+    pub fn saturation_and_ties() {
+        let (player_x, player_o) = players();
+        let mut stats = PlayerStats::new(player_x);
+        stats.first_moves[8] = u16::MAX;
+        stats.opening_wins[8] = u16::MAX;
+        stats.record_game(&finalized(player_x, player_o, &[8, 0, 7, 1, 6]));
+        assert_eq!(stats.first_moves[8], u16::MAX);
+        assert_eq!(stats.opening_wins[8], u16::MAX);
+
+        // Among equally played openings the first cell wins
+        let mut stats = PlayerStats::new(player_x);
+        stats.record_game(&finalized(player_x, player_o, &[8, 0, 7, 1, 6]));
+        stats.record_game(&finalized(player_x, player_o, &[2, 0, 5, 1, 8]));
+        assert_eq!(stats.favorite_opening(), Some((2, 0)));
+        assert_eq!(stats.view().favorite_opening, Some("c1".to_string()));
+    }
+}
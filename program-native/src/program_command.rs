@@ -1,3 +1,8 @@
+use bincode;
+use game::{Cell, GameConfig};
+use player_name::PlayerName;
+use result::Result;
+use serde;
 use simple_serde::SimpleSerde;
 
 #[repr(C)]
@@ -11,12 +16,48 @@ pub enum Command {
     Join,         // Player O wants to join
     KeepAlive,    // Player X/O keep alive
     Move(u8, u8), // Player X/O mark board position (x, y)
+
+    FinalizeWithTrophy, // Award a trophy for a won game
+    Reset,              // Reuse a finished game account for a new game
+
+    InitGameWithConfig(GameConfig), // Initialize a game account with non-default rules
+    Swap,                           // Player O takes over X's first move (swap rule)
+    SetName(PlayerName),            // Player X/O set their display name
+    KeepAliveSeq(u64),              // Player X/O keep alive with a sequence number
+    ClaimTimeout,                   // Player X/O claim the win from an unresponsive opponent
+    RequestAbort,                   // Player X/O ask to void the game
+    ConfirmAbort,                   // Player X/O agree to void the game
+    Watch,                          // Start spectating a game
+    Unwatch,                        // Stop spectating a game
+    Freeze,                         // Moderator stops play for review
+    Unfreeze,                       // Moderator resumes play
+    Upgrade,                        // Rewrite an old game account in the current layout
+    LinkGames,                      // Player X/O play two games with swapped colors as a match
+    MoveWild(u8, u8, Cell),         // Player X/O place either mark at (x, y) in a wild game
+    KeepAliveMany,                  // Player X/O keep alive in each of the games that follow
+    InitArchive,                    // Initialize an archive account for the dashboard
+}
+
+// Instructions carry only as many bytes as their command encodes to, which for most commands is
+// far less than the largest variant, so decoding doesn't hold the input to size_of::<Command>()
+impl SimpleSerde for Command {
+    fn deserialize<'a>(input: &'a [u8]) -> Result<Command>
+    where
+        Command: serde::Deserialize<'a>,
+    {
+        bincode::deserialize(input).map_err(Command::map_to_invalid_args)
+    }
+
+    // Nor does the output have to be larger than the encoding
+    fn serialize(&self, output: &mut [u8]) -> Result<()> {
+        bincode::serialize_into(output, self).map_err(Command::map_to_invalid_args)
+    }
 }
-impl SimpleSerde for Command {}
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use game::TimeoutUnit;
 
     #[test]
     pub fn serialize() {
@@ -55,4 +96,94 @@ mod test {
         cmd.serialize(&mut b).unwrap();
         assert_eq!(b, [6, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
     }
+
+    // A command encoded into a buffer exactly as long as its encoding
+    fn encode(cmd: &Command, len: usize) -> Vec<u8> {
+        let mut b = vec![0; len];
+        cmd.serialize(&mut b).unwrap();
+        b
+    }
+
+    #[test]
+    pub fn serialize_later_commands() {
+        assert_eq!(encode(&Command::FinalizeWithTrophy, 4), [7, 0, 0, 0]);
+        assert_eq!(encode(&Command::Reset, 4), [8, 0, 0, 0]);
+
+        let config = GameConfig {
+            move_timeout: 0x0102,
+            join_deadline: 3,
+            swap_rule: true,
+            ..GameConfig::default()
+        };
+        let cmd = Command::InitGameWithConfig(config);
+        let mut b = encode(&cmd, 37);
+        assert_eq!(
+            b,
+            vec![
+                9, 0, 0, 0, 2, 1, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0
+            ]
+        );
+        assert_eq!(Command::deserialize(&b).unwrap(), cmd);
+        assert!(cmd.serialize(&mut b[..36]).is_err());
+
+        // The timeout unit takes a byte, then the handicap and the conduct limit
+        let cmd = Command::InitGameWithConfig(GameConfig {
+            timeout_unit: TimeoutUnit::Slots,
+            center_handicap: true,
+            conduct_limit: 3,
+            ..config
+        });
+        cmd.serialize(&mut b).unwrap();
+        assert_eq!(b[31..].to_vec(), vec![1, 1, 3, 0, 0, 0]);
+        assert_eq!(Command::deserialize(&b).unwrap(), cmd);
+        b[31] = 2;
+        assert!(Command::deserialize(&b).is_err());
+        b[31] = 0;
+        b[20] = 2;
+        assert!(Command::deserialize(&b).is_err());
+
+        let cmd = Command::SetName(PlayerName::new("ab").unwrap());
+        let b = encode(&cmd, 21);
+        assert_eq!(b[..8].to_vec(), vec![11, 0, 0, 0, 2, b'a', b'b', 0]);
+        assert_eq!(Command::deserialize(&b).unwrap(), cmd);
+
+        assert_eq!(
+            encode(&Command::KeepAliveSeq(0x0102), 12),
+            [12, 0, 0, 0, 2, 1, 0, 0, 0, 0, 0, 0]
+        );
+
+        let cmd = Command::MoveWild(1, 2, Cell::O);
+        let b = encode(&cmd, 10);
+        assert_eq!(b, [22, 0, 0, 0, 1, 2, 2, 0, 0, 0]);
+        assert_eq!(Command::deserialize(&b).unwrap(), cmd);
+
+        assert_eq!(encode(&Command::Swap, 4), [10, 0, 0, 0]);
+        assert_eq!(encode(&Command::ClaimTimeout, 4), [13, 0, 0, 0]);
+        assert_eq!(encode(&Command::RequestAbort, 4), [14, 0, 0, 0]);
+        assert_eq!(encode(&Command::ConfirmAbort, 4), [15, 0, 0, 0]);
+        assert_eq!(encode(&Command::Watch, 4), [16, 0, 0, 0]);
+        assert_eq!(encode(&Command::Unwatch, 4), [17, 0, 0, 0]);
+        assert_eq!(encode(&Command::Freeze, 4), [18, 0, 0, 0]);
+        assert_eq!(encode(&Command::Unfreeze, 4), [19, 0, 0, 0]);
+        assert_eq!(encode(&Command::Upgrade, 4), [20, 0, 0, 0]);
+        assert_eq!(encode(&Command::LinkGames, 4), [21, 0, 0, 0]);
+        assert_eq!(encode(&Command::KeepAliveMany, 4), [23, 0, 0, 0]);
+        assert_eq!(encode(&Command::InitArchive, 4), [24, 0, 0, 0]);
+    }
+
+    #[test]
+    pub fn deserialize_short_input() {
+        // The client sends a move as its tag and the two coordinates, nothing more
+        assert_eq!(
+            Command::deserialize(&[6, 0, 0, 0, 1, 2]).unwrap(),
+            Command::Move(1, 2)
+        );
+        assert_eq!(
+            Command::deserialize(&[7, 0, 0, 0]).unwrap(),
+            Command::FinalizeWithTrophy
+        );
+        assert!(Command::deserialize(&[6, 0, 0, 0, 1]).is_err());
+        assert!(Command::deserialize(&[]).is_err());
+    }
 }
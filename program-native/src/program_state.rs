@@ -1,17 +1,61 @@
+use archive;
+use bincode;
 use dashboard;
 use game;
+use linked_match;
+use result::{ProgramError, Result};
+use serde;
 use simple_serde::SimpleSerde;
+use std::mem::size_of;
+use trophy;
+use upgrade;
+use watch_list;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(feature = "cargo-clippy", allow(large_enum_variant))]
 pub enum State {
-    Uninitialized,                   // State is not initialized yet
-    Dashboard(dashboard::Dashboard), // State holds dashboard state
-    Game(game::Game),                // State holds game state
+    Uninitialized,                          // State is not initialized yet
+    Dashboard(dashboard::Dashboard),        // State holds dashboard state
+    Game(game::Game),                       // State holds game state
+    Trophy(trophy::Trophy),                 // State holds a trophy for a won game
+    WatchList(watch_list::WatchList),       // State holds the spectators of a game
+    LinkedMatch(linked_match::LinkedMatch), // State holds two games played as one match
+    Archive(archive::Archive),              // State holds the finalized games of a dashboard
 }
 impl Default for State {
     fn default() -> State {
         State::Uninitialized
     }
 }
-impl SimpleSerde for State {}
+// Dashboards still in the v1 layout are read in it, every other account in the current layout
+impl SimpleSerde for State {
+    fn deserialize<'a>(input: &'a [u8]) -> Result<State>
+    where
+        State: serde::Deserialize<'a>,
+    {
+        if let Some(dashboard) = upgrade::upgrade_dashboard(input)? {
+            return Ok(State::Dashboard(dashboard));
+        }
+        if input.len() < size_of::<State>() {
+            warn!(
+                "deserialize fail: input too small: {} < {}",
+                input.len(),
+                size_of::<State>()
+            );
+            Err(ProgramError::InvalidUserdata)?;
+        }
+        bincode::deserialize(input).map_err(State::map_to_invalid_args)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::mem::size_of;
+
+    #[test]
+    pub fn fits_in_account() {
+        // Clients allocate 255 bytes of userdata for every dashboard and game account
+        assert!(size_of::<State>() <= 255);
+    }
+}
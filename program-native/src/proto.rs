@@ -0,0 +1,260 @@
+// Protobuf encoding of games, for gRPC game servers.  The message types are generated from
+// proto/game.proto by build.rs; the conversions to and from the engine types follow them
+use consts::BOARD_ITEM_FREE;
+use player_name::PlayerName;
+use result::{ProgramError, Result};
+use rules::cell_index;
+use solana_sdk::pubkey::Pubkey;
+use state;
+use std::convert::TryFrom;
+
+include!(concat!(env!("OUT_DIR"), "/tictactoe.rs"));
+
+impl From<state::GameState> for GameState {
+    fn from(game_state: state::GameState) -> GameState {
+        match game_state {
+            state::GameState::Waiting => GameState::Waiting,
+            state::GameState::XMove => GameState::XMove,
+            state::GameState::OMove => GameState::OMove,
+            state::GameState::XWon => GameState::XWon,
+            state::GameState::OWon => GameState::OWon,
+            state::GameState::Draw => GameState::Draw,
+            state::GameState::Aborted => GameState::Aborted,
+        }
+    }
+}
+
+impl From<GameState> for state::GameState {
+    fn from(game_state: GameState) -> state::GameState {
+        match game_state {
+            GameState::Waiting => state::GameState::Waiting,
+            GameState::XMove => state::GameState::XMove,
+            GameState::OMove => state::GameState::OMove,
+            GameState::XWon => state::GameState::XWon,
+            GameState::OWon => state::GameState::OWon,
+            GameState::Draw => state::GameState::Draw,
+            GameState::Aborted => state::GameState::Aborted,
+        }
+    }
+}
+
+impl From<state::GameConfig> for GameConfig {
+    fn from(config: state::GameConfig) -> GameConfig {
+        GameConfig {
+            move_timeout: config.move_timeout,
+            join_deadline: config.join_deadline,
+            variant: match config.variant {
+                state::Variant::Classic => Variant::Classic,
+                state::Variant::Wild => Variant::Wild,
+            } as i32,
+            allow_solo: config.allow_solo,
+            swap_rule: config.swap_rule,
+            liveness: match config.liveness {
+                state::Liveness::Timestamp => Liveness::Timestamp,
+                state::Liveness::Sequence => Liveness::Sequence,
+            } as i32,
+            heartbeat_limit: u32::from(config.heartbeat_limit),
+            timeout_unit: match config.timeout_unit {
+                state::TimeoutUnit::Seconds => TimeoutUnit::Seconds,
+                state::TimeoutUnit::Slots => TimeoutUnit::Slots,
+            } as i32,
+            center_handicap: config.center_handicap,
+            conduct_limit: config.conduct_limit,
+        }
+    }
+}
+
+fn config_from_protobuf(pb: GameConfig) -> Result<state::GameConfig> {
+    let variant = match Variant::try_from(pb.variant) {
+        Ok(Variant::Classic) => state::Variant::Classic,
+        Ok(Variant::Wild) => state::Variant::Wild,
+        Err(_) => Err(ProgramError::InvalidUserdata)?,
+    };
+    let liveness = match Liveness::try_from(pb.liveness) {
+        Ok(Liveness::Timestamp) => state::Liveness::Timestamp,
+        Ok(Liveness::Sequence) => state::Liveness::Sequence,
+        Err(_) => Err(ProgramError::InvalidUserdata)?,
+    };
+    let timeout_unit = match TimeoutUnit::try_from(pb.timeout_unit) {
+        Ok(TimeoutUnit::Seconds) => state::TimeoutUnit::Seconds,
+        Ok(TimeoutUnit::Slots) => state::TimeoutUnit::Slots,
+        Err(_) => Err(ProgramError::InvalidUserdata)?,
+    };
+    Ok(state::GameConfig {
+        move_timeout: pb.move_timeout,
+        join_deadline: pb.join_deadline,
+        variant,
+        allow_solo: pb.allow_solo,
+        swap_rule: pb.swap_rule,
+        liveness,
+        heartbeat_limit: byte(pb.heartbeat_limit)?,
+        timeout_unit,
+        center_handicap: pb.center_handicap,
+        conduct_limit: pb.conduct_limit,
+    })
+}
+
+fn byte(value: u32) -> Result<u8> {
+    if value > u32::from(u8::MAX) {
+        Err(ProgramError::InvalidUserdata)?;
+    }
+    Ok(value as u8)
+}
+
+fn pubkey(bytes: &[u8]) -> Result<Pubkey> {
+    if bytes.len() != 32 {
+        Err(ProgramError::InvalidUserdata)?;
+    }
+    Ok(Pubkey::new(bytes))
+}
+
+// The two per-player entries of a repeated field
+fn pair<T: Copy>(values: &[T]) -> Result<[T; 2]> {
+    match *values {
+        [x, o] => Ok([x, o]),
+        _ => Err(ProgramError::InvalidUserdata.into()),
+    }
+}
+
+impl state::Game {
+    pub fn to_protobuf(&self) -> Game {
+        self.debug_assert_final_board();
+        Game {
+            keep_alive: <[u64; 2]>::from(self.keep_alive).to_vec(),
+            game_state: GameState::from(self.game_state) as i32,
+            player_x: self.player_x.as_ref().to_vec(),
+            player_o: self.player_o.as_ref().to_vec(),
+            board: self.board.to_vec(),
+            trophy_minted: self.trophy_minted,
+            moves: self
+                .move_history()
+                .iter()
+                .map(|m| Move {
+                    x: m.x as u32,
+                    y: m.y as u32,
+                })
+                .collect(),
+            generation: self.generation,
+            config: Some(self.config.into()),
+            names: self
+                .names
+                .iter()
+                .map(|name| name.as_str().unwrap_or_default().to_string())
+                .collect(),
+            idle_heartbeats: self.idle_heartbeats.iter().map(|&n| u32::from(n)).collect(),
+            abort_requested: self.abort_requested.to_vec(),
+            frozen: self.frozen,
+            created_at: self.created_at,
+            finished_at: self.finished_at,
+            turn: match self.turn {
+                state::Seat::X => Seat::X,
+                state::Seat::O => Seat::O,
+            } as i32,
+            invalid_attempts: self.invalid_attempts.to_vec(),
+            linked: self.linked,
+        }
+    }
+
+    // Protobuf games come from outside, so they are checked like any other untrusted userdata
+    pub fn from_protobuf(pb: Game) -> Result<state::Game> {
+        let game_state = GameState::try_from(pb.game_state)
+            .map_err(|_| ProgramError::InvalidUserdata)?
+            .into();
+        if pb.board.len() != 9 || pb.moves.len() > 9 {
+            Err(ProgramError::InvalidUserdata)?;
+        }
+        let mut board = [BOARD_ITEM_FREE; 9];
+        board.copy_from_slice(&pb.board);
+        let mut moves = [0; 9];
+        for (i, m) in pb.moves.iter().enumerate() {
+            moves[i] = cell_index(m.x as usize, m.y as usize)
+                .map_err(|_| ProgramError::InvalidUserdata)? as u8;
+        }
+        if pb.names.len() != 2 {
+            Err(ProgramError::InvalidUserdata)?;
+        }
+        let mut names = [PlayerName::default(); 2];
+        for (name, pb_name) in names.iter_mut().zip(&pb.names) {
+            if !pb_name.is_empty() {
+                *name = PlayerName::new(pb_name).map_err(|_| ProgramError::InvalidUserdata)?;
+            }
+        }
+        let idle_heartbeats = pair(&pb.idle_heartbeats)?;
+
+        let mut game = state::Game {
+            keep_alive: pair(&pb.keep_alive)?.into(),
+            game_state,
+            player_x: pubkey(&pb.player_x)?,
+            player_o: pubkey(&pb.player_o)?,
+            board,
+            trophy_minted: pb.trophy_minted,
+            moves,
+            move_count: pb.moves.len() as u8,
+            generation: pb.generation,
+            config: config_from_protobuf(pb.config.ok_or(ProgramError::InvalidUserdata)?)?,
+            names,
+            idle_heartbeats: [byte(idle_heartbeats[0])?, byte(idle_heartbeats[1])?],
+            abort_requested: pair(&pb.abort_requested)?,
+            final_board_hash: 0,
+            frozen: pb.frozen,
+            created_at: pb.created_at,
+            finished_at: pb.finished_at,
+            turn: match Seat::try_from(pb.turn) {
+                Ok(Seat::X) => state::Seat::X,
+                Ok(Seat::O) => state::Seat::O,
+                Err(_) => Err(ProgramError::InvalidUserdata)?,
+            },
+            invalid_attempts: pair(&pb.invalid_attempts)?,
+            linked: pb.linked,
+        };
+        if game.is_finished() {
+            game.final_board_hash = game.board_hash() as u32;
+        }
+        game.validate()?;
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use prost::Message;
+    use serde_json;
+    use state::GameConfig;
+
+    #[test]
+    pub fn round_trip() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let config = GameConfig {
+            move_timeout: 30,
+            swap_rule: true,
+            ..GameConfig::default()
+        };
+        let mut g = state::Game::create_with_config(&player_x, config, 1).unwrap();
+        g.join(player_o, 2).unwrap();
+        g.set_name(player_o, PlayerName::new("olive").unwrap())
+            .unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        g.next_move(player_o, 0, 0).unwrap();
+        g.keep_alive(player_x, 3).unwrap();
+        g.request_abort(player_o).unwrap();
+
+        let bytes = g.to_protobuf().encode_to_vec();
+        let decoded = state::Game::from_protobuf(Game::decode(&bytes[..]).unwrap()).unwrap();
+        assert_eq!(decoded, g);
+
+        // Typical games are much smaller than their JSON
+        assert!(bytes.len() < serde_json::to_string(&g).unwrap().len());
+
+        let mut pb = g.to_protobuf();
+        pb.player_x.pop();
+        assert!(state::Game::from_protobuf(pb).is_err());
+        let mut pb = g.to_protobuf();
+        pb.board[8] = 3;
+        assert!(state::Game::from_protobuf(pb).is_err());
+        let mut pb = g.to_protobuf();
+        pb.game_state = 42;
+        assert!(state::Game::from_protobuf(pb).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+// The conversion is in the code pymethods generates for methods returning PyResult
+#![allow(clippy::useless_conversion)]
+
+use game::{Game, GameState};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use result::{ErrorContext, GameError, ProgramError};
+use solana_sdk::pubkey::Pubkey;
+
+impl From<GameError> for PyErr {
+    fn from(err: GameError) -> PyErr {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+// The Game engine for Python, mostly for training agents against.  Games are played offline
+// between two fixed seats, so next_move always plays for whoever is to move
+#[pyclass(name = "Game")]
+pub struct PyGame {
+    game: Game,
+}
+
+#[pymethods]
+impl PyGame {
+    #[new]
+    pub fn new() -> PyResult<PyGame> {
+        let mut game = Game::create(&Pubkey::new(&[1; 32]));
+        game.join(Pubkey::new(&[2; 32]), 1)?;
+        Ok(PyGame { game })
+    }
+
+    pub fn next_move(&mut self, x: usize, y: usize) -> PyResult<()> {
+        let player = match self.game.game_state {
+            GameState::XMove => *self.game.player_x(),
+            GameState::OMove => *self.game.player_o(),
+            _ => Err(ProgramError::InvalidMove.at(ErrorContext::Coordinate(x, y)))?,
+        };
+        Ok(self.game.next_move(player, x, y)?)
+    }
+
+    pub fn available_moves(&self) -> Vec<(usize, usize)> {
+        self.game.available_moves()
+    }
+
+    pub fn to_flat_features(&self) -> Vec<f32> {
+        self.game.to_flat_features().to_vec()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(
+            self.game.game_state,
+            GameState::Waiting | GameState::XMove | GameState::OMove
+        )
+    }
+
+    // "X" or "O", None while the game is going or if it ended without a winner
+    pub fn winner(&self) -> Option<&'static str> {
+        match self.game.game_state {
+            GameState::XWon => Some("X"),
+            GameState::OWon => Some("O"),
+            _ => None,
+        }
+    }
+
+    pub fn game_state(&self) -> String {
+        format!("{:?}", self.game.game_state)
+    }
+}
+
+#[pymodule]
+pub fn tictactoe(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
+    m.add_class::<PyGame>()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn next_move() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let game = Bound::new(py, PyGame::new().unwrap()).unwrap();
+            game.call_method1("next_move", (1, 1)).unwrap();
+            game.call_method1("next_move", (0, 0)).unwrap();
+            assert!(game.call_method1("next_move", (1, 1)).is_err());
+
+            let features: Vec<f32> = game
+                .call_method0("to_flat_features")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(features.len(), 27);
+            assert_eq!(features[4], 1.0); // X in the center
+            assert_eq!(features[9], 1.0); // O in the top left corner
+            assert_eq!(features[18..].iter().sum::<f32>(), 7.0);
+
+            let moves: Vec<(usize, usize)> = game
+                .call_method0("available_moves")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(moves.len(), 7);
+            assert!(!moves.contains(&(1, 1)));
+
+            for &(x, y) in &[(0, 1), (2, 0), (2, 1)] {
+                game.call_method1("next_move", (x, y)).unwrap();
+            }
+            let game = game.borrow();
+            assert!(game.is_finished());
+            assert_eq!(game.winner(), Some("X"));
+            assert_eq!(game.game_state(), "XWon");
+        });
+    }
+}
@@ -0,0 +1,277 @@
+use bincode;
+use game::{cell_name, Game, GameState};
+use result::{ProgramError, Result};
+#[cfg(feature = "json")]
+use serde_json;
+use solana_sdk::pubkey::Pubkey;
+
+// Bumped whenever the fields of FinalizedGame change
+pub const RECORD_VERSION: u8 = 1;
+
+// How a finished game came to its outcome
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum EndReason {
+    Line,             // The winner completed a line
+    BoardFull,        // The board filled up without a line
+    Timeout,          // The winner claimed a timeout against an idle opponent
+    Aborted,          // The players voided the game
+    ForfeitByConduct, // The loser reached the game's limit of rejected moves
+}
+
+// Archival record of a finished game, the one format exports, trophies and stats agree on.
+// The binary form is the bincode encoding, always LEN bytes
+#[repr(C)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FinalizedGame {
+    pub version: u8,           // RECORD_VERSION the record was written with
+    pub game: Pubkey,          // Game account
+    pub player_x: Pubkey,      // Player who initialized the game
+    pub player_o: Pubkey,      // Player who joined the game
+    pub outcome: GameState,    // XWon, OWon, Draw or Aborted
+    pub end_reason: EndReason, // How the outcome came about
+    pub winning_line: [u8; 3], // Board indices of the winning line, zero unless ended by Line
+    pub moves: [u8; 9],        // Board index of each move, in the order they were made
+    pub move_count: u8,        // Number of valid entries in `moves`
+    pub created_at: u64,       // Time the game was created, 0 if unknown
+    pub finished_at: u64,      // Time the game ended, 0 if unknown
+}
+
+impl FinalizedGame {
+    // Serialized size of a FinalizedGame
+    pub const LEN: usize = 1 + 32 + 32 + 32 + 4 + 4 + 3 + 9 + 1 + 8 + 8;
+
+    pub fn winning_line(&self) -> Option<[usize; 3]> {
+        match self.end_reason {
+            EndReason::Line => Some([
+                self.winning_line[0] as usize,
+                self.winning_line[1] as usize,
+                self.winning_line[2] as usize,
+            ]),
+            _ => None,
+        }
+    }
+
+    // Moves as space separated cells, in Game::move_notation form
+    pub fn move_notation(&self) -> String {
+        self.moves[..self.move_count as usize]
+            .iter()
+            .map(|&i| cell_name(i as usize))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; FinalizedGame::LEN]> {
+        let mut bytes = [0; FinalizedGame::LEN];
+        bincode::serialize_into(&mut bytes[..], self).map_err(|_| ProgramError::InvalidUserdata)?;
+        Ok(bytes)
+    }
+
+    // Records from other versions are refused rather than misread
+    pub fn from_bytes(bytes: &[u8]) -> Result<FinalizedGame> {
+        if bytes.len() != FinalizedGame::LEN || bytes[0] != RECORD_VERSION {
+            Err(ProgramError::InvalidUserdata)?;
+        }
+        bincode::deserialize(bytes).map_err(|_| ProgramError::InvalidUserdata.into())
+    }
+
+    pub fn view(&self) -> FinalizedGameView {
+        FinalizedGameView {
+            version: self.version,
+            game: self.game.to_string(),
+            player_x: self.player_x.to_string(),
+            player_o: self.player_o.to_string(),
+            outcome: self.outcome,
+            end_reason: self.end_reason,
+            winning_line: self.winning_line(),
+            moves: self.move_notation(),
+            move_count: self.move_count as usize,
+            created_at: self.created_at,
+            finished_at: self.finished_at,
+        }
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(&self.view()).map_err(|_| ProgramError::InvalidUserdata.into())
+    }
+}
+
+// Indexer friendly rendering of a FinalizedGame
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FinalizedGameView {
+    pub version: u8,
+    pub game: String,
+    pub player_x: String,
+    pub player_o: String,
+    pub outcome: GameState,
+    pub end_reason: EndReason,
+    pub winning_line: Option<[usize; 3]>,
+    pub moves: String,
+    pub move_count: usize,
+    pub created_at: u64,
+    pub finished_at: u64,
+}
+
+impl Game {
+    // The archival record of a finished game.  Games still in progress have no outcome to
+    // record yet
+    pub fn finalize_record(&self, game_pubkey: &Pubkey) -> Result<FinalizedGame> {
+        if !self.is_finished() {
+            Err(ProgramError::GameInProgress)?;
+        }
+        let winning_line = self.winning_line();
+        let end_reason = match self.game_state {
+            GameState::Draw => EndReason::BoardFull,
+            GameState::Aborted => EndReason::Aborted,
+            _ if self.forfeited_by_conduct().is_some() => EndReason::ForfeitByConduct,
+            _ if winning_line.is_some() => EndReason::Line,
+            _ => EndReason::Timeout,
+        };
+        let mut line = [0; 3];
+        if let Some(winning_line) = winning_line {
+            for (cell, &i) in line.iter_mut().zip(winning_line.iter()) {
+                *cell = i as u8;
+            }
+        }
+        Ok(FinalizedGame {
+            version: RECORD_VERSION,
+            game: *game_pubkey,
+            player_x: self.player_x,
+            player_o: self.player_o,
+            outcome: self.game_state,
+            end_reason,
+            winning_line: line,
+            moves: self.moves,
+            move_count: self.move_count,
+            created_at: self.created_at,
+            finished_at: self.finished_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::{play, GameConfig};
+    use serde_json;
+
+    fn players() -> (Pubkey, Pubkey) {
+        (Pubkey::new(&[1; 32]), Pubkey::new(&[2; 32]))
+    }
+
+    fn round_trip(record: &FinalizedGame) {
+        let bytes = record.to_bytes().unwrap();
+        assert_eq!(bincode::serialize(record).unwrap(), bytes.to_vec());
+        assert_eq!(&FinalizedGame::from_bytes(&bytes).unwrap(), record);
+    }
+
+    #[test]
+    pub fn win() {
+        let (player_x, player_o) = players();
+        let mut g = Game::create_with_config(&player_x, GameConfig::default(), 3).unwrap();
+        g.join(player_o, 4).unwrap();
+        for &(player, x, y) in &[
+            (player_x, 1, 1),
+            (player_o, 0, 0),
+            (player_x, 2, 0),
+            (player_o, 1, 0),
+            (player_x, 0, 2),
+        ] {
+            g.next_move(player, x, y).unwrap();
+        }
+        g.set_finished_at(9);
+
+        let record = g.finalize_record(&Pubkey::new(&[3; 32])).unwrap();
+        assert_eq!(record.outcome, GameState::XWon);
+        assert_eq!(record.end_reason, EndReason::Line);
+        assert_eq!(record.winning_line(), Some([2, 4, 6]));
+        assert_eq!(record.move_notation(), g.move_notation());
+        assert_eq!((record.created_at, record.finished_at), (3, 9));
+        round_trip(&record);
+    }
+
+    #[test]
+    pub fn draw() {
+        let g = play(&[0, 1, 2, 4, 3, 5, 7, 6, 8]);
+
+        let record = g.finalize_record(&Pubkey::new(&[3; 32])).unwrap();
+        assert_eq!(record.outcome, GameState::Draw);
+        assert_eq!(record.end_reason, EndReason::BoardFull);
+        assert_eq!(record.winning_line(), None);
+        assert_eq!(record.move_count, 9);
+        round_trip(&record);
+    }
+
+    // The game has no resignation; walking away and letting the opponent claim a timeout
+    // is the way to concede
+    #[test]
+    pub fn forfeit() {
+        let (player_x, player_o) = players();
+        let config = GameConfig {
+            move_timeout: 10,
+            ..GameConfig::default()
+        };
+        let mut g = Game::create_with_config(&player_x, config, 1).unwrap();
+        g.join(player_o, 2).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        g.claim_timeout(player_x, 20).unwrap();
+
+        let record = g.finalize_record(&Pubkey::new(&[3; 32])).unwrap();
+        assert_eq!(record.outcome, GameState::XWon);
+        assert_eq!(record.end_reason, EndReason::Timeout);
+        assert_eq!(record.winning_line(), None);
+        round_trip(&record);
+
+        let mut g = Game::new(player_x, player_o);
+        g.request_abort(player_x).unwrap();
+        g.confirm_abort(player_o).unwrap();
+        let record = g.finalize_record(&Pubkey::new(&[3; 32])).unwrap();
+        assert_eq!(record.end_reason, EndReason::Aborted);
+    }
+
+    #[test]
+    pub fn live_games_have_no_record() {
+        let (player_x, player_o) = players();
+        let mut g = Game::create(&player_x);
+        assert_eq!(
+            g.finalize_record(&Pubkey::new(&[3; 32])).unwrap_err().kind,
+            ProgramError::GameInProgress
+        );
+        g.join(player_o, 1).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        assert!(g.finalize_record(&Pubkey::new(&[3; 32])).is_err());
+    }
+
+    #[test]
+    pub fn json_is_stable() {
+        let (player_x, player_o) = players();
+        let mut g = Game::new(player_x, player_o);
+        for &(player, x, y) in &[
+            (player_x, 0, 0),
+            (player_o, 1, 0),
+            (player_x, 0, 1),
+            (player_o, 1, 1),
+            (player_x, 0, 2),
+        ] {
+            g.next_move(player, x, y).unwrap();
+        }
+        let record = g.finalize_record(&Pubkey::new(&[3; 32])).unwrap();
+        assert_eq!(
+            serde_json::to_string(&record.view()).unwrap(),
+            format!(
+                "{{\"version\":1,\"game\":\"{}\",\"player_x\":\"{}\",\"player_o\":\"{}\",\
+                 \"outcome\":\"XWon\",\"end_reason\":\"Line\",\"winning_line\":[0,3,6],\
+                 \"moves\":\"a1 b1 a2 b2 a3\",\"move_count\":5,\"created_at\":0,\
+                 \"finished_at\":0}}",
+                Pubkey::new(&[3; 32]),
+                player_x,
+                player_o
+            )
+        );
+
+        let mut bytes = record.to_bytes().unwrap();
+        bytes[0] = RECORD_VERSION + 1;
+        assert!(FinalizedGame::from_bytes(&bytes).is_err());
+        assert!(FinalizedGame::from_bytes(&bytes[1..]).is_err());
+    }
+}
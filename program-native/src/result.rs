@@ -1,22 +1,3 @@
-use std;
-
-#[derive(Debug)]
-pub enum ProgramError {
-    InvalidInput,
-    GameInProgress,
-    InvalidMove,
-    InvalidUserdata,
-    InvalidTimestamp,
-    NotYourTurn,
-    PlayerNotFound,
-}
-
-pub type Result<T> = std::result::Result<T, ProgramError>;
-
-impl std::fmt::Display for ProgramError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "error")
-    }
-}
-
-impl std::error::Error for ProgramError {}
+#[cfg(feature = "anyhow")]
+pub use errors::GameResultExt;
+pub use errors::{ErrorContext, GameError, ProgramError, Result};
@@ -0,0 +1,96 @@
+// Deterministic randomness for bots, simulations and anything else that needs to be
+// reproducible from a seed
+pub trait GameRng {
+    fn next_u64(&mut self) -> u64;
+
+    // Uniformly chosen element of `items`, None if it is empty
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        // Reject the top of the range that would favor the first items
+        let len = items.len() as u64;
+        let zone = u64::MAX - u64::MAX % len;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return items.get((value % len) as usize);
+            }
+        }
+    }
+}
+
+// SplitMix64, see http://xoshiro.di.unimi.it/splitmix64.c
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn from_seed(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    // Fold a 32 byte seed, such as a hash, into a u64 one.  The bytes are read as little
+    // endian words so a seed gives the same numbers on every platform
+    pub fn from_bytes(seed: &[u8; 32]) -> SplitMix64 {
+        let mut state = 0;
+        for chunk in seed.chunks(8) {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            state ^= u64::from_le_bytes(word);
+        }
+        SplitMix64::from_seed(state)
+    }
+}
+
+impl GameRng for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn known_answers() {
+        let mut rng = SplitMix64::from_seed(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+
+        let mut seed = [0; 32];
+        seed[..8].copy_from_slice(&[0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01]);
+        let mut rng = SplitMix64::from_bytes(&seed);
+        assert_eq!(rng, SplitMix64::from_seed(0x0123_4567_89ab_cdef));
+        assert_eq!(rng.next_u64(), 0x157a_3807_a48f_aa9d);
+        assert_eq!(rng.next_u64(), 0xd573_529b_34a1_d093);
+    }
+
+    #[test]
+    pub fn choose() {
+        let mut rng = SplitMix64::from_seed(42);
+        let empty: [u8; 0] = [];
+        assert_eq!(rng.choose(&empty), None);
+
+        let items = [0, 1, 2, 3, 4, 5];
+        let mut counts = [0u32; 6];
+        let draws = 60_000;
+        for _ in 0..draws {
+            counts[*rng.choose(&items).unwrap()] += 1;
+        }
+        let expected = f64::from(draws) / 6.0;
+        let chi_square: f64 = counts
+            .iter()
+            .map(|&count| (f64::from(count) - expected).powi(2) / expected)
+            .sum();
+        // Well above the 0.1% critical value for 5 degrees of freedom (20.5)
+        assert!(chi_square < 30.0, "chi square {}", chi_square);
+    }
+}
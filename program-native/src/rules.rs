@@ -0,0 +1,1155 @@
+use consts::{BOARD_ITEM_FREE, BOARD_ITEM_O, BOARD_ITEM_X, BOARD_WIDTH, WIN_LINES};
+pub use minimax::pack_board;
+use result::{ErrorContext, GameError, ProgramError, Result};
+#[cfg(feature = "json")]
+use serde_json;
+use solana_sdk::pubkey::Pubkey;
+use state::{Cell, ClockSnapshot, Game, GameState, JoinOutcome, Liveness, Now, Seat, Variant};
+use std;
+use std::sync::OnceLock;
+use trophy::Trophy;
+
+// Rules that only need the board.  Boards are the 9 cells of a game in board index order,
+// y * 3 + x, holding BOARD_ITEM_xyz
+
+// The win lines of a variant
+pub fn lines(variant: Variant) -> &'static [[usize; 3]] {
+    match variant {
+        Variant::Classic | Variant::Wild => &WIN_LINES,
+    }
+}
+
+// The first line of `board` filled with `x_or_o`
+pub fn find_line(variant: Variant, board: &[u8], x_or_o: u8) -> Option<[usize; 3]> {
+    lines(variant)
+        .iter()
+        .find(|line| line.iter().all(|&i| board.get(i) == Some(&x_or_o)))
+        .cloned()
+}
+
+// Board index of the cell in column x, row y.  Coordinates off the board are an InvalidMove
+// rather than aliasing onto another cell, as y * 3 + x alone would map (4, 1) onto (1, 2)
+pub fn cell_index(x: usize, y: usize) -> Result<usize> {
+    if x < BOARD_WIDTH && y < BOARD_WIDTH {
+        Ok(y * BOARD_WIDTH + x)
+    } else {
+        Err(ProgramError::InvalidMove.at(ErrorContext::Coordinate(x, y)))
+    }
+}
+
+// Column and row of a board index, the inverse of cell_index
+pub fn cell_coords(index: usize) -> Result<(usize, usize)> {
+    let (x, y) = (index % BOARD_WIDTH, index / BOARD_WIDTH);
+    cell_index(x, y).map(|_| (x, y))
+}
+
+// Whether (x, y) is on the board and still free
+pub fn is_free(board: &[u8], x: usize, y: usize) -> bool {
+    cell_index(x, y).is_ok_and(|i| board.get(i) == Some(&BOARD_ITEM_FREE))
+}
+
+pub fn is_full(board: &[u8]) -> bool {
+    board.iter().all(|&item| item != BOARD_ITEM_FREE)
+}
+
+// The board pack_board encoded, None for numbers past the last 9 cell board
+pub fn unpack_board(encoded: u32) -> Option<[u8; 9]> {
+    let mut board = [BOARD_ITEM_FREE; 9];
+    let mut rest = encoded;
+    for cell in board.iter_mut() {
+        *cell = (rest % 3) as u8;
+        rest /= 3;
+    }
+    if rest == 0 {
+        Some(board)
+    } else {
+        None
+    }
+}
+
+// Mark of the player to move.  X always moves first, so the mark counts determine who is next
+pub fn mark_to_move(board: &[u8]) -> u8 {
+    let count = |x_or_o| board.iter().filter(|&&item| item == x_or_o).count();
+    if count(BOARD_ITEM_X) > count(BOARD_ITEM_O) {
+        BOARD_ITEM_O
+    } else {
+        BOARD_ITEM_X
+    }
+}
+
+// XWon, OWon or Draw once the board decides the game, None while it is still open
+pub fn outcome(variant: Variant, board: &[u8]) -> Option<GameState> {
+    if find_line(variant, board, BOARD_ITEM_X).is_some() {
+        Some(GameState::XWon)
+    } else if find_line(variant, board, BOARD_ITEM_O).is_some() {
+        Some(GameState::OWon)
+    } else if is_full(board) {
+        Some(GameState::Draw)
+    } else {
+        None
+    }
+}
+
+// A free cell that would complete a line of `x_or_o`
+pub fn completing_cell(variant: Variant, board: &[u8], x_or_o: u8) -> Option<usize> {
+    (0..board.len()).find(|&i| {
+        let mut board = board.to_vec();
+        board[i] == BOARD_ITEM_FREE && {
+            board[i] = x_or_o;
+            find_line(variant, &board, x_or_o).is_some()
+        }
+    })
+}
+
+// Whether some way of filling the rest of `board` ends with neither player completing a line
+pub fn can_be_drawn(variant: Variant, board: &[u8]) -> bool {
+    match outcome(variant, board) {
+        Some(GameState::Draw) => true,
+        Some(_) => false,
+        None => {
+            let x_or_o = mark_to_move(board);
+            let mut board = board.to_vec();
+            (0..board.len()).any(|i| {
+                board[i] == BOARD_ITEM_FREE && {
+                    board[i] = x_or_o;
+                    let drawn = can_be_drawn(variant, &board);
+                    board[i] = BOARD_ITEM_FREE;
+                    drawn
+                }
+            })
+        }
+    }
+}
+
+// What a mutating method is about to do to the game, for ensure_mutable and the transition
+// table
+#[derive(Copy, Clone, Debug, Serialize, PartialEq)]
+pub enum Action {
+    Join,
+    Move,
+    SetName,
+    Swap,
+    Abort,
+    ClaimTimeout,
+    KeepAlive, // Accepted and ignored once the game is over
+    Reset,     // Closes a finished game to start a new one in its account
+    Finalize,  // Settles the trophy of a finished game
+    Freeze,    // Freezes or unfreezes the game for review
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::Join,
+        Action::Move,
+        Action::SetName,
+        Action::Swap,
+        Action::Abort,
+        Action::ClaimTimeout,
+        Action::KeepAlive,
+        Action::Reset,
+        Action::Finalize,
+        Action::Freeze,
+    ];
+
+    // Whether the action is part of playing the game, which cannot happen before player O
+    // joins.  UIs keep the board disabled while none of these are allowed
+    pub fn is_play(self) -> bool {
+        matches!(self, Action::Move | Action::Swap | Action::ClaimTimeout)
+    }
+
+    // The action in snake case, as the diagram labels its edges
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Join => "join",
+            Action::Move => "move",
+            Action::SetName => "set_name",
+            Action::Swap => "swap",
+            Action::Abort => "abort",
+            Action::ClaimTimeout => "claim_timeout",
+            Action::KeepAlive => "keep_alive",
+            Action::Reset => "reset",
+            Action::Finalize => "finalize",
+            Action::Freeze => "freeze",
+        }
+    }
+}
+
+// Everything the engine lets happen to a game that is not frozen, as (from, action, to).  An
+// action allowed in a state has at least one row out of it; actions that leave the state as
+// it is loop back to it.  A move can also end the game for the opponent, when it is the
+// player's last strike under a conduct limit.  reset starts the next game in the account of a
+// finished one
+const TRANSITIONS: [(GameState, Action, GameState); 39] = [
+    (GameState::Waiting, Action::Join, GameState::XMove),
+    (GameState::Waiting, Action::SetName, GameState::Waiting),
+    (GameState::Waiting, Action::Abort, GameState::Aborted),
+    (GameState::Waiting, Action::KeepAlive, GameState::Waiting),
+    (GameState::Waiting, Action::Freeze, GameState::Waiting),
+    (GameState::XMove, Action::Join, GameState::XMove),
+    (GameState::XMove, Action::Move, GameState::OMove),
+    (GameState::XMove, Action::Move, GameState::XWon),
+    (GameState::XMove, Action::Move, GameState::OWon),
+    (GameState::XMove, Action::Move, GameState::Draw),
+    (GameState::XMove, Action::SetName, GameState::XMove),
+    (GameState::XMove, Action::Abort, GameState::XMove),
+    (GameState::XMove, Action::Abort, GameState::Aborted),
+    (GameState::XMove, Action::ClaimTimeout, GameState::OWon),
+    (GameState::XMove, Action::KeepAlive, GameState::XMove),
+    (GameState::XMove, Action::Freeze, GameState::XMove),
+    (GameState::OMove, Action::Join, GameState::OMove),
+    (GameState::OMove, Action::Move, GameState::XMove),
+    (GameState::OMove, Action::Move, GameState::XWon),
+    (GameState::OMove, Action::Move, GameState::OWon),
+    (GameState::OMove, Action::SetName, GameState::OMove),
+    (GameState::OMove, Action::Swap, GameState::OMove),
+    (GameState::OMove, Action::Abort, GameState::OMove),
+    (GameState::OMove, Action::Abort, GameState::Aborted),
+    (GameState::OMove, Action::ClaimTimeout, GameState::XWon),
+    (GameState::OMove, Action::KeepAlive, GameState::OMove),
+    (GameState::OMove, Action::Freeze, GameState::OMove),
+    (GameState::XWon, Action::KeepAlive, GameState::XWon),
+    (GameState::XWon, Action::Reset, GameState::Waiting),
+    (GameState::XWon, Action::Finalize, GameState::XWon),
+    (GameState::OWon, Action::KeepAlive, GameState::OWon),
+    (GameState::OWon, Action::Reset, GameState::Waiting),
+    (GameState::OWon, Action::Finalize, GameState::OWon),
+    (GameState::Draw, Action::KeepAlive, GameState::Draw),
+    (GameState::Draw, Action::Reset, GameState::Waiting),
+    (GameState::Draw, Action::Finalize, GameState::Draw),
+    (GameState::Aborted, Action::KeepAlive, GameState::Aborted),
+    (GameState::Aborted, Action::Reset, GameState::Waiting),
+    (GameState::Aborted, Action::Finalize, GameState::Aborted),
+];
+
+// The state machine of a game, for clients to generate their rules from
+pub fn transition_table() -> &'static [(GameState, Action, GameState)] {
+    &TRANSITIONS
+}
+
+// Whether the transition table has `action` out of `state`.  The engine and the processing
+// layer both go by this, so the table cannot fall behind the rules
+pub fn is_action_allowed(state: GameState, action: Action) -> bool {
+    TRANSITIONS
+        .iter()
+        .any(|&(from, allowed, _)| from == state && allowed == action)
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize)]
+struct Transition {
+    from: GameState,
+    action: Action,
+    to: GameState,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize)]
+struct StateMachine {
+    states: [GameState; 7],
+    actions: [Action; 10],
+    transitions: Vec<Transition>,
+}
+
+// The transition table as JSON, for the web client's build:
+// {"states":[..],"actions":[..],"transitions":[{"from":..,"action":..,"to":..},..]}
+#[cfg(feature = "json")]
+pub fn state_machine_json() -> String {
+    let machine = StateMachine {
+        states: GameState::ALL,
+        actions: Action::ALL,
+        transitions: TRANSITIONS
+            .iter()
+            .map(|&(from, action, to)| Transition { from, action, to })
+            .collect(),
+    };
+    serde_json::to_string(&machine).unwrap()
+}
+
+impl GameState {
+    pub const ALL: [GameState; 7] = [
+        GameState::Waiting,
+        GameState::XMove,
+        GameState::OMove,
+        GameState::XWon,
+        GameState::OWon,
+        GameState::Draw,
+        GameState::Aborted,
+    ];
+
+    // The actions the transition table allows in this state, in Action::ALL order
+    pub fn allowed_actions(&self) -> &'static [Action] {
+        static ALLOWED: OnceLock<Vec<Vec<Action>>> = OnceLock::new();
+        let allowed = ALLOWED.get_or_init(|| {
+            GameState::ALL
+                .iter()
+                .map(|&state| {
+                    Action::ALL
+                        .iter()
+                        .cloned()
+                        .filter(|&action| is_action_allowed(state, action))
+                        .collect()
+                })
+                .collect()
+        });
+        &allowed[*self as usize]
+    }
+}
+
+// Every way of filling the board ends the game, so next_move never leaves a full board with
+// a player to move
+const _: () = assert!(Game::IS_STALEMATE_IMPOSSIBLE);
+
+impl Game {
+    pub const IS_STALEMATE_IMPOSSIBLE: bool = true;
+
+    // Every mutating method checks in here first, so the board and state of a finished game
+    // only change by resetting it, and a frozen game only takes keep alives
+    pub(crate) fn ensure_mutable(&self, action: Action) -> Result<()> {
+        // The first terminal transition to land wins; whatever arrives after it is told
+        // which outcome it lost to
+        if self.is_finished() && !is_action_allowed(self.game_state, action) {
+            Err(ProgramError::GameFinished.at(ErrorContext::Outcome(self.game_state)))?;
+        }
+        if self.frozen && !matches!(action, Action::KeepAlive | Action::Freeze) {
+            Err(ProgramError::GameFrozen)?;
+        }
+        Ok(())
+    }
+
+    // ensure_mutable for `player` playing the game, which has to wait for player O to join
+    pub(crate) fn ensure_playable(&self, action: Action, player: Pubkey) -> Result<()> {
+        self.ensure_mutable(action)?;
+        if self.game_state == GameState::Waiting && action.is_play() {
+            Err(ProgramError::GameNotStarted.at(ErrorContext::Player(player)))?;
+        }
+        Ok(())
+    }
+
+    // Whether the transition table has `action` by `player` out of the game's state, refused
+    // with the error the engine would give.  The processing layer asks before dispatching, so
+    // the table clients build their rules from is the authority on what a game accepts
+    pub fn ensure_allowed(&self, action: Action, player: Pubkey) -> Result<()> {
+        if is_action_allowed(self.game_state, action) {
+            return Ok(());
+        }
+        let kind = match action {
+            _ if self.is_finished() => ProgramError::GameFinished,
+            _ if self.game_state == GameState::Waiting && action.is_play() => {
+                Err(ProgramError::GameNotStarted.at(ErrorContext::Player(player)))?
+            }
+            Action::Reset | Action::Finalize => ProgramError::GameInProgress,
+            Action::Swap => ProgramError::NotYourTurn,
+            _ => ProgramError::InvalidInput,
+        };
+        Err(kind.at(ErrorContext::Outcome(self.game_state)))
+    }
+
+    // Enter a terminal state, remembering the board the game finished with
+    pub(crate) fn finish(&mut self, game_state: GameState) {
+        self.game_state = game_state;
+        self.final_board_hash = self.board_hash() as u32;
+    }
+
+    // Take seat O.  Joining again with the same key succeeds without changing the game, so a
+    // retried transaction is harmless, while losing the race for the seat fails with SeatTaken
+    pub fn join(self: &mut Game, player_o: Pubkey, timestamp: u64) -> Result<JoinOutcome> {
+        self.join_with_clock(player_o, Now::Seconds(timestamp))
+    }
+
+    pub fn join_at(
+        self: &mut Game,
+        player_o: Pubkey,
+        clock: &ClockSnapshot,
+    ) -> Result<JoinOutcome> {
+        self.join_with_clock(player_o, Now::Clock(*clock))
+    }
+
+    fn join_with_clock(self: &mut Game, player_o: Pubkey, now: Now) -> Result<JoinOutcome> {
+        game_log!(
+            "join: player={} now={:?} state={:?}",
+            player_o,
+            now,
+            self.game_state
+        );
+        let result = self.apply_join(player_o, now);
+        self.log_outcome("join", &result);
+        result
+    }
+
+    fn apply_join(self: &mut Game, player_o: Pubkey, now: Now) -> Result<JoinOutcome> {
+        self.ensure_mutable(Action::Join)?;
+        let timestamp = self.reading(now)?;
+        let outcome = JoinOutcome {
+            seat: Seat::O,
+            opponent: self.player_x,
+        };
+        if self.game_state == GameState::Waiting {
+            if self.has_timed_out(timestamp) {
+                Err(ProgramError::TimedOut.at(ErrorContext::Timestamp(timestamp)))?;
+            }
+            if player_o == self.player_x && !self.config.allow_solo {
+                Err(ProgramError::SoloNotAllowed.at(ErrorContext::Player(player_o)))?;
+            }
+            self.player_o = player_o;
+            self.game_state = GameState::XMove;
+
+            if self.config.liveness == Liveness::Sequence {
+                // Sequence numbers start from zero, whatever the clock says
+                Ok(outcome)
+            } else if timestamp <= self.keep_alive.get(Seat::O) {
+                Err(ProgramError::InvalidTimestamp.at(ErrorContext::Timestamp(timestamp)))
+            } else {
+                self.keep_alive.set(Seat::O, timestamp);
+                Ok(outcome)
+            }
+        } else if player_o == self.player_o {
+            Ok(outcome)
+        } else {
+            Err(ProgramError::SeatTaken.at(ErrorContext::Player(self.player_o)))
+        }
+    }
+
+    // Under the swap rule player O may answer X's first move by taking over that move, and
+    // player O's place, leaving the original player X to make O's first move
+    pub fn swap_seats(self: &mut Game, player: Pubkey) -> Result<()> {
+        self.ensure_playable(Action::Swap, player)?;
+        if !self.config.swap_rule {
+            Err(ProgramError::InvalidInput.at(ErrorContext::Player(player)))?;
+        }
+        if self.game_state != GameState::OMove || self.move_count != 1 {
+            Err(ProgramError::NotYourTurn.at(ErrorContext::Player(player)))?;
+        }
+        if player != self.player_o {
+            Err(ProgramError::PlayerNotFound.at(ErrorContext::Player(player)))?;
+        }
+        std::mem::swap(&mut self.player_x, &mut self.player_o);
+        self.keep_alive.swap();
+        self.invalid_attempts.swap(0, 1);
+        Ok(())
+    }
+
+    fn is_winner(&self, x_or_o: u8) -> bool {
+        self.line_of(x_or_o).is_some()
+    }
+
+    fn line_of(&self, x_or_o: u8) -> Option<[usize; 3]> {
+        find_line(self.config.variant, &self.board, x_or_o)
+    }
+
+    // The three board indices that won the game, if it has been won.  A wild line is won by
+    // whoever completed it, whichever mark it is made of
+    pub fn winning_line(&self) -> Option<[usize; 3]> {
+        match self.game_state {
+            GameState::XWon | GameState::OWon if self.config.variant == Variant::Wild => self
+                .line_of(BOARD_ITEM_X)
+                .or_else(|| self.line_of(BOARD_ITEM_O)),
+            GameState::XWon => self.line_of(BOARD_ITEM_X),
+            GameState::OWon => self.line_of(BOARD_ITEM_O),
+            _ => None,
+        }
+    }
+
+    // Whether board index `index` is one of the three cells of the winning_line
+    pub fn is_in_winning_line(&self, index: usize) -> bool {
+        self.winning_line()
+            .is_some_and(|line| line.contains(&index))
+    }
+
+    // Lines filled by X and by O.  The winning move can complete two lines at once
+    pub fn count_winning_lines(&self) -> (u8, u8) {
+        let count = |x_or_o| {
+            lines(self.config.variant)
+                .iter()
+                .filter(|line| line.iter().all(|&i| self.board[i] == x_or_o))
+                .count() as u8
+        };
+        (count(BOARD_ITEM_X), count(BOARD_ITEM_O))
+    }
+
+    // Lines holding two of `mark` and a free third cell, where `mark` wins with the next move.
+    // None for anything but BOARD_ITEM_X or BOARD_ITEM_O
+    pub fn threats(&self, mark: u8) -> Vec<[usize; 3]> {
+        if mark != BOARD_ITEM_X && mark != BOARD_ITEM_O {
+            return vec![];
+        }
+        let count =
+            |line: &[usize; 3], item| line.iter().filter(|&&i| self.board[i] == item).count();
+        lines(self.config.variant)
+            .iter()
+            .filter(|line| count(line, mark) == 2 && count(line, BOARD_ITEM_FREE) == 1)
+            .cloned()
+            .collect()
+    }
+
+    // Lines `mark` could still win on, those without an opponent mark yet.  Zero for anything
+    // but BOARD_ITEM_X or BOARD_ITEM_O
+    pub fn potential_lines_for_player(&self, mark: u8) -> u8 {
+        let opponent = match mark {
+            BOARD_ITEM_X => BOARD_ITEM_O,
+            BOARD_ITEM_O => BOARD_ITEM_X,
+            _ => return 0,
+        };
+        lines(self.config.variant)
+            .iter()
+            .filter(|line| line.iter().all(|&i| self.board[i] != opponent))
+            .count() as u8
+    }
+
+    pub fn next_move(self: &mut Game, player: Pubkey, x: usize, y: usize) -> Result<()> {
+        game_log!(
+            "next_move: player={} x={} y={} state={:?}",
+            player,
+            x,
+            y,
+            self.game_state
+        );
+        let result = self.apply_move(player, x, y);
+        debug_assert!(
+            result.is_err()
+                || !self.is_board_full()
+                || matches!(
+                    self.game_state,
+                    GameState::XWon | GameState::OWon | GameState::Draw
+                )
+        );
+        self.log_outcome("next_move", &result);
+        result
+    }
+
+    // Place `mark` at (x, y) in a wild game.  Either player may place either mark, and
+    // whoever completes a line of either mark wins
+    pub fn next_move_wild(
+        self: &mut Game,
+        player: Pubkey,
+        x: usize,
+        y: usize,
+        mark: Cell,
+    ) -> Result<()> {
+        game_log!(
+            "next_move_wild: player={} x={} y={} mark={:?} state={:?}",
+            player,
+            x,
+            y,
+            mark,
+            self.game_state
+        );
+        let result = self.apply_wild_move(player, x, y, mark);
+        self.log_outcome("next_move_wild", &result);
+        result
+    }
+
+    fn apply_wild_move(
+        self: &mut Game,
+        player: Pubkey,
+        x: usize,
+        y: usize,
+        mark: Cell,
+    ) -> Result<()> {
+        self.ensure_playable(Action::Move, player)?;
+        if self.config.variant != Variant::Wild {
+            Err(ProgramError::WrongVariant)?;
+        }
+        let board_index = cell_index(x, y)?;
+        if self.board[board_index] != BOARD_ITEM_FREE || mark == Cell::Free {
+            Err(ProgramError::InvalidMove.at(ErrorContext::Coordinate(x, y)))?;
+        }
+        if self.forbidden_cell() == Some(board_index) {
+            Err(ProgramError::HandicapViolation.at(ErrorContext::Coordinate(x, y)))?;
+        }
+
+        // The line is won by whoever places its last mark, whichever mark that is
+        let seat = self.player_to_move(player)?;
+        let (next, won_state) = match seat {
+            Seat::X => ((Seat::O, GameState::OMove), GameState::XWon),
+            Seat::O => ((Seat::X, GameState::XMove), GameState::OWon),
+        };
+        self.board[board_index] = mark.item();
+        self.opponent_acted(seat);
+        self.abort_requested = [false; 2];
+        self.moves[self.move_count as usize] = board_index as u8;
+        self.move_count += 1;
+
+        if self.is_winner(mark.item()) {
+            self.finish(won_state);
+        } else if self.is_board_full() {
+            self.finish(GameState::Draw);
+        } else {
+            self.turn = next.0;
+            self.game_state = next.1;
+        }
+        Ok(())
+    }
+
+    // The seat `player` is to move from: NotYourTurn while nobody is to move, PlayerNotFound
+    // for the opponent and for anyone not in the game
+    fn player_to_move(&self, player: Pubkey) -> Result<Seat> {
+        let to_move = match self.seat_to_move() {
+            Some(seat) => seat,
+            None => Err(ProgramError::NotYourTurn.at(ErrorContext::Player(player)))?,
+        };
+        if self.seat_of(&player).ok() != Some(to_move) {
+            Err(ProgramError::PlayerNotFound.at(ErrorContext::Player(player)))?;
+        }
+        Ok(to_move)
+    }
+
+    fn apply_move(self: &mut Game, player: Pubkey, x: usize, y: usize) -> Result<()> {
+        self.ensure_playable(Action::Move, player)?;
+        if self.config.variant == Variant::Wild {
+            Err(ProgramError::WrongVariant)?;
+        }
+        let board_index = cell_index(x, y)?;
+        if self.board[board_index] != BOARD_ITEM_FREE {
+            Err(ProgramError::InvalidMove.at(ErrorContext::Coordinate(x, y)))?;
+        }
+        if self.forbidden_cell() == Some(board_index) {
+            Err(ProgramError::HandicapViolation.at(ErrorContext::Coordinate(x, y)))?;
+        }
+
+        let seat = self.player_to_move(player)?;
+        let (x_or_o, won_state) = match seat {
+            Seat::X => {
+                self.game_state = GameState::OMove;
+                (BOARD_ITEM_X, GameState::XWon)
+            }
+            Seat::O => {
+                self.game_state = GameState::XMove;
+                (BOARD_ITEM_O, GameState::OWon)
+            }
+        };
+        self.board[board_index] = x_or_o;
+        self.opponent_acted(seat);
+        self.abort_requested = [false; 2];
+        self.moves[self.move_count as usize] = board_index as u8;
+        self.move_count += 1;
+
+        let winner = self.is_winner(x_or_o);
+
+        if winner {
+            self.finish(won_state);
+        } else if self.is_board_full() {
+            self.finish(GameState::Draw);
+        }
+
+        Ok(())
+    }
+
+    // Count a move `player` had rejected with `err` against them, if the rejection was their
+    // own doing: a cell already taken, or a move while the opponent is to move.  Under a
+    // conduct limit the attempt that reaches it forfeits the game to the opponent.  Returns
+    // whether the attempt counted
+    pub fn record_invalid_attempt(self: &mut Game, player: Pubkey, err: GameError) -> bool {
+        let to_move = match self.seat_to_move() {
+            Some(seat) => seat,
+            None => return false,
+        };
+        if self.ensure_mutable(Action::Move).is_err() {
+            return false;
+        }
+        let seat = match self.seat_of(&player) {
+            Ok(seat) => seat,
+            Err(_) => return false,
+        };
+        let conduct = match (err.kind, err.context) {
+            (ProgramError::InvalidMove, ErrorContext::Coordinate(x, y)) => {
+                cell_index(x, y).is_ok() && !is_free(&self.board, x, y)
+            }
+            (ProgramError::PlayerNotFound, ErrorContext::Player(_)) => seat != to_move,
+            _ => false,
+        };
+        if !conduct {
+            return false;
+        }
+
+        let attempts = &mut self.invalid_attempts[seat.index()];
+        *attempts = attempts.saturating_add(1);
+        let limit = self.config.conduct_limit;
+        if limit > 0 && *attempts >= limit {
+            self.finish(match seat {
+                Seat::X => GameState::OWon,
+                Seat::O => GameState::XWon,
+            });
+        }
+        true
+    }
+
+    // The player in `seat`
+    pub fn player(&self, seat: Seat) -> Pubkey {
+        match seat {
+            Seat::X => self.player_x,
+            Seat::O => self.player_o,
+        }
+    }
+
+    // The seat `player` holds.  Player O holds theirs once they joined.  A solo player holds
+    // both, and is taken to sit in the seat whose turn it is, or in X's seat when it is nobody's
+    // turn
+    pub fn seat_of(&self, player: &Pubkey) -> Result<Seat> {
+        let joined = self.game_state != GameState::Waiting;
+        let to_move = self.seat_to_move().unwrap_or(Seat::X);
+        if *player == self.player(to_move) {
+            Ok(to_move)
+        } else if *player == self.player_x {
+            Ok(Seat::X)
+        } else if joined && *player == self.player_o {
+            Ok(Seat::O)
+        } else {
+            Err(ProgramError::PlayerNotFound.at(ErrorContext::Player(*player)))
+        }
+    }
+
+    // The seat to move, None unless the game is being played
+    pub(crate) fn seat_to_move(&self) -> Option<Seat> {
+        match self.game_state {
+            GameState::XMove => Some(Seat::X),
+            GameState::OMove => Some(Seat::O),
+            _ => None,
+        }
+    }
+
+    // Place the mark of whoever is to move, for harnesses building positions without the
+    // players' keys.  Everything but the player check still applies
+    #[cfg(any(test, feature = "testing"))]
+    pub fn apply_instruction_unchecked(&mut self, x: usize, y: usize) -> Result<()> {
+        // Player X makes the first move once the game has started
+        self.ensure_playable(Action::Move, self.player_x)?;
+        let player = self.current_player().ok_or(ProgramError::NotYourTurn)?;
+        self.apply_move(player, x, y)
+    }
+
+    // Ask to void the game.  Player X may abort a game nobody has joined yet on their own, and
+    // so may a solo player.  Otherwise the opponent has to confirm_abort before the next move
+    pub fn request_abort(self: &mut Game, player: Pubkey) -> Result<()> {
+        self.ensure_mutable(Action::Abort)?;
+        let seat = self.seat_of(&player)?;
+        if self.game_state == GameState::Waiting || self.player_o == self.player_x {
+            self.finish(GameState::Aborted);
+        } else {
+            self.abort_requested[seat.index()] = true;
+        }
+        Ok(())
+    }
+
+    // Agree to the abort requested by the opponent of `player`
+    pub fn confirm_abort(self: &mut Game, player: Pubkey) -> Result<()> {
+        self.ensure_mutable(Action::Abort)?;
+        if self.game_state == GameState::Waiting {
+            Err(ProgramError::GameNotStarted.at(ErrorContext::Player(player)))?;
+        }
+        let requested = match self.game_state {
+            GameState::XMove | GameState::OMove => {
+                self.abort_requested[self.seat_of(&player)?.opponent().index()]
+            }
+            _ => false,
+        };
+        if !requested {
+            Err(ProgramError::NotYourTurn.at(ErrorContext::Player(player)))?;
+        }
+        self.finish(GameState::Aborted);
+        Ok(())
+    }
+
+    // Void the board of a linked match that was just won on its other board.  The processing
+    // layer does this in the instruction that finished the winning board
+    pub(crate) fn void_as_sibling(self: &mut Game) -> Result<()> {
+        self.ensure_mutable(Action::Abort)?;
+        self.finish(GameState::Aborted);
+        Ok(())
+    }
+
+    // Stop play while a moderator reviews the game.  Keep alives are still accepted, and
+    // nobody can claim a timeout while the game is frozen, so a freeze costs neither player
+    // the game.  The processing layer checks that `moderator` moderates the dashboard, who is
+    // only named in the log
+    #[cfg_attr(not(feature = "logging"), allow(unused_variables))]
+    pub fn freeze(self: &mut Game, moderator: Pubkey) -> Result<()> {
+        self.ensure_mutable(Action::Freeze)?;
+        game_log!(
+            "freeze: moderator={} state={:?}",
+            moderator,
+            self.game_state
+        );
+        self.frozen = true;
+        Ok(())
+    }
+
+    // Resume play at the position the game was frozen in
+    #[cfg_attr(not(feature = "logging"), allow(unused_variables))]
+    pub fn unfreeze(self: &mut Game, moderator: Pubkey) -> Result<()> {
+        self.ensure_mutable(Action::Freeze)?;
+        game_log!(
+            "unfreeze: moderator={} state={:?}",
+            moderator,
+            self.game_state
+        );
+        self.frozen = false;
+        Ok(())
+    }
+
+    // Check that the game is internally consistent: only known board items, a mark count that
+    // alternating play can produce, and a game state that agrees with the board
+    pub fn validate(&self) -> Result<()> {
+        if self
+            .board
+            .iter()
+            .any(|&item| item != BOARD_ITEM_FREE && item != BOARD_ITEM_X && item != BOARD_ITEM_O)
+        {
+            Err(ProgramError::InvalidBoardState)?;
+        }
+
+        let wild = self.config.variant == Variant::Wild;
+        let count = |x_or_o| self.board.iter().filter(|&&item| item == x_or_o).count();
+        let (mut x_count, mut o_count) = (count(BOARD_ITEM_X), count(BOARD_ITEM_O));
+        let (mut x_won, mut o_won) = (self.is_winner(BOARD_ITEM_X), self.is_winner(BOARD_ITEM_O));
+        let full = x_count + o_count == self.board.len();
+        if wild {
+            // The marks do not tell whose moves they were.  X still makes the even numbered
+            // moves, and a line goes to whoever made the last one
+            let marks = x_count + o_count;
+            let line = x_won || o_won;
+            x_count = marks.div_ceil(2);
+            o_count = marks / 2;
+            x_won = line && marks % 2 == 1;
+            o_won = line && marks % 2 == 0;
+            let turn = match self.game_state {
+                GameState::XMove => Seat::X,
+                GameState::OMove => Seat::O,
+                _ => self.turn,
+            };
+            if turn != self.turn {
+                Err(ProgramError::InvalidBoardState)?;
+            }
+        }
+
+        let forfeit = self.forfeited_by_conduct().is_some();
+        let consistent = match self.game_state {
+            GameState::Waiting => x_count == 0 && o_count == 0,
+            GameState::XMove => x_count == o_count && !x_won && !o_won,
+            GameState::OMove => x_count == o_count + 1 && !x_won && !o_won && !full,
+            // A game won by timeout ends without a line, with the loser to move
+            // and so does a forfeit, with either player to move
+            GameState::XWon => {
+                (x_count == o_count + 1 || forfeit && x_count == o_count)
+                    && !o_won
+                    && (x_won || !full)
+            }
+            GameState::OWon => {
+                (x_count == o_count || forfeit && x_count == o_count + 1)
+                    && !x_won
+                    && (o_won || !full)
+            }
+            GameState::Draw => full && !x_won && !o_won,
+            GameState::Aborted => {
+                (x_count == o_count || x_count == o_count + 1) && !x_won && !o_won
+            }
+        };
+        if !consistent || self.move_count as usize != x_count + o_count {
+            Err(ProgramError::InvalidBoardState)?;
+        }
+
+        // X makes the even numbered moves and O the odd ones, with their own marks unless the
+        // game is wild
+        for (i, &board_index) in self.moves[..self.move_count as usize].iter().enumerate() {
+            let x_or_o = if i % 2 == 0 {
+                BOARD_ITEM_X
+            } else {
+                BOARD_ITEM_O
+            };
+            let item = self.board.get(board_index as usize);
+            let placed = match item {
+                Some(&item) if wild => item != BOARD_ITEM_FREE,
+                item => item == Some(&x_or_o),
+            };
+            if !placed {
+                let context = match cell_coords(board_index as usize) {
+                    Ok((x, y)) => ErrorContext::Coordinate(x, y),
+                    Err(_) => ErrorContext::None,
+                };
+                Err(ProgramError::InvalidBoardState.at(context))?;
+            }
+        }
+
+        if self.is_finished() && self.final_board_hash != self.board_hash() as u32 {
+            Err(ProgramError::InvalidBoardState)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        match self.game_state {
+            GameState::XWon | GameState::OWon | GameState::Draw | GameState::Aborted => true,
+            GameState::Waiting | GameState::XMove | GameState::OMove => false,
+        }
+    }
+
+    // The player whose turn it is, if the game is in progress
+    pub fn current_player(&self) -> Option<Pubkey> {
+        match self.game_state {
+            GameState::XMove => Some(self.player_x),
+            GameState::OMove => Some(self.player_o),
+            _ => None,
+        }
+    }
+
+    // The free cell the player to move may not take, the center for X's first move in a game
+    // with the center handicap
+    pub fn forbidden_cell(&self) -> Option<usize> {
+        if self.config.center_handicap
+            && self.game_state == GameState::XMove
+            && self.move_count() == 0
+        {
+            Some(4)
+        } else {
+            None
+        }
+    }
+
+    // Free cells as (x, y) that the player to move may take, in board order.  Empty once the
+    // game is over
+    pub fn available_moves(&self) -> Vec<(usize, usize)> {
+        if self.marks_to_move().is_none() {
+            return vec![];
+        }
+        let forbidden = self.forbidden_cell();
+        (0..self.board.len())
+            .filter(|&i| self.board[i] == BOARD_ITEM_FREE && Some(i) != forbidden)
+            .filter_map(|i| cell_coords(i).ok())
+            .collect()
+    }
+
+    // Free cells as bits, bit i set when board[i] is free.  Unlike available_moves this only
+    // looks at the board, not at whether anyone is to move
+    pub fn legal_moves_bitmask(&self) -> u16 {
+        self.board
+            .iter()
+            .enumerate()
+            .filter(|&(_, &item)| item == BOARD_ITEM_FREE)
+            .fold(0, |mask, (i, _)| mask | 1 << i)
+    }
+
+    // The cells of a legal_moves_bitmask as (x, y), in board order.  Bits past the board are
+    // ignored
+    pub fn move_from_bitmask(mask: u16) -> Vec<(usize, usize)> {
+        (0..9)
+            .filter(|&i| mask & 1 << i != 0)
+            .filter_map(|i| cell_coords(i).ok())
+            .collect()
+    }
+
+    // Marks of the player to move and of their opponent
+    pub(crate) fn marks_to_move(&self) -> Option<(u8, u8)> {
+        match self.game_state {
+            GameState::XMove => Some((BOARD_ITEM_X, BOARD_ITEM_O)),
+            GameState::OMove => Some((BOARD_ITEM_O, BOARD_ITEM_X)),
+            _ => None,
+        }
+    }
+
+    // Whether playing (x, y) wins the game for the player to move
+    pub fn is_winning_move(&self, x: usize, y: usize) -> bool {
+        self.completes_line(x, y, |(mine, _)| mine)
+    }
+
+    // Whether playing (x, y) occupies a cell the opponent needs to win on their next move
+    pub fn is_blocking_move(&self, x: usize, y: usize) -> bool {
+        self.completes_line(x, y, |(_, theirs)| theirs)
+    }
+
+    fn completes_line(&self, x: usize, y: usize, mark: fn((u8, u8)) -> u8) -> bool {
+        match (self.marks_to_move(), cell_index(x, y)) {
+            (Some(marks), Ok(board_index)) if self.board[board_index] == BOARD_ITEM_FREE => {
+                let mut board = self.board;
+                board[board_index] = mark(marks);
+                find_line(self.config.variant, &board, mark(marks)).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    // Produce the Trophy for a won game.  Each game awards at most one Trophy, and drawn or
+    // aborted games award none
+    pub fn finalize_with_trophy(
+        self: &mut Game,
+        game_pubkey: &Pubkey,
+        timestamp: u64,
+    ) -> Result<Option<Trophy>> {
+        self.ensure_mutable(Action::Finalize)?;
+        if !self.is_finished() {
+            Err(ProgramError::GameInProgress.at(ErrorContext::Outcome(self.game_state)))?;
+        }
+        let record = self.finalize_record(game_pubkey)?;
+        let trophy = match Trophy::from_record(&record, self.board, timestamp) {
+            Some(trophy) => trophy,
+            None => return Ok(None),
+        };
+        if self.trophy_minted {
+            Err(ProgramError::TrophyAlreadyMinted)?;
+        }
+
+        self.trophy_minted = true;
+        Ok(Some(trophy))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use consts::win_lines;
+    use rng::{GameRng, SplitMix64};
+    use std::collections::BTreeSet;
+
+    // Every triple of cells of an n×n board that are evenly spaced, one step apart along a
+    // row, column or diagonal, found by trying them all
+    fn brute_force_lines(n: usize) -> BTreeSet<[usize; 3]> {
+        let step = |a: usize, b: usize| {
+            (
+                (b % n) as isize - (a % n) as isize,
+                (b / n) as isize - (a / n) as isize,
+            )
+        };
+        let mut lines = BTreeSet::new();
+        for a in 0..n * n {
+            for b in a + 1..n * n {
+                for c in b + 1..n * n {
+                    let (dx, dy) = step(a, b);
+                    if step(b, c) == (dx, dy) && dx.abs() <= 1 && dy.abs() <= 1 {
+                        lines.insert([a, b, c]);
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    fn sorted(lines: &[[usize; 3]]) -> BTreeSet<[usize; 3]> {
+        lines
+            .iter()
+            .map(|line| {
+                let mut line = *line;
+                line.sort();
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn win_line_tables() {
+        const CLASSIC: [[usize; 3]; 8] = win_lines::<3, 3, 8>();
+        const FOUR_BY_FOUR: [[usize; 3]; 24] = win_lines::<4, 3, 24>();
+        assert_eq!(CLASSIC, WIN_LINES);
+        assert_eq!(sorted(&WIN_LINES), brute_force_lines(3));
+        assert_eq!(sorted(&FOUR_BY_FOUR), brute_force_lines(4));
+        assert_eq!(
+            win_lines::<4, 4, 10>()[8..],
+            [[0, 5, 10, 15], [3, 6, 9, 12]]
+        );
+    }
+
+    // Random games checked against the win test as it was written before the table, one
+    // condition per line
+    #[test]
+    pub fn win_lines_differential() {
+        fn same(x_or_o: u8, triple: &[u8]) -> bool {
+            triple.iter().all(|&i| i == x_or_o)
+        }
+        fn won(b: &[u8; 9], x_or_o: u8) -> bool {
+            same(x_or_o, &b[0..3])
+                || same(x_or_o, &b[3..6])
+                || same(x_or_o, &b[6..9])
+                || same(x_or_o, &[b[0], b[3], b[6]])
+                || same(x_or_o, &[b[1], b[4], b[7]])
+                || same(x_or_o, &[b[2], b[5], b[8]])
+                || same(x_or_o, &[b[0], b[4], b[8]])
+                || same(x_or_o, &[b[2], b[4], b[6]])
+        }
+
+        let (player_x, player_o) = (Pubkey::new(&[1; 32]), Pubkey::new(&[2; 32]));
+        let mut rng = SplitMix64::from_seed(54);
+        for _ in 0..500 {
+            let mut game = Game::new(player_x, player_o);
+            let mut board = [BOARD_ITEM_FREE; 9];
+            let mut x_or_o = BOARD_ITEM_X;
+            loop {
+                let free: Vec<usize> = (0..9).filter(|&i| board[i] == BOARD_ITEM_FREE).collect();
+                let i = *rng.choose(&free).unwrap();
+                let player = if x_or_o == BOARD_ITEM_X {
+                    player_x
+                } else {
+                    player_o
+                };
+                let (x, y) = cell_coords(i).unwrap();
+                game.next_move(player, x, y).unwrap();
+                board[i] = x_or_o;
+
+                let expected = if won(&board, x_or_o) {
+                    if x_or_o == BOARD_ITEM_X {
+                        GameState::XWon
+                    } else {
+                        GameState::OWon
+                    }
+                } else if free.len() == 1 {
+                    GameState::Draw
+                } else if x_or_o == BOARD_ITEM_X {
+                    GameState::OMove
+                } else {
+                    GameState::XMove
+                };
+                assert_eq!(game.game_state, expected);
+                assert_eq!(
+                    outcome(Variant::Classic, &board).is_some(),
+                    game.is_finished()
+                );
+                if game.is_finished() {
+                    break;
+                }
+                x_or_o = BOARD_ITEM_X + BOARD_ITEM_O - x_or_o;
+            }
+        }
+    }
+
+    #[test]
+    pub fn board_rules() {
+        let mut board = [BOARD_ITEM_FREE; 9];
+        assert_eq!(mark_to_move(&board), BOARD_ITEM_X);
+        assert!(is_free(&board, 2, 2));
+        assert!(!is_free(&board, 3, 0));
+        assert_eq!(outcome(Variant::Classic, &board), None);
+
+        board[0] = BOARD_ITEM_X;
+        board[4] = BOARD_ITEM_O;
+        board[1] = BOARD_ITEM_X;
+        assert_eq!(mark_to_move(&board), BOARD_ITEM_O);
+        assert!(!is_free(&board, 1, 0));
+        assert_eq!(
+            completing_cell(Variant::Classic, &board, BOARD_ITEM_X),
+            Some(2)
+        );
+        assert_eq!(
+            completing_cell(Variant::Classic, &board, BOARD_ITEM_O),
+            None
+        );
+
+        board[2] = BOARD_ITEM_X;
+        assert_eq!(
+            find_line(Variant::Classic, &board, BOARD_ITEM_X),
+            Some([0, 1, 2])
+        );
+        assert_eq!(find_line(Variant::Classic, &board, BOARD_ITEM_O), None);
+        assert_eq!(outcome(Variant::Classic, &board), Some(GameState::XWon));
+        assert!(!is_full(&board));
+
+        /*
+            X|O|X
+            -+-+-
+            X|O|O
+            -+-+-
+            O|X|X
+        */
+        let board = [1, 2, 1, 1, 2, 2, 2, 1, 1];
+        assert!(is_full(&board));
+        assert_eq!(outcome(Variant::Classic, &board), Some(GameState::Draw));
+
+        // Short boards are never won
+        assert_eq!(find_line(Variant::Classic, &[1, 1], BOARD_ITEM_X), None);
+    }
+}
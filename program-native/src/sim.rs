@@ -0,0 +1,176 @@
+use game::{Game, GameState};
+use record::FinalizedGame;
+use result::Result;
+use rng::{GameRng, SplitMix64};
+#[cfg(feature = "json")]
+use serde_json;
+use solana_sdk::pubkey::Pubkey;
+#[cfg(feature = "json")]
+use std::io::BufRead;
+use std::io::{self, Write};
+
+// Result of a finished game, as recorded by simulations and tournaments
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CompletedGame {
+    pub player_x: String,   // Pubkey or strategy name of player X
+    pub player_o: String,   // Pubkey or strategy name of player O
+    pub outcome: GameState, // Final state of the game
+    pub moves: String,      // Moves in Game::move_notation form
+    pub move_count: u8,     // Number of moves played
+    pub seed: u64,          // Seed the game was played with
+}
+
+impl CompletedGame {
+    // The finished `game`, through its FinalizedGame record.  Games still in progress fail
+    // like Game::finalize_record
+    pub fn new(player_x: &str, player_o: &str, game: &Game, seed: u64) -> Result<CompletedGame> {
+        let record = game.finalize_record(&Pubkey::default())?;
+        Ok(CompletedGame::from_record(
+            player_x, player_o, &record, seed,
+        ))
+    }
+
+    // The players go by the names the simulation gave them rather than their keys
+    pub fn from_record(
+        player_x: &str,
+        player_o: &str,
+        record: &FinalizedGame,
+        seed: u64,
+    ) -> CompletedGame {
+        CompletedGame {
+            player_x: player_x.to_string(),
+            player_o: player_o.to_string(),
+            outcome: record.outcome,
+            moves: record.move_notation(),
+            move_count: record.move_count,
+            seed,
+        }
+    }
+}
+
+// Play a game between two bots that pick uniformly random moves
+pub fn random_game(seed: u64) -> CompletedGame {
+    let player_x = Pubkey::new(&[1; 32]);
+    let player_o = Pubkey::new(&[2; 32]);
+    let mut rng = SplitMix64::from_seed(seed);
+    let mut game = Game::create(&player_x);
+    game.join(player_o, 1).unwrap();
+    while let Some(player) = game.current_player() {
+        let (x, y) = *rng.choose(&game.available_moves()).unwrap();
+        game.next_move(player, x, y).unwrap();
+    }
+    CompletedGame::new("random", "random", &game, seed).expect("a random game plays to the end")
+}
+
+// One JSON object per line
+#[cfg(feature = "json")]
+pub fn export_jsonl<W: Write>(games: &[CompletedGame], mut w: W) -> io::Result<()> {
+    for game in games {
+        serde_json::to_writer(&mut w, game)?;
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+pub fn load_jsonl<R: BufRead>(r: R) -> io::Result<Vec<CompletedGame>> {
+    let mut games = vec![];
+    for line in r.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        games.push(serde_json::from_str(&line)?);
+    }
+    Ok(games)
+}
+
+pub fn export_csv<W: Write>(games: &[CompletedGame], mut w: W) -> io::Result<()> {
+    writeln!(w, "player_x,player_o,outcome,moves,move_count,seed")?;
+    for game in games {
+        writeln!(
+            w,
+            "{},{},{:?},{},{},{}",
+            csv_field(&game.player_x),
+            csv_field(&game.player_o),
+            game.outcome,
+            csv_field(&game.moves),
+            game.move_count,
+            game.seed
+        )?;
+    }
+    Ok(())
+}
+
+// Quote a field if it contains a separator, quote or line break, doubling any quotes
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn games() -> Vec<CompletedGame> {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 1, 1).unwrap();
+        g.next_move(player_x, 1, 0).unwrap();
+        g.next_move(player_o, 2, 2).unwrap();
+        g.next_move(player_x, 2, 0).unwrap();
+        vec![
+            CompletedGame::new("minimax", "random, seeded", &g, 7).unwrap(),
+            CompletedGame::new("\"quoted\"", "random", &g, 8).unwrap(),
+        ]
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    pub fn jsonl_round_trip() {
+        let games = games();
+        let mut out = vec![];
+        export_jsonl(&games, &mut out).unwrap();
+        assert_eq!(load_jsonl(&out[..]).unwrap(), games);
+
+        // The schema other tools rely on
+        let first = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            first,
+            r#"{"player_x":"minimax","player_o":"random, seeded","outcome":"XWon","moves":"a1 b2 b1 c3 c1","move_count":5,"seed":7}"#
+        );
+    }
+
+    #[test]
+    pub fn csv_escaping() {
+        let mut out = vec![];
+        export_csv(&games(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "player_x,player_o,outcome,moves,move_count,seed\n\
+             minimax,\"random, seeded\",XWon,a1 b2 b1 c3 c1,5,7\n\
+             \"\"\"quoted\"\"\",random,XWon,a1 b2 b1 c3 c1,5,8\n"
+        );
+        assert_eq!(csv_field("a1, b2"), "\"a1, b2\"");
+    }
+
+    #[test]
+    pub fn random_game() {
+        let game = super::random_game(3);
+        assert_eq!(super::random_game(3), game);
+        assert!(game.move_count >= 5);
+        assert!((0..16).any(|seed| super::random_game(seed).moves != game.moves));
+        let live = Game::new(Pubkey::new(&[1; 32]), Pubkey::new(&[2; 32]));
+        assert!(CompletedGame::new("random", "random", &live, 3).is_err());
+    }
+}
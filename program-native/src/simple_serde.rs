@@ -1,12 +1,12 @@
 use bincode;
-use result::{ProgramError, Result};
+use result::{GameError, ProgramError, Result};
 use serde;
 use std::mem::size_of;
 
 pub trait SimpleSerde {
-    fn map_to_invalid_args(err: std::boxed::Box<bincode::ErrorKind>) -> ProgramError {
+    fn map_to_invalid_args(err: std::boxed::Box<bincode::ErrorKind>) -> GameError {
         warn!("invalid argument: {:?}", err);
-        ProgramError::InvalidUserdata
+        ProgramError::InvalidUserdata.into()
     }
 
     fn deserialize<'a>(input: &'a [u8]) -> Result<Self>
@@ -19,7 +19,7 @@ pub trait SimpleSerde {
                 input.len(),
                 size_of::<Self>()
             );
-            Err(ProgramError::InvalidUserdata)
+            Err(ProgramError::InvalidUserdata.into())
         } else {
             bincode::deserialize(input).map_err(Self::map_to_invalid_args)
         }
@@ -35,7 +35,7 @@ pub trait SimpleSerde {
                 output.len(),
                 size_of::<Self>()
             );
-            Err(ProgramError::InvalidUserdata)
+            Err(ProgramError::InvalidUserdata.into())
         } else {
             let writer = std::io::BufWriter::new(output);
             bincode::serialize_into(writer, self).map_err(Self::map_to_invalid_args)
@@ -0,0 +1,181 @@
+use bincode;
+use game::Game;
+use result::{GameError, ProgramError};
+use solana_sdk::hash::{hash, Hash};
+use std;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+// Snapshot file layout: MAGIC | LAYOUT_VERSION | payload length (u32 LE) | payload | checksum,
+// where the payload is the bincode serialized Game and the checksum is the SHA-256 of the payload
+const MAGIC: &[u8; 4] = b"TTTG";
+const LAYOUT_VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 4;
+const CHECKSUM_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u8),
+    BadChecksum,
+    InvalidGame(GameError),
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> SnapshotError {
+        SnapshotError::Io(err)
+    }
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+pub type Result<T> = std::result::Result<T, SnapshotError>;
+
+impl Game {
+    pub fn to_snapshot(&self) -> Result<Vec<u8>> {
+        let payload = bincode::serialize(self)
+            .map_err(|_| SnapshotError::InvalidGame(ProgramError::InvalidUserdata.into()))?;
+        let mut snapshot = Vec::with_capacity(HEADER_LEN + payload.len() + CHECKSUM_LEN);
+        snapshot.extend_from_slice(MAGIC);
+        snapshot.push(LAYOUT_VERSION);
+        snapshot.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        snapshot.extend_from_slice(&payload);
+        snapshot.extend_from_slice(hash(&payload).as_ref());
+        Ok(snapshot)
+    }
+
+    pub fn from_snapshot(snapshot: &[u8]) -> Result<Game> {
+        if snapshot.len() < HEADER_LEN {
+            Err(SnapshotError::Truncated)?;
+        }
+        if &snapshot[0..4] != MAGIC {
+            Err(SnapshotError::BadMagic)?;
+        }
+        if snapshot[4] != LAYOUT_VERSION {
+            Err(SnapshotError::UnsupportedVersion(snapshot[4]))?;
+        }
+
+        let mut len = [0; 4];
+        len.copy_from_slice(&snapshot[5..HEADER_LEN]);
+        let payload_end = HEADER_LEN + u32::from_le_bytes(len) as usize;
+        if snapshot.len() < payload_end + CHECKSUM_LEN {
+            Err(SnapshotError::Truncated)?;
+        }
+        let payload = &snapshot[HEADER_LEN..payload_end];
+        if hash(payload) != Hash::new(&snapshot[payload_end..payload_end + CHECKSUM_LEN]) {
+            Err(SnapshotError::BadChecksum)?;
+        }
+
+        let game: Game = bincode::deserialize(payload)
+            .map_err(|_| SnapshotError::InvalidGame(ProgramError::InvalidUserdata.into()))?;
+        game.validate().map_err(SnapshotError::InvalidGame)?;
+        Ok(game)
+    }
+
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        File::create(path)?.write_all(&self.to_snapshot()?)?;
+        Ok(())
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Game> {
+        let mut snapshot = vec![];
+        File::open(path)?.read_to_end(&mut snapshot)?;
+        Game::from_snapshot(&snapshot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::GameState;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn game_in_progress() -> (Game, Pubkey, Pubkey) {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::new(player_x, player_o);
+        g.next_move(player_x, 0, 0).unwrap();
+        g.next_move(player_o, 1, 0).unwrap();
+        g.next_move(player_x, 0, 1).unwrap();
+        (g, player_x, player_o)
+    }
+
+    #[test]
+    pub fn round_trip() {
+        let (g, player_x, player_o) = game_in_progress();
+        let path = std::env::temp_dir().join(format!("tictactoe-{}.snapshot", std::process::id()));
+
+        g.save_to(&path).unwrap();
+        let mut resumed = Game::load_from(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed, g);
+
+        // The resumed game continues where it left off
+        resumed.next_move(player_o, 1, 1).unwrap();
+        resumed.next_move(player_x, 0, 2).unwrap();
+        assert_eq!(resumed.game_state, GameState::XWon);
+    }
+
+    #[test]
+    pub fn corruption() {
+        let (g, _, _) = game_in_progress();
+        let snapshot = g.to_snapshot().unwrap();
+
+        match Game::from_snapshot(&snapshot[..3]) {
+            Err(SnapshotError::Truncated) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match Game::from_snapshot(&snapshot[..snapshot.len() - 1]) {
+            Err(SnapshotError::Truncated) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let mut bad_magic = snapshot.clone();
+        bad_magic[0] = b'X';
+        match Game::from_snapshot(&bad_magic) {
+            Err(SnapshotError::BadMagic) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let mut bad_version = snapshot.clone();
+        bad_version[4] = LAYOUT_VERSION + 1;
+        match Game::from_snapshot(&bad_version) {
+            Err(SnapshotError::UnsupportedVersion(_)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let mut bad_checksum = snapshot.clone();
+        bad_checksum[HEADER_LEN] ^= 0xff;
+        match Game::from_snapshot(&bad_checksum) {
+            Err(SnapshotError::BadChecksum) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        match Game::load_from(std::env::temp_dir().join("tictactoe-missing.snapshot")) {
+            Err(SnapshotError::Io(_)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    pub fn invalid_game() {
+        let (mut g, _, _) = game_in_progress();
+        g.game_state = GameState::Draw;
+        match Game::from_snapshot(&g.to_snapshot().unwrap()) {
+            Err(SnapshotError::InvalidGame(GameError {
+                kind: ProgramError::InvalidBoardState,
+                ..
+            })) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,914 @@
+use bincode;
+#[cfg(feature = "logging")]
+use consts::LOG_TARGET;
+use consts::{
+    BOARD_ITEM_FREE, BOARD_ITEM_O, BOARD_ITEM_X, FNV_OFFSET_BASIS, FNV_PRIME, NO_TIMEOUT,
+    POSITION_COUNT,
+};
+use minimax::{minimax, pack_board};
+use player_name::PlayerName;
+use result::{ErrorContext, ProgramError, Result};
+#[cfg(feature = "msgpack")]
+use rmp_serde;
+use rules::{cell_coords, cell_index, outcome, unpack_board, Action};
+#[cfg(feature = "json")]
+use serde_json;
+use solana_sdk::pubkey::Pubkey;
+use std;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::OnceLock;
+
+// Name of a cell in move notation: column a-c followed by row 1-3
+pub fn cell_name(board_index: usize) -> String {
+    match cell_coords(board_index) {
+        Ok((x, y)) => format!("{}{}", (b'a' + x as u8) as char, y + 1),
+        Err(_) => "??".to_string(),
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Variant {
+    Classic, // Three in a row on a 3x3 board
+    Wild,    // Classic board and lines, but either player may place either mark
+}
+
+impl Variant {
+    // Cells on the board, and so the most moves a game can have
+    pub fn cell_count(self) -> u8 {
+        match self {
+            Variant::Classic | Variant::Wild => 9,
+        }
+    }
+}
+
+// How players prove they are still around.  Timestamp mode compares keep alive timestamps
+// against the config timeouts.  Sequence mode has players send strictly increasing sequence
+// numbers instead, and a player may claim a timeout once they have sent `heartbeat_limit`
+// heartbeats without the opponent doing anything
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Liveness {
+    Timestamp,
+    Sequence,
+}
+
+// What the timeouts of a game are counted in.  Encoded as a single byte, which keeps a
+// GameConfig small enough for an instruction
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum TimeoutUnit {
+    Seconds, // Unix timestamps
+    Slots,   // Slot numbers, steadier than validator clocks for short timeouts
+}
+
+impl From<TimeoutUnit> for u8 {
+    fn from(unit: TimeoutUnit) -> u8 {
+        match unit {
+            TimeoutUnit::Seconds => 0,
+            TimeoutUnit::Slots => 1,
+        }
+    }
+}
+
+impl TryFrom<u8> for TimeoutUnit {
+    type Error = &'static str;
+
+    fn try_from(byte: u8) -> std::result::Result<TimeoutUnit, &'static str> {
+        match byte {
+            0 => Ok(TimeoutUnit::Seconds),
+            1 => Ok(TimeoutUnit::Slots),
+            _ => Err("unknown timeout unit"),
+        }
+    }
+}
+
+// The clock as the runtime reported it for an instruction
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ClockSnapshot {
+    pub slot: u64,
+    pub unix_timestamp: u64,
+}
+
+impl ClockSnapshot {
+    // solana-sdk 0.11 only hands programs the tick height, which stands in for both readings
+    pub fn from_tick_height(tick_height: u64) -> ClockSnapshot {
+        ClockSnapshot {
+            slot: tick_height,
+            unix_timestamp: tick_height,
+        }
+    }
+
+    pub fn reading(&self, unit: TimeoutUnit) -> u64 {
+        match unit {
+            TimeoutUnit::Seconds => self.unix_timestamp,
+            TimeoutUnit::Slots => self.slot,
+        }
+    }
+}
+
+// Where the player a game is waiting on stands against their timeout
+#[derive(Copy, Clone, Debug, Serialize, PartialEq)]
+pub enum PlayerLiveness {
+    Fresh,                      // Well within the timeout, or nobody can time out
+    Warning { remaining: u64 }, // Past the warning threshold, `remaining` left before a claim
+    Claimable,                  // Timed out, the opponent may claim the win
+}
+
+// Most games one KeepAliveMany instruction keeps alive
+pub const KEEP_ALIVE_BATCH_MAX: usize = 8;
+
+// What a batched keep alive did in one of its games.  Only Applied changes the game
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum KeepAliveStatus {
+    Applied,              // The signer's seat was kept alive
+    AlreadyCurrent,       // The seat was kept alive at this time or later, a retried batch
+    NotAPlayer,           // The signer plays neither seat of the game
+    Finished,             // The game is over, nothing to keep alive
+    Failed(ProgramError), // Refused for another reason, like the game using sequence numbers
+}
+
+// When liveness_status starts warning, in percent of the timeout.  A reporting choice of the
+// client, the game itself only knows the timeout
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WarningConfig {
+    pub warning_percent: u8, // Capped at 100, where the warning only shows with nothing left
+}
+
+impl Default for WarningConfig {
+    fn default() -> WarningConfig {
+        WarningConfig {
+            warning_percent: 80,
+        }
+    }
+}
+
+impl WarningConfig {
+    // Idle time at which the warning starts.  Exact for every timeout, NO_TIMEOUT included
+    pub fn warning_after(&self, timeout: u64) -> u64 {
+        let percent = u128::from(self.warning_percent.min(100));
+        (u128::from(timeout) * percent / 100) as u64
+    }
+}
+
+// A time handed to the engine, either a bare timestamp in seconds or a whole clock snapshot
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Now {
+    Seconds(u64),
+    Clock(ClockSnapshot),
+}
+
+// Rules chosen by player X when the game is created
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GameConfig {
+    pub move_timeout: u64,         // Time the mover may go without a keep alive
+    pub join_deadline: u64,        // Time a Waiting game stays open for player O
+    pub variant: Variant,          // Rule set in use
+    pub allow_solo: bool,          // Player X may also join as player O
+    pub swap_rule: bool,           // Player O may take over X's first move rather than reply
+    pub liveness: Liveness,        // How keep alives are expressed
+    pub heartbeat_limit: u8,       // Unanswered heartbeats before a timeout claim (Sequence)
+    pub timeout_unit: TimeoutUnit, // What move_timeout, join_deadline and keep alives count
+    pub center_handicap: bool,     // X may not open in the center
+    pub conduct_limit: u32,        // Rejected moves that forfeit the game, 0 for no limit
+}
+impl Default for GameConfig {
+    fn default() -> GameConfig {
+        GameConfig {
+            move_timeout: NO_TIMEOUT,
+            join_deadline: NO_TIMEOUT,
+            variant: Variant::Classic,
+            allow_solo: true,
+            swap_rule: false,
+            liveness: Liveness::Timestamp,
+            heartbeat_limit: 0,
+            timeout_unit: TimeoutUnit::Seconds,
+            center_handicap: false,
+            conduct_limit: 0,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.move_timeout == 0 || self.join_deadline == 0 {
+            Err(ProgramError::InvalidConfig)?;
+        }
+        if self.liveness == Liveness::Sequence && self.heartbeat_limit == 0 {
+            Err(ProgramError::InvalidConfig)?;
+        }
+        Ok(())
+    }
+}
+
+// A mark placed at (x, y)
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Move {
+    pub x: usize,
+    pub y: usize,
+}
+
+// What a cell of the board holds, the BOARD_ITEM_xyz values as a type
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Cell {
+    Free,
+    X,
+    O,
+}
+
+impl Cell {
+    pub fn item(self) -> u8 {
+        match self {
+            Cell::Free => BOARD_ITEM_FREE,
+            Cell::X => BOARD_ITEM_X,
+            Cell::O => BOARD_ITEM_O,
+        }
+    }
+
+    pub fn from_item(item: u8) -> Option<Cell> {
+        match item {
+            BOARD_ITEM_FREE => Some(Cell::Free),
+            BOARD_ITEM_X => Some(Cell::X),
+            BOARD_ITEM_O => Some(Cell::O),
+            _ => None,
+        }
+    }
+}
+
+// The two places at a game
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum Seat {
+    #[default]
+    X,
+    O,
+}
+
+impl Seat {
+    pub(crate) fn index(self) -> usize {
+        match self {
+            Seat::X => 0,
+            Seat::O => 1,
+        }
+    }
+
+    pub fn opponent(self) -> Seat {
+        match self {
+            Seat::X => Seat::O,
+            Seat::O => Seat::X,
+        }
+    }
+}
+
+// Keep alive timestamp (or sequence number) of each player, 0 until the player first checks
+// in.  Encodes exactly like the [u64; 2] of X then O it wraps
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct KeepAlives([u64; 2]);
+
+impl KeepAlives {
+    pub fn get(&self, seat: Seat) -> u64 {
+        self.0[seat.index()]
+    }
+
+    pub fn set(&mut self, seat: Seat, value: u64) {
+        self.0[seat.index()] = value;
+    }
+
+    pub fn is_set(&self, seat: Seat) -> bool {
+        self.get(seat) != 0
+    }
+
+    // Follow the players when they change seats
+    pub fn swap(&mut self) {
+        self.0.swap(0, 1);
+    }
+}
+
+impl From<[u64; 2]> for KeepAlives {
+    fn from(keep_alives: [u64; 2]) -> KeepAlives {
+        KeepAlives(keep_alives)
+    }
+}
+
+impl From<KeepAlives> for [u64; 2] {
+    fn from(keep_alives: KeepAlives) -> [u64; 2] {
+        keep_alives.0
+    }
+}
+
+// What a successful join got the player: their seat and who they play against
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct JoinOutcome {
+    pub seat: Seat,
+    pub opponent: Pubkey,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum GameState {
+    Waiting,
+    XMove,
+    OMove,
+    XWon,
+    OWon,
+    Draw,
+    Aborted, // Voided by the players, there is no winner
+}
+impl Default for GameState {
+    fn default() -> GameState {
+        GameState::Waiting
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Game {
+    pub(crate) keep_alive: KeepAlives,     // Last keep alive per seat
+    pub game_state: GameState,             // Current state of the game
+    pub(crate) player_x: Pubkey,           // Player who initialized the game
+    pub(crate) player_o: Pubkey,           // Player who joined the game
+    pub(crate) board: [u8; 9],             // Tracks the player moves (BOARD_ITEM_xyz)
+    pub(crate) trophy_minted: bool,        // A Trophy has been awarded for this game
+    pub(crate) moves: [u8; 9],             // Board index of each move, in the order they were made
+    pub(crate) move_count: u8,             // Number of valid entries in `moves`
+    pub(crate) generation: u32,            // Number of times the game account has been reset
+    pub(crate) config: GameConfig,         // Rules the game is played under
+    pub(crate) names: [PlayerName; 2],     // Display name of each player
+    pub(crate) idle_heartbeats: [u8; 2],   // Heartbeats of each player since the other acted
+    pub(crate) abort_requested: [bool; 2], // Player X/O asked to abort the game
+    pub(crate) final_board_hash: u32,      // Low bits of board_hash() when the game finished
+    pub(crate) frozen: bool,               // A moderator stopped play for review
+    pub(crate) created_at: u64,            // Time the game was created, 0 if created without one
+    pub(crate) finished_at: u64,           // Time the game ended, 0 until it does
+    pub(crate) turn: Seat,                 // Seat to move in a wild game, the marks do not tell
+    pub(crate) invalid_attempts: [u32; 2], // Moves of each player rejected for their conduct
+    pub(crate) linked: bool,               // A board of a linked match, played with the match
+}
+
+impl Game {
+    // A game for player X created at `timestamp`, with X's keep alive starting there too.  The
+    // default pubkey, an account no one holds, and a zero timestamp are refused
+    pub fn try_create(player_x: &Pubkey, timestamp: u64) -> Result<Game> {
+        Game::check_creation(player_x, timestamp)?;
+        Game::create_with_config(player_x, GameConfig::default(), timestamp)
+    }
+
+    // The bare game, without checks or a creation time.  For replays of a game already
+    // created, and for tests
+    pub fn create(player_x: &Pubkey) -> Game {
+        let mut game = Game::default();
+        game.player_x = *player_x;
+        assert_eq!(game.game_state, GameState::Waiting);
+        game
+    }
+
+    fn check_creation(player_x: &Pubkey, timestamp: u64) -> Result<()> {
+        if *player_x == Pubkey::default() {
+            Err(ProgramError::InvalidInput.at(ErrorContext::Player(*player_x)))?;
+        }
+        if timestamp == 0 {
+            Err(ProgramError::InvalidTimestamp.at(ErrorContext::Timestamp(timestamp)))?;
+        }
+        Ok(())
+    }
+
+    pub fn create_with_config(
+        player_x: &Pubkey,
+        config: GameConfig,
+        timestamp: u64,
+    ) -> Result<Game> {
+        Game::create_with_clock(player_x, config, Now::Seconds(timestamp))
+    }
+
+    // create_with_config for games timed by the slot as well as by the second.  What the
+    // program creates games with, so it checks the creator and the reading like try_create
+    pub fn create_at(player_x: &Pubkey, config: GameConfig, clock: &ClockSnapshot) -> Result<Game> {
+        let game = Game::create_with_clock(player_x, config, Now::Clock(*clock))?;
+        Game::check_creation(player_x, game.created_at)?;
+        Ok(game)
+    }
+
+    fn create_with_clock(player_x: &Pubkey, config: GameConfig, now: Now) -> Result<Game> {
+        config.validate()?;
+        let mut game = Game::create(player_x);
+        game.config = config;
+        let timestamp = game.reading(now)?;
+        game.created_at = timestamp;
+        if config.liveness == Liveness::Timestamp {
+            game.keep_alive.set(Seat::X, timestamp);
+        }
+        Ok(game)
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn player_x(&self) -> &Pubkey {
+        &self.player_x
+    }
+
+    pub fn player_o(&self) -> &Pubkey {
+        &self.player_o
+    }
+
+    pub fn board(&self) -> &[u8; 9] {
+        &self.board
+    }
+
+    pub fn names(&self) -> &[PlayerName; 2] {
+        &self.names
+    }
+
+    // Set the display name of one of the players.  In a solo game both seats take the name
+    pub fn set_name(self: &mut Game, player: Pubkey, name: PlayerName) -> Result<()> {
+        self.ensure_mutable(Action::SetName)?;
+        name.validate()?;
+        let joined = self.game_state != GameState::Waiting;
+        let mut found = false;
+        if player == self.player_x {
+            self.names[0] = name;
+            found = true;
+        }
+        if joined && player == self.player_o {
+            self.names[1] = name;
+            found = true;
+        }
+        if found {
+            Ok(())
+        } else {
+            Err(ProgramError::PlayerNotFound.at(ErrorContext::Player(player)))
+        }
+    }
+
+    #[cfg(test)]
+    pub fn new(player_x: Pubkey, player_o: Pubkey) -> Game {
+        let mut game = Game::create(&player_x);
+        game.join(player_o, 1).unwrap();
+        game
+    }
+
+    #[cfg(feature = "logging")]
+    pub(crate) fn log_outcome<T>(&self, action: &str, result: &Result<T>) {
+        match *result {
+            Ok(_) => info!(
+                target: LOG_TARGET,
+                "{}: accepted state={:?}", action, self.game_state
+            ),
+            Err(ref err) => info!(
+                target: LOG_TARGET,
+                "{}: rejected reason={:?} state={:?}", action, err.kind, self.game_state
+            ),
+        }
+    }
+
+    #[cfg(not(feature = "logging"))]
+    #[inline(always)]
+    pub(crate) fn log_outcome<T>(&self, _action: &str, _result: &Result<T>) {}
+
+    // FNV-1a over the board cells.  Cheap pre-check before a full comparison of two games
+    pub fn board_hash(&self) -> u64 {
+        self.board.iter().fold(FNV_OFFSET_BASIS, |hash, &cell| {
+            (hash ^ u64::from(cell)).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    // FNV-1a over the encoding of every field, so two games hash alike exactly when they
+    // compare equal, barring collisions.  Meant for tests comparing whole games
+    pub fn snapshot_hash(&self) -> u64 {
+        bincode::serialize(self)
+            .expect("a game always encodes")
+            .iter()
+            .fold(FNV_OFFSET_BASIS, |hash, &byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    // The board packed into base 3 digits, see minimax::pack_board.  Every board fits in 15 bits
+    pub fn board_as_u32(&self) -> u32 {
+        pack_board(&self.board)
+    }
+
+    // The game between player_x and player_o at a board packed by board_as_u32.  The order of
+    // the moves is not part of the encoding, so the history takes X's and O's cells in board
+    // order.  Numbers that are no board, and boards alternating play cannot reach, are refused
+    pub fn from_board_u32(encoded: u32, player_x: Pubkey, player_o: Pubkey) -> Result<Game> {
+        let board = unpack_board(encoded).ok_or(ProgramError::InvalidUserdata)?;
+        let mut game = Game::create(&player_x);
+        game.player_o = player_o;
+        game.set_board(board)?;
+        Ok(game)
+    }
+
+    // Put the game at `s`, the nine cells in board order with X, O and _ for a free cell, for
+    // tests that start from a position instead of replaying its moves.  The history and the
+    // game state follow from the board as in from_board_u32.  A board alternating play cannot
+    // reach fails with InvalidBoardState and leaves the game as it was
+    pub fn set_board_from_str(&mut self, s: &str) -> Result<()> {
+        let mut board = [BOARD_ITEM_FREE; 9];
+        if s.chars().count() != board.len() {
+            Err(ProgramError::InvalidInput)?;
+        }
+        for (cell, c) in board.iter_mut().zip(s.chars()) {
+            *cell = match c {
+                'X' => BOARD_ITEM_X,
+                'O' => BOARD_ITEM_O,
+                '_' => BOARD_ITEM_FREE,
+                _ => Err(ProgramError::InvalidInput)?,
+            };
+        }
+        let mut game = self.clone();
+        game.set_board(board)?;
+        *self = game;
+        Ok(())
+    }
+
+    // Replace the board, the history taking X's and O's cells in board order
+    fn set_board(&mut self, board: [u8; 9]) -> Result<()> {
+        let cells = |x_or_o| (0..9u8).filter(move |&i| board[i as usize] == x_or_o);
+        let (xs, os): (Vec<u8>, Vec<u8>) =
+            (cells(BOARD_ITEM_X).collect(), cells(BOARD_ITEM_O).collect());
+        if xs.len() != os.len() && xs.len() != os.len() + 1 {
+            Err(ProgramError::InvalidBoardState)?;
+        }
+
+        self.board = board;
+        self.moves = [0; 9];
+        for i in 0..xs.len() + os.len() {
+            self.moves[i] = if i % 2 == 0 { xs[i / 2] } else { os[i / 2] };
+        }
+        self.move_count = (xs.len() + os.len()) as u8;
+        match outcome(self.config.variant, &board) {
+            Some(game_state) => self.finish(game_state),
+            None if xs.len() == os.len() => self.game_state = GameState::XMove,
+            None => self.game_state = GameState::OMove,
+        }
+        self.validate()
+    }
+
+    // Position of the board among every board alternating play reaches, in board_as_u32
+    // order, or None for a board it cannot reach.  A classic game always holds one of them,
+    // a wild game need not, as either player may place either mark
+    pub fn position_index(&self) -> Option<u16> {
+        positions()
+            .binary_search(&self.board_as_u32())
+            .ok()
+            .map(|index| index as u16)
+    }
+
+    // The game at the board with index `index`, see from_board_u32
+    pub fn from_position_index(index: u16, player_x: Pubkey, player_o: Pubkey) -> Result<Game> {
+        let encoded = positions()
+            .get(index as usize)
+            .ok_or(ProgramError::InvalidUserdata)?;
+        Game::from_board_u32(*encoded, player_x, player_o)
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.move_count as usize
+    }
+
+    // Free cells left on the board of the game's variant
+    pub fn empty_cells(&self) -> u8 {
+        let cells = self.config.variant.cell_count() as usize;
+        self.board[..cells]
+            .iter()
+            .filter(|&&item| item == BOARD_ITEM_FREE)
+            .count() as u8
+    }
+
+    pub fn filled_cells(&self) -> u8 {
+        self.config.variant.cell_count() - self.empty_cells()
+    }
+
+    // Moves made and the most moves the variant allows, for progress bars
+    pub fn progress(&self) -> (u8, u8) {
+        (self.filled_cells(), self.config.variant.cell_count())
+    }
+
+    pub fn is_board_full(&self) -> bool {
+        self.empty_cells() == 0
+    }
+
+    // Start a new game in the account of a finished one, created by either of its players.
+    // The generation counter lets anyone holding on to the old game detect that the account
+    // has been reused.  An account awards at most one trophy however often it is reset, so
+    // the trophy flag carries over to the new game, and so do the rules it is played under
+    pub fn reset(self: &mut Game, new_player_x: &Pubkey, timestamp: u64) -> Result<()> {
+        self.reset_with_clock(new_player_x, Now::Seconds(timestamp))
+    }
+
+    // reset for games timed by the slot as well as by the second
+    pub fn reset_at(self: &mut Game, new_player_x: &Pubkey, clock: &ClockSnapshot) -> Result<()> {
+        self.reset_with_clock(new_player_x, Now::Clock(*clock))
+    }
+
+    fn reset_with_clock(self: &mut Game, new_player_x: &Pubkey, now: Now) -> Result<()> {
+        self.ensure_mutable(Action::Reset)?;
+        if !self.is_finished() {
+            Err(ProgramError::GameInProgress.at(ErrorContext::Outcome(self.game_state)))?;
+        }
+        self.seat_of(new_player_x)?;
+        let mut game = Game::create_with_clock(new_player_x, self.config, now)?;
+        Game::check_creation(new_player_x, game.created_at)?;
+        game.generation = self.generation.wrapping_add(1);
+        game.trophy_minted = self.trophy_minted;
+        *self = game;
+        Ok(())
+    }
+
+    // `now` in the unit the game counts its timeouts in.  A bare timestamp is in seconds, which
+    // a game timed in slots refuses rather than compare it against slot numbers
+    pub(crate) fn reading(&self, now: Now) -> Result<u64> {
+        match now {
+            Now::Clock(clock) => Ok(clock.reading(self.config.timeout_unit)),
+            Now::Seconds(timestamp) => match self.config.timeout_unit {
+                TimeoutUnit::Seconds => Ok(timestamp),
+                TimeoutUnit::Slots => {
+                    Err(ProgramError::TimeoutUnitMismatch.at(ErrorContext::Timestamp(timestamp)))
+                }
+            },
+        }
+    }
+
+    // Moves carry no clock, so the program stamps the time of the instruction that ended the
+    // game once the engine has finished it
+    pub(crate) fn set_finished_at(&mut self, timestamp: u64) {
+        self.finished_at = timestamp;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    // Commands on a linked board pass the match and the other board, so the match learns of
+    // a win in the instruction that makes it
+    pub fn is_linked(&self) -> bool {
+        self.linked
+    }
+
+    pub(crate) fn set_linked(&mut self) {
+        self.linked = true;
+    }
+
+    // Moves of the player in `seat` rejected for an occupied cell or for being out of turn
+    pub fn invalid_attempts(&self, seat: Seat) -> u32 {
+        self.invalid_attempts[seat.index()]
+    }
+
+    // The seat that lost the game by reaching the conduct limit, if one did
+    pub fn forfeited_by_conduct(&self) -> Option<Seat> {
+        let loser = match self.game_state {
+            GameState::XWon => Seat::O,
+            GameState::OWon => Seat::X,
+            _ => return None,
+        };
+        let limit = self.config.conduct_limit;
+        if limit > 0 && self.invalid_attempts(loser) >= limit {
+            Some(loser)
+        } else {
+            None
+        }
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    // True if `generation` refers to an earlier game played in this account
+    pub fn is_stale(&self, generation: u32) -> bool {
+        generation != self.generation
+    }
+
+    // Canonical form used for deduplication: player_x holds the lower pubkey.  If the players
+    // need to be swapped, every seat-specific field (board marks, keep alives, game state) is
+    // swapped along with them
+    pub fn normalize_player_order(&self) -> Game {
+        let mut game = self.clone();
+        if game.is_normalized() {
+            return game;
+        }
+
+        std::mem::swap(&mut game.player_x, &mut game.player_o);
+        game.keep_alive.swap();
+        for cell in game.board.iter_mut() {
+            *cell = match *cell {
+                BOARD_ITEM_X => BOARD_ITEM_O,
+                BOARD_ITEM_O => BOARD_ITEM_X,
+                free => free,
+            };
+        }
+        game.game_state = match game.game_state {
+            GameState::XMove => GameState::OMove,
+            GameState::OMove => GameState::XMove,
+            GameState::XWon => GameState::OWon,
+            GameState::OWon => GameState::XWon,
+            state => state,
+        };
+        if game.is_finished() {
+            game.final_board_hash = game.board_hash() as u32;
+        }
+        game
+    }
+
+    pub fn is_normalized(&self) -> bool {
+        self.player_x <= self.player_o
+    }
+
+    // The board of a finished game must be the one it finished with.  Checked in debug builds
+    // whenever a game is encoded
+    pub(crate) fn debug_assert_final_board(&self) {
+        debug_assert!(
+            !self.is_finished() || self.final_board_hash == self.board_hash() as u32,
+            "board of a finished game changed"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        self.debug_assert_final_board();
+        serde_json::to_string(self).map_err(|_| ProgramError::InvalidUserdata.into())
+    }
+
+    // Games from outside are checked like any other untrusted userdata
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Game> {
+        let game: Game = serde_json::from_str(json).map_err(|_| ProgramError::InvalidUserdata)?;
+        game.validate()?;
+        Ok(game)
+    }
+
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        self.debug_assert_final_board();
+        rmp_serde::to_vec(self).map_err(|_| ProgramError::InvalidUserdata.into())
+    }
+
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Game> {
+        let game: Game = rmp_serde::from_slice(bytes).map_err(|_| ProgramError::InvalidUserdata)?;
+        game.validate()?;
+        Ok(game)
+    }
+
+    // Moves played so far, in order
+    pub fn move_history(&self) -> Vec<Move> {
+        self.moves[..self.move_count as usize]
+            .iter()
+            .filter_map(|&i| cell_coords(i as usize).ok())
+            .map(|(x, y)| Move { x, y })
+            .collect()
+    }
+
+    // Moves played so far as space separated cells, columns a-c and rows 1-3, e.g. "b2 a1"
+    pub fn move_notation(&self) -> String {
+        self.moves[..self.move_count as usize]
+            .iter()
+            .map(|&i| cell_name(i as usize))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Render the board with column numbers across the top and row numbers down the side
+    pub fn display_board_with_coords(&self) -> String {
+        let mut lines = vec!["  0 1 2".to_string()];
+        for (y, row) in self.board.chunks(3).enumerate() {
+            if y > 0 {
+                lines.push("  -+-+-".to_string());
+            }
+            let cells: Vec<&str> = row
+                .iter()
+                .map(|&item| match item {
+                    BOARD_ITEM_X => "X",
+                    BOARD_ITEM_O => "O",
+                    _ => " ",
+                })
+                .collect();
+            lines.push(format!("{} {}", y, cells.join("|")).trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+
+    // Render the board as an HTML table fragment, with the winning line highlighted
+    pub fn to_html_table(&self) -> String {
+        let winning_line = self.winning_line();
+        let mut html = "<table class=\"board\">".to_string();
+        for (y, row) in self.board.chunks(3).enumerate() {
+            html.push_str("<tr>");
+            for (x, &item) in row.iter().enumerate() {
+                let (class, text) = match item {
+                    BOARD_ITEM_X => ("cell-x", "X"),
+                    BOARD_ITEM_O => ("cell-o", "O"),
+                    _ => ("cell-free", "&nbsp;"),
+                };
+                let winning = match winning_line {
+                    Some(line) if cell_index(x, y).is_ok_and(|i| line.contains(&i)) => " winning",
+                    _ => "",
+                };
+                html.push_str(&format!("<td class=\"{}{}\">{}</td>", class, winning, text));
+            }
+            html.push_str("</tr>");
+        }
+        html.push_str("</table>");
+        html
+    }
+
+    // One-hot board encoding for ML models: plane 0 holds X, plane 1 holds O, plane 2 free cells
+    pub fn to_flat_features(&self) -> [f32; 27] {
+        let mut features = [0.0; 27];
+        for (i, &cell) in self.board.iter().enumerate() {
+            let plane = match cell {
+                BOARD_ITEM_X => 0,
+                BOARD_ITEM_O => 1,
+                _ => 2,
+            };
+            features[plane * 9 + i] = 1.0;
+        }
+        features
+    }
+}
+
+// The packed boards of every reachable position, sorted.  Worked out on first use, by the
+// minimax build.rs solves PERFECT_PLAY_TABLE with, so the two agree on the positions
+fn positions() -> &'static [u32] {
+    static POSITIONS: OnceLock<Vec<u32>> = OnceLock::new();
+    POSITIONS.get_or_init(|| {
+        let mut values = BTreeMap::new();
+        minimax(&mut [BOARD_ITEM_FREE; 9], BOARD_ITEM_X, &mut values);
+        let positions: Vec<u32> = values.keys().cloned().collect();
+        assert_eq!(positions.len(), POSITION_COUNT);
+        positions
+    })
+}
+
+// Assembles a Game from optional parts, for tests and client code that need a game in a
+// particular setup without replaying create/join by hand
+#[derive(Debug, Default)]
+pub struct GameBuilder {
+    player_x: Option<Pubkey>,
+    player_o: Option<Pubkey>,
+    invited_player: Option<Pubkey>,
+    config: GameConfig,
+    timestamp: u64,
+    join_timestamp: u64,
+}
+
+impl GameBuilder {
+    pub fn new() -> GameBuilder {
+        GameBuilder::default()
+    }
+
+    pub fn player_x(mut self, player_x: Pubkey) -> GameBuilder {
+        self.player_x = Some(player_x);
+        self
+    }
+
+    // Setting player O produces a game that has already been joined
+    pub fn player_o(mut self, player_o: Pubkey) -> GameBuilder {
+        self.player_o = Some(player_o);
+        self
+    }
+
+    // The game keeps no invitation, so the builder only checks that player O is the invited
+    // player
+    pub fn invited_player(mut self, player: Pubkey) -> GameBuilder {
+        self.invited_player = Some(player);
+        self
+    }
+
+    pub fn config(mut self, config: GameConfig) -> GameBuilder {
+        self.config = config;
+        self
+    }
+
+    // The move timeout, on top of the config set so far
+    pub fn timeout(mut self, move_timeout: u64) -> GameBuilder {
+        self.config.move_timeout = move_timeout;
+        self
+    }
+
+    // The tick height the game is created at, which has to be set like player X
+    pub fn timestamp(mut self, timestamp: u64) -> GameBuilder {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn join_timestamp(mut self, timestamp: u64) -> GameBuilder {
+        self.join_timestamp = timestamp;
+        self
+    }
+
+    // The game the program would create and join at the tick heights given, checked the way
+    // the program checks it
+    pub fn build(self) -> Result<Game> {
+        let player_x = self.player_x.ok_or(ProgramError::InvalidInput)?;
+        let clock = ClockSnapshot::from_tick_height;
+        let mut game = Game::create_at(&player_x, self.config, &clock(self.timestamp))?;
+        if let Some(player_o) = self.player_o {
+            if self.invited_player.is_some() && self.invited_player != Some(player_o) {
+                Err(ProgramError::PlayerNotFound.at(ErrorContext::Player(player_o)))?;
+            }
+            game.join_at(player_o, &clock(self.join_timestamp))?;
+        }
+        Ok(game)
+    }
+}
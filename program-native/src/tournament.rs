@@ -0,0 +1,524 @@
+use result::{ProgramError, Result};
+use rng::{GameRng, SplitMix64};
+use solana_sdk::pubkey::Pubkey;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+// Largest field a Tournament accepts, which bounds its serialized size
+pub const MAX_TOURNAMENT_PLAYERS: usize = 16;
+
+// Games of a full round robin, more than a knockout of as many players ever needs
+pub const MAX_TOURNAMENT_GAMES: usize = MAX_TOURNAMENT_PLAYERS * (MAX_TOURNAMENT_PLAYERS - 1) / 2;
+
+// Result of a game between (player_x, player_o)
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum GameResult {
+    XWon,
+    OWon,
+    Draw,
+}
+
+// Every player plays every other player once.  Results are keyed by (player_x, player_o)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoundRobinTournament {
+    pub players: Vec<Pubkey>,
+    pub results: HashMap<(Pubkey, Pubkey), GameResult>,
+}
+
+impl RoundRobinTournament {
+    pub fn new(players: Vec<Pubkey>) -> RoundRobinTournament {
+        RoundRobinTournament {
+            players,
+            results: HashMap::new(),
+        }
+    }
+
+    // All unique pairings, the earlier player in `players` playing X
+    pub fn schedule(&self) -> Vec<(Pubkey, Pubkey)> {
+        let mut pairs = vec![];
+        for (i, &player_x) in self.players.iter().enumerate() {
+            for &player_o in &self.players[i + 1..] {
+                pairs.push((player_x, player_o));
+            }
+        }
+        pairs
+    }
+
+    pub fn record(&mut self, player_x: Pubkey, player_o: Pubkey, result: GameResult) {
+        self.results.insert((player_x, player_o), result);
+    }
+
+    // Points `player` earned against `opponent`: 2 for a win, 1 for a draw, 0 for a loss
+    fn points_against(&self, player: &Pubkey, opponent: &Pubkey) -> u32 {
+        let as_x = self
+            .results
+            .get(&(*player, *opponent))
+            .map(|result| match result {
+                GameResult::XWon => 2,
+                GameResult::Draw => 1,
+                GameResult::OWon => 0,
+            });
+        let as_o = self
+            .results
+            .get(&(*opponent, *player))
+            .map(|result| match result {
+                GameResult::OWon => 2,
+                GameResult::Draw => 1,
+                GameResult::XWon => 0,
+            });
+        as_x.unwrap_or(0) + as_o.unwrap_or(0)
+    }
+
+    // Players and their points, best first.  Players level on points are ordered by the
+    // points they earned in the games between them, then by pubkey
+    pub fn standings(&self) -> Vec<(Pubkey, u32)> {
+        let points = |player: &Pubkey| -> u32 {
+            self.players
+                .iter()
+                .map(|opponent| self.points_against(player, opponent))
+                .sum()
+        };
+        let standings: Vec<(Pubkey, u32)> = self
+            .players
+            .iter()
+            .map(|player| (*player, points(player)))
+            .collect();
+
+        let head_to_head = |player: &Pubkey, total: u32| -> u32 {
+            standings
+                .iter()
+                .filter(|&&(opponent, points)| points == total && opponent != *player)
+                .map(|(opponent, _)| self.points_against(player, opponent))
+                .sum()
+        };
+        let mut keyed: Vec<(u32, u32, Pubkey)> = standings
+            .iter()
+            .map(|&(player, total)| (total, head_to_head(&player, total), player))
+            .collect();
+        keyed.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+
+        keyed
+            .into_iter()
+            .map(|(total, _, player)| (player, total))
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Format {
+    SingleElim, // Knockout, the number of players must be a power of two
+    RoundRobin, // Everyone plays everyone once
+}
+
+// One game of a tournament
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Pairing {
+    pub player_x: Pubkey,
+    pub player_o: Pubkey,
+    pub round: u8,                  // Round the game belongs to, starting at 0
+    pub result: Option<GameResult>, // None until reported
+    pub winner: Option<Pubkey>,     // Player who advances (SingleElim), None for draws otherwise
+}
+
+// A Pairing as the tournament stores it, with the players as indices into its player list.
+// Every field is always present, so each game takes the same number of bytes
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+struct GameSlot {
+    player_x: u8,       // Index of player X
+    player_o: u8,       // Index of player O
+    round: u8,          // Round the game belongs to, starting at 0
+    reported: bool,     // `result` holds the outcome
+    result: GameResult, // Outcome of the game once reported
+    draw_to_o: bool,    // Player O advances from a drawn knockout game
+}
+
+impl GameSlot {
+    const EMPTY: GameSlot = GameSlot {
+        player_x: 0,
+        player_o: 0,
+        round: 0,
+        reported: false,
+        result: GameResult::Draw,
+        draw_to_o: false,
+    };
+
+    // Index of the player who goes through
+    fn winner(&self, format: Format) -> Option<u8> {
+        if !self.reported {
+            return None;
+        }
+        match (self.result, format) {
+            (GameResult::XWon, _) => Some(self.player_x),
+            (GameResult::OWon, _) => Some(self.player_o),
+            (GameResult::Draw, Format::RoundRobin) => None,
+            (GameResult::Draw, Format::SingleElim) if self.draw_to_o => Some(self.player_o),
+            (GameResult::Draw, Format::SingleElim) => Some(self.player_x),
+        }
+    }
+}
+
+// Pairings and results of a multi-player event.  The Games themselves are played elsewhere and
+// their outcomes reported back here.  Players and games are kept in arrays of the largest
+// field's size, so every tournament serializes to the same number of bytes
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Tournament {
+    format: Format,
+    players: [Pubkey; MAX_TOURNAMENT_PLAYERS], // The first `player_count` are in use
+    player_count: u8,                          // Number of players
+    game_count: u8,                            // Number of games scheduled so far
+    round: u8,                                 // Current round
+    rng: SplitMix64,                           // Breaks drawn knockout games
+    #[serde(with = "game_slots")]
+    games: [GameSlot; MAX_TOURNAMENT_GAMES], // The first `game_count` are in use
+}
+
+// Serde only derives arrays of up to 32 elements, so the game slots are written out by hand,
+// as the tuple bincode encodes every array as
+mod game_slots {
+    use super::{GameSlot, MAX_TOURNAMENT_GAMES};
+    use serde::de::{Deserializer, Error, SeqAccess, Visitor};
+    use serde::ser::{SerializeTuple, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(
+        games: &[GameSlot; MAX_TOURNAMENT_GAMES],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(MAX_TOURNAMENT_GAMES)?;
+        for game in games.iter() {
+            tuple.serialize_element(game)?;
+        }
+        tuple.end()
+    }
+
+    struct GameSlots;
+
+    impl<'de> Visitor<'de> for GameSlots {
+        type Value = [GameSlot; MAX_TOURNAMENT_GAMES];
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} game slots", MAX_TOURNAMENT_GAMES)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut games = [GameSlot::EMPTY; MAX_TOURNAMENT_GAMES];
+            for (i, game) in games.iter_mut().enumerate() {
+                *game = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+            Ok(games)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<[GameSlot; MAX_TOURNAMENT_GAMES], D::Error> {
+        deserializer.deserialize_tuple(MAX_TOURNAMENT_GAMES, GameSlots)
+    }
+}
+
+impl Tournament {
+    pub fn new(players: Vec<Pubkey>, format: Format, seed: u64) -> Result<Tournament> {
+        if players.len() < 2 || players.len() > MAX_TOURNAMENT_PLAYERS {
+            Err(ProgramError::InvalidConfig)?;
+        }
+        if format == Format::SingleElim && !players.len().is_power_of_two() {
+            Err(ProgramError::InvalidConfig)?;
+        }
+        let mut tournament = Tournament {
+            format,
+            players: [Pubkey::default(); MAX_TOURNAMENT_PLAYERS],
+            player_count: players.len() as u8,
+            game_count: 0,
+            round: 0,
+            rng: SplitMix64::from_seed(seed),
+            games: [GameSlot::EMPTY; MAX_TOURNAMENT_GAMES],
+        };
+        tournament.players[..players.len()].copy_from_slice(&players);
+        let count = players.len() as u8;
+        match format {
+            Format::SingleElim => {
+                for player_x in (0..count).step_by(2) {
+                    tournament.schedule(player_x, player_x + 1);
+                }
+            }
+            Format::RoundRobin => {
+                for player_x in 0..count {
+                    for player_o in player_x + 1..count {
+                        tournament.schedule(player_x, player_o);
+                    }
+                }
+            }
+        }
+        Ok(tournament)
+    }
+
+    // Add a game of the current round.  The player limit leaves room for every game either
+    // format schedules
+    fn schedule(&mut self, player_x: u8, player_o: u8) {
+        self.games[self.game_count as usize] = GameSlot {
+            player_x,
+            player_o,
+            round: self.round,
+            ..GameSlot::EMPTY
+        };
+        self.game_count += 1;
+    }
+
+    pub fn players(&self) -> &[Pubkey] {
+        &self.players[..self.player_count as usize]
+    }
+
+    fn slots(&self) -> &[GameSlot] {
+        &self.games[..self.game_count as usize]
+    }
+
+    fn pairing(&self, slot: &GameSlot) -> Pairing {
+        let player = |index: u8| self.players[index as usize];
+        Pairing {
+            player_x: player(slot.player_x),
+            player_o: player(slot.player_o),
+            round: slot.round,
+            result: if slot.reported {
+                Some(slot.result)
+            } else {
+                None
+            },
+            winner: slot.winner(self.format).map(player),
+        }
+    }
+
+    pub fn games(&self) -> Vec<Pairing> {
+        self.slots().iter().map(|slot| self.pairing(slot)).collect()
+    }
+
+    // Games of the current round still waiting for a result, with their game index
+    pub fn next_pairings(&self) -> Vec<(usize, Pubkey, Pubkey)> {
+        self.slots()
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.round == self.round && !slot.reported)
+            .map(|(i, slot)| {
+                let pairing = self.pairing(slot);
+                (i, pairing.player_x, pairing.player_o)
+            })
+            .collect()
+    }
+
+    pub fn report_result(&mut self, game_idx: usize, outcome: GameResult) -> Result<()> {
+        let round = self.round;
+        let coin = self.rng.next_u64();
+        let game_count = self.game_count as usize;
+        let slot = match self.games[..game_count].get_mut(game_idx) {
+            Some(slot) if slot.round == round && !slot.reported => slot,
+            _ => Err(ProgramError::InvalidInput)?,
+        };
+        slot.reported = true;
+        slot.result = outcome;
+        // Somebody has to advance, so a drawn knockout game goes to a seeded coin flip
+        slot.draw_to_o =
+            self.format == Format::SingleElim && outcome == GameResult::Draw && coin & 1 != 0;
+
+        if self.format == Format::SingleElim && self.next_pairings().is_empty() {
+            let winners: Vec<u8> = self
+                .slots()
+                .iter()
+                .filter(|slot| slot.round == round)
+                .filter_map(|slot| slot.winner(self.format))
+                .collect();
+            if winners.len() > 1 {
+                self.round += 1;
+                for pair in winners.chunks(2) {
+                    self.schedule(pair[0], pair[1]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.slots().iter().all(|slot| slot.reported)
+    }
+
+    // Winner of a completed knockout
+    pub fn champion(&self) -> Option<Pubkey> {
+        match self.format {
+            Format::SingleElim if self.is_complete() => self
+                .slots()
+                .last()
+                .and_then(|slot| slot.winner(self.format))
+                .map(|index| self.players[index as usize]),
+            _ => None,
+        }
+    }
+
+    // Players and their points, best first.  Round robin points are as for
+    // RoundRobinTournament, a knockout player scores a point for each game they went through
+    pub fn standings(&self) -> Vec<(Pubkey, u32)> {
+        let games = self.games();
+        match self.format {
+            Format::RoundRobin => {
+                let mut round_robin = RoundRobinTournament::new(self.players().to_vec());
+                for game in &games {
+                    if let Some(result) = game.result {
+                        round_robin.record(game.player_x, game.player_o, result);
+                    }
+                }
+                round_robin.standings()
+            }
+            Format::SingleElim => {
+                let mut standings: Vec<(Pubkey, u32)> = self
+                    .players()
+                    .iter()
+                    .map(|player| {
+                        let wins = games
+                            .iter()
+                            .filter(|game| game.winner == Some(*player))
+                            .count();
+                        (*player, wins as u32)
+                    })
+                    .collect();
+                // Stable, so players out in the same round keep their bracket order
+                standings.sort_by_key(|&(_, wins)| Reverse(wins));
+                standings
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bincode;
+
+    #[test]
+    pub fn round_robin() {
+        let players: Vec<Pubkey> = (1..=4).map(|i| Pubkey::new(&[i; 32])).collect();
+        let (a, b, c, d) = (players[0], players[1], players[2], players[3]);
+        let mut tournament = RoundRobinTournament::new(players.clone());
+
+        let schedule = tournament.schedule();
+        assert_eq!(
+            schedule,
+            vec![(a, b), (a, c), (a, d), (b, c), (b, d), (c, d)]
+        );
+
+        // c beats b head to head, so finishes above b on equal points
+        tournament.record(a, b, GameResult::Draw);
+        tournament.record(a, c, GameResult::XWon);
+        tournament.record(a, d, GameResult::XWon);
+        tournament.record(b, c, GameResult::OWon);
+        tournament.record(b, d, GameResult::XWon);
+        tournament.record(c, d, GameResult::Draw);
+        assert_eq!(tournament.results.len(), 6);
+
+        assert_eq!(tournament.standings(), vec![(a, 5), (c, 3), (b, 3), (d, 1)]);
+    }
+
+    fn players(n: u8) -> Vec<Pubkey> {
+        (1..=n).map(|i| Pubkey::new(&[i; 32])).collect()
+    }
+
+    #[test]
+    pub fn single_elim() {
+        let players = players(8);
+        let mut tournament = Tournament::new(players.clone(), Format::SingleElim, 7).unwrap();
+
+        let mut rounds = 0;
+        while !tournament.next_pairings().is_empty() {
+            for (i, _, _) in tournament.next_pairings() {
+                // The first game of every round is drawn, the rest go to player X
+                let outcome = if i == 0 || tournament.games()[i - 1].round != tournament.round {
+                    GameResult::Draw
+                } else {
+                    GameResult::XWon
+                };
+                tournament.report_result(i, outcome).unwrap();
+            }
+            rounds += 1;
+        }
+        assert_eq!(rounds, 3);
+        assert_eq!(tournament.games().len(), 7);
+        assert!(tournament.is_complete());
+
+        let champion = tournament.champion().unwrap();
+        let standings = tournament.standings();
+        assert_eq!(standings[0], (champion, 3));
+        assert_eq!(standings.iter().map(|s| s.1).sum::<u32>(), 7);
+
+        // The same seed breaks the draws the same way
+        let mut again = Tournament::new(players, Format::SingleElim, 7).unwrap();
+        for (i, game) in tournament.games().iter().enumerate() {
+            again.report_result(i, game.result.unwrap()).unwrap();
+        }
+        assert_eq!(again, tournament);
+
+        let bytes = bincode::serialize(&tournament).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Tournament>(&bytes).unwrap(),
+            tournament
+        );
+    }
+
+    #[test]
+    pub fn round_robin_three_way_tie() {
+        let players = players(4);
+        let (a, b, c, d) = (players[0], players[1], players[2], players[3]);
+        let mut tournament = Tournament::new(players, Format::RoundRobin, 0).unwrap();
+        assert_eq!(tournament.next_pairings().len(), 6);
+
+        // a beats b, b beats c, c beats a, and everyone beats d
+        for (i, player_x, player_o) in tournament.next_pairings() {
+            let outcome = if player_x == a && player_o == c {
+                GameResult::OWon
+            } else {
+                GameResult::XWon
+            };
+            tournament.report_result(i, outcome).unwrap();
+        }
+        assert!(tournament.is_complete());
+        assert_eq!(tournament.champion(), None);
+        assert_eq!(tournament.standings(), vec![(a, 4), (b, 4), (c, 4), (d, 0)]);
+    }
+
+    #[test]
+    pub fn serialized_size() {
+        let size = |tournament: &Tournament| bincode::serialized_size(tournament).unwrap();
+
+        // The format, the players and their count, the game count, the round, the rng state
+        // and the games
+        let expected = 4 + 32 * 16 + 1 + 1 + 1 + 8 + 9 * 120;
+        let small = Tournament::new(players(2), Format::SingleElim, 0).unwrap();
+        assert_eq!(size(&small), expected);
+
+        let mut full = Tournament::new(players(16), Format::RoundRobin, 0).unwrap();
+        assert_eq!(full.next_pairings().len(), MAX_TOURNAMENT_GAMES);
+        for (i, _, _) in full.next_pairings() {
+            full.report_result(i, GameResult::Draw).unwrap();
+        }
+        assert_eq!(size(&full), expected);
+
+        let bytes = bincode::serialize(&full).unwrap();
+        assert_eq!(bincode::deserialize::<Tournament>(&bytes).unwrap(), full);
+        assert!(bincode::deserialize::<Tournament>(&bytes[..bytes.len() - 9]).is_err());
+    }
+
+    #[test]
+    pub fn invalid_reports() {
+        assert!(Tournament::new(players(1), Format::RoundRobin, 0).is_err());
+        assert!(Tournament::new(players(17), Format::RoundRobin, 0).is_err());
+        assert!(Tournament::new(players(6), Format::SingleElim, 0).is_err());
+        assert!(Tournament::new(players(16), Format::SingleElim, 0).is_ok());
+
+        let mut tournament = Tournament::new(players(4), Format::SingleElim, 0).unwrap();
+        assert!(tournament.report_result(2, GameResult::XWon).is_err());
+        tournament.report_result(0, GameResult::XWon).unwrap();
+        assert!(tournament.report_result(0, GameResult::OWon).is_err());
+        tournament.report_result(1, GameResult::OWon).unwrap();
+        tournament.report_result(2, GameResult::XWon).unwrap();
+        assert!(tournament.report_result(2, GameResult::XWon).is_err());
+        assert!(tournament.report_result(3, GameResult::XWon).is_err());
+        assert_eq!(tournament.champion(), Some(Pubkey::new(&[1; 32])));
+    }
+}
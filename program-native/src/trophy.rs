@@ -0,0 +1,160 @@
+use game::{GameState, BOARD_ITEM_O, BOARD_ITEM_X};
+use record::FinalizedGame;
+use solana_sdk::pubkey::Pubkey;
+
+// Compact record of a won game, written once to its own account when the game is finalized
+#[repr(C)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Trophy {
+    pub game: Pubkey,   // Game account the trophy was awarded for
+    pub winner: Pubkey, // Player who won the game
+    pub loser: Pubkey,  // Player who lost the game
+    pub board: [u8; 9], // Final board (BOARD_ITEM_xyz)
+    pub timestamp: u64, // Time of finalization
+}
+
+impl Trophy {
+    // Serialized size of a Trophy
+    pub const LEN: usize = 32 + 32 + 32 + 9 + 8;
+
+    // The trophy of the game `record` was made for, finalized at `timestamp`.  The record has
+    // no board, as wild marks do not follow from the moves, so the final `board` comes along.
+    // Games without a winner award none
+    pub fn from_record(record: &FinalizedGame, board: [u8; 9], timestamp: u64) -> Option<Trophy> {
+        let (winner, loser) = match record.outcome {
+            GameState::XWon => (record.player_x, record.player_o),
+            GameState::OWon => (record.player_o, record.player_x),
+            _ => return None,
+        };
+        Some(Trophy {
+            game: record.game,
+            winner,
+            loser,
+            board,
+            timestamp,
+        })
+    }
+
+    pub fn view(&self) -> TrophyView {
+        let board_item = |item: u8| match item {
+            BOARD_ITEM_X => 'X',
+            BOARD_ITEM_O => 'O',
+            _ => ' ',
+        };
+        let mut board = [' '; 9];
+        for (cell, &item) in board.iter_mut().zip(self.board.iter()) {
+            *cell = board_item(item);
+        }
+        TrophyView {
+            game: self.game.to_string(),
+            winner: self.winner.to_string(),
+            loser: self.loser.to_string(),
+            board,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+// UI friendly rendering of a Trophy
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TrophyView {
+    pub game: String,
+    pub winner: String,
+    pub loser: String,
+    pub board: [char; 9],
+    pub timestamp: u64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bincode;
+    use game::play;
+    use program_command::Command;
+    use program_state::State;
+    use result::ProgramError;
+    use simple_serde::SimpleSerde;
+    use solana_sdk::account::{Account, KeyedAccount};
+    use std::mem::size_of;
+
+    #[test]
+    pub fn serialize() {
+        let trophy = Trophy {
+            game: Pubkey::new(&[3; 32]),
+            winner: Pubkey::new(&[1; 32]),
+            loser: Pubkey::new(&[2; 32]),
+            board: [1, 2, 0, 1, 2, 0, 1, 0, 0],
+            timestamp: 42,
+        };
+        assert_eq!(
+            bincode::serialized_size(&trophy).unwrap() as usize,
+            Trophy::LEN
+        );
+
+        let mut b = vec![0; 255];
+        State::Trophy(trophy.clone()).serialize(&mut b).unwrap();
+        match State::deserialize(&b).unwrap() {
+            State::Trophy(t) => assert_eq!(t, trophy),
+            state => panic!("unexpected state: {:?}", state),
+        }
+
+        let view = trophy.view();
+        assert_eq!(view.winner, trophy.winner.to_string());
+        assert_eq!(view.board, ['X', 'O', ' ', 'X', 'O', ' ', 'X', ' ', ' ']);
+        assert_eq!(view.timestamp, 42);
+    }
+
+    #[test]
+    pub fn from_record() {
+        let key = Pubkey::new(&[3; 32]);
+        let won = play(&[0, 3, 1, 4, 8, 5]);
+        let record = won.finalize_record(&key).unwrap();
+        let trophy = Trophy::from_record(&record, won.board, 42).unwrap();
+        assert_eq!((trophy.game, trophy.winner), (key, *won.player_o()));
+        assert_eq!((trophy.loser, trophy.board), (*won.player_x(), won.board));
+
+        let drawn = play(&[0, 4, 8, 2, 6, 3, 5, 7, 1]);
+        let record = drawn.finalize_record(&key).unwrap();
+        assert_eq!(Trophy::from_record(&record, drawn.board, 42), None);
+    }
+
+    #[test]
+    pub fn finalized_by_a_player() {
+        let program_id = Pubkey::new(&[9; 32]);
+        let account = |tokens, space| Account::new(tokens, space, program_id);
+        let player_x = Pubkey::new(&[1; 32]);
+        let mut accounts = [
+            (Pubkey::new(&[5; 32]), account(0, 0)),
+            (Pubkey::new(&[3; 32]), account(100, size_of::<State>())),
+            (Pubkey::new(&[4; 32]), account(0, size_of::<State>())),
+            (Pubkey::new(&[6; 32]), account(0, size_of::<State>())),
+        ];
+        let game = play(&[0, 3, 1, 4, 2]);
+        State::Dashboard(Default::default())
+            .serialize(&mut accounts[1].1.userdata)
+            .unwrap();
+        State::Game(game)
+            .serialize(&mut accounts[2].1.userdata)
+            .unwrap();
+
+        let mut finalize = |signer: Pubkey| {
+            accounts[0].0 = signer;
+            let mut data = vec![0; size_of::<Command>()];
+            Command::FinalizeWithTrophy.serialize(&mut data).unwrap();
+            let mut info: Vec<KeyedAccount> = accounts
+                .iter_mut()
+                .enumerate()
+                .map(|(i, &mut (ref key, ref mut account))| KeyedAccount::new(key, i == 0, account))
+                .collect();
+            let result = ::process_instruction(&mut info, &data, 1);
+            result.map(|_| State::deserialize(&info[3].account.userdata).unwrap())
+        };
+        // Someone outside the game cannot settle it, nor pick the trophy account
+        let err = finalize(Pubkey::new(&[5; 32])).unwrap_err();
+        assert_eq!(err.kind, ProgramError::PlayerNotFound);
+        match finalize(player_x).unwrap() {
+            State::Trophy(trophy) => assert_eq!(trophy.winner, player_x),
+            state => panic!("no trophy: {:?}", state),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use bincode;
+use dashboard::{Dashboard, MAX_COMPLETED_GAMES};
+use game::{pack_board, Game, GameState, KeepAlives};
+use program_state::State;
+use result::{ProgramError, Result};
+use simple_serde::SimpleSerde;
+use solana_sdk::pubkey::Pubkey;
+
+// Bytes of a State::Game in the first account layout: the State tag followed by the keep
+// alives, state, players and board the Game started out with.  Every later field was
+// appended after these
+pub const GAME_V1_LEN: usize = 4 + 16 + 4 + 32 + 32 + 9;
+
+const STATE_GAME_TAG: [u8; 4] = [2, 0, 0, 0];
+
+// Bytes of a State::Dashboard in the layout it had before dashboards had a moderator: the
+// State tag, the game count, the pending game, then the completed games with the index of the
+// latest one and the generation of each.  The first layout ended before the generations,
+// leaving them zero, which reads the same
+pub const DASHBOARD_V1_LEN: usize =
+    4 + 8 + 32 + 32 * MAX_COMPLETED_GAMES + 1 + 4 * MAX_COMPLETED_GAMES;
+
+const STATE_DASHBOARD_TAG: [u8; 4] = [1, 0, 0, 0];
+
+#[derive(Deserialize)]
+struct GameV1 {
+    keep_alive: [u64; 2],
+    game_state: GameState,
+    player_x: Pubkey,
+    player_o: Pubkey,
+    board: [u8; 9],
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DashboardV1 {
+    pub(crate) total_games: u64,
+    pub(crate) pending_game: Pubkey,
+    pub(crate) completed_games: [Pubkey; MAX_COMPLETED_GAMES],
+    pub(crate) latest_completed_game_index: u8,
+    pub(crate) completed_generations: [u32; MAX_COMPLETED_GAMES],
+}
+
+// Whether the userdata holds a game in the first layout.  Those leave everything past the
+// v1 fields zero, while a game in the current layout never does: its config always has
+// nonzero timeouts
+pub fn is_v1_game(userdata: &[u8]) -> bool {
+    userdata.len() >= GAME_V1_LEN
+        && userdata[..4] == STATE_GAME_TAG
+        && userdata[GAME_V1_LEN..].iter().all(|&byte| byte == 0)
+}
+
+// The game account's game in the current layout, or None when it is in the current layout
+// already so that upgrading twice does nothing.  The move history a v1 game lacks is
+// rebuilt from its board the way Game::from_board_u32 does
+pub fn upgrade_game(userdata: &[u8]) -> Result<Option<Game>> {
+    if !is_v1_game(userdata) {
+        return match State::deserialize(userdata)? {
+            State::Game(ref game) if game.validate().is_ok() => Ok(None),
+            _ => Err(ProgramError::InvalidUserdata.into()),
+        };
+    }
+
+    let v1: GameV1 = bincode::deserialize(&userdata[4..GAME_V1_LEN])
+        .map_err(|_| ProgramError::InvalidUserdata)?;
+    let mut game = match v1.game_state {
+        GameState::Waiting => Game::create(&v1.player_x),
+        _ => Game::from_board_u32(pack_board(&v1.board), v1.player_x, v1.player_o)?,
+    };
+    // A game won on time has no line on its board to tell from_board_u32 it is over
+    if matches!(game.game_state, GameState::XMove | GameState::OMove)
+        && matches!(v1.game_state, GameState::XWon | GameState::OWon)
+    {
+        game.finish(v1.game_state);
+    }
+    game.keep_alive = KeepAlives::from(v1.keep_alive);
+    if game.game_state != v1.game_state || game.board != v1.board {
+        Err(ProgramError::InvalidUserdata)?;
+    }
+    game.validate()?;
+    Ok(Some(game))
+}
+
+// Whether the userdata holds a dashboard in the v1 layout, which leaves everything past the
+// v1 fields zero.  The current layout never does, it ends in a nonzero layout version
+pub fn is_v1_dashboard(userdata: &[u8]) -> bool {
+    userdata.len() >= DASHBOARD_V1_LEN
+        && userdata[..4] == STATE_DASHBOARD_TAG
+        && userdata[DASHBOARD_V1_LEN..].iter().all(|&byte| byte == 0)
+}
+
+// The dashboard account's dashboard in the current layout, or None when it is not a v1
+// dashboard.  State::deserialize reads dashboards through this, so a v1 dashboard is written
+// back in the current layout by the first instruction that updates it
+pub fn upgrade_dashboard(userdata: &[u8]) -> Result<Option<Dashboard>> {
+    if !is_v1_dashboard(userdata) {
+        return Ok(None);
+    }
+    let v1: DashboardV1 = bincode::deserialize(&userdata[4..DASHBOARD_V1_LEN])
+        .map_err(|_| ProgramError::InvalidUserdata)?;
+    Ok(Some(Dashboard::from(v1)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::{BOARD_ITEM_FREE, BOARD_ITEM_O, BOARD_ITEM_X};
+    use std::mem::size_of;
+
+    // A v1 game account as the first program wrote it: tag, keep alives, state, players,
+    // board, then the zeros the rest of the 255 byte account started out with
+    fn golden_v1(game_state: u32, board: [u8; 9]) -> Vec<u8> {
+        let mut userdata = vec![2, 0, 0, 0];
+        userdata.extend_from_slice(&[5, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+        userdata.extend_from_slice(&game_state.to_le_bytes());
+        userdata.extend_from_slice(&[1; 32]);
+        userdata.extend_from_slice(&[2; 32]);
+        userdata.extend_from_slice(&board);
+        assert_eq!(userdata.len(), GAME_V1_LEN);
+        userdata.resize(255, 0);
+        userdata
+    }
+
+    #[test]
+    pub fn upgrade_v1() {
+        let (player_x, player_o) = (Pubkey::new(&[1; 32]), Pubkey::new(&[2; 32]));
+        let mut board = [BOARD_ITEM_FREE; 9];
+        board[4] = BOARD_ITEM_X;
+        board[0] = BOARD_ITEM_O;
+        let userdata = golden_v1(1, board);
+        assert!(is_v1_game(&userdata));
+
+        let game = upgrade_game(&userdata).unwrap().unwrap();
+        assert_eq!(game.game_state, GameState::XMove);
+        assert_eq!((*game.player_x(), *game.player_o()), (player_x, player_o));
+        assert_eq!(game.board(), &board);
+        assert_eq!(game.move_notation(), "b2 a1");
+        assert_eq!(KeepAlives::from([5, 6]), game.keep_alive);
+
+        // Rewritten in the current layout the account is left alone
+        let mut rewritten = vec![0; size_of::<State>()];
+        State::Game(game.clone()).serialize(&mut rewritten).unwrap();
+        assert!(!is_v1_game(&rewritten));
+        assert_eq!(upgrade_game(&rewritten).unwrap(), None);
+        match State::deserialize(&rewritten).unwrap() {
+            State::Game(decoded) => assert_eq!(decoded, game),
+            state => panic!("{:?}", state),
+        }
+    }
+
+    #[test]
+    pub fn upgrade_v1_waiting_and_timed_out() {
+        let game = upgrade_game(&golden_v1(0, [BOARD_ITEM_FREE; 9]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(game.game_state, GameState::Waiting);
+        assert_eq!(game.move_count(), 0);
+
+        // O won on time, X never answered O's reply
+        let mut board = [BOARD_ITEM_FREE; 9];
+        board[4] = BOARD_ITEM_X;
+        board[0] = BOARD_ITEM_O;
+        let game = upgrade_game(&golden_v1(4, board)).unwrap().unwrap();
+        assert_eq!(game.game_state, GameState::OWon);
+        assert!(game.validate().is_ok());
+    }
+
+    #[test]
+    pub fn refuse_inconsistent_v1() {
+        let mut board = [BOARD_ITEM_FREE; 9];
+        board[4] = BOARD_ITEM_X;
+        // X to move after X moved
+        assert!(upgrade_game(&golden_v1(1, board)).is_err());
+        // Waiting with marks on the board
+        assert!(upgrade_game(&golden_v1(0, board)).is_err());
+        // Not a game at all
+        assert!(upgrade_game(&vec![0; size_of::<State>()]).is_err());
+        assert!(upgrade_game(&[2, 0, 0]).is_err());
+    }
+}
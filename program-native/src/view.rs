@@ -0,0 +1,127 @@
+use game::{
+    is_action_allowed, Action, Game, GameState, PlayerLiveness, Seat, BOARD_ITEM_O, BOARD_ITEM_X,
+};
+use watch_list::WatchList;
+
+// Snapshot of a game in the shape UIs want to render it
+#[derive(Debug, Serialize, PartialEq)]
+pub struct GameView {
+    pub game_state: GameState,
+    pub player_x: String,
+    pub player_o: Option<String>,
+    pub player_x_name: Option<String>,
+    pub player_o_name: Option<String>,
+    pub board: [char; 9],
+    pub move_count: usize,
+    pub watchers: u64,                    // Spectators, 0 without the watch list
+    pub frozen: bool,                     // Play is stopped while a moderator reviews the game
+    pub accepts_moves: bool,              // Not frozen, O has joined, and the game is not over
+    pub center_handicap: bool,            // X may not open in the center
+    pub invalid_attempts: [u32; 2],       // Moves of X and O rejected for their conduct
+    pub liveness: Option<PlayerLiveness>, // Timeout countdown, None unless the view was given one
+}
+
+impl GameView {
+    pub fn new(game: &Game) -> GameView {
+        let mut board = [' '; 9];
+        for (cell, &item) in board.iter_mut().zip(game.board().iter()) {
+            *cell = match item {
+                BOARD_ITEM_X => 'X',
+                BOARD_ITEM_O => 'O',
+                _ => ' ',
+            };
+        }
+        let name = |index: usize| game.names()[index].as_str().map(str::to_string);
+        GameView {
+            game_state: game.game_state,
+            player_x: game.player_x().to_string(),
+            player_o: match game.game_state {
+                GameState::Waiting => None,
+                _ => Some(game.player_o().to_string()),
+            },
+            player_x_name: name(0),
+            player_o_name: name(1),
+            board,
+            move_count: game.move_count(),
+            watchers: 0,
+            frozen: game.is_frozen(),
+            accepts_moves: !game.is_frozen() && is_action_allowed(game.game_state, Action::Move),
+            center_handicap: game.config().center_handicap,
+            invalid_attempts: [
+                game.invalid_attempts(Seat::X),
+                game.invalid_attempts(Seat::O),
+            ],
+            liveness: None,
+        }
+    }
+
+    // Show the countdown from Game::liveness_status
+    pub fn with_liveness(mut self, liveness: PlayerLiveness) -> GameView {
+        self.liveness = Some(liveness);
+        self
+    }
+
+    pub fn with_watch_list(mut self, watch_list: &WatchList) -> GameView {
+        self.watchers = watch_list.count();
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use player_name::PlayerName;
+    use serde_json;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    pub fn names() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let mut g = Game::create(&player_x);
+        g.set_name(player_x, PlayerName::new("alice").unwrap())
+            .unwrap();
+
+        let view = GameView::new(&g);
+        assert!(!view.accepts_moves);
+        assert_eq!(view.player_x, player_x.to_string());
+        assert_eq!(view.player_x_name, Some("alice".to_string()));
+        assert_eq!(view.player_o, None);
+        assert_eq!(view.player_o_name, None);
+
+        g.join(player_o, 1).unwrap();
+        g.next_move(player_x, 1, 1).unwrap();
+        let view = GameView::new(&g);
+        assert_eq!(view.player_o, Some(player_o.to_string()));
+        assert_eq!(view.player_o_name, None);
+        assert_eq!(view.board[4], 'X');
+        assert_eq!(view.move_count, 1);
+        assert!(view.accepts_moves);
+    }
+
+    #[test]
+    pub fn watchers() {
+        let game_pubkey = Pubkey::new(&[3; 32]);
+        let g = Game::create(&Pubkey::new(&[1; 32]));
+        assert_eq!(GameView::new(&g).watchers, 0);
+
+        let mut watch_list = WatchList::new(&game_pubkey);
+        for i in 0..12 {
+            watch_list.watch(Pubkey::new(&[10 + i; 32])).unwrap();
+        }
+        let view = GameView::new(&g).with_watch_list(&watch_list);
+        assert_eq!(view.watchers, 12);
+    }
+
+    #[test]
+    pub fn liveness() {
+        let g = Game::create(&Pubkey::new(&[1; 32]));
+        assert_eq!(GameView::new(&g).liveness, None);
+
+        let warning = PlayerLiveness::Warning { remaining: 3 };
+        let view = GameView::new(&g).with_liveness(warning);
+        assert_eq!(view.liveness, Some(warning));
+        let json = serde_json::to_string(&view).unwrap();
+        assert!(json.ends_with("\"liveness\":{\"Warning\":{\"remaining\":3}}}"));
+    }
+}
@@ -0,0 +1,196 @@
+use game::{Game, GameState};
+use result::{ErrorContext, ProgramError, Result};
+use solana_sdk::pubkey::Pubkey;
+
+// The fields of a token account a wager checks, the first ones of the token program's
+// account layout
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TokenAccount {
+    pub mint: Pubkey,  // Token the account holds
+    pub owner: Pubkey, // Key that may move the tokens
+    pub amount: u64,   // Tokens in the account
+}
+
+impl TokenAccount {
+    // Bytes of the token program's layout ahead of everything a wager ignores
+    pub const LEN: usize = 32 + 32 + 8;
+
+    pub fn decode(userdata: &[u8]) -> Result<TokenAccount> {
+        if userdata.len() < TokenAccount::LEN {
+            Err(ProgramError::InvalidUserdata)?;
+        }
+        let mut amount = [0; 8];
+        amount.copy_from_slice(&userdata[64..72]);
+        Ok(TokenAccount {
+            mint: Pubkey::new(&userdata[..32]),
+            owner: Pubkey::new(&userdata[32..64]),
+            amount: u64::from_le_bytes(amount),
+        })
+    }
+}
+
+// A stake of `mint` tokens each player puts up on a game, held by an escrow token account
+// the game's key owns until the game is settled
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Wager {
+    pub game: Pubkey,   // Game account the stakes are on
+    pub mint: Pubkey,   // Token staked
+    pub escrow: Pubkey, // Token account holding both stakes
+    pub stake: u64,     // Tokens each player puts up
+}
+
+// Tokens settlement moves out of the escrow, to the token account of `player`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TokenTransfer {
+    pub player: Pubkey,
+    pub amount: u64,
+}
+
+impl Wager {
+    // The token account `player` stakes from: it holds the wager's token, is the player's own
+    // and covers the stake
+    pub fn check_stake_account(&self, account: &TokenAccount, player: &Pubkey) -> Result<()> {
+        if account.mint != self.mint {
+            Err(ProgramError::TokenMintMismatch.at(ErrorContext::Player(*player)))?;
+        }
+        if account.owner != *player {
+            Err(ProgramError::TokenOwnerMismatch.at(ErrorContext::Player(*player)))?;
+        }
+        if account.amount < self.stake {
+            Err(ProgramError::InsufficientTokens.at(ErrorContext::Player(*player)))?;
+        }
+        Ok(())
+    }
+
+    // The escrow holds the wager's token on behalf of the game
+    pub fn check_escrow(&self, account: &TokenAccount) -> Result<()> {
+        if account.mint != self.mint {
+            Err(ProgramError::TokenMintMismatch)?;
+        }
+        if account.owner != self.game {
+            Err(ProgramError::TokenOwnerMismatch)?;
+        }
+        Ok(())
+    }
+}
+
+impl Game {
+    // Pay out the `escrowed` tokens of a finished game.  The winner takes everything; a draw
+    // or an aborted game splits it, the odd unit going to player X, who put the first stake
+    // in
+    pub fn settle_tokens(&self, escrowed: u64) -> Result<Vec<TokenTransfer>> {
+        let transfer = |player: &Pubkey, amount| TokenTransfer {
+            player: *player,
+            amount,
+        };
+        Ok(match self.game_state {
+            GameState::XWon => vec![transfer(self.player_x(), escrowed)],
+            GameState::OWon => vec![transfer(self.player_o(), escrowed)],
+            GameState::Draw | GameState::Aborted if self.player_o() == &Pubkey::default() => {
+                vec![transfer(self.player_x(), escrowed)]
+            }
+            GameState::Draw | GameState::Aborted => {
+                let half = escrowed / 2;
+                vec![
+                    transfer(self.player_x(), escrowed - half),
+                    transfer(self.player_o(), half),
+                ]
+            }
+            GameState::Waiting | GameState::XMove | GameState::OMove => {
+                Err(ProgramError::GameInProgress.at(ErrorContext::Outcome(self.game_state)))?
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::play;
+
+    fn wager() -> Wager {
+        Wager {
+            game: Pubkey::new(&[3; 32]),
+            mint: Pubkey::new(&[8; 32]),
+            escrow: Pubkey::new(&[9; 32]),
+            stake: 50,
+        }
+    }
+
+    #[test]
+    pub fn settlement() {
+        let player_x = Pubkey::new(&[1; 32]);
+        let player_o = Pubkey::new(&[2; 32]);
+        let won = play(&[0, 3, 1, 4, 2]);
+        assert_eq!(
+            won.settle_tokens(100).unwrap(),
+            [TokenTransfer {
+                player: player_x,
+                amount: 100
+            }]
+        );
+        let lost = play(&[0, 3, 1, 4, 8, 5]);
+        assert_eq!(lost.settle_tokens(100).unwrap()[0].player, player_o);
+
+        // A split pot leaves the odd unit with X
+        let drawn = play(&[0, 4, 8, 2, 6, 3, 5, 7, 1]);
+        let amounts: Vec<(Pubkey, u64)> = drawn
+            .settle_tokens(101)
+            .unwrap()
+            .iter()
+            .map(|transfer| (transfer.player, transfer.amount))
+            .collect();
+        assert_eq!(amounts, [(player_x, 51), (player_o, 50)]);
+
+        let mut aborted = Game::try_create(&player_x, 1).unwrap();
+        aborted.request_abort(player_x).unwrap();
+        assert_eq!(aborted.settle_tokens(50).unwrap()[0].amount, 50);
+
+        let err = play(&[0]).settle_tokens(100).unwrap_err();
+        assert_eq!(err.kind, ProgramError::GameInProgress);
+    }
+
+    #[test]
+    pub fn token_account_checks() {
+        let player = Pubkey::new(&[1; 32]);
+        let mut userdata = vec![8; 32];
+        userdata.extend_from_slice(&[1; 32]);
+        userdata.extend_from_slice(&60u64.to_le_bytes());
+        userdata.extend_from_slice(&[0; 93]);
+        let account = TokenAccount::decode(&userdata).unwrap();
+        assert_eq!(account.amount, 60);
+        wager().check_stake_account(&account, &player).unwrap();
+        assert!(TokenAccount::decode(&userdata[..71]).is_err());
+
+        let kind = |account: TokenAccount| {
+            wager()
+                .check_stake_account(&account, &player)
+                .unwrap_err()
+                .kind
+        };
+        let other_mint = TokenAccount {
+            mint: Pubkey::new(&[7; 32]),
+            ..account
+        };
+        assert_eq!(kind(other_mint), ProgramError::TokenMintMismatch);
+        let other_owner = TokenAccount {
+            owner: Pubkey::new(&[2; 32]),
+            ..account
+        };
+        assert_eq!(kind(other_owner), ProgramError::TokenOwnerMismatch);
+        let short = TokenAccount {
+            amount: 49,
+            ..account
+        };
+        assert_eq!(kind(short), ProgramError::InsufficientTokens);
+
+        let escrow = TokenAccount {
+            owner: wager().game,
+            amount: 0,
+            ..account
+        };
+        wager().check_escrow(&escrow).unwrap();
+        let err = wager().check_escrow(&other_owner).unwrap_err();
+        assert_eq!(err.kind, ProgramError::TokenOwnerMismatch);
+    }
+}
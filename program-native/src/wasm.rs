@@ -0,0 +1,118 @@
+use bs58;
+use game::Game;
+use result::{GameError, ProgramError, Result};
+use solana_sdk::pubkey::Pubkey;
+use wasm_bindgen::prelude::*;
+
+impl From<GameError> for JsValue {
+    fn from(err: GameError) -> JsValue {
+        JsValue::from_str(&format!("{:?}", err.kind))
+    }
+}
+
+fn parse_pubkey(b58: &str) -> Result<Pubkey> {
+    match bs58::decode(b58).into_vec() {
+        Ok(ref bytes) if bytes.len() == 32 => Ok(Pubkey::new(bytes)),
+        _ => Err(ProgramError::InvalidInput.into()),
+    }
+}
+
+// The Game engine for JavaScript.  Players are passed as base58 strings, and errors are
+// thrown as the name of the ProgramError
+#[wasm_bindgen]
+pub struct WasmGame {
+    game: Game,
+}
+
+#[wasm_bindgen]
+impl WasmGame {
+    pub fn create(player_x_b58: &str) -> std::result::Result<WasmGame, JsValue> {
+        Ok(WasmGame {
+            game: Game::create(&parse_pubkey(player_x_b58)?),
+        })
+    }
+
+    pub fn join(&mut self, player_o_b58: &str, timestamp: u64) -> std::result::Result<(), JsValue> {
+        self.game.join(parse_pubkey(player_o_b58)?, timestamp)?;
+        Ok(())
+    }
+
+    pub fn next_move(
+        &mut self,
+        player_b58: &str,
+        x: usize,
+        y: usize,
+    ) -> std::result::Result<(), JsValue> {
+        Ok(self.game.next_move(parse_pubkey(player_b58)?, x, y)?)
+    }
+
+    pub fn keep_alive(
+        &mut self,
+        player_b58: &str,
+        timestamp: u64,
+    ) -> std::result::Result<(), JsValue> {
+        Ok(self.game.keep_alive(parse_pubkey(player_b58)?, timestamp)?)
+    }
+
+    pub fn to_json(&self) -> std::result::Result<String, JsValue> {
+        Ok(self.game.to_json()?)
+    }
+
+    pub fn from_json(json: &str) -> std::result::Result<WasmGame, JsValue> {
+        Ok(WasmGame {
+            game: Game::from_json(json)?,
+        })
+    }
+}
+
+// A new game for player X, or the error as a string if the pubkey does not parse
+#[wasm_bindgen]
+pub fn new_game_js(player_x_b58: &str) -> JsValue {
+    match parse_pubkey(player_x_b58) {
+        Ok(player_x) => WasmGame {
+            game: Game::create(&player_x),
+        }
+        .into(),
+        Err(err) => err.into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn parse_pubkey() {
+        let player_x = Pubkey::new(&[1; 32]);
+        assert_eq!(
+            super::parse_pubkey(&player_x.to_string()).unwrap(),
+            player_x
+        );
+        assert!(super::parse_pubkey("0OIl").is_err());
+        assert!(super::parse_pubkey("2g").is_err());
+    }
+}
+
+// Run with `wasm-pack test --headless --chrome -- --features wasm`
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_test {
+    use super::*;
+    use game::GameState;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub fn round_trip() {
+        let player_x = Pubkey::new(&[1; 32]).to_string();
+        let player_o = Pubkey::new(&[2; 32]).to_string();
+        let mut g = WasmGame::create(&player_x).unwrap();
+        g.join(&player_o, 1).unwrap();
+        g.next_move(&player_x, 1, 1).unwrap();
+        assert!(g.next_move(&player_x, 0, 0).is_err());
+
+        let g = WasmGame::from_json(&g.to_json().unwrap()).unwrap();
+        assert_eq!(g.game.game_state, GameState::OMove);
+        assert!(new_game_js("not base58!").is_string());
+    }
+}
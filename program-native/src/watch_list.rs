@@ -0,0 +1,169 @@
+use result::{ErrorContext, ProgramError, Result};
+use solana_sdk::pubkey::Pubkey;
+
+pub const MAX_WATCHERS: usize = 5;
+
+// Spectators of one game, kept in an account of their own.  The first MAX_WATCHERS are listed
+// and the rest only counted, which keeps the account the size of every other one
+#[repr(C)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct WatchList {
+    game: Pubkey,                     // Game account being watched
+    watchers: [Pubkey; MAX_WATCHERS], // Listed watchers, the first `listed` are in use
+    listed: u8,                       // Number of listed watchers
+    overflow: u32,                    // Watchers that did not fit in the list
+}
+
+impl WatchList {
+    pub fn new(game: &Pubkey) -> WatchList {
+        WatchList {
+            game: *game,
+            ..WatchList::default()
+        }
+    }
+
+    pub fn game(&self) -> &Pubkey {
+        &self.game
+    }
+
+    pub fn watchers(&self) -> &[Pubkey] {
+        &self.watchers[..self.listed as usize]
+    }
+
+    // Everyone watching, listed or not
+    pub fn count(&self) -> u64 {
+        u64::from(self.listed) + u64::from(self.overflow)
+    }
+
+    // Watching again is a no-op for listed watchers.  Once the list is full only the overflow
+    // counter goes up, since there is nothing to recognize a repeated request by
+    pub fn watch(&mut self, watcher: Pubkey) -> Result<()> {
+        if self.watchers().contains(&watcher) {
+            return Ok(());
+        }
+        if (self.listed as usize) < MAX_WATCHERS {
+            self.watchers[self.listed as usize] = watcher;
+            self.listed += 1;
+        } else {
+            self.overflow = self.overflow.saturating_add(1);
+        }
+        Ok(())
+    }
+
+    // Stop watching.  Only listed watchers can leave: overflow watchers are not told apart
+    // from keys that never watched, so they stay counted
+    pub fn unwatch(&mut self, watcher: Pubkey) -> Result<()> {
+        match self.watchers().iter().position(|&listed| listed == watcher) {
+            Some(index) => {
+                let last = self.listed as usize - 1;
+                self.watchers.swap(index, last);
+                self.watchers[last] = Pubkey::default();
+                self.listed -= 1;
+            }
+            None => Err(ProgramError::NotWatching.at(ErrorContext::Player(watcher)))?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::Game;
+    use program_command::Command;
+    use program_state::State;
+    use simple_serde::SimpleSerde;
+    use solana_sdk::account::{Account, KeyedAccount};
+    use std::mem::size_of;
+
+    #[test]
+    pub fn watch_and_unwatch() {
+        let game = Pubkey::new(&[3; 32]);
+        let alice = Pubkey::new(&[1; 32]);
+        let bob = Pubkey::new(&[2; 32]);
+        let mut list = WatchList::new(&game);
+        assert_eq!(list.game(), &game);
+
+        list.watch(alice).unwrap();
+        list.watch(bob).unwrap();
+        list.watch(alice).unwrap();
+        assert_eq!(list.count(), 2);
+        assert_eq!(list.watchers(), &[alice, bob]);
+
+        list.unwatch(alice).unwrap();
+        assert_eq!(list.watchers(), &[bob]);
+        let err = list.unwatch(alice).unwrap_err();
+        assert_eq!(err.kind, ProgramError::NotWatching);
+        assert_eq!(err.context, ErrorContext::Player(alice));
+        list.unwatch(bob).unwrap();
+        assert_eq!(list.count(), 0);
+        assert_eq!(list, WatchList::new(&game));
+    }
+
+    #[test]
+    pub fn overflow() {
+        let mut list = WatchList::new(&Pubkey::new(&[3; 32]));
+        for i in 0..MAX_WATCHERS as u8 + 2 {
+            list.watch(Pubkey::new(&[10 + i; 32])).unwrap();
+        }
+        assert_eq!(list.watchers().len(), MAX_WATCHERS);
+        assert_eq!(list.overflow, 2);
+        assert_eq!(list.count(), MAX_WATCHERS as u64 + 2);
+
+        // A listed watcher leaving frees a slot, the counter is untouched
+        list.unwatch(Pubkey::new(&[10; 32])).unwrap();
+        assert_eq!(list.watchers().len(), MAX_WATCHERS - 1);
+        assert_eq!(list.overflow, 2);
+
+        // Nor can a key that never watched drain the counter
+        let err = list.unwatch(Pubkey::new(&[99; 32])).unwrap_err();
+        assert_eq!(err.kind, ProgramError::NotWatching);
+        assert_eq!(list.overflow, 2);
+    }
+
+    #[test]
+    pub fn process_watch() {
+        let program_id = Pubkey::new(&[9; 32]);
+        let account = |tokens, space| Account::new(tokens, space, program_id);
+        let mut accounts = [
+            (Pubkey::new(&[5; 32]), account(0, 0)),
+            (Pubkey::new(&[3; 32]), account(100, size_of::<State>())),
+            (Pubkey::new(&[4; 32]), account(0, size_of::<State>())),
+            (Pubkey::new(&[6; 32]), account(0, size_of::<State>())),
+        ];
+        let game = Game::create(&Pubkey::new(&[1; 32]));
+        State::Dashboard(Default::default())
+            .serialize(&mut accounts[1].1.userdata)
+            .unwrap();
+        State::Game(game)
+            .serialize(&mut accounts[2].1.userdata)
+            .unwrap();
+
+        let mut process = |command: Command| {
+            let mut data = vec![0; size_of::<Command>()];
+            command.serialize(&mut data).unwrap();
+            let mut info: Vec<KeyedAccount> = accounts
+                .iter_mut()
+                .enumerate()
+                .map(|(i, &mut (ref key, ref mut account))| KeyedAccount::new(key, i == 0, account))
+                .collect();
+            let result = ::process_instruction(&mut info, &data, 1);
+            let watchers = match State::deserialize(&info[3].account.userdata).unwrap() {
+                State::WatchList(list) => list.count(),
+                _ => 0,
+            };
+            result.map(|_| watchers)
+        };
+        assert_eq!(process(Command::Watch).unwrap(), 1);
+        assert_eq!(process(Command::Watch).unwrap(), 1);
+        assert_eq!(process(Command::Unwatch).unwrap(), 0);
+        assert_eq!(
+            process(Command::Unwatch).unwrap_err().kind,
+            ProgramError::NotWatching
+        );
+
+        // Spectators are not paid for by the dashboard
+        assert_eq!(accounts[0].1.lamports, 0);
+        assert_eq!(accounts[1].1.lamports, 100);
+    }
+}
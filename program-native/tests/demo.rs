@@ -0,0 +1,112 @@
+// Runs the demo's transactions through the program's exported entrypoint and checks every step
+// against a local game with the same oracle examples/demo.rs checks the cluster with
+extern crate solana_sdk;
+extern crate tictactoe;
+
+use solana_sdk::account::{Account, KeyedAccount};
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, KeypairUtil};
+use solana_sdk::transaction::Transaction;
+use std::collections::HashMap;
+use tictactoe::batch::decode_game;
+use tictactoe::demo::{
+    init_dashboard_tx, init_game_tx, init_player_tx, join_tx, move_tx, verify_step, SCRIPT,
+};
+use tictactoe::game::{Game, GameState};
+
+// Accounts by key, standing in for the cluster
+struct Bank {
+    program_id: Pubkey,
+    accounts: HashMap<Pubkey, Account>,
+}
+
+impl Bank {
+    fn add(&mut self, key: Pubkey, space: usize) {
+        let account = Account::new(100, space, self.program_id);
+        self.accounts.insert(key, account);
+    }
+
+    fn execute(&mut self, tx: &Transaction) {
+        let instruction = &tx.instructions[0];
+        let keys: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .map(|&i| tx.account_keys[i as usize])
+            .collect();
+        let mut accounts: Vec<Account> =
+            keys.iter().map(|key| self.accounts[key].clone()).collect();
+        {
+            let mut info: Vec<KeyedAccount> = keys
+                .iter()
+                .zip(accounts.iter_mut())
+                .enumerate()
+                .map(|(i, (key, account))| KeyedAccount::new(key, i < tx.signatures.len(), account))
+                .collect();
+            tictactoe::process(&self.program_id, &mut info, &instruction.userdata, 1).unwrap();
+        }
+        for (key, account) in keys.into_iter().zip(accounts) {
+            self.accounts.insert(key, account);
+        }
+    }
+
+    fn game(&self, key: &Pubkey) -> Game {
+        decode_game(&self.accounts[key].userdata).unwrap()
+    }
+}
+
+#[test]
+fn script_matches_the_local_game() {
+    let program_id = Pubkey::new(&[9; 32]);
+    let mut bank = Bank {
+        program_id,
+        accounts: HashMap::new(),
+    };
+    let last_id = Hash::default();
+    let (dashboard, game) = (Keypair::new(), Keypair::new());
+    let players = [Keypair::new(), Keypair::new()];
+    bank.add(dashboard.pubkey(), 255);
+    bank.add(game.pubkey(), 255);
+    for player in players.iter() {
+        bank.add(player.pubkey(), 0);
+    }
+
+    let (dashboard_key, game_key) = (dashboard.pubkey(), game.pubkey());
+    let (player_x, player_o) = (players[0].pubkey(), players[1].pubkey());
+    bank.execute(&init_dashboard_tx(&program_id, &dashboard, last_id).unwrap());
+    for player in players.iter() {
+        bank.execute(&init_player_tx(&program_id, &dashboard, &player.pubkey(), last_id).unwrap());
+    }
+    bank.execute(&init_game_tx(&program_id, &game, &dashboard_key, &player_x, last_id).unwrap());
+    bank.execute(&join_tx(&program_id, &players[1], &dashboard_key, &game_key, last_id).unwrap());
+
+    let mut local = Game::create(&player_x);
+    local.join(player_o, 2).unwrap();
+    verify_step(&local, &bank.game(&game_key)).unwrap();
+    for (i, &(x, y)) in SCRIPT.iter().enumerate() {
+        let player = &players[i % 2];
+        let tx = move_tx(
+            &program_id,
+            player,
+            &dashboard_key,
+            &game_key,
+            (x, y),
+            last_id,
+        );
+        bank.execute(&tx.unwrap());
+        local
+            .next_move(player.pubkey(), x as usize, y as usize)
+            .unwrap();
+        verify_step(&local, &bank.game(&game_key)).unwrap();
+    }
+    assert_eq!(local.game_state, GameState::XWon);
+
+    // A local game that went another way no longer matches the cluster's
+    let mut other = Game::create(&player_x);
+    other.join(player_o, 2).unwrap();
+    for (i, &(x, y)) in [(0, 0), (0, 1), (1, 0), (1, 1), (0, 2)].iter().enumerate() {
+        other.next_move(players[i % 2].pubkey(), x, y).unwrap();
+    }
+    let err = verify_step(&other, &bank.game(&game_key)).unwrap_err();
+    assert_eq!(err.cells, [2, 6]);
+}